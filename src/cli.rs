@@ -1,13 +1,131 @@
-use crate::constants::DEFAULT_MAX_FILE_SIZE;
-use clap::Parser;
-
-fn parse_chunk_index(s: &str) -> Result<isize, String> {
-    let idx: isize = s.parse().map_err(|_| format!("invalid chunk index: {s}"))?;
-    if idx >= -1 {
-        Ok(idx)
-    } else {
-        Err("chunk index must be >= 0 or -1 (none)".to_string())
+use crate::config::{
+    ChunkUnit, HeaderPosition, InstructionsMode, LogFormat, OutputFormat, PathStyle, SanitizeMode,
+    SortKey, SplitStrategy, SummaryTarget,
+};
+use crate::constants::{
+    DEFAULT_EXT_GROUP_BELOW, DEFAULT_FILTER_TIMEOUT_SECS, DEFAULT_MAX_FILE_SIZE,
+    DEFAULT_PLAIN_SEPARATOR,
+};
+use crate::fixtures::{self, Fixture};
+use crate::models::{self, ModelPreset};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Parse `--chunk-index`: a comma-separated list of indices and inclusive
+/// ranges such as `0,2-4`, or `-1` for none (an empty list). The result is
+/// sorted ranges with overlapping and adjacent ones merged; they are only
+/// expanded once checked against the chunk count, so `0-99999999999` costs
+/// nothing.
+pub fn parse_chunk_indices(s: &str) -> Result<Vec<RangeInclusive<usize>>, String> {
+    let s = s.trim();
+    if s == "-1" {
+        return Ok(Vec::new());
+    }
+    let index = |part: &str| {
+        part.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid chunk index: {s} (use e.g. 0,2-4, or -1 for none)"))
+    };
+    let mut ranges = Vec::new();
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (index(start)?, index(end)?);
+                if start > end {
+                    return Err(format!("invalid chunk range {part}: start exceeds end"));
+                }
+                ranges.push(start..=end);
+            }
+            None => {
+                let idx = index(part)?;
+                ranges.push(idx..=idx);
+            }
+        }
+    }
+    ranges.sort_unstable_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                *last = *last.start()..=*last.end().max(range.end());
+            }
+            _ => merged.push(range),
+        }
     }
+    Ok(merged)
+}
+
+/// Parse `--modified-since` / `--modified-before`: a duration back from now
+/// (`90m`, `12h`, `7d`, `2w`; `s` for seconds), a date (`2024-05-01`, local
+/// midnight), or an RFC 3339 timestamp.
+pub fn parse_time_bound(s: &str) -> Result<SystemTime, String> {
+    parse_time_bound_at(s, SystemTime::now())
+}
+
+/// `parse_time_bound` with durations counted back from `now`.
+pub fn parse_time_bound_at(
+    s: &str,
+    now: SystemTime,
+) -> Result<SystemTime, String> {
+    let s = s.trim();
+    let invalid =
+        || format!("invalid time: {s} (use e.g. 7d, 12h, 2024-05-01, or 2024-05-01T09:00:00Z)");
+    if let Ok(stamp) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(stamp.into());
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let midnight = date.and_time(chrono::NaiveTime::MIN);
+        return midnight
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .map(SystemTime::from)
+            .ok_or_else(invalid);
+    }
+    let unit = match s.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let count: u64 = s[..s.len() - 1].parse().map_err(|_| invalid())?;
+    count
+        .checked_mul(unit)
+        .and_then(|secs| now.checked_sub(Duration::from_secs(secs)))
+        .ok_or_else(invalid)
+}
+
+/// Parse a `--max-size-ext` pair, `ext=bytes`. The extension is lowercased
+/// and may be given with its leading dot (`.json=32768`).
+pub fn parse_ext_size(s: &str) -> Result<(String, u64), String> {
+    let invalid = || format!("invalid --max-size-ext pair {s:?} (use ext=bytes, e.g. json=32768)");
+    let (ext, bytes) = s.split_once('=').ok_or_else(invalid)?;
+    let ext = crate::context::gather::normalize_ext(ext).ok_or_else(invalid)?;
+    let bytes = bytes.trim().parse().map_err(|_| invalid())?;
+    Ok((ext, bytes))
+}
+
+/// Helper commands; without one, `context-gather` gathers the given paths.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Print a shell completion script to stdout.
+    Completions {
+        /// Shell to generate completions for.
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+/// Write the completion script for `shell` to `out`.
+pub fn write_completions(
+    shell: Shell,
+    out: &mut dyn std::io::Write,
+) {
+    clap_complete::generate(shell, &mut Cli::command(), "context-gather", out);
 }
 
 #[derive(Parser, Debug)]
@@ -16,11 +134,24 @@ fn parse_chunk_index(s: &str) -> Result<isize, String> {
     about = "Gather text file contents, group them by folder, output as XML-like context to clipboard, \
                    then show token count."
 )]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    /// Run a helper command instead of gathering files.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// File paths (supporting globs), defaults to "."
     #[arg(default_value = ".", num_args(1..))]
     pub paths: Vec<String>,
 
+    /// Name for the file read from stdin by a `-` path argument, relative to the working directory.
+    #[arg(long = "stdin-name", value_name = "PATH", default_value = "stdin.txt")]
+    pub stdin_name: PathBuf,
+
+    /// Read newline-separated paths from a file, or from stdin with `-` (blank lines and `#` comments are skipped).
+    #[arg(long = "paths-from", value_name = "FILE|-")]
+    pub paths_from: Option<PathBuf>,
+
     /// Open interactive TUI for file selection; with --chunk-size, also stream chunks (alias for --select + --stream).
     #[arg(short = 'i', long = "interactive", default_value_t = false)]
     pub interactive: bool,
@@ -29,6 +160,22 @@ pub struct Cli {
     #[arg(long = "select", default_value_t = false)]
     pub select: bool,
 
+    /// After the file-selection TUI, write the selected paths (one per line) to PATH, or stdout with `-`.
+    #[arg(long = "emit-selection", value_name = "PATH|-")]
+    pub emit_selection: Option<PathBuf>,
+
+    /// Write the --emit-selection list as a JSON array instead of lines.
+    #[arg(
+        long = "emit-selection-json",
+        default_value_t = false,
+        requires = "emit_selection"
+    )]
+    pub emit_selection_json: bool,
+
+    /// In the TUI extension list, group extensions with fewer than N files under one "other" entry (0 disables).
+    #[arg(long = "ext-group-below", value_name = "N", default_value_t = DEFAULT_EXT_GROUP_BELOW)]
+    pub ext_group_below: usize,
+
     /// After chunking, open the chunk streaming REPL (requires --chunk-size).
     #[arg(
         long = "stream",
@@ -43,18 +190,199 @@ pub struct Cli {
     #[arg(short = 'n', long = "no-clipboard", default_value_t = false)]
     pub no_clipboard: bool,
 
+    /// Copy by piping the text to this shell command's stdin (e.g. `wl-copy`) instead of the system clipboard.
+    #[arg(
+        long = "clipboard-cmd",
+        value_name = "CMD",
+        conflicts_with = "no_clipboard"
+    )]
+    pub clipboard_cmd: Option<String>,
+
     /// Print XML output to stdout.
     #[arg(short = 'o', long = "stdout", default_value_t = false)]
     pub stdout: bool,
 
+    /// Write the output to this file (chunks go to `<stem>-<N>.<ext>`).
+    #[arg(
+        short = 'w',
+        long = "output",
+        value_name = "PATH",
+        conflicts_with = "multi_step",
+        conflicts_with = "stream"
+    )]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite existing files written by --output.
+    #[arg(long = "force", default_value_t = false, requires = "output")]
+    pub force: bool,
+
     /// Maximum file size in bytes before skipping files.
     #[arg(long = "max-size", default_value_t = DEFAULT_MAX_FILE_SIZE)]
     pub max_size: u64,
 
+    /// Maximum size in bytes for files with extension EXT, instead of --max-size (repeatable, e.g. json=32768).
+    #[arg(long = "max-size-ext", value_name = "EXT=BYTES", value_parser = parse_ext_size)]
+    pub max_size_ext: Vec<(String, u64)>,
+
+    /// Keep files over --max-size, cut to their first --max-size bytes at a line boundary and marked truncated (likewise for --max-file-tokens).
+    #[arg(long = "truncate-oversize", default_value_t = false)]
+    pub truncate_oversize: bool,
+
+    /// Convert CRLF line endings to LF and drop a UTF-8 byte order mark; converted files are marked eol="crlf".
+    #[arg(long = "normalize-eol", default_value_t = false)]
+    pub normalize_eol: bool,
+
+    /// Leave out zero-byte files (counted as skipped in the summary).
+    #[arg(long = "skip-empty", default_value_t = false)]
+    pub skip_empty: bool,
+
+    /// Leave out lockfiles, minified bundles, source maps, single-line files over 16 KiB, and files `.gitattributes` marks linguist-generated.
+    #[arg(long = "skip-generated", default_value_t = false)]
+    pub skip_generated: bool,
+
+    /// Keep generated files even when a config file sets skip_generated.
+    #[arg(
+        long = "keep-generated",
+        default_value_t = false,
+        conflicts_with = "skip_generated"
+    )]
+    pub keep_generated: bool,
+
+    /// Stub files whose contents duplicate another file's, keeping the lexicographically first path.
+    #[arg(long = "dedup-contents", default_value_t = false)]
+    pub dedup_contents: bool,
+
+    /// List binary, oversize, and other skipped files as <skipped-file> entries in the file map.
+    #[arg(long = "list-skipped", default_value_t = false)]
+    pub list_skipped: bool,
+
+    /// Skip SHA-256 digests in the file map (faster on very large trees).
+    #[arg(long = "no-hashes", default_value_t = false)]
+    pub no_hashes: bool,
+
+    /// Restrict directory walks to these subdirectories of each directory argument (comma-separated).
+    #[arg(long = "only-dirs", value_name = "DIRS", value_delimiter = ',')]
+    pub only_dirs: Vec<String>,
+
+    /// Maximum directory depth to walk below each directory argument (0 = only explicitly named files).
+    #[arg(long = "max-depth", value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Follow symlinked files and directories while walking (cycles are skipped).
+    #[arg(long = "follow-symlinks")]
+    pub follow_symlinks: bool,
+
+    /// Include hidden files and directories (dotfiles) in directory walks.
+    #[arg(long = "hidden", default_value_t = false)]
+    pub hidden: bool,
+
+    /// Do not respect `.gitignore`, `.ignore`, `.cgignore`, or git exclude files while walking.
+    #[arg(long = "no-ignore", default_value_t = false)]
+    pub no_ignore: bool,
+
+    /// Do not respect `.cgignore` files (context-only exclusions) while walking.
+    #[arg(long = "no-cgignore", default_value_t = false)]
+    pub no_cgignore: bool,
+
+    /// Inside a git repository, gather the files `git ls-files` lists instead of walking directories.
+    #[arg(long = "git-ls-files", default_value_t = false)]
+    pub git_ls_files: bool,
+
+    /// With --git-ls-files, also gather untracked files that .gitignore does not exclude.
+    #[arg(
+        long = "git-others",
+        default_value_t = false,
+        requires = "git_ls_files"
+    )]
+    pub git_others: bool,
+
+    /// Gather only the files staged in the git index, e.g. to review a commit before making it.
+    #[arg(
+        long = "staged",
+        default_value_t = false,
+        conflicts_with = "git_ls_files"
+    )]
+    pub staged: bool,
+
+    /// With --staged, read each file's staged contents from the index rather than the working tree.
+    #[arg(long = "staged-content", default_value_t = false, requires = "staged")]
+    pub staged_content: bool,
+
+    /// Keep only files modified at or after this time: a duration back from now (7d, 12h), a date, or an RFC 3339 timestamp.
+    #[arg(long = "modified-since", value_name = "WHEN", value_parser = parse_time_bound)]
+    pub modified_since: Option<SystemTime>,
+
+    /// Keep only files modified before this time (same formats as --modified-since).
+    #[arg(long = "modified-before", value_name = "WHEN", value_parser = parse_time_bound)]
+    pub modified_before: Option<SystemTime>,
+
+    /// Threads for the parallel directory walk (default: 0, chosen automatically).
+    #[arg(long = "walk-threads", value_name = "N", default_value_t = 0)]
+    pub walk_threads: usize,
+
     /// Glob patterns to exclude files from processing.
     #[arg(long = "exclude-paths")]
     pub exclude: Vec<String>,
 
+    /// Do not apply the built-in excludes for dependency and build directories (see --print-default-excludes).
+    #[arg(long = "no-default-excludes", default_value_t = false)]
+    pub no_default_excludes: bool,
+
+    /// Print the built-in exclude patterns, one per line, and exit.
+    #[arg(long = "print-default-excludes", default_value_t = false)]
+    pub print_default_excludes: bool,
+
+    /// Exclude files by extension, with or without the leading dot (case-insensitive, e.g. `lock`, `.min.js`).
+    #[arg(long = "exclude-ext", value_name = "EXT")]
+    pub exclude_ext: Vec<String>,
+
+    /// Keep only files matching these glob patterns (applied after --exclude-paths).
+    #[arg(long = "only", value_name = "GLOB")]
+    pub only: Vec<String>,
+
+    /// Shell command that receives gathered paths on stdin (NUL-separated) and prints the ones to keep.
+    #[arg(long = "filter-cmd", value_name = "CMD")]
+    pub filter_cmd: Option<String>,
+
+    /// Seconds --filter-cmd may run before the gather is aborted.
+    #[arg(
+        long = "filter-timeout",
+        value_name = "SECS",
+        default_value_t = DEFAULT_FILTER_TIMEOUT_SECS,
+        requires = "filter_cmd"
+    )]
+    pub filter_timeout: u64,
+
+    /// Glob patterns for files listed in the file map (tokens, size) without their contents.
+    #[arg(long = "stub-paths", value_name = "GLOB")]
+    pub stub_paths: Vec<String>,
+
+    /// Glob patterns for files moved to the front of the ordering, so they land in the earliest chunks.
+    #[arg(long = "first", value_name = "GLOB")]
+    pub first: Vec<String>,
+
+    /// Also gather the README* files of every folder that contributes a file, listed first in their folder.
+    #[arg(long = "include-readmes", default_value_t = false)]
+    pub include_readmes: bool,
+
+    /// Order files in the file map and output by path (default), tokens, size, or mtime.
+    #[arg(long = "sort", value_enum, default_value_t = SortKey::Path)]
+    pub sort: SortKey,
+
+    /// Reverse the --sort order (ties stay in path order).
+    #[arg(long = "reverse", default_value_t = false)]
+    pub reverse: bool,
+
+    /// Write paths relative to the git root (or working directory), absolute, or as bare file names.
+    /// Defaults to paths relative to the working directory.
+    #[arg(long = "path-style", value_enum, value_name = "STYLE")]
+    pub path_style: Option<PathStyle>,
+
+    /// Model preset setting both the context limit and tokenizer (e.g. gpt-4o, claude-sonnet);
+    /// --model-context and --tokenizer-model still override it.
+    #[arg(long = "model", value_name = "NAME", value_parser = models::parse_preset)]
+    pub model: Option<&'static ModelPreset>,
+
     /// Maximum token count for model context; warn if exceeded (default 200000).
     #[arg(long = "model-context")]
     pub model_context: Option<usize>,
@@ -67,6 +395,38 @@ pub struct Cli {
     )]
     pub no_model_context: bool,
 
+    /// Skip files whose contents exceed N tokens (with --truncate-oversize, keep their first lines up to N tokens).
+    #[arg(
+        long = "max-file-tokens",
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_file_tokens: Option<usize>,
+
+    /// Warn about each file whose contents exceed N tokens.
+    #[arg(long = "warn-file-tokens", value_name = "N")]
+    pub warn_file_tokens: Option<usize>,
+
+    /// Abort with exit code 6 when more than N files would be gathered.
+    #[arg(long = "max-files", value_name = "N")]
+    pub max_files: Option<usize>,
+
+    /// With --max-files, keep the first N files in sort order instead of aborting.
+    #[arg(
+        long = "max-files-truncate",
+        default_value_t = false,
+        requires = "max_files"
+    )]
+    pub max_files_truncate: bool,
+
+    /// Exit with code 5 when the total token count exceeds --model-context.
+    #[arg(
+        long = "fail-over-budget",
+        default_value_t = false,
+        conflicts_with = "no_model_context"
+    )]
+    pub fail_over_budget: bool,
+
     /// Tokenizer model name (defaults to GPT-5.2).
     #[arg(long = "tokenizer-model")]
     pub tokenizer_model: Option<String>,
@@ -75,25 +435,311 @@ pub struct Cli {
     #[arg(short = 'c', long = "chunk-size")]
     pub chunk_size: Option<usize>,
 
-    /// Which chunk to copy/print (0-based); -1 means none.
+    /// Where files larger than one chunk are split: after any line, or preferably at blank lines, closing braces and headings.
+    #[arg(long = "split-strategy", value_enum, default_value_t = SplitStrategy::Smart)]
+    pub split_strategy: SplitStrategy,
+
+    /// Repeat the last N lines of each part of a split file at the start of the next part.
+    #[arg(long = "part-overlap", default_value_t = 0)]
+    pub part_overlap: usize,
+
+    /// Split a file into at most N parts; the rest of a file that needs more is dropped and the file marked truncated.
+    #[arg(long = "max-parts", value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_parts: Option<usize>,
+
+    /// Keep this percent of --chunk-size free in every chunk, for wrappers a chat UI adds when pasting (the header still declares the full size).
+    #[arg(long = "chunk-headroom", value_name = "PERCENT", default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..100))]
+    pub chunk_headroom: u8,
+
+    /// What --chunk-size counts: tokens, or characters or bytes for destinations with those limits (skips the tokenizer while chunking).
+    #[arg(long = "chunk-unit", value_enum, default_value_t = ChunkUnit::Tokens)]
+    pub chunk_unit: ChunkUnit,
+
+    /// When chunking, pull the rest of a folder's files into the chunk being filled, if they fit, before moving on.
+    #[arg(long = "group-by-folder", default_value_t = false)]
+    pub group_by_folder: bool,
+
+    /// Print a recommended --chunk-size and chunk count for the gathered files, then exit without output.
+    #[arg(
+        long = "suggest-chunk-size",
+        default_value_t = false,
+        conflicts_with_all = ["chunk_size", "multi_step", "dry_run", "stream", "output"]
+    )]
+    pub suggest_chunk_size: bool,
+
+    /// Chunk the output with the size --suggest-chunk-size would recommend.
+    #[arg(
+        long = "auto-chunk-size",
+        default_value_t = false,
+        conflicts_with_all = ["chunk_size", "suggest_chunk_size"]
+    )]
+    pub auto_chunk_size: bool,
+
+    /// Which chunks to copy/print (0-based), e.g. `2` or `0,2-4`; -1 means none.
     #[arg(
         short = 'k',
         long = "chunk-index",
-        value_parser = parse_chunk_index,
+        value_parser = parse_chunk_indices,
         requires = "chunk_size",
         allow_hyphen_values = true
     )]
-    pub chunk_index: Option<isize>,
+    // Fully qualified so clap parses one value into a list instead of repeating the flag
+    pub chunk_index: Option<::std::vec::Vec<RangeInclusive<usize>>>,
 
     /// Enable multi-step mode: copy only header initially; then serve files on demand (use --select or -i for TUI).
-    #[arg(short = 'm', long = "multi-step", conflicts_with = "chunk_size")]
+    /// With --chunk-size, the header's file map is split into parts under that limit.
+    #[arg(short = 'm', long = "multi-step", conflicts_with = "chunk_index")]
     pub multi_step: bool,
 
     /// Include git metadata (branch, recent commits, diff) in the header.
     #[arg(long = "git-info", default_value_t = false)]
     pub git_info: bool,
 
+    /// Leave git metadata out even when a config file sets git_info.
+    #[arg(
+        long = "no-git-info",
+        default_value_t = false,
+        conflicts_with = "git_info"
+    )]
+    pub no_git_info: bool,
+
     /// Escape XML special characters in content (default: off; attributes are always escaped when needed).
     #[arg(long = "escape-xml", default_value_t = false)]
     pub escape_xml: bool,
+
+    /// Leave content unescaped even when a config file sets escape_xml.
+    #[arg(
+        long = "no-escape-xml",
+        default_value_t = false,
+        conflicts_with = "escape_xml"
+    )]
+    pub no_escape_xml: bool,
+
+    /// Wrap file contents in CDATA sections instead of escaping them (fewer tokens than --escape-xml).
+    #[arg(long = "cdata", default_value_t = false, conflicts_with = "escape_xml")]
+    pub cdata: bool,
+
+    /// Check that the output is well-formed XML before printing or copying it; exit 7 if not.
+    #[arg(long = "validate-xml", default_value_t = false)]
+    pub validate_xml: bool,
+
+    /// Strip or replace C0 control characters (ANSI escapes, NULs) in text files; tab, newline and CR stay.
+    #[arg(long = "sanitize", value_enum, default_value_t = SanitizeMode::Keep)]
+    pub sanitize: SanitizeMode,
+
+    /// Drop line and block comments from files in known languages (Rust, C-family, JS/TS, Python, HTML, ...); others are left alone.
+    #[arg(long = "strip-comments", default_value_t = false)]
+    pub strip_comments: bool,
+
+    /// Save tokens on whitespace: strip trailing spaces, collapse blank-line runs, indent with one tab per level (Markdown and YAML are left alone).
+    #[arg(long = "squeeze", default_value_t = false)]
+    pub squeeze: bool,
+
+    /// Replace secrets (AWS keys, `api_key =` values, bearer tokens, PEM private keys, and config `redact_patterns`) with «REDACTED:rule» and report counts per file.
+    #[arg(long = "redact", default_value_t = false)]
+    pub redact: bool,
+
+    /// Prefix each line of file contents with its right-aligned 1-based line number and `: `.
+    #[arg(long = "line-numbers", default_value_t = false)]
+    pub line_numbers: bool,
+
+    /// Render the `<more>` marker without the `remaining-tokens` hint.
+    #[arg(long = "legacy-snippets", default_value_t = false)]
+    pub legacy_snippets: bool,
+
+    /// Emit the header chunk (file map and instructions) first (default) or after the content.
+    #[arg(long = "header-position", value_enum, default_value_t = HeaderPosition::First)]
+    pub header_position: HeaderPosition,
+
+    /// Leave the <file-map> out of the output; the folder-grouped contents and instructions stay.
+    #[arg(long = "no-file-map", default_value_t = false)]
+    pub no_file_map: bool,
+
+    /// Custom text for the header's <instructions> section.
+    #[arg(
+        long = "instructions",
+        value_name = "TEXT",
+        conflicts_with = "instructions_file"
+    )]
+    pub instructions: Option<String>,
+
+    /// Read the custom header instructions from a file.
+    #[arg(long = "instructions-file", value_name = "PATH")]
+    pub instructions_file: Option<PathBuf>,
+
+    /// Replace the built-in instructions with the custom text, or append it after them.
+    #[arg(long = "instructions-mode", value_enum, default_value_t = InstructionsMode::Replace)]
+    pub instructions_mode: InstructionsMode,
+
+    /// Render the output through a Jinja template instead of the built-in XML; it sees `files`, `file_map`, `chunks`, and `git`.
+    #[arg(
+        long = "template",
+        value_name = "PATH",
+        conflicts_with_all = ["multi_step", "stream", "format"]
+    )]
+    pub template: Option<PathBuf>,
+
+    /// Print, copy, and stream chunks last-to-first; chunk indices and markers keep their logical order.
+    #[arg(long = "reverse-chunk-output", default_value_t = false)]
+    pub reverse_chunk_output: bool,
+
+    /// Print a detailed report (per-extension token table) to stderr.
+    #[arg(long = "report", default_value_t = false)]
+    pub report: bool,
+
+    /// Print the run summary as one JSON object instead of the `OK ...` line
+    /// (to stderr, or stdout with `--summary-json=stdout`).
+    #[arg(
+        long = "summary-json",
+        value_enum,
+        value_name = "STREAM",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "stderr"
+    )]
+    pub summary_json: Option<SummaryTarget>,
+
+    /// List the files that would be gathered (path, bytes, tokens, split) without copying anything.
+    #[arg(
+        long = "dry-run",
+        default_value_t = false,
+        conflicts_with_all = ["multi_step", "stream", "output"]
+    )]
+    pub dry_run: bool,
+
+    /// Emit output even when no files were gathered (otherwise exit with code 4).
+    #[arg(long = "allow-empty", default_value_t = false)]
+    pub allow_empty: bool,
+
+    /// Ignore `.context-gather.toml` and the user config file.
+    #[arg(long = "no-config", default_value_t = false)]
+    pub no_config: bool,
+
+    /// Load settings from this config file instead of the discovered ones.
+    #[arg(long = "config", value_name = "PATH", conflicts_with = "no_config")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration (after flags, CG_* variables, and config files) as JSON to stderr and exit.
+    #[arg(long = "print-config", default_value_t = false)]
+    pub print_config: bool,
+
+    /// Print a built-in synthetic corpus rendered by NAME and exit (for golden tests).
+    #[arg(long = "render-fixture", value_name = "NAME", value_parser = fixtures::parse_fixture, hide = true)]
+    pub render_fixture: Option<&'static Fixture>,
+
+    /// Log more: -v adds details such as which config files were loaded, -vv debug output.
+    /// RUST_LOG overrides this.
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Log errors only and skip the summary line and notes.
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        default_value_t = false,
+        conflicts_with = "verbose"
+    )]
+    pub quiet: bool,
+
+    /// Log format on stderr: human (default) or json (one object per event, with a stable event_code).
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Human)]
+    pub log_format: LogFormat,
+
+    /// Output format: xml (default), json (one JSON object per chunk when chunking), or plain.
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Xml)]
+    pub format: OutputFormat,
+
+    /// Fence around each file's path line in --format plain.
+    #[arg(long = "plain-separator", value_name = "STR", default_value = DEFAULT_PLAIN_SEPARATOR)]
+    pub plain_separator: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_indices_accept_lists_and_ranges() {
+        assert_eq!(parse_chunk_indices("2"), Ok(vec![2..=2]));
+        assert_eq!(parse_chunk_indices("0,2-4"), Ok(vec![0..=0, 2..=4]));
+        assert_eq!(parse_chunk_indices("3, 1,1-2"), Ok(vec![1..=3]));
+        assert_eq!(parse_chunk_indices("5-6,0,2-5"), Ok(vec![0..=0, 2..=6]));
+        assert_eq!(parse_chunk_indices("-1"), Ok(vec![]));
+        // Huge ranges stay ranges
+        assert_eq!(
+            parse_chunk_indices("0-99999999999"),
+            Ok(vec![0..=99_999_999_999])
+        );
+    }
+
+    #[test]
+    fn time_bounds_accept_durations_dates_and_timestamps() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(30 * 24 * 60 * 60);
+        let ago = |secs| Ok(now - Duration::from_secs(secs));
+        assert_eq!(parse_time_bound_at("7d", now), ago(7 * 24 * 60 * 60));
+        assert_eq!(parse_time_bound_at("12h", now), ago(12 * 60 * 60));
+        assert_eq!(parse_time_bound_at("90m", now), ago(90 * 60));
+        assert_eq!(parse_time_bound_at("2w", now), ago(14 * 24 * 60 * 60));
+        assert_eq!(parse_time_bound_at("45s", now), ago(45));
+        assert_eq!(
+            parse_time_bound_at("1970-01-02T00:00:00Z", now),
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_time_bound_at("1970-01-02T01:00:00+01:00", now),
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(24 * 60 * 60))
+        );
+        let local = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_time(chrono::NaiveTime::MIN)
+            .and_local_timezone(chrono::Local)
+            .unwrap();
+        assert_eq!(parse_time_bound_at("2024-05-01", now), Ok(local.into()));
+    }
+
+    #[test]
+    fn time_bounds_reject_malformed_input() {
+        let now = SystemTime::now();
+        for bad in [
+            "",
+            "7",
+            "d",
+            "7y",
+            "-3d",
+            "2024-13-01",
+            "yesterday",
+            "999999999999999w",
+        ] {
+            assert!(
+                parse_time_bound_at(bad, now).is_err(),
+                "{bad:?} should fail"
+            );
+        }
+    }
+
+    #[test]
+    fn ext_sizes_parse_pairs_and_name_bad_ones() {
+        assert_eq!(parse_ext_size("json=32768"), Ok(("json".into(), 32768)));
+        assert_eq!(parse_ext_size(".MD=10"), Ok(("md".into(), 10)));
+        assert_eq!(parse_ext_size("min.js=5"), Ok(("min.js".into(), 5)));
+        for bad in ["json", "=10", ".=10", "json=", "json=big", "json=-1"] {
+            let err = parse_ext_size(bad).unwrap_err();
+            assert!(
+                err.contains(&format!("{bad:?}")),
+                "{err} should name {bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn chunk_indices_reject_malformed_input() {
+        for bad in ["2-", "-2", "4-2", "", "1,,2", "a", "1-b", "-1,2"] {
+            assert!(parse_chunk_indices(bad).is_err(), "{bad:?} should fail");
+        }
+        assert!(
+            parse_chunk_indices("4-2")
+                .unwrap_err()
+                .contains("start exceeds end")
+        );
+    }
 }
@@ -1,73 +1,784 @@
-use crate::cli::Cli;
+use crate::cli::{Cli, Command};
+use crate::config_env::EnvSettings;
+use crate::config_file::{self, ConfigSource, LoadedConfig};
 use crate::constants::DEFAULT_MODEL_CONTEXT;
+use crate::context::gather;
+use crate::fixtures::Fixture;
 use anyhow::Result;
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ChunkCopy {
     /// User did not specify --chunk-index; use defaults.
     Default,
     /// User specified --chunk-index -1; copy/print none.
     None,
-    /// User specified an explicit chunk index.
-    Index(usize),
+    /// User specified explicit chunk indices, as sorted inclusive ranges
+    /// that neither overlap nor touch.
+    Indices(Vec<std::ops::RangeInclusive<usize>>),
 }
 
-/// Application configuration derived from CLI arguments
-#[derive(Debug, Clone)]
+/// Output format for the assembled context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// XML-like `<shared-context>` markup.
+    #[default]
+    Xml,
+    /// A JSON object with `file_map` and `files` arrays (one object per chunk).
+    Json,
+    /// Raw contents under `===== path =====` separators, with no markup.
+    Plain,
+}
+
+impl OutputFormat {
+    /// File extension used for written output.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Xml => "xml",
+            OutputFormat::Json => "json",
+            OutputFormat::Plain => "txt",
+        }
+    }
+}
+
+/// Order of gathered files in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// Folder, then path.
+    #[default]
+    Path,
+    /// Token count, smallest first.
+    Tokens,
+    /// Byte size, smallest first.
+    Size,
+    /// Modification time, oldest first.
+    Mtime,
+}
+
+/// Where a file too large for one chunk is split into parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitStrategy {
+    /// After the last line that fits.
+    Lines,
+    /// At the best nearby blank line, closing brace or Markdown heading.
+    #[default]
+    Smart,
+}
+
+/// What `--chunk-size` counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkUnit {
+    /// Tokenizer tokens.
+    #[default]
+    Tokens,
+    /// Unicode scalar values.
+    Chars,
+    /// UTF-8 bytes.
+    Bytes,
+}
+
+impl ChunkUnit {
+    /// Plural noun for messages and attributes, e.g. `bytes`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChunkUnit::Tokens => "tokens",
+            ChunkUnit::Chars => "chars",
+            ChunkUnit::Bytes => "bytes",
+        }
+    }
+}
+
+/// How file paths are written in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathStyle {
+    /// Relative to the git repository root, or the working directory outside a repository.
+    Relative,
+    /// Absolute, canonicalized paths.
+    Absolute,
+    /// The file name alone; folders are still grouped by their relative path.
+    FileName,
+}
+
+/// Where the header (file map and instructions) goes relative to the content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderPosition {
+    #[default]
+    First,
+    Last,
+}
+
+/// Whether `--instructions` text replaces the built-in header instructions or
+/// follows them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum InstructionsMode {
+    #[default]
+    Replace,
+    Append,
+}
+
+/// What `--sanitize` does with C0 control characters other than tab,
+/// newline and carriage return in gathered text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SanitizeMode {
+    /// Remove them, along with whole ANSI escape sequences.
+    Strip,
+    /// Put U+FFFD in their place.
+    Replace,
+    /// Leave contents as read.
+    #[default]
+    Keep,
+}
+
+/// Format of warnings and other log events on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Human,
+    /// One JSON object per event, with a stable `event_code` field.
+    Json,
+}
+
+/// Stream that receives `--summary-json` output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryTarget {
+    Stderr,
+    Stdout,
+}
+
+/// Application configuration derived from CLI arguments and config files
+#[derive(Debug, Clone, Serialize)]
 pub struct Config {
     pub paths: Vec<String>,
+    /// Extra path list source (`-` for stdin).
+    pub paths_from: Option<PathBuf>,
+    /// Path given to the file read from stdin by a `-` path argument.
+    pub stdin_name: PathBuf,
     pub interactive: bool,
     pub select: bool,
+    /// Where to write the TUI selection (`-` for stdout).
+    pub emit_selection: Option<PathBuf>,
+    pub emit_selection_json: bool,
+    pub ext_group_below: usize,
+    /// Command-line arguments minus paths and selection flags, for the
+    /// reproduction hint printed after an interactive selection.
+    pub reproduce_args: Vec<String>,
     pub stream: bool,
     pub no_clipboard: bool,
+    /// Shell command that receives copied text on stdin.
+    pub clipboard_cmd: Option<String>,
     pub stdout: bool,
+    /// Write output to this file (or one file per chunk).
+    pub output: Option<PathBuf>,
+    pub force: bool,
     pub max_size: u64,
+    /// Size limits by lowercase extension, over `max_size`.
+    pub max_size_ext: BTreeMap<String, u64>,
+    pub truncate_oversize: bool,
+    pub normalize_eol: bool,
+    pub skip_empty: bool,
+    pub skip_generated: bool,
+    pub list_skipped: bool,
+    pub dedup_contents: bool,
+    pub no_hashes: bool,
+    pub walk_threads: usize,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub hidden: bool,
+    pub no_ignore: bool,
+    pub no_cgignore: bool,
+    pub git_ls_files: bool,
+    pub git_others: bool,
+    pub modified_since: Option<std::time::SystemTime>,
+    pub modified_before: Option<std::time::SystemTime>,
+    pub staged: bool,
+    pub staged_content: bool,
+    pub only_dirs: Vec<String>,
     pub exclude: Vec<String>,
+    pub exclude_ext: Vec<String>,
+    /// Apply `constants::DEFAULT_EXCLUDES` alongside `exclude`.
+    pub default_excludes: bool,
+    pub print_default_excludes: bool,
+    pub only: Vec<String>,
+    pub stub_paths: Vec<String>,
+    pub first: Vec<String>,
+    pub include_readmes: bool,
+    pub filter_cmd: Option<String>,
+    pub filter_timeout: u64,
+    pub sort: SortKey,
+    pub reverse: bool,
+    /// `None` keeps paths as gathered: relative to the working directory.
+    pub path_style: Option<PathStyle>,
     pub model_context: Option<usize>,
+    pub fail_over_budget: bool,
+    pub max_files: Option<usize>,
+    pub max_files_truncate: bool,
+    pub max_file_tokens: Option<usize>,
+    pub warn_file_tokens: Option<usize>,
     pub tokenizer_model: Option<String>,
     pub chunk_size: Option<usize>,
+    pub split_strategy: SplitStrategy,
+    pub part_overlap: usize,
+    pub max_parts: Option<usize>,
+    pub chunk_headroom: u8,
+    pub chunk_unit: ChunkUnit,
+    pub group_by_folder: bool,
+    pub suggest_chunk_size: bool,
+    /// Replace `chunk_size` with the suggested size once tokens are counted.
+    pub auto_chunk_size: bool,
     pub chunk_copy: ChunkCopy,
     /// Enable multi-step mode: copy only header initially and serve files on demand.
     pub multi_step: bool,
     pub git_info: bool,
     pub escape_xml: bool,
+    pub cdata: bool,
+    pub validate_xml: bool,
+    pub sanitize: SanitizeMode,
+    pub strip_comments: bool,
+    pub squeeze: bool,
+    pub redact: bool,
+    /// Extra `--redact` patterns by rule name, from config files.
+    pub redact_patterns: BTreeMap<String, String>,
+    pub line_numbers: bool,
+    pub legacy_snippets: bool,
+    pub header_position: HeaderPosition,
+    pub no_file_map: bool,
+    pub instructions: Option<String>,
+    pub instructions_file: Option<PathBuf>,
+    pub instructions_mode: InstructionsMode,
+    pub template: Option<PathBuf>,
+    pub reverse_chunk_output: bool,
+    pub format: OutputFormat,
+    pub plain_separator: String,
+    pub report: bool,
+    pub summary_json: Option<SummaryTarget>,
+    pub allow_empty: bool,
+    pub dry_run: bool,
+    /// Number of `-v` flags.
+    pub verbose: u8,
+    pub quiet: bool,
+    pub log_format: LogFormat,
+    pub print_config: bool,
+    #[serde(skip)]
+    pub render_fixture: Option<&'static Fixture>,
+    /// Config files that were loaded, in load order.
+    pub config_sources: Vec<ConfigSource>,
+    /// Config file keys that took effect, with the file each came from.
+    pub config_origins: Vec<(&'static str, PathBuf)>,
 }
 
 impl Config {
-    /// Parse CLI arguments into a Config
+    /// Parse CLI arguments into a Config, layered over `CG_*` environment
+    /// variables and the user and project config files (unless `--no-config`).
     pub fn from_cli() -> Result<Self> {
-        let cli = Cli::parse();
-        let paths = cli.paths.clone();
+        let raw: Vec<std::ffi::OsString> = std::env::args_os().collect();
+        let matches = Cli::command().get_matches_from(&raw);
+        let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        // Like --help, helper commands print and exit before any gathering
+        if let Some(Command::Completions { shell }) = cli.command {
+            crate::cli::write_completions(shell, &mut std::io::stdout());
+            std::process::exit(0);
+        }
+        let reproduce_args = reproduction_args(&raw, &matches);
+        let loaded = Self::load_file_config(&cli, &std::env::current_dir()?)?;
+        let env = EnvSettings::from_env()?;
+        let mut config = Self::from_parts(cli, &matches, loaded, env);
+        config.reproduce_args = reproduce_args;
+        Ok(config)
+    }
+
+    /// Resolve config files for this run: none with `--no-config`, only the
+    /// named file with `--config`, otherwise the user and `cwd` project files.
+    pub fn load_file_config(
+        cli: &Cli,
+        cwd: &Path,
+    ) -> Result<LoadedConfig> {
+        if cli.no_config {
+            Ok(LoadedConfig::default())
+        } else if let Some(path) = &cli.config {
+            config_file::load_explicit(path)
+        } else {
+            config_file::load_layered(cwd)
+        }
+    }
+
+    /// Merge parsed CLI arguments over environment and file settings:
+    /// explicit CLI values win, then `CG_*` variables, then config files.
+    pub fn from_parts(
+        cli: Cli,
+        matches: &ArgMatches,
+        loaded: LoadedConfig,
+        env: EnvSettings,
+    ) -> Self {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        // Whether the command line (or environment) decides a config key's value
+        let overridden = |key: &str| match key {
+            "paths" => given("paths") || cli.paths_from.is_some(),
+            "exclude" | "max_size_ext" | "redact_patterns" => false,
+            "chunk_size" => cli.chunk_size.is_some() || env.chunk_size.is_some() || cli.multi_step,
+            "max_size" => given("max_size") || env.max_size.is_some(),
+            "model_context" => {
+                cli.model_context.is_some()
+                    || cli.model.is_some()
+                    || cli.no_model_context
+                    || env.model_context.is_some()
+            }
+            "tokenizer_model" => {
+                cli.tokenizer_model.is_some()
+                    || cli.model.is_some()
+                    || std::env::var_os("CG_TOKENIZER_MODEL").is_some()
+            }
+            "escape_xml" => cli.escape_xml || cli.no_escape_xml,
+            "git_info" => cli.git_info || cli.no_git_info,
+            "skip_generated" => cli.skip_generated || cli.keep_generated,
+            "redact" => cli.redact,
+            other => given(other),
+        };
+        let mut config_origins = Vec::new();
+        for (i, source) in loaded.sources.iter().enumerate() {
+            let later = &loaded.sources[i + 1..];
+            for &key in &source.keys {
+                let merged = matches!(key, "exclude" | "max_size_ext" | "redact_patterns");
+                let superseded = !merged && later.iter().any(|s| s.keys.contains(&key));
+                if !superseded && !overridden(key) {
+                    config_origins.push((key, source.path.clone()));
+                }
+            }
+        }
+        let config_sources = loaded.sources;
+        let file = loaded.settings;
+        let mut exclude = file.exclude.unwrap_or_default();
+        exclude.extend(env.exclude);
+        exclude.extend(cli.exclude);
+        let max_size = match env.max_size.or(file.max_size) {
+            Some(size) if !given("max_size") => size,
+            _ => cli.max_size,
+        };
+        // Per-extension limits: the file's table, with command-line pairs on top
+        let mut max_size_ext: BTreeMap<String, u64> = file
+            .max_size_ext
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(ext, size)| Some((gather::normalize_ext(&ext)?, size)))
+            .collect();
+        max_size_ext.extend(cli.max_size_ext.iter().cloned());
+        // a configured chunk size is for chunked output; multi-step only splits
+        // its header when --chunk-size is given explicitly
+        let chunk_size = cli.chunk_size.or(env
+            .chunk_size
+            .or(file.chunk_size)
+            .filter(|_| !cli.multi_step));
+        // A --model preset fills whichever of the specific flags were not given
+        let (cli_model_context, cli_tokenizer_model) = match cli.model {
+            Some(preset) => {
+                let (context, tokenizer) = preset.fill(cli.model_context, cli.tokenizer_model);
+                (Some(context), Some(tokenizer))
+            }
+            None => (cli.model_context, cli.tokenizer_model),
+        };
+        let tokenizer_model = cli_tokenizer_model.or_else(|| {
+            file.tokenizer_model
+                .filter(|_| std::env::var_os("CG_TOKENIZER_MODEL").is_none())
+        });
+        // A path list replaces the implicit "." unless paths were also given;
+        // configured paths are the default when neither is on the command line
+        let paths = if cli.paths_from.is_some() && !given("paths") {
+            Vec::new()
+        } else if !given("paths")
+            && let Some(paths) = file.paths
+        {
+            paths
+        } else {
+            cli.paths.clone()
+        };
         let chunk_copy = match cli.chunk_index {
             None => ChunkCopy::Default,
-            Some(-1) => ChunkCopy::None,
-            Some(idx) => ChunkCopy::Index(idx as usize),
+            Some(indices) if indices.is_empty() => ChunkCopy::None,
+            Some(indices) => ChunkCopy::Indices(indices),
         };
         let model_context = if cli.no_model_context {
             None
         } else {
-            Some(cli.model_context.unwrap_or(DEFAULT_MODEL_CONTEXT))
+            Some(
+                cli_model_context
+                    .or(env.model_context)
+                    .or(file.model_context)
+                    .unwrap_or(DEFAULT_MODEL_CONTEXT),
+            )
         };
         let select = cli.select || cli.interactive;
-        let stream = cli.stream || (cli.interactive && cli.chunk_size.is_some());
-        let escape_xml = cli.escape_xml;
-        Ok(Config {
+        let stream = cli.stream || (cli.interactive && chunk_size.is_some());
+        let escape_xml = !cli.no_escape_xml && (cli.escape_xml || file.escape_xml.unwrap_or(false));
+        Config {
             paths,
+            paths_from: cli.paths_from,
+            stdin_name: cli.stdin_name,
             interactive: cli.interactive,
             select,
+            emit_selection: cli.emit_selection,
+            emit_selection_json: cli.emit_selection_json,
+            ext_group_below: cli.ext_group_below,
+            reproduce_args: Vec::new(),
             stream,
-            no_clipboard: cli.no_clipboard,
+            no_clipboard: cli.no_clipboard || env.no_clipboard,
+            clipboard_cmd: cli.clipboard_cmd.or(file.clipboard_cmd),
             stdout: cli.stdout,
-            max_size: cli.max_size,
-            exclude: cli.exclude,
+            output: cli.output,
+            force: cli.force,
+            max_size,
+            max_size_ext,
+            truncate_oversize: cli.truncate_oversize,
+            normalize_eol: cli.normalize_eol,
+            skip_empty: cli.skip_empty,
+            skip_generated: !cli.keep_generated
+                && (cli.skip_generated || file.skip_generated.unwrap_or(false)),
+            list_skipped: cli.list_skipped,
+            dedup_contents: cli.dedup_contents,
+            no_hashes: cli.no_hashes,
+            walk_threads: cli.walk_threads,
+            max_depth: cli.max_depth,
+            follow_symlinks: cli.follow_symlinks,
+            hidden: cli.hidden,
+            no_ignore: cli.no_ignore,
+            no_cgignore: cli.no_cgignore,
+            git_ls_files: cli.git_ls_files,
+            git_others: cli.git_others,
+            modified_since: cli.modified_since,
+            modified_before: cli.modified_before,
+            staged: cli.staged,
+            staged_content: cli.staged_content,
+            only_dirs: cli.only_dirs,
+            exclude,
+            exclude_ext: cli.exclude_ext,
+            default_excludes: !cli.no_default_excludes,
+            print_default_excludes: cli.print_default_excludes,
+            only: cli.only,
+            stub_paths: cli.stub_paths,
+            first: cli.first,
+            include_readmes: cli.include_readmes,
+            filter_cmd: cli.filter_cmd,
+            filter_timeout: cli.filter_timeout,
+            sort: cli.sort,
+            reverse: cli.reverse,
+            path_style: cli.path_style,
             model_context,
-            tokenizer_model: cli.tokenizer_model,
-            chunk_size: cli.chunk_size,
+            fail_over_budget: cli.fail_over_budget,
+            max_files: cli.max_files,
+            max_files_truncate: cli.max_files_truncate,
+            max_file_tokens: cli.max_file_tokens,
+            warn_file_tokens: cli.warn_file_tokens,
+            tokenizer_model,
+            chunk_size,
+            split_strategy: cli.split_strategy,
+            part_overlap: cli.part_overlap,
+            max_parts: cli.max_parts,
+            chunk_headroom: cli.chunk_headroom,
+            chunk_unit: cli.chunk_unit,
+            group_by_folder: cli.group_by_folder,
+            suggest_chunk_size: cli.suggest_chunk_size,
+            auto_chunk_size: cli.auto_chunk_size,
             chunk_copy,
             multi_step: cli.multi_step,
-            git_info: cli.git_info,
-            escape_xml,
-        })
+            git_info: !cli.no_git_info && (cli.git_info || file.git_info.unwrap_or(false)),
+            escape_xml: escape_xml && !cli.cdata,
+            cdata: cli.cdata,
+            validate_xml: cli.validate_xml,
+            sanitize: cli.sanitize,
+            strip_comments: cli.strip_comments,
+            squeeze: cli.squeeze,
+            redact: cli.redact || file.redact.unwrap_or(false),
+            redact_patterns: file.redact_patterns.unwrap_or_default(),
+            line_numbers: cli.line_numbers,
+            legacy_snippets: cli.legacy_snippets,
+            header_position: cli.header_position,
+            no_file_map: cli.no_file_map,
+            instructions: cli.instructions,
+            instructions_file: cli.instructions_file,
+            instructions_mode: cli.instructions_mode,
+            template: cli.template,
+            reverse_chunk_output: cli.reverse_chunk_output,
+            format: cli.format,
+            plain_separator: cli.plain_separator,
+            report: cli.report,
+            summary_json: cli.summary_json,
+            allow_empty: cli.allow_empty,
+            dry_run: cli.dry_run,
+            verbose: cli.verbose,
+            quiet: cli.quiet,
+            log_format: cli.log_format,
+            print_config: cli.print_config,
+            render_fixture: cli.render_fixture,
+            config_sources,
+            config_origins,
+        }
+    }
+}
+
+/// Field name endings whose values are masked in the effective-config dump.
+const SECRET_SUFFIXES: &[&str] = &["api_key", "secret", "password"];
+
+impl Config {
+    /// The effective configuration as JSON, with secret values masked.
+    pub fn to_masked_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).expect("Config serializes to JSON");
+        mask_secrets(&mut value);
+        value
+    }
+}
+
+fn mask_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if SECRET_SUFFIXES.iter().any(|s| key.ends_with(s)) {
+                    if !field.is_null() {
+                        *field = serde_json::Value::from("***");
+                    }
+                } else {
+                    mask_secrets(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_secrets),
+        _ => {}
+    }
+}
+
+/// Drop positional paths and selection-related flags from `raw` so the rest
+/// can be replayed with `--paths-from`.
+fn reproduction_args(
+    raw: &[std::ffi::OsString],
+    matches: &ArgMatches,
+) -> Vec<String> {
+    let mut drop: Vec<usize> = Vec::new();
+    for id in ["paths", "interactive", "select", "emit_selection_json"] {
+        if matches.value_source(id) == Some(ValueSource::CommandLine)
+            && let Some(indices) = matches.indices_of(id)
+        {
+            drop.extend(indices);
+        }
+    }
+    // Options with a separate value also need their flag position dropped
+    for id in ["emit_selection", "paths_from"] {
+        if let Some(indices) = matches.indices_of(id) {
+            for idx in indices {
+                drop.push(idx);
+                let inline = raw
+                    .get(idx)
+                    .is_some_and(|a| a.to_string_lossy().starts_with("--"));
+                if !inline {
+                    drop.push(idx - 1);
+                }
+            }
+        }
+    }
+    raw.iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, _)| !drop.contains(i))
+        .map(|(_, a)| a.to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_file::FileConfig;
+    use clap::Parser;
+
+    fn write(
+        dir: &Path,
+        name: &str,
+        text: &str,
+    ) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn resolve(
+        args: &[&str],
+        cwd: &Path,
+    ) -> LoadedConfig {
+        let cli =
+            Cli::try_parse_from(std::iter::once("context-gather").chain(args.iter().copied()))
+                .unwrap();
+        Config::load_file_config(&cli, cwd).unwrap()
+    }
+
+    #[test]
+    fn discovers_project_config_by_default() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let project = write(
+            dir.path(),
+            config_file::PROJECT_CONFIG_FILE,
+            "chunk_size = 7\n",
+        );
+
+        let loaded = resolve(&[], dir.path());
+        assert_eq!(loaded.settings.chunk_size, Some(7));
+        let last = loaded.sources.last().unwrap();
+        assert_eq!(last.path, project);
+        assert_eq!(last.keys, vec!["chunk_size"]);
+    }
+
+    #[test]
+    fn explicit_config_replaces_discovery() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        write(
+            dir.path(),
+            config_file::PROJECT_CONFIG_FILE,
+            "chunk_size = 7\n",
+        );
+        let other = write(dir.path(), "other.toml", "paths = [\"src\"]\n");
+
+        let loaded = resolve(&["--config", other.to_str().unwrap()], dir.path());
+        assert_eq!(loaded.settings.chunk_size, None);
+        assert_eq!(loaded.settings.paths, Some(vec!["src".to_string()]));
+        assert_eq!(loaded.sources.len(), 1);
+        assert_eq!(loaded.sources[0].path, other);
+
+        let cli = Cli::try_parse_from(["context-gather", "--config", "missing.toml"]).unwrap();
+        assert!(Config::load_file_config(&cli, dir.path()).is_err());
+    }
+
+    #[test]
+    fn no_config_loads_nothing() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        write(
+            dir.path(),
+            config_file::PROJECT_CONFIG_FILE,
+            "not valid = [\n",
+        );
+
+        let loaded = resolve(&["--no-config"], dir.path());
+        assert!(loaded.sources.is_empty());
+        assert!(loaded.settings.keys().is_empty());
+    }
+
+    fn merged(
+        args: &[&str],
+        file: FileConfig,
+        env: EnvSettings,
+    ) -> Config {
+        let argv: Vec<&str> = std::iter::once("context-gather")
+            .chain(args.iter().copied())
+            .collect();
+        let matches = Cli::command().try_get_matches_from(&argv).unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        let loaded = LoadedConfig {
+            settings: file,
+            sources: Vec::new(),
+        };
+        Config::from_parts(cli, &matches, loaded, env)
+    }
+
+    #[test]
+    fn env_overrides_files_and_cli_overrides_env() {
+        let file = FileConfig {
+            chunk_size: Some(100),
+            max_size: Some(200),
+            model_context: Some(300),
+            exclude: Some(vec!["file/**".into()]),
+            ..FileConfig::default()
+        };
+        let env = EnvSettings {
+            chunk_size: Some(1000),
+            max_size: Some(2000),
+            model_context: Some(3000),
+            exclude: vec!["env/**".into()],
+            no_clipboard: true,
+        };
+
+        let config = merged(&[], file.clone(), env.clone());
+        assert_eq!(config.chunk_size, Some(1000));
+        assert_eq!(config.max_size, 2000);
+        assert_eq!(config.model_context, Some(3000));
+        assert_eq!(config.exclude, vec!["file/**", "env/**"]);
+        assert!(config.no_clipboard);
+
+        let config = merged(
+            &[
+                "-c",
+                "10",
+                "--max-size",
+                "20",
+                "--model-context",
+                "30",
+                "--exclude-paths",
+                "cli/**",
+            ],
+            file.clone(),
+            env,
+        );
+        assert_eq!(config.chunk_size, Some(10));
+        assert_eq!(config.max_size, 20);
+        assert_eq!(config.model_context, Some(30));
+        assert_eq!(config.exclude, vec!["file/**", "env/**", "cli/**"]);
+
+        let config = merged(&[], file, EnvSettings::default());
+        assert_eq!(config.chunk_size, Some(100));
+        assert_eq!(config.max_size, 200);
+        assert_eq!(config.model_context, Some(300));
+        assert!(!config.no_clipboard);
+    }
+
+    #[test]
+    fn max_size_ext_merges_file_table_under_cli_pairs() {
+        let file = FileConfig {
+            max_size_ext: Some([(".JSON".to_string(), 100), ("md".to_string(), 200)].into()),
+            ..FileConfig::default()
+        };
+        let config = merged(
+            &["--max-size-ext", "json=10", "--max-size-ext", "csv=30"],
+            file,
+            EnvSettings::default(),
+        );
+        let expected: BTreeMap<String, u64> =
+            [("csv".into(), 30), ("json".into(), 10), ("md".into(), 200)].into();
+        assert_eq!(config.max_size_ext, expected);
+    }
+
+    #[test]
+    fn env_model_context_yields_to_no_model_context() {
+        let env = EnvSettings {
+            model_context: Some(3000),
+            ..EnvSettings::default()
+        };
+        let config = merged(&["--no-model-context"], FileConfig::default(), env);
+        assert_eq!(config.model_context, None);
+    }
+
+    #[test]
+    fn masked_json_hides_secret_fields() {
+        let mut value = serde_json::json!({
+            "openai_api_key": "sk-123",
+            "nested": [{ "client_secret": "abc", "unset_password": null }],
+            "tokenizer_model": "gpt-4o",
+        });
+        mask_secrets(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "openai_api_key": "***",
+                "nested": [{ "client_secret": "***", "unset_password": null }],
+                "tokenizer_model": "gpt-4o",
+            })
+        );
     }
 }
@@ -0,0 +1,133 @@
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+
+/// Settings read from `CG_*` environment variables. They apply when the
+/// matching flag is not given and win over config files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvSettings {
+    /// `CG_CHUNK_SIZE`
+    pub chunk_size: Option<usize>,
+    /// `CG_MAX_SIZE`
+    pub max_size: Option<u64>,
+    /// `CG_MODEL_CONTEXT`
+    pub model_context: Option<usize>,
+    /// `CG_EXCLUDE`, colon-separated globs.
+    pub exclude: Vec<String>,
+    /// `CG_NO_CLIPBOARD`
+    pub no_clipboard: bool,
+}
+
+impl EnvSettings {
+    /// Read settings from the process environment.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Read settings through `lookup`; unset and empty variables are ignored.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let get = |name: &str| lookup(name).filter(|v| !v.trim().is_empty());
+        let chunk_size = parse_number::<usize>("CG_CHUNK_SIZE", get("CG_CHUNK_SIZE"))?;
+        if chunk_size == Some(0) {
+            return Err(anyhow!(
+                "CG_CHUNK_SIZE must be > 0 (unset it to disable chunking)"
+            ));
+        }
+        Ok(Self {
+            chunk_size,
+            max_size: parse_number("CG_MAX_SIZE", get("CG_MAX_SIZE"))?,
+            model_context: parse_number("CG_MODEL_CONTEXT", get("CG_MODEL_CONTEXT"))?,
+            exclude: get("CG_EXCLUDE")
+                .map(|v| {
+                    v.split(':')
+                        .map(str::trim)
+                        .filter(|p| !p.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            no_clipboard: parse_bool("CG_NO_CLIPBOARD", get("CG_NO_CLIPBOARD"))?,
+        })
+    }
+}
+
+fn parse_number<T: FromStr>(
+    name: &str,
+    value: Option<String>,
+) -> Result<Option<T>> {
+    value
+        .map(|v| {
+            v.trim()
+                .parse()
+                .map_err(|_| anyhow!("{name}: invalid value {v:?} (expected a whole number)"))
+        })
+        .transpose()
+}
+
+fn parse_bool(
+    name: &str,
+    value: Option<String>,
+) -> Result<bool> {
+    let Some(v) = value else {
+        return Ok(false);
+    };
+    match v.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(anyhow!(
+            "{name}: invalid value {v:?} (expected 1/0, true/false, yes/no, or on/off)"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(vars: &[(&str, &str)]) -> Result<EnvSettings> {
+        EnvSettings::from_lookup(|name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn reads_every_variable() {
+        let env = settings(&[
+            ("CG_CHUNK_SIZE", "39000"),
+            ("CG_MAX_SIZE", " 4096 "),
+            ("CG_MODEL_CONTEXT", "128000"),
+            ("CG_EXCLUDE", "target/**::*.lock"),
+            ("CG_NO_CLIPBOARD", "Yes"),
+        ])
+        .unwrap();
+        assert_eq!(
+            env,
+            EnvSettings {
+                chunk_size: Some(39000),
+                max_size: Some(4096),
+                model_context: Some(128000),
+                exclude: vec!["target/**".into(), "*.lock".into()],
+                no_clipboard: true,
+            }
+        );
+        assert_eq!(
+            settings(&[("CG_CHUNK_SIZE", "")]).unwrap(),
+            EnvSettings::default()
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_variable() {
+        for (name, value) in [
+            ("CG_CHUNK_SIZE", "big"),
+            ("CG_CHUNK_SIZE", "0"),
+            ("CG_MAX_SIZE", "-1"),
+            ("CG_MODEL_CONTEXT", "1e6"),
+            ("CG_NO_CLIPBOARD", "maybe"),
+        ] {
+            let err = settings(&[(name, value)]).unwrap_err().to_string();
+            assert!(err.starts_with(name), "{err}");
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name of the per-project config, looked up in the current directory.
+pub const PROJECT_CONFIG_FILE: &str = ".context-gather.toml";
+
+/// Settings read from a config file. Keys mirror the `Config` fields;
+/// unknown keys are rejected.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// Default paths, used when none are given on the command line.
+    pub paths: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub chunk_size: Option<usize>,
+    pub max_size: Option<u64>,
+    /// Per-extension size limits, as a `[max_size_ext]` table.
+    pub max_size_ext: Option<BTreeMap<String, u64>>,
+    pub model_context: Option<usize>,
+    pub tokenizer_model: Option<String>,
+    pub escape_xml: Option<bool>,
+    pub git_info: Option<bool>,
+    pub skip_generated: Option<bool>,
+    pub redact: Option<bool>,
+    /// Extra `--redact` patterns by rule name, as a `[redact_patterns]` table.
+    pub redact_patterns: Option<BTreeMap<String, String>>,
+    pub clipboard_cmd: Option<String>,
+}
+
+/// A config file that was loaded, with the keys it set.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub keys: Vec<&'static str>,
+}
+
+/// Merged file settings and the files they were read from, in load order.
+#[derive(Debug, Clone, Default)]
+pub struct LoadedConfig {
+    pub settings: FileConfig,
+    pub sources: Vec<ConfigSource>,
+}
+
+impl LoadedConfig {
+    /// Layer the file at `path` over what has been loaded so far.
+    fn push(
+        &mut self,
+        path: &Path,
+    ) -> Result<()> {
+        let file = FileConfig::load(path)?;
+        self.sources.push(ConfigSource {
+            path: path.to_path_buf(),
+            keys: file.keys(),
+        });
+        self.settings = file.over(std::mem::take(&mut self.settings));
+        Ok(())
+    }
+}
+
+/// `extra`'s entries layered over `base`'s.
+fn merge_tables<V>(
+    base: Option<BTreeMap<String, V>>,
+    extra: Option<BTreeMap<String, V>>,
+) -> Option<BTreeMap<String, V>> {
+    match (base, extra) {
+        (Some(mut base), Some(extra)) => {
+            base.extend(extra);
+            Some(base)
+        }
+        (base, extra) => extra.or(base),
+    }
+}
+
+impl FileConfig {
+    /// Parse a config file, naming the file in any error.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    /// Names of the keys set in this file.
+    pub fn keys(&self) -> Vec<&'static str> {
+        [
+            ("paths", self.paths.is_some()),
+            ("exclude", self.exclude.is_some()),
+            ("chunk_size", self.chunk_size.is_some()),
+            ("max_size", self.max_size.is_some()),
+            ("max_size_ext", self.max_size_ext.is_some()),
+            ("model_context", self.model_context.is_some()),
+            ("tokenizer_model", self.tokenizer_model.is_some()),
+            ("escape_xml", self.escape_xml.is_some()),
+            ("git_info", self.git_info.is_some()),
+            ("skip_generated", self.skip_generated.is_some()),
+            ("redact", self.redact.is_some()),
+            ("redact_patterns", self.redact_patterns.is_some()),
+            ("clipboard_cmd", self.clipboard_cmd.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(key, set)| set.then_some(key))
+        .collect()
+    }
+
+    /// Layer `self` over `base`: values set here win, excludes are combined,
+    /// and `max_size_ext` and `redact_patterns` tables are merged key by key.
+    pub fn over(
+        self,
+        base: FileConfig,
+    ) -> FileConfig {
+        let exclude = match (base.exclude, self.exclude) {
+            (Some(mut base), Some(extra)) => {
+                base.extend(extra);
+                Some(base)
+            }
+            (base, extra) => extra.or(base),
+        };
+        let max_size_ext = merge_tables(base.max_size_ext, self.max_size_ext);
+        let redact_patterns = merge_tables(base.redact_patterns, self.redact_patterns);
+        FileConfig {
+            paths: self.paths.or(base.paths),
+            exclude,
+            chunk_size: self.chunk_size.or(base.chunk_size),
+            max_size: self.max_size.or(base.max_size),
+            max_size_ext,
+            model_context: self.model_context.or(base.model_context),
+            tokenizer_model: self.tokenizer_model.or(base.tokenizer_model),
+            escape_xml: self.escape_xml.or(base.escape_xml),
+            git_info: self.git_info.or(base.git_info),
+            skip_generated: self.skip_generated.or(base.skip_generated),
+            redact: self.redact.or(base.redact),
+            redact_patterns,
+            clipboard_cmd: self.clipboard_cmd.or(base.clipboard_cmd),
+        }
+    }
+}
+
+/// Location of the user-wide config: `$XDG_CONFIG_HOME/context-gather/config.toml`,
+/// falling back to `~/.config/context-gather/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("context-gather").join("config.toml"))
+}
+
+/// Load the user config, then the project config in `project_dir` on top of it.
+/// Missing files are skipped; unreadable or invalid files are errors.
+pub fn load_layered(project_dir: &Path) -> Result<LoadedConfig> {
+    let mut loaded = LoadedConfig::default();
+    let candidates = [
+        user_config_path(),
+        Some(project_dir.join(PROJECT_CONFIG_FILE)),
+    ];
+    for path in candidates.into_iter().flatten() {
+        if path.is_file() {
+            loaded.push(&path)?;
+        }
+    }
+    Ok(loaded)
+}
+
+/// Load only the config file at `path`; it must exist.
+pub fn load_explicit(path: &Path) -> Result<LoadedConfig> {
+    let mut loaded = LoadedConfig::default();
+    loaded.push(path)?;
+    Ok(loaded)
+}
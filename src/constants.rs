@@ -1,4 +1,41 @@
 // Centralized magic numbers & default values
 pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Seconds a `--filter-cmd` may run before the gather is aborted.
+pub const DEFAULT_FILTER_TIMEOUT_SECS: u64 = 30;
 pub const HEADER_VERSION: &str = "1";
 pub const DEFAULT_MODEL_CONTEXT: usize = 200_000;
+/// Extensions with fewer files than this are grouped under "other" in the TUI.
+pub const DEFAULT_EXT_GROUP_BELOW: usize = 2;
+/// Largest chunk `--suggest-chunk-size` recommends, in tokens: about what a
+/// chat box accepts in one paste.
+pub const SUGGEST_PASTE_LIMIT: usize = 100_000;
+/// `--suggest-chunk-size` notes when a bundle needs more chunks than this.
+pub const SUGGEST_MAX_CHUNKS: usize = 8;
+/// Consecutive blank REPL inputs tolerated before the prompt gives up.
+pub const MAX_EMPTY_READS: usize = 10;
+/// Dependency, build-output, and VCS directories excluded from every gather
+/// unless `--no-default-excludes` is given; `--print-default-excludes` lists them.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "**/.git/**",
+    "**/.hg/**",
+    "**/.svn/**",
+    "**/node_modules/**",
+    "**/bower_components/**",
+    "**/target/**",
+    "**/dist/**",
+    "**/.venv/**",
+    "**/venv/**",
+    "**/__pycache__/**",
+    "**/.tox/**",
+    "**/.mypy_cache/**",
+    "**/.pytest_cache/**",
+    "**/.gradle/**",
+    "**/.next/**",
+];
+/// `--skip-generated` drops files that are a single line longer than this
+/// many bytes, as minified output is.
+pub const GENERATED_LINE_BYTES: usize = 16 * 1024;
+/// Default fence around each file's path in `--format plain`.
+pub const DEFAULT_PLAIN_SEPARATOR: &str = "=====";
+/// Fence around the path of one part of a split file in `--format plain`.
+pub const PLAIN_PART_SEPARATOR: &str = "-----";
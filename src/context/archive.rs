@@ -0,0 +1,188 @@
+//! Archive arguments: a `.zip`, `.tar`, `.tar.gz` or `.tgz` path is read
+//! entry by entry into memory, and its files become candidates rooted at the
+//! archive's own path (`code.zip/src/main.rs`). Nothing is extracted to
+//! disk, so there is nothing to clean up when a run fails part way.
+//!
+//! Entries are read only up to their size limit: one whose declared size is
+//! over it is not decompressed at all, and the rest are read through
+//! `take`, so neither a forged header nor a zip bomb can exhaust memory.
+
+use crate::events;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// One regular file read out of an archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// The entry's bytes, or for one over its size limit only the first
+    /// `limit` of them with `truncate`, and none without.
+    pub bytes: Vec<u8>,
+    /// The full size: as declared, or as far as reading got past the limit.
+    pub size: u64,
+    /// Modification time recorded in the archive, if any.
+    pub mtime: Option<SystemTime>,
+}
+
+/// Whether `path` is a file named like an archive this module reads.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    [".zip", ".tar", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+        && path.is_file()
+}
+
+/// An entry name as a relative path, or `None` (with a warning) when it is
+/// absolute or climbs out with `..`.
+fn entry_path(
+    archive: &Path,
+    name: &Path,
+) -> Option<PathBuf> {
+    let mut rel = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => rel.push(part),
+            Component::CurDir => {}
+            _ => {
+                warn!(
+                    event_code = events::ARCHIVE_ENTRY_SKIPPED,
+                    path = %archive.display(),
+                    "skipping entry {} of {}: it points outside the archive",
+                    name.display(),
+                    archive.display()
+                );
+                return None;
+            }
+        }
+    }
+    (!rel.as_os_str().is_empty()).then_some(rel)
+}
+
+/// The bytes of an entry that declares `declared` bytes, read from `reader`
+/// up to `limit`; see `ArchiveEntry`. The declared size is trusted only to
+/// skip reading, never to allocate.
+fn read_entry(
+    reader: impl Read,
+    declared: u64,
+    limit: u64,
+    truncate: bool,
+) -> std::io::Result<(Vec<u8>, u64)> {
+    if declared > limit && !truncate {
+        return Ok((Vec::new(), declared));
+    }
+    let mut bytes = Vec::new();
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut bytes)?;
+    let size = declared.max(bytes.len() as u64);
+    if size > limit {
+        bytes.truncate(if truncate { limit as usize } else { 0 });
+    }
+    Ok((bytes, size))
+}
+
+/// The regular files in the archive at `path`, by their path inside it.
+/// Directories, links and entries with unsafe names are left out. Each entry
+/// is read up to `limit` of its path inside the archive, and one over it
+/// keeps its first `limit` bytes with `truncate`, else none.
+pub fn read_archive(
+    path: &Path,
+    limit: impl Fn(&Path) -> u64,
+    truncate: bool,
+) -> Result<Vec<(PathBuf, ArchiveEntry)>> {
+    let name = path.to_string_lossy().to_ascii_lowercase();
+    let file = fs::File::open(path)?;
+    let entries = if name.ends_with(".zip") {
+        read_zip(path, file, limit, truncate)
+    } else if name.ends_with(".tar") {
+        read_tar(path, file, limit, truncate)
+    } else {
+        read_tar(path, flate2::read::GzDecoder::new(file), limit, truncate)
+    };
+    entries.with_context(|| format!("cannot read archive {}", path.display()))
+}
+
+fn read_zip(
+    path: &Path,
+    file: fs::File,
+    limit: impl Fn(&Path) -> u64,
+    truncate: bool,
+) -> Result<Vec<(PathBuf, ArchiveEntry)>> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if !entry.is_file() || entry.is_symlink() {
+            continue;
+        }
+        let Some(rel) = entry_path(path, Path::new(entry.name())) else {
+            continue;
+        };
+        let mtime = entry.last_modified().and_then(|t| {
+            chrono::NaiveDate::from_ymd_opt(t.year().into(), t.month().into(), t.day().into())?
+                .and_hms_opt(t.hour().into(), t.minute().into(), t.second().into())
+                .map(|t| SystemTime::from(t.and_utc()))
+        });
+        let declared = entry.size();
+        let (bytes, size) = read_entry(&mut entry, declared, limit(&rel), truncate)?;
+        entries.push((rel, ArchiveEntry { bytes, size, mtime }));
+    }
+    Ok(entries)
+}
+
+fn read_tar(
+    path: &Path,
+    reader: impl Read,
+    limit: impl Fn(&Path) -> u64,
+    truncate: bool,
+) -> Result<Vec<(PathBuf, ArchiveEntry)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let Some(rel) = entry_path(path, &entry.path()?) else {
+            continue;
+        };
+        let mtime = entry
+            .header()
+            .mtime()
+            .ok()
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let declared = entry.size();
+        let (bytes, size) = read_entry(&mut entry, declared, limit(&rel), truncate)?;
+        entries.push((rel, ArchiveEntry { bytes, size, mtime }));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_read_no_further_than_the_limit() {
+        // Within the limit, the declared size is only a hint
+        let (bytes, size) = read_entry(&b"hello"[..], 5, 8, false).unwrap();
+        assert_eq!((bytes.as_slice(), size), (&b"hello"[..], 5));
+
+        // Over it by declaration: nothing is read
+        let (bytes, size) = read_entry(std::io::repeat(b'x'), 1 << 40, 8, false).unwrap();
+        assert_eq!((bytes.len(), size), (0, 1 << 40));
+
+        // A forged small size stops one byte past the limit
+        let (bytes, size) = read_entry(std::io::repeat(b'x'), 4, 8, false).unwrap();
+        assert_eq!((bytes.len(), size), (0, 9));
+        let (bytes, size) = read_entry(std::io::repeat(b'x'), 4, 8, true).unwrap();
+        assert_eq!((bytes.len(), size), (8, 9));
+    }
+}
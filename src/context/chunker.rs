@@ -1,17 +1,102 @@
 // Smart chunk builder: structure-aware, token-bounded
-use crate::context::types::FileContents;
-use crate::context::xml::{maybe_escape_attr, maybe_escape_text};
-use crate::tokenizer::count as count_tokens;
+use crate::config::{ChunkUnit, SplitStrategy};
+use crate::constants::SUGGEST_MAX_CHUNKS;
+use crate::context::plain;
+use crate::context::types::{FileContents, FileKind};
+use crate::context::xml::{
+    binary_placeholder, cdata_section, encoding_attr, eol_attr, lines_attr, maybe_escape_attr,
+    maybe_escape_text, truncated_attr,
+};
+use crate::events;
+use crate::tokenizer::{count as count_tokens, measure, par_map};
 use path_slash::PathExt;
+use std::collections::VecDeque;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use tracing::warn;
 
 /// Metadata for each file in the context header
+#[derive(Debug, Clone, Default)]
 pub struct FileMeta {
     pub id: usize,
     pub path: PathBuf,
     pub tokens: usize,
     pub parts: usize,
+    /// Byte size of the file on disk, or of its contents when not read from disk.
+    pub size: usize,
+    /// Last modification time (RFC 3339), if known.
+    pub mtime: Option<String>,
+    /// SHA-256 hex digest of the file on disk, if hashed.
+    pub sha256: Option<String>,
+    /// Stubbed files have no contents blocks (`parts` is 0).
+    pub stubbed: bool,
+    /// Path of the file this one duplicates (`--dedup-contents`); always stubbed.
+    pub duplicate_of: Option<PathBuf>,
+    pub external: bool,
+    pub via_readme: bool,
+    pub priority: bool,
+    /// A binary attachment rendered as a placeholder (`size` is its byte size).
+    pub binary: bool,
+    /// Cut to `--max-size` by `--truncate-oversize` (`size` is the full size),
+    /// or after its last part by `--max-parts` (`tokens` counts the parts kept).
+    pub truncated: bool,
+    /// Encoding the file was decoded from, when not UTF-8.
+    pub encoding: Option<String>,
+    /// Had CRLF line endings before `--normalize-eol`.
+    pub crlf: bool,
+    /// The lines kept by a `path:start-end` argument.
+    pub lines: Option<crate::context::types::LineRange>,
+    /// Tokens before content transformations; `None` when the file is unchanged.
+    pub original_tokens: Option<usize>,
+    /// Index of the rendered chunk holding each part, in part order; empty
+    /// until the chunks are final, and for files without contents blocks.
+    pub chunks: Vec<usize>,
+}
+
+fn meta_for(
+    file_id: usize,
+    file: &FileContents,
+    tokens: usize,
+    parts: usize,
+) -> FileMeta {
+    FileMeta {
+        id: file_id,
+        path: file.path.clone(),
+        tokens,
+        parts: if file.stubbed { 0 } else { parts },
+        size: match (&file.kind, file.size) {
+            (FileKind::Binary { size, .. }, _) => *size as usize,
+            (FileKind::Text, Some(size)) => size as usize,
+            (FileKind::Text, None) => file.contents.len(),
+        },
+        mtime: file.mtime.clone(),
+        sha256: file.sha256.clone(),
+        stubbed: file.stubbed,
+        duplicate_of: file.duplicate_of.clone(),
+        external: file.external,
+        via_readme: file.via_readme,
+        priority: file.priority,
+        truncated: file.truncated,
+        encoding: file.encoding.clone(),
+        crlf: file.crlf,
+        lines: file.lines,
+        binary: matches!(file.kind, FileKind::Binary { .. }),
+        original_tokens: file.original_tokens,
+        chunks: Vec::new(),
+    }
+}
+
+/// Token count of a file's rendered contents, reusing the stashed raw count
+/// when the contents are not escaped.
+fn content_tokens(
+    file: &FileContents,
+    contents: &str,
+    escape_xml: bool,
+) -> usize {
+    match file.tokens {
+        Some(tokens) if !escape_xml => tokens,
+        _ => count_tokens(contents),
+    }
 }
 
 /// Represents one chunk body (file-contents blocks only; wrappers are added later).
@@ -19,12 +104,32 @@ pub struct Chunk {
     pub index: usize,
     pub xml: String,
     pub tokens: usize,
+    /// The blocks in the chunk, in order.
+    pub files: Vec<FileRef>,
+}
+
+/// One file, or one part of a file, placed in a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileRef {
+    /// Index of the source file in the input slice.
+    pub file_id: usize,
+    /// `(part, total)` when the file was split across blocks.
+    pub part: Option<(usize, usize)>,
+    pub tokens: usize,
 }
 
 /// Represents one file block inside a chunk
 pub struct FileBlock {
     pub xml: String,
     pub tokens: usize,
+    /// Index of the source file in the input slice.
+    pub file_id: usize,
+    /// `(part, total)` when the file was split across blocks.
+    pub part: Option<(usize, usize)>,
+    /// Block body without the `<file-contents>` wrapper.
+    pub body: String,
+    /// Leading lines of `body` repeated from the previous part.
+    pub overlap: usize,
 }
 
 /// Represents a chunk body prior to wrapper rendering
@@ -33,32 +138,85 @@ pub struct ChunkBody {
     pub tokens: usize,
 }
 
+impl ChunkBody {
+    /// The files and parts of files in this chunk, in order.
+    pub fn files(&self) -> Vec<FileRef> {
+        self.blocks
+            .iter()
+            .map(|b| FileRef {
+                file_id: b.file_id,
+                part: b.part,
+                tokens: b.tokens,
+            })
+            .collect()
+    }
+}
+
+/// Record in `metas` which chunk each file's blocks landed in, where
+/// `bodies[0]` is rendered as chunk `first_index`.
+pub fn assign_chunks(
+    metas: &mut [FileMeta],
+    bodies: &[ChunkBody],
+    first_index: usize,
+) {
+    for meta in metas.iter_mut() {
+        meta.chunks.clear();
+    }
+    for (idx, body) in bodies.iter().enumerate() {
+        for block in &body.blocks {
+            metas[block.file_id].chunks.push(first_index + idx);
+        }
+    }
+}
+
 /// Build metadata for files without chunking or splitting.
 pub fn build_file_meta(
     files: &[FileContents],
     escape_xml: bool,
 ) -> Vec<FileMeta> {
-    files
-        .iter()
-        .enumerate()
-        .map(|(file_id, file)| {
-            let contents = maybe_escape_text(&file.contents, escape_xml);
-            let content_tokens = count_tokens(contents.as_ref());
-            FileMeta {
-                id: file_id,
-                path: file.path.clone(),
-                tokens: content_tokens,
-                parts: 1,
+    par_map(files, |file_id, file| {
+        let contents = maybe_escape_text(&file.contents, escape_xml);
+        let content_tokens = content_tokens(file, contents.as_ref(), escape_xml);
+        meta_for(file_id, file, content_tokens, 1)
+    })
+}
+
+/// How file blocks are wrapped.
+#[derive(Debug, Clone, Copy)]
+enum BlockStyle<'a> {
+    /// `<file-contents>` elements, with bodies escaped or in CDATA sections.
+    Xml { escape_xml: bool, cdata: bool },
+    /// `--format plain` path markers fenced by `separator`.
+    Plain { separator: &'a str },
+}
+
+impl BlockStyle<'_> {
+    fn escape_xml(self) -> bool {
+        matches!(
+            self,
+            BlockStyle::Xml {
+                escape_xml: true,
+                ..
             }
-        })
-        .collect()
+        )
+    }
 }
 
 fn wrap_file(
-    path: &Path,
+    file: &FileContents,
     body: &str,
-    escape_xml: bool,
+    style: BlockStyle,
 ) -> String {
+    let path = file.path.as_path();
+    let (escape_xml, cdata) = match style {
+        BlockStyle::Xml { escape_xml, cdata } => (escape_xml, cdata),
+        BlockStyle::Plain { separator } => return plain::file_block(path, body, separator),
+    };
+    let body = if cdata {
+        cdata_section(body)
+    } else {
+        body.to_string()
+    };
     let filename = path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -77,20 +235,60 @@ fn wrap_file(
     let path_attr = maybe_escape_attr(&path_str, escape_xml);
     let filename_attr = maybe_escape_attr(&filename, escape_xml);
     let folder_attr = maybe_escape_attr(&folder_display, escape_xml);
+    if file.is_empty() {
+        return format!(
+            "    <file-contents path=\"{path_attr}\" name=\"{filename_attr}\" folder=\"{folder_attr}\" empty=\"true\"/>\n"
+        );
+    }
     format!(
-        "    <file-contents path=\"{}\" name=\"{}\" folder=\"{}\">\n{}\n    </file-contents>\n",
-        path_attr, filename_attr, folder_attr, body
+        "    <file-contents path=\"{}\" name=\"{}\" folder=\"{}\"{}{}{}{}>\n{}\n    </file-contents>\n",
+        path_attr,
+        filename_attr,
+        folder_attr,
+        encoding_attr(file),
+        eol_attr(file),
+        lines_attr(file),
+        truncated_attr(file),
+        body
     )
 }
 
+/// How a part's body relates to its neighbours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PartMarks {
+    /// Leading lines repeated from the previous part.
+    pub(crate) overlap: usize,
+    /// The body stops mid-line and the next part carries on with that line.
+    pub(crate) wrapped: bool,
+    /// The file is cut after this part by `--max-parts`.
+    pub(crate) truncated: bool,
+}
+
 // Wrap a sub-part of a file into its own XML block
 fn wrap_part(
-    path: &Path,
+    file: &FileContents,
     idx: usize,
     total: usize,
+    marks: PartMarks,
     body: &str,
-    escape_xml: bool,
+    style: BlockStyle,
 ) -> String {
+    let path = file.path.as_path();
+    let (escape_xml, cdata) = match style {
+        BlockStyle::Xml { escape_xml, cdata } => (escape_xml, cdata),
+        BlockStyle::Plain { .. } => {
+            return plain::part_block(path, idx, total, marks, body);
+        }
+    };
+    // Parts end with a newline, or break a line that goes on in the next
+    // part; keep the closing tag on its own line
+    let body = if cdata {
+        cdata_section(body) + "\n"
+    } else if marks.wrapped {
+        format!("{body}\n")
+    } else {
+        body.to_string()
+    };
     let filename = path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -109,64 +307,368 @@ fn wrap_part(
     let path_attr = maybe_escape_attr(&path_str, escape_xml);
     let filename_attr = maybe_escape_attr(&filename, escape_xml);
     let folder_attr = maybe_escape_attr(&folder_display, escape_xml);
+    let overlap_attr = if marks.overlap > 0 {
+        format!(" overlap=\"{}\"", marks.overlap)
+    } else {
+        String::new()
+    };
+    let wrapped_attr = if marks.wrapped {
+        " wrapped=\"true\""
+    } else {
+        ""
+    };
+    let truncated_attr = if marks.truncated {
+        " truncated=\"true\""
+    } else {
+        truncated_attr(file)
+    };
     format!(
-        "    <file-contents path=\"{}\" name=\"{}\" folder=\"{}\" part=\"{}/{}\">\n{}    </file-contents>\n",
-        path_attr, filename_attr, folder_attr, idx, total, body
+        "    <file-contents path=\"{}\" name=\"{}\" folder=\"{}\" part=\"{}/{}\"{}{}{}{}{}{}>\n{}    </file-contents>\n",
+        path_attr,
+        filename_attr,
+        folder_attr,
+        idx,
+        total,
+        encoding_attr(file),
+        eol_attr(file),
+        lines_attr(file),
+        overlap_attr,
+        wrapped_attr,
+        truncated_attr,
+        body
     )
 }
 
+/// A `--line-numbers` line without its `N: ` prefix.
+fn without_line_number(line: &str) -> &str {
+    let trimmed = line.trim_start_matches(' ');
+    match trimmed.split_once(": ") {
+        Some((num, rest)) if !num.is_empty() && num.bytes().all(|b| b.is_ascii_digit()) => rest,
+        _ => line,
+    }
+}
+
+/// How good a place the point after `lines[idx]` is to end a part: after a
+/// `}` line at zero indentation or before a Markdown heading (2), after a
+/// blank line (1), or not a boundary (0).
+fn boundary_score(
+    lines: &[String],
+    idx: usize,
+) -> u8 {
+    let line = without_line_number(lines[idx].trim_end_matches(['\n', '\r']));
+    let next_is_heading = lines.get(idx + 1).is_some_and(|next| {
+        let next = without_line_number(next);
+        let hashes = next.len() - next.trim_start_matches('#').len();
+        (1..=6).contains(&hashes) && next[hashes..].starts_with(' ')
+    });
+    let closes_block = line.trim_end().ends_with('}') && !line.starts_with([' ', '\t']);
+    if next_is_heading || closes_block {
+        2
+    } else if line.trim().is_empty() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Which of the lines in a full part to end it after, as an index into
+/// `ends`: the best-scored boundary in the back half of the part (the later
+/// one on a tie), else the last boundary anywhere, else `None` for a hard
+/// cut after the last line.
+fn pick_cut(
+    ends: &[(usize, usize)],
+    scores: &[u8],
+) -> Option<usize> {
+    let candidates = || (0..ends.len()).filter(|&k| scores[ends[k].0] > 0);
+    candidates()
+        .filter(|&k| 2 * (k + 1) >= ends.len())
+        .max_by_key(|&k| (scores[ends[k].0], k))
+        .or_else(|| candidates().next_back())
+}
+
+/// Bytes per unit of budget after which a line is first checked on a
+/// prefix of that size.
+const LONG_LINE_PROBE: usize = 8;
+
+/// One part of a split file.
+struct Part {
+    body: String,
+    /// Where `body` lies in the file's lines joined back together.
+    span: Range<usize>,
+    marks: PartMarks,
+}
+
+/// Byte length of the longest prefix of `text` that `fits`, cut at a char
+/// boundary; 0 when not even one character does. Assumes a prefix that does
+/// not fit has no longer prefix that does. Probes doubling prefixes before
+/// bisecting, so a huge `text` costs about as much as the piece that fits.
+fn longest_fitting_prefix(
+    text: &str,
+    fits: impl Fn(&str) -> bool,
+) -> usize {
+    let cuts: Vec<usize> = text.char_indices().map(|(i, c)| i + c.len_utf8()).collect();
+    let mut hi = 1;
+    while hi < cuts.len() && fits(&text[..cuts[hi - 1]]) {
+        hi *= 2;
+    }
+    let lo = hi / 2;
+    let hi = hi.min(cuts.len());
+    match lo + cuts[lo..hi].partition_point(|&cut| fits(&text[..cut])) {
+        0 => 0,
+        n => cuts[n - 1],
+    }
+}
+
+/// Move `cut` back to the start of an entity it would split, so each piece
+/// of an escaped line stays well formed.
+fn entity_safe_cut(
+    text: &str,
+    cut: usize,
+) -> usize {
+    match text[..cut].rfind('&') {
+        Some(amp) if !text[amp..cut].contains(';') => amp,
+        _ => cut,
+    }
+}
+
+/// How many lines of a run fit after the text a part already holds, where
+/// `size_with(n)` measures the part with the first `n` of them added,
+/// `overhead` is `size_with(0)` and `sizes` are cached sizes of the lines
+/// on their own. Tokens do not add up exactly across lines, so the sizes
+/// only say how far to try next; each try is measured. Lines at and after
+/// the first one too big to fit alone are left to the caller.
+fn lines_that_fit(
+    sizes: &[usize],
+    overhead: usize,
+    max_tokens: usize,
+    size_with: impl Fn(usize) -> usize,
+) -> usize {
+    let limit = sizes
+        .iter()
+        .position(|&size| size > max_tokens)
+        .unwrap_or(sizes.len());
+    let fits = |n: usize| size_with(n) <= max_tokens;
+    // `lo` lines fit and measure `used`; `hi` lines do not fit
+    let (mut lo, mut used) = (0, overhead);
+    let mut hi = loop {
+        let mut n = lo;
+        let mut estimate = used;
+        while n < limit && estimate.saturating_add(sizes[n]) <= max_tokens {
+            estimate += sizes[n];
+            n += 1;
+        }
+        if n == lo {
+            if n == limit {
+                return lo;
+            }
+            n += 1;
+        }
+        let size = size_with(n);
+        if size > max_tokens {
+            break n;
+        }
+        lo = n;
+        used = size;
+    };
+    // Overshoots are usually by a line or two
+    let mut step = 1;
+    while hi - lo > step && !fits(hi - step) {
+        hi -= step;
+        step *= 2;
+    }
+    if hi - lo > step {
+        lo = hi - step;
+    }
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// Split `lines` into `total_parts`-numbered parts. With `line_sizes` (each
+/// line measured in `options.unit`), runs of lines are placed with a few
+/// measurements per part; without, each line is tried on its own, which
+/// re-measures the growing part once per line.
 fn split_with_total(
     lines: &[String],
-    path: &Path,
+    line_sizes: Option<&[usize]>,
+    file: &FileContents,
     max_tokens: usize,
-    escape_xml: bool,
+    style: BlockStyle,
+    options: ChunkOptions,
     total_parts: usize,
-) -> Vec<String> {
+) -> Vec<Part> {
+    let scores: Vec<u8> = match options.strategy {
+        SplitStrategy::Lines => vec![0; lines.len()],
+        SplitStrategy::Smart => (0..lines.len())
+            .map(|idx| boundary_score(lines, idx))
+            .collect(),
+    };
+    let fits = |part_idx: usize, marks: PartMarks, body: &str| {
+        let part = wrap_part(file, part_idx, total_parts, marks, body, style);
+        measure(&part, options.unit) <= max_tokens
+    };
+    // Byte offset of each line, and of the end, in the joined lines
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(lines.iter().scan(0, |end, line| {
+            *end += line.len();
+            Some(*end)
+        }))
+        .collect();
     let mut parts = Vec::new();
     let mut current = String::new();
+    // Where `current` starts in the joined lines
+    let mut start = 0usize;
+    // Line index and length of `current` after each new (not repeated) line
+    let mut ends: Vec<(usize, usize)> = Vec::new();
+    let mut overlap = 0usize;
     let mut part_idx = 1usize;
+    let mut idx = 0usize;
+    // Bytes of `lines[idx]` already emitted in wrapped parts
+    let mut offset = 0usize;
 
-    for line in lines {
-        if current.is_empty() {
-            current.push_str(line);
-            let wrapped = wrap_part(path, part_idx, total_parts, &current, escape_xml);
-            let wrapped_tokens = count_tokens(&wrapped);
-            if wrapped_tokens > max_tokens {
-                warn!(
-                    "line in {:?} exceeds chunk size {}; emitting oversize part",
-                    path, max_tokens
-                );
-                parts.push(std::mem::take(&mut current));
-                part_idx += 1;
+    while idx < lines.len() {
+        let marks = PartMarks {
+            overlap,
+            wrapped: false,
+            truncated: false,
+        };
+        // Set when the batch below already found `lines[idx]` does not fit
+        let mut known_full = false;
+        if offset == 0
+            && let Some(sizes) = line_sizes
+        {
+            let pending = &lines[idx..];
+            let overhead = measure(
+                &wrap_part(file, part_idx, total_parts, marks, &current, style),
+                options.unit,
+            );
+            let n = lines_that_fit(&sizes[idx..], overhead, max_tokens, |n| {
+                let mut body = current.clone();
+                body.extend(pending[..n].iter().map(String::as_str));
+                let part = wrap_part(file, part_idx, total_parts, marks, &body, style);
+                measure(&part, options.unit)
+            });
+            for line in &pending[..n] {
+                current.push_str(line);
+                ends.push((idx, current.len()));
+                idx += 1;
+            }
+            if idx == lines.len() {
+                break;
             }
-            continue;
+            known_full = sizes[idx] <= max_tokens;
         }
-
+        let line = &lines[idx][offset..];
         let prev_len = current.len();
-        current.push_str(line);
-        let wrapped = wrap_part(path, part_idx, total_parts, &current, escape_xml);
-        let wrapped_tokens = count_tokens(&wrapped);
-        if wrapped_tokens > max_tokens {
-            current.truncate(prev_len);
-            parts.push(std::mem::take(&mut current));
-            part_idx += 1;
-
+        // A line far longer than the budget (minified code) is ruled out on
+        // a prefix first, so it is not tokenized whole for every part
+        let probe = line.floor_char_boundary(max_tokens.saturating_mul(LONG_LINE_PROBE));
+        let hopeless = known_full
+            || probe < line.len() && {
+                current.push_str(&line[..probe]);
+                let probe_fits = fits(part_idx, marks, &current);
+                current.truncate(prev_len);
+                !probe_fits
+            };
+        if !hopeless {
             current.push_str(line);
-            let wrapped = wrap_part(path, part_idx, total_parts, &current, escape_xml);
-            let wrapped_tokens = count_tokens(&wrapped);
-            if wrapped_tokens > max_tokens {
-                warn!(
-                    "line in {:?} exceeds chunk size {}; emitting oversize part",
-                    path, max_tokens
-                );
-                parts.push(std::mem::take(&mut current));
+            if fits(part_idx, marks, &current) {
+                ends.push((idx, current.len()));
+                idx += 1;
+                offset = 0;
+                continue;
+            }
+            current.truncate(prev_len);
+        }
+        let last = if ends.is_empty() {
+            if overlap > 0 {
+                // Not even one new line fits after the repeated ones
+                current.clear();
+                overlap = 0;
+                start = starts[idx] + offset;
+                continue;
+            }
+            // Break the line itself, leaving its newline for the last piece
+            let wrapped = PartMarks {
+                overlap: 0,
+                wrapped: true,
+                truncated: false,
+            };
+            let text = line.strip_suffix('\n').unwrap_or(line);
+            let mut cut = longest_fitting_prefix(text, |piece| fits(part_idx, wrapped, piece));
+            if style.escape_xml() {
+                cut = entity_safe_cut(text, cut);
+            }
+            if cut > 0 {
+                parts.push(Part {
+                    body: text[..cut].to_string(),
+                    span: start..start + cut,
+                    marks: wrapped,
+                });
                 part_idx += 1;
+                offset += cut;
+                start += cut;
+                continue;
             }
+            warn!(
+                event_code = events::CHUNK_OVERSIZE,
+                "line in {:?} exceeds chunk size {}; emitting oversize part", file.path, max_tokens
+            );
+            current.push_str(line);
+            ends.push((idx, current.len()));
+            idx
+        } else {
+            // End the part at the chosen boundary, or after the last line
+            // that fit, and pick up again after it
+            let k = pick_cut(&ends, &scores).unwrap_or(ends.len() - 1);
+            current.truncate(ends[k].1);
+            ends.truncate(k + 1);
+            ends[k].0
+        };
+        let part_lines = overlap + ends.len();
+        parts.push(Part {
+            span: start..start + current.len(),
+            body: std::mem::take(&mut current),
+            marks: PartMarks {
+                overlap,
+                wrapped: false,
+                truncated: false,
+            },
+        });
+        ends.clear();
+        part_idx += 1;
+        idx = last + 1;
+        offset = 0;
+        // Seed the next part with the tail of this one, using at most half
+        // the chunk so every part still makes progress
+        overlap = options.overlap.min(part_lines);
+        while overlap > 0 {
+            current = lines[idx - overlap..idx].concat();
+            if measure(&current, options.unit) <= max_tokens / 2 {
+                break;
+            }
+            overlap -= 1;
+            current.clear();
         }
+        start = starts[idx - overlap];
     }
 
-    if !current.is_empty() {
-        parts.push(current);
+    if !ends.is_empty() {
+        parts.push(Part {
+            span: start..start + current.len(),
+            body: current,
+            marks: PartMarks {
+                overlap,
+                wrapped: false,
+                truncated: false,
+            },
+        });
     }
 
     parts
@@ -174,18 +676,52 @@ fn split_with_total(
 
 fn split_file_into_parts(
     contents: &str,
-    path: &Path,
+    file: &FileContents,
     max_tokens: usize,
-    escape_xml: bool,
-) -> Vec<String> {
+    style: BlockStyle,
+    options: ChunkOptions,
+) -> Vec<Part> {
     let lines: Vec<String> = contents
         .split('\n')
         .map(|line| format!("{line}\n"))
         .collect();
+    // Measured once for every round below. A line far past the budget is
+    // not worth tokenizing whole; it never joins a run of lines anyway
+    let line_sizes: Vec<usize> = lines
+        .iter()
+        .map(|line| {
+            if line.len() > max_tokens.saturating_mul(LONG_LINE_PROBE) {
+                usize::MAX
+            } else {
+                measure(line, options.unit)
+            }
+        })
+        .collect();
+    split_lines(&lines, Some(&line_sizes), file, max_tokens, style, options)
+}
+
+/// Split until the part count written into each part matches how many
+/// parts there turn out to be.
+fn split_lines(
+    lines: &[String],
+    line_sizes: Option<&[usize]>,
+    file: &FileContents,
+    max_tokens: usize,
+    style: BlockStyle,
+    options: ChunkOptions,
+) -> Vec<Part> {
     let mut target_parts = 1usize;
     let mut parts = Vec::new();
     for _ in 0..16 {
-        parts = split_with_total(&lines, path, max_tokens, escape_xml, target_parts);
+        parts = split_with_total(
+            lines,
+            line_sizes,
+            file,
+            max_tokens,
+            style,
+            options,
+            target_parts,
+        );
         let actual = parts.len().max(1);
         if actual == target_parts {
             return parts;
@@ -195,78 +731,237 @@ fn split_file_into_parts(
     parts
 }
 
+/// How oversize files are split into parts and blocks packed into chunks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChunkOptions {
+    /// Where parts may end.
+    pub strategy: SplitStrategy,
+    /// Lines of each part repeated at the start of the next one.
+    pub overlap: usize,
+    /// Prefer packing the rest of a folder's files next to each other.
+    pub group_by_folder: bool,
+    /// What `max_tokens` counts; block and chunk `tokens` are in this unit.
+    pub unit: ChunkUnit,
+    /// Most parts a split file may have; the rest of the file is dropped.
+    pub max_parts: Option<usize>,
+}
+
+/// One block of a planned chunk: which file, which part of it, and how big
+/// the rendered block is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedBlock {
+    /// Index of the source file in the input slice.
+    pub file_id: usize,
+    /// `(part, total)` when the file was split across blocks.
+    pub part: Option<(usize, usize)>,
+    /// Size of the rendered block, in the chunk unit.
+    pub tokens: usize,
+    /// For a part, where its body lies in the file's lines joined back
+    /// together and how it meets its neighbours.
+    piece: Option<(Range<usize>, PartMarks)>,
+}
+
+/// The blocks one chunk will hold, in order, without their text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkPlan {
+    pub blocks: Vec<PlannedBlock>,
+    pub tokens: usize,
+}
+
+/// Lays out chunk bodies without keeping any of their text: the blocks of
+/// each chunk, as `build_chunk_bodies` with the same arguments renders them.
+pub fn plan_chunk_bodies(
+    files: &[FileContents],
+    max_tokens: usize,
+    escape_xml: bool,
+    cdata: bool,
+    options: ChunkOptions,
+) -> (Vec<ChunkPlan>, Vec<FileMeta>) {
+    plan_bodies(
+        files,
+        max_tokens,
+        BlockStyle::Xml { escape_xml, cdata },
+        options,
+    )
+}
+
+/// Like `plan_chunk_bodies`, for `build_plain_chunk_bodies`.
+pub fn plan_plain_chunk_bodies(
+    files: &[FileContents],
+    max_tokens: usize,
+    separator: &str,
+    options: ChunkOptions,
+) -> (Vec<ChunkPlan>, Vec<FileMeta>) {
+    plan_bodies(files, max_tokens, BlockStyle::Plain { separator }, options)
+}
+
+/// The chunk plan behind `build_chunks`.
+pub fn plan_chunks(
+    files: &[FileContents],
+    max_tokens: usize,
+    escape_xml: bool,
+) -> Vec<ChunkPlan> {
+    plan_chunk_bodies(
+        files,
+        max_tokens,
+        escape_xml,
+        false,
+        ChunkOptions::default(),
+    )
+    .0
+}
+
 /// Builds chunk bodies and metadata for header
-/// Splits between file-contents blocks, and splits oversize files
+/// Splits between file-contents blocks, and splits oversize files where
+/// `split` says. With `cdata`, each block's body is wrapped in a CDATA section.
 pub fn build_chunk_bodies(
     files: &[FileContents],
     max_tokens: usize,
     escape_xml: bool,
+    cdata: bool,
+    options: ChunkOptions,
 ) -> (Vec<ChunkBody>, Vec<FileMeta>) {
-    let mut metas = Vec::<FileMeta>::new();
-    let mut blocks = Vec::<FileBlock>::new();
+    let style = BlockStyle::Xml { escape_xml, cdata };
+    let (plans, metas) = plan_bodies(files, max_tokens, style, options);
+    (render_plans(files, plans, style), metas)
+}
 
-    for (file_id, file) in files.iter().enumerate() {
-        let contents = maybe_escape_text(&file.contents, escape_xml);
-        let contents_str = contents.as_ref();
-        let content_tokens = count_tokens(contents_str);
-        let file_block = wrap_file(&file.path, contents_str, escape_xml);
-        let block_tokens = count_tokens(&file_block);
+/// Like `build_chunk_bodies`, but with `--format plain` blocks: each file
+/// under a `separator`-fenced path line, split files under part markers.
+pub fn build_plain_chunk_bodies(
+    files: &[FileContents],
+    max_tokens: usize,
+    separator: &str,
+    options: ChunkOptions,
+) -> (Vec<ChunkBody>, Vec<FileMeta>) {
+    let style = BlockStyle::Plain { separator };
+    let (plans, metas) = plan_bodies(files, max_tokens, style, options);
+    (render_plans(files, plans, style), metas)
+}
 
-        if max_tokens == 0 || block_tokens <= max_tokens {
-            blocks.push(FileBlock {
-                xml: file_block,
-                tokens: block_tokens,
-            });
-            metas.push(FileMeta {
-                id: file_id,
-                path: file.path.clone(),
-                tokens: content_tokens,
-                parts: 1,
-            });
-            continue;
-        }
+/// The placeholder block standing in for a binary file, if `file` is one.
+fn placeholder_block(
+    file: &FileContents,
+    style: BlockStyle,
+) -> Option<String> {
+    match style {
+        BlockStyle::Xml { escape_xml, .. } => binary_placeholder(file, "    ", escape_xml),
+        BlockStyle::Plain { separator } => plain::binary_block(file, separator),
+    }
+}
 
-        let parts = split_file_into_parts(contents_str, &file.path, max_tokens, escape_xml);
-        let parts_count = parts.len().max(1);
-        for (idx, body) in parts.iter().enumerate() {
-            let wrapped = wrap_part(&file.path, idx + 1, parts_count, body, escape_xml);
-            let wrapped_tokens = count_tokens(&wrapped);
-            if wrapped_tokens > max_tokens {
-                warn!(
-                    "file {:?} part {} exceeds chunk size {}; emitting oversize part",
-                    file.path,
-                    idx + 1,
-                    max_tokens
-                );
-            }
-            blocks.push(FileBlock {
-                xml: wrapped,
-                tokens: wrapped_tokens,
-            });
+/// The blocks of one file (none when stubbed, several when split) and its
+/// header metadata.
+fn plan_file(
+    file_id: usize,
+    file: &FileContents,
+    max_tokens: usize,
+    style: BlockStyle,
+    options: ChunkOptions,
+) -> (Vec<PlannedBlock>, FileMeta) {
+    let escape_xml = style.escape_xml();
+    let contents = maybe_escape_text(&file.contents, escape_xml);
+    let contents_str = contents.as_ref();
+    let content_tokens = content_tokens(file, contents_str, escape_xml);
+    if file.stubbed {
+        return (Vec::new(), meta_for(file_id, file, content_tokens, 0));
+    }
+    let whole = |tokens| PlannedBlock {
+        file_id,
+        part: None,
+        tokens,
+        piece: None,
+    };
+    if let Some(placeholder) = placeholder_block(file, style) {
+        let block = whole(measure(&placeholder, options.unit));
+        return (vec![block], meta_for(file_id, file, content_tokens, 1));
+    }
+    let block_tokens = measure(&wrap_file(file, contents_str, style), options.unit);
+
+    if max_tokens == 0 || block_tokens <= max_tokens {
+        return (
+            vec![whole(block_tokens)],
+            meta_for(file_id, file, content_tokens, 1),
+        );
+    }
+
+    let mut parts = split_file_into_parts(contents_str, file, max_tokens, style, options);
+    let mut dropped = 0;
+    if let Some(max_parts) = options
+        .max_parts
+        .filter(|&max| parts.len() > max && max > 0)
+    {
+        let last = &mut parts[max_parts - 1];
+        last.marks.truncated = true;
+        dropped = count_tokens(contents_str.get(last.span.end..).unwrap_or_default());
+        parts.truncate(max_parts);
+        warn!(
+            event_code = events::FILE_PARTS_TRUNCATED,
+            path = %file.path.display(),
+            "{} needs more than --max-parts {max_parts} parts; dropped the {dropped} tokens after part {max_parts}",
+            file.path.display()
+        );
+    }
+    let parts_count = parts.len().max(1);
+    let mut blocks = Vec::with_capacity(parts_count);
+    for (idx, part) in parts.into_iter().enumerate() {
+        let wrapped = wrap_part(file, idx + 1, parts_count, part.marks, &part.body, style);
+        let wrapped_tokens = measure(&wrapped, options.unit);
+        if wrapped_tokens > max_tokens {
+            warn!(
+                event_code = events::CHUNK_OVERSIZE,
+                "file {:?} part {} exceeds chunk size {}; emitting oversize part",
+                file.path,
+                idx + 1,
+                max_tokens
+            );
         }
-        metas.push(FileMeta {
-            id: file_id,
-            path: file.path.clone(),
-            tokens: content_tokens,
-            parts: parts_count,
+        blocks.push(PlannedBlock {
+            file_id,
+            part: Some((idx + 1, parts_count)),
+            tokens: wrapped_tokens,
+            piece: Some((part.span, part.marks)),
         });
     }
+    let mut meta = meta_for(
+        file_id,
+        file,
+        content_tokens.saturating_sub(dropped),
+        parts_count,
+    );
+    meta.truncated |= dropped > 0;
+    (blocks, meta)
+}
 
-    let mut chunks = Vec::<ChunkBody>::new();
-    let mut current = ChunkBody {
-        blocks: Vec::new(),
-        tokens: 0,
-    };
+fn plan_bodies(
+    files: &[FileContents],
+    max_tokens: usize,
+    style: BlockStyle,
+    options: ChunkOptions,
+) -> (Vec<ChunkPlan>, Vec<FileMeta>) {
+    // Files are planned and measured in parallel; packing stays in order
+    let per_file = par_map(files, |file_id, file| {
+        plan_file(file_id, file, max_tokens, style, options)
+    });
+    let mut metas = Vec::<FileMeta>::with_capacity(files.len());
+    let mut blocks = Vec::<PlannedBlock>::new();
+    for (file_blocks, meta) in per_file {
+        blocks.extend(file_blocks);
+        metas.push(meta);
+    }
+
+    if options.group_by_folder {
+        blocks = group_by_folder(blocks, files, max_tokens);
+    }
+
+    let mut chunks = Vec::<ChunkPlan>::new();
+    let mut current = ChunkPlan::default();
     for block in blocks {
         if max_tokens > 0
             && !current.blocks.is_empty()
             && current.tokens + block.tokens > max_tokens
         {
-            chunks.push(current);
-            current = ChunkBody {
-                blocks: Vec::new(),
-                tokens: 0,
-            };
+            chunks.push(std::mem::take(&mut current));
         }
         current.tokens += block.tokens;
         current.blocks.push(block);
@@ -278,6 +973,93 @@ pub fn build_chunk_bodies(
     (chunks, metas)
 }
 
+/// Render the blocks `plans` lay out. Sizes come from the plan, which
+/// measured the same text.
+fn render_plans(
+    files: &[FileContents],
+    plans: Vec<ChunkPlan>,
+    style: BlockStyle,
+) -> Vec<ChunkBody> {
+    let escape_xml = style.escape_xml();
+    // Lines of each split file joined back together, on first use
+    let mut joined: Vec<Option<String>> = vec![None; files.len()];
+    let mut bodies = Vec::with_capacity(plans.len());
+    for plan in plans {
+        let mut blocks = Vec::with_capacity(plan.blocks.len());
+        for planned in plan.blocks {
+            let file = &files[planned.file_id];
+            let (xml, body, overlap) = match (planned.part, planned.piece) {
+                (Some((idx, total)), Some((span, marks))) => {
+                    let lines = joined[planned.file_id].get_or_insert_with(|| {
+                        format!("{}\n", maybe_escape_text(&file.contents, escape_xml))
+                    });
+                    let body = lines[span].to_string();
+                    (
+                        wrap_part(file, idx, total, marks, &body, style),
+                        body,
+                        marks.overlap,
+                    )
+                }
+                _ => match placeholder_block(file, style) {
+                    Some(placeholder) => (placeholder, String::new(), 0),
+                    None => {
+                        let contents = maybe_escape_text(&file.contents, escape_xml);
+                        (wrap_file(file, &contents, style), contents.into_owned(), 0)
+                    }
+                },
+            };
+            blocks.push(FileBlock {
+                xml,
+                tokens: planned.tokens,
+                file_id: planned.file_id,
+                part: planned.part,
+                body,
+                overlap,
+            });
+        }
+        bodies.push(ChunkBody {
+            blocks,
+            tokens: plan.tokens,
+        });
+    }
+    bodies
+}
+
+/// Reorder `blocks` so that a folder's remaining blocks follow the last one
+/// placed instead of waiting for their turn, when they fit in the chunk
+/// being filled or a new chunk has to start anyway. Blocks of one folder keep
+/// their relative order, and packing still respects `max_tokens`.
+fn group_by_folder(
+    blocks: Vec<PlannedBlock>,
+    files: &[FileContents],
+    max_tokens: usize,
+) -> Vec<PlannedBlock> {
+    let mut pending: VecDeque<PlannedBlock> = blocks.into();
+    let mut ordered: Vec<PlannedBlock> = Vec::with_capacity(pending.len());
+    let mut used = 0usize;
+    while let Some(next) = pending.front() {
+        let fits = |block: &PlannedBlock| max_tokens == 0 || used + block.tokens <= max_tokens;
+        let pick = ordered
+            .last()
+            .map(|last| &files[last.file_id].folder)
+            .filter(|&folder| files[next.file_id].folder != *folder)
+            .and_then(|folder| {
+                pending
+                    .iter()
+                    .position(|b| files[b.file_id].folder == *folder)
+            })
+            .filter(|&idx| fits(&pending[idx]) || !fits(next))
+            .unwrap_or(0);
+        let block = pending.remove(pick).expect("pick is a pending index");
+        if used > 0 && !fits(&block) {
+            used = 0;
+        }
+        used += block.tokens;
+        ordered.push(block);
+    }
+    ordered
+}
+
 /// Builds smart chunks and metadata for header
 /// Splits between file-contents blocks, and splits oversize files
 pub fn build_chunks(
@@ -285,12 +1067,19 @@ pub fn build_chunks(
     max_tokens: usize,
     escape_xml: bool,
 ) -> (Vec<Chunk>, Vec<FileMeta>) {
-    let (bodies, metas) = build_chunk_bodies(files, max_tokens, escape_xml);
+    let (bodies, metas) = build_chunk_bodies(
+        files,
+        max_tokens,
+        escape_xml,
+        false,
+        ChunkOptions::default(),
+    );
     let chunks = bodies
         .into_iter()
         .enumerate()
         .map(|(idx, body)| Chunk {
             index: idx,
+            files: body.files(),
             xml: body.blocks.into_iter().map(|b| b.xml).collect(),
             tokens: body.tokens,
         })
@@ -298,6 +1087,62 @@ pub fn build_chunks(
     (chunks, metas)
 }
 
+/// The size chunks are built to when `headroom` percent of `chunk_size` is
+/// kept free; at least 1 while chunking is on.
+pub fn chunk_budget(
+    chunk_size: usize,
+    headroom: u8,
+) -> usize {
+    if chunk_size == 0 {
+        return 0;
+    }
+    (chunk_size - chunk_size * usize::from(headroom) / 100).max(1)
+}
+
+/// A recommended `--chunk-size` and the number of chunks it should produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSuggestion {
+    pub chunk_size: usize,
+    pub chunks: usize,
+    /// Largest chunk size considered: the smaller of 80% of the model context
+    /// and the paste limit.
+    pub ceiling: usize,
+}
+
+impl ChunkSuggestion {
+    /// More chunks than are comfortable to paste one after another.
+    pub fn too_many_chunks(&self) -> bool {
+        self.chunks > SUGGEST_MAX_CHUNKS
+    }
+}
+
+/// Suggest a chunk size for `total_tokens` of content. Chunks stay at or
+/// under `min(model_context × 0.8, paste_limit)` and are as few as that
+/// allows, then evened out so the last one is not a sliver. The size gets
+/// 10% headroom (capped at the ceiling) for wrappers and uneven splits at
+/// file boundaries, rounded up to a multiple of 1,000 tokens.
+pub fn suggest_chunk_size(
+    total_tokens: usize,
+    model_context: Option<usize>,
+    paste_limit: usize,
+) -> ChunkSuggestion {
+    const ROUND: usize = 1_000;
+    let ceiling = model_context
+        .map_or(paste_limit, |ctx| (ctx / 5 * 4).min(paste_limit))
+        .max(ROUND);
+    let chunks = total_tokens.div_ceil(ceiling).max(1);
+    let even = total_tokens.div_ceil(chunks);
+    let chunk_size = (even + even / 10)
+        .div_ceil(ROUND)
+        .saturating_mul(ROUND)
+        .clamp(ROUND, ceiling);
+    ChunkSuggestion {
+        chunk_size,
+        chunks,
+        ceiling,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -312,6 +1157,7 @@ mod tests {
             folder: PathBuf::new(),
             path: PathBuf::from("dummy.txt"),
             contents: "hello world\n".repeat(10),
+            ..Default::default()
         }];
         // Build chunks with generous limit
         let (chunks, metas) = build_chunks(&files, 1000, false);
@@ -324,4 +1170,177 @@ mod tests {
             "Sum of file.tokens should be <= total tokens ({sum_meta} <= {total_tokens})"
         );
     }
+
+    #[test]
+    fn empty_files_render_as_self_closing_tags() {
+        let files = vec![
+            FileContents {
+                path: PathBuf::from("pkg/__init__.py"),
+                folder: PathBuf::from("pkg"),
+                ..Default::default()
+            },
+            FileContents {
+                path: PathBuf::from("pkg/main.py"),
+                folder: PathBuf::from("pkg"),
+                contents: "print('hi')\n".into(),
+                ..Default::default()
+            },
+        ];
+        let (chunks, _) = build_chunks(&files, 1000, false);
+        let xml_all: String = chunks.iter().map(|c| c.xml.clone()).collect();
+        assert!(xml_all.contains(
+            "<file-contents path=\"pkg/__init__.py\" name=\"__init__.py\" folder=\"pkg\" empty=\"true\"/>\n"
+        ));
+        assert_eq!(xml_all.matches("</file-contents>").count(), 1);
+    }
+
+    #[test]
+    fn chunk_budget_keeps_headroom_free() {
+        assert_eq!(chunk_budget(1_000, 0), 1_000);
+        assert_eq!(chunk_budget(1_000, 20), 800);
+        assert_eq!(chunk_budget(333, 10), 300);
+        assert_eq!(chunk_budget(1, 99), 1);
+        assert_eq!(chunk_budget(0, 50), 0);
+    }
+
+    #[test]
+    fn small_totals_fit_one_chunk() {
+        let s = suggest_chunk_size(12_345, Some(200_000), 100_000);
+        assert_eq!(s.chunks, 1);
+        assert_eq!(s.chunk_size, 14_000);
+        assert_eq!(s.ceiling, 100_000);
+        assert!(!s.too_many_chunks());
+
+        let empty = suggest_chunk_size(0, None, 100_000);
+        assert_eq!((empty.chunks, empty.chunk_size), (1, 1_000));
+    }
+
+    #[test]
+    fn medium_totals_split_evenly_under_the_ceiling() {
+        // 80% of a 128k context (102,400) is looser than the paste limit.
+        let s = suggest_chunk_size(231_400, Some(128_000), 100_000);
+        assert_eq!(s.ceiling, 100_000);
+        assert_eq!(s.chunks, 3);
+        assert_eq!(s.chunk_size, 85_000);
+        assert!(s.chunk_size * s.chunks >= 231_400);
+
+        // A small model context wins over the paste limit.
+        let s = suggest_chunk_size(50_000, Some(32_000), 100_000);
+        assert_eq!(s.ceiling, 25_600);
+        assert_eq!(s.chunks, 2);
+        assert_eq!(s.chunk_size, 25_600);
+    }
+
+    #[test]
+    fn enormous_totals_flag_too_many_chunks() {
+        let s = suggest_chunk_size(5_000_000, None, 100_000);
+        assert_eq!(s.chunks, 50);
+        assert_eq!(s.chunk_size, 100_000);
+        assert!(s.too_many_chunks());
+
+        let s = suggest_chunk_size(usize::MAX / 2, Some(1_000_000), 100_000);
+        assert!(s.chunk_size <= s.ceiling);
+        assert!(s.too_many_chunks());
+    }
+
+    /// Parts of `contents` as `(body, overlap, wrapped)`, split with or
+    /// without the cached line sizes.
+    fn split_both_ways(
+        contents: &str,
+        max_tokens: usize,
+        escape_xml: bool,
+        options: ChunkOptions,
+    ) -> [Vec<(String, usize, bool)>; 2] {
+        let file = FileContents {
+            path: PathBuf::from("src/big.rs"),
+            contents: contents.to_string(),
+            ..Default::default()
+        };
+        let style = BlockStyle::Xml {
+            escape_xml,
+            cdata: false,
+        };
+        let contents = maybe_escape_text(contents, escape_xml);
+        let lines: Vec<String> = contents
+            .split('\n')
+            .map(|line| format!("{line}\n"))
+            .collect();
+        let cached = split_file_into_parts(&contents, &file, max_tokens, style, options);
+        let linear = split_lines(&lines, None, &file, max_tokens, style, options);
+        let joined = lines.concat();
+        for part in cached.iter().chain(&linear) {
+            assert_eq!(joined[part.span.clone()], part.body);
+        }
+        [cached, linear].map(|parts| {
+            parts
+                .into_iter()
+                .map(|part| (part.body, part.marks.overlap, part.marks.wrapped))
+                .collect()
+        })
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(64))]
+        #[test]
+        fn cached_line_sizes_keep_part_boundaries(
+            lines in proptest::collection::vec("[ a-z{}#<&]{0,40}", 1..120),
+            limit in 40usize..400,
+            overlap in 0usize..3,
+            smart: bool,
+            by_chars: bool,
+            escape_xml: bool,
+        ) {
+            let options = ChunkOptions {
+                strategy: if smart { SplitStrategy::Smart } else { SplitStrategy::Lines },
+                overlap,
+                unit: if by_chars { ChunkUnit::Chars } else { ChunkUnit::Tokens },
+                ..Default::default()
+            };
+            let [cached, linear] = split_both_ways(&lines.join("\n"), limit, escape_xml, options);
+            proptest::prop_assert_eq!(cached, linear);
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture` prints the timings.
+    #[test]
+    #[ignore = "timing comparison; slow in debug builds"]
+    fn cached_line_sizes_split_large_files_faster() {
+        use std::time::Instant;
+        let contents: String = (0..6_000)
+            .map(|i| match i % 12 {
+                0 => format!("fn handler_{i}(request: &Request) -> Response {{\n"),
+                11 => "}\n\n".to_string(),
+                _ => format!("    let value_{i} = compute(request, {i}, \"field_{i}\");\n"),
+            })
+            .collect();
+        let file = FileContents {
+            path: PathBuf::from("src/big.rs"),
+            contents: contents.clone(),
+            ..Default::default()
+        };
+        let style = BlockStyle::Xml {
+            escape_xml: false,
+            cdata: false,
+        };
+        let options = ChunkOptions::default();
+        let lines: Vec<String> = contents
+            .split('\n')
+            .map(|line| format!("{line}\n"))
+            .collect();
+
+        let started = Instant::now();
+        let cached = split_file_into_parts(&contents, &file, 4_000, style, options);
+        let cached_time = started.elapsed();
+        let started = Instant::now();
+        let linear = split_lines(&lines, None, &file, 4_000, style, options);
+        let linear_time = started.elapsed();
+        println!(
+            "{} parts: cached {cached_time:?}, line by line {linear_time:?}",
+            cached.len()
+        );
+
+        let bodies = |parts: Vec<Part>| parts.into_iter().map(|p| p.body).collect::<Vec<_>>();
+        assert_eq!(bodies(cached), bodies(linear));
+        assert!(cached_time * 5 < linear_time);
+    }
 }
@@ -0,0 +1,117 @@
+//! Telling text from binary. File bytes are text when they are UTF-8, UTF-16
+//! with a byte order mark, or windows-1252 (a superset of Latin-1) that
+//! decodes without control characters other than whitespace and escape.
+//! UTF-8 may carry a few NULs, as snapshots do, which `--sanitize` can strip;
+//! a leading sample dense with NULs is binary even when it is valid UTF-8.
+
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// How many leading bytes are sampled for NULs.
+const SAMPLE_BYTES: usize = 8 * 1024;
+
+/// Decoded text and the encoding it was decoded from, `None` for UTF-8.
+pub type Decoded = (String, Option<&'static str>);
+
+/// Whether the sample is binary for its NULs: more than a quarter of its
+/// bytes. Text with stray NULs, even a short `key\0value` line, stays text;
+/// zero-filled data and BOM-less UTF-16, which is roughly half NULs, do not.
+fn dense_nuls(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(SAMPLE_BYTES)];
+    let nuls = sample.iter().filter(|&&b| b == 0).count();
+    nuls * 4 > sample.len()
+}
+
+/// Decode text that is not UTF-8: UTF-16 with a byte order mark, or else
+/// windows-1252. `None` when the bytes look binary: malformed UTF-16, or
+/// control characters other than whitespace and escape once decoded.
+fn decode_legacy(bytes: &[u8]) -> Option<(String, &'static str)> {
+    let encoding = match Encoding::for_bom(bytes) {
+        Some((encoding, _)) if encoding != UTF_8 => encoding,
+        _ => WINDOWS_1252,
+    };
+    let bom = if encoding == WINDOWS_1252 { 0 } else { 2 };
+    let text = encoding.decode_without_bom_handling_and_without_replacement(&bytes[bom..])?;
+    let binary = text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'));
+    (!binary).then(|| (text.into_owned(), encoding.name()))
+}
+
+/// Decode `bytes` as text, or hand them back when they look binary.
+pub fn decode(bytes: Vec<u8>) -> Result<Decoded, Vec<u8>> {
+    let utf16 = matches!(Encoding::for_bom(&bytes), Some((encoding, _)) if encoding != UTF_8);
+    if !utf16 && dense_nuls(&bytes) {
+        return Err(bytes);
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok((text, None)),
+        Err(err) => {
+            let bytes = err.into_bytes();
+            match decode_legacy(&bytes) {
+                Some((text, encoding)) => Ok((text, Some(encoding))),
+                None => Err(bytes),
+            }
+        }
+    }
+}
+
+/// Whether `bytes` would be skipped as binary.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    decode(bytes.to_vec()).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_with_many_emoji_is_text() {
+        let text = "🚀🎉✨🔥💯 ship it 🙌🏽👩‍💻🇯🇵\n".repeat(400);
+        assert!(!is_binary(text.as_bytes()));
+        assert_eq!(decode(text.clone().into_bytes()), Ok((text, None)));
+    }
+
+    #[test]
+    fn png_header_is_binary() {
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        png.extend([0, 0, 0, 0x0d, b'I', b'H', b'D', b'R', 0, 0, 0x01, 0]);
+        assert!(is_binary(&png));
+        // Even without the NULs, the SUB control byte gives it away
+        assert!(is_binary(&png[..8]));
+    }
+
+    #[test]
+    fn mostly_text_with_one_nul_stays_text() {
+        let text = format!("{}\0{}", "snapshot line\n".repeat(20), "tail\n");
+        let (decoded, encoding) = decode(text.clone().into_bytes()).unwrap();
+        assert_eq!((decoded, encoding), (text, None));
+        assert!(!is_binary(b"key\0value\n"));
+    }
+
+    #[test]
+    fn dense_nuls_are_binary_even_as_valid_utf8() {
+        assert!(is_binary(&[0u8; 4096]));
+        let utf16_without_bom: Vec<u8> = "plain ascii"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert!(is_binary(&utf16_without_bom));
+        // Only the leading sample is counted
+        let mut late = "text\n".repeat(SAMPLE_BYTES / 5).into_bytes();
+        late.extend([0u8; 4096]);
+        assert!(!is_binary(&late));
+    }
+
+    #[test]
+    fn legacy_encodings_decode_with_their_name() {
+        assert_eq!(
+            decode(b"na\xefve\n".to_vec()),
+            Ok(("naïve\n".to_string(), Some("windows-1252")))
+        );
+        let utf16: Vec<u8> = [0xfe, 0xff]
+            .into_iter()
+            .chain("hé".encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        assert_eq!(decode(utf16), Ok(("hé".to_string(), Some("UTF-16BE"))));
+    }
+}
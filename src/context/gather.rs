@@ -1,44 +1,130 @@
-pub use crate::context::types::FileContents;
+pub use crate::context::types::{FileContents, FileKind, LineRange};
 
 use std::{
+    collections::{BTreeMap, HashSet},
     fs,
+    io::{BufRead, Read},
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 
+use crate::context::detect;
+use crate::events;
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use encoding_rs::{Encoding, UTF_8, UTF_16LE};
 use glob::glob;
-use ignore::WalkBuilder;
+use ignore::gitignore::Gitignore;
+use ignore::{Match, WalkBuilder, WalkState};
+use sha2::{Digest, Sha256};
+
+/// Why a candidate file was left out of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    TooLarge,
+    /// Contents over `--max-file-tokens`.
+    TooManyTokens,
+    Binary,
+    Unreadable,
+    /// Zero bytes, left out by `--skip-empty`.
+    Empty,
+    /// A lockfile, bundle, or other generated file left out by `--skip-generated`.
+    Generated,
+}
+
+impl SkipReason {
+    /// Stable `event_code` for the warning logged when a file is skipped.
+    pub fn event_code(self) -> &'static str {
+        match self {
+            SkipReason::TooLarge => events::FILE_SKIPPED_TOO_LARGE,
+            SkipReason::TooManyTokens => events::FILE_SKIPPED_TOO_MANY_TOKENS,
+            SkipReason::Binary => events::FILE_SKIPPED_BINARY,
+            SkipReason::Unreadable => events::FILE_SKIPPED_UNREADABLE,
+            SkipReason::Empty => events::FILE_SKIPPED_EMPTY,
+            SkipReason::Generated => events::FILE_SKIPPED_GENERATED,
+        }
+    }
+
+    /// The `reason` attribute of a `<skipped-file>` entry.
+    pub fn name(self) -> &'static str {
+        match self {
+            SkipReason::TooLarge => "too-large",
+            SkipReason::TooManyTokens => "too-many-tokens",
+            SkipReason::Binary => "binary",
+            SkipReason::Unreadable => "unreadable",
+            SkipReason::Empty => "empty",
+            SkipReason::Generated => "generated",
+        }
+    }
+}
+
+/// A candidate file that could not be read, with the warning shown for it.
+#[derive(Debug, Clone)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+    pub message: String,
+    /// On-disk size in bytes, when the file could still be stat'ed.
+    pub size: Option<u64>,
+}
+
+/// Error returned by `read_file` for files skipped on purpose (size, binary).
+#[derive(Debug)]
+pub struct SkipError {
+    pub reason: SkipReason,
+    pub message: String,
+}
+
+impl std::fmt::Display for SkipError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SkipError {}
 
 #[derive(Debug, Clone, Default)]
 pub struct FileCollection {
     pub files: Vec<FileContents>,
-    pub skipped: Vec<String>,
+    pub skipped: Vec<SkippedFile>,
 }
 
 pub fn expand_paths(paths: Vec<String>) -> Result<Vec<PathBuf>> {
     let mut expanded = Vec::new();
 
     for p in paths {
+        let p = if Path::new(&p).exists() {
+            p
+        } else {
+            expand_home_and_vars(&p)
+        };
         let literal_path = PathBuf::from(&p);
         if literal_path.exists() {
             expanded.push(literal_path);
             continue;
         }
 
-        // Normalize Windows path separators for glob patterns
-        let pattern = p.replace('\\', "/");
-        let pattern_results =
-            glob(&pattern).map_err(|e| anyhow!("Invalid glob pattern {}: {:?}", pattern, e))?;
-
-        // If no matches, consider it a normal path
-        let mut has_match = false;
-        for path_res in pattern_results {
-            has_match = true;
-            let path = path_res?;
-            expanded.push(path);
+        // Each brace alternative is globbed on its own; alternatives that
+        // match nothing are dropped unless none match
+        let before = expanded.len();
+        for alternative in expand_braces(&p) {
+            if Path::new(&alternative).exists() {
+                expanded.push(PathBuf::from(alternative));
+                continue;
+            }
+            // Normalize Windows path separators for glob patterns
+            let pattern = alternative.replace('\\', "/");
+            let pattern_results =
+                glob(&pattern).map_err(|e| anyhow!("Invalid glob pattern {}: {:?}", pattern, e))?;
+            for path_res in pattern_results {
+                expanded.push(path_res?);
+            }
         }
         // If it's not a valid glob or no matches found, treat as a literal path
-        if !has_match {
+        if expanded.len() == before {
             expanded.push(PathBuf::from(&p));
         }
     }
@@ -46,37 +132,373 @@ pub fn expand_paths(paths: Vec<String>) -> Result<Vec<PathBuf>> {
     Ok(expanded)
 }
 
+/// Expand `{a,b,c}` alternations in a glob pattern, zsh style, into one
+/// pattern per alternative, in order: `src/*.{rs,toml}` gives `src/*.rs`
+/// and `src/*.toml`. Groups may nest and several groups multiply out. A
+/// group without a comma, or without its closing brace, stays literal, and
+/// `\{`, `\}`, and `\,` stand for the characters themselves.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some((open, commas, close)) = first_brace_group(pattern) else {
+        return vec![unescape_braces(pattern)];
+    };
+    let (prefix, suffix) = (&pattern[..open], &pattern[close + 1..]);
+    let bounds: Vec<usize> = std::iter::once(open)
+        .chain(commas)
+        .chain(std::iter::once(close))
+        .collect();
+    bounds
+        .windows(2)
+        .flat_map(|w| expand_braces(&format!("{prefix}{}{suffix}", &pattern[w[0] + 1..w[1]])))
+        .collect()
+}
+
+/// The first brace group with alternatives: its opening brace, the commas
+/// between its alternatives, and its closing brace.
+fn first_brace_group(pattern: &str) -> Option<(usize, Vec<usize>, usize)> {
+    let bytes = pattern.as_bytes();
+    let mut open = None;
+    let mut depth = 0usize;
+    let mut commas = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if matches!(bytes.get(i + 1), Some(b'{' | b'}' | b',')) => i += 1,
+            b'{' => {
+                if depth == 0 {
+                    open = Some(i);
+                    commas.clear();
+                }
+                depth += 1;
+            }
+            b',' if depth == 1 => commas.push(i),
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 && !commas.is_empty() {
+                    return open.map(|open| (open, commas, i));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Drop the backslash from `\{`, `\}`, and `\,`.
+fn unescape_braces(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some('{' | '}' | ',') if c == '\\' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parse a newline-separated path list, trimming whitespace and skipping
+/// blank lines and `#` comments.
+pub fn read_path_list(reader: impl BufRead) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        paths.push(line.to_string());
+    }
+    Ok(paths)
+}
+
+/// Read a path list from `source`, or from stdin when `source` is `-`.
+pub fn load_path_list(source: &Path) -> Result<Vec<String>> {
+    if source == Path::new("-") {
+        return read_path_list(std::io::stdin().lock());
+    }
+    let file = fs::File::open(source)
+        .map_err(|e| anyhow!("cannot read path list {}: {e}", source.display()))?;
+    read_path_list(std::io::BufReader::new(file))
+}
+
+/// Expand a leading `~` (or, best effort, `~user`: a sibling of the home
+/// directory) and `$VAR` / `${VAR}` references in a path argument or
+/// pattern. Unset variables and unknown users are left as written, logged at
+/// debug level. Windows `%VAR%` is deliberately left alone: cmd.exe expands
+/// it before the program starts, and `%` is a legal filename character.
+pub fn expand_home_and_vars(arg: &str) -> String {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from);
+    expand_with(arg, home.as_deref(), |name| std::env::var(name).ok())
+}
+
+fn expand_with(
+    arg: &str,
+    home: Option<&Path>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> String {
+    let (prefix, rest) = match arg.strip_prefix('~') {
+        Some(after) => {
+            let end = after.find(['/', '\\']).unwrap_or(after.len());
+            let (user, rest) = after.split_at(end);
+            let dir = home.and_then(|home| match user {
+                "" => Some(home.to_path_buf()),
+                user => home
+                    .parent()
+                    .map(|homes| homes.join(user))
+                    .filter(|dir| dir.is_dir()),
+            });
+            match dir {
+                Some(dir) => (dir.to_string_lossy().into_owned(), rest),
+                None => {
+                    tracing::debug!("cannot expand ~{user} in {arg}; leaving it as written");
+                    (String::new(), arg)
+                }
+            }
+        }
+        None => (String::new(), arg),
+    };
+    let mut out = prefix;
+    let mut chars = rest.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let braced = chars.next_if(|&(_, c)| c == '{').is_some();
+        let start = at + 1 + usize::from(braced);
+        let mut end = start;
+        while let Some(&(i, c)) = chars.peek() {
+            let ok = c == '_' || c.is_ascii_alphabetic() || (i > start && c.is_ascii_digit());
+            if !ok {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        let closed = !braced || chars.next_if(|&(_, c)| c == '}').is_some();
+        let written = &rest[at..chars.peek().map_or(rest.len(), |&(i, _)| i)];
+        let name = &rest[start..end];
+        if name.is_empty() || !closed {
+            out.push_str(written);
+            continue;
+        }
+        match lookup(name) {
+            Some(value) => out.push_str(&value),
+            None => {
+                tracing::debug!("${name} is not set; leaving {written} in {arg} as written");
+                out.push_str(written);
+            }
+        }
+    }
+    out
+}
+
+/// Split a `path:start-end` or `path:start+count` argument into its path and
+/// the lines it selects; `Ok(None)` when `arg` has no range suffix. A drive
+/// letter (`C:\src\main.rs`, or `C:10-20` for a drive-relative path) is
+/// never read as one. Errors on a range that selects nothing (`:0-5`,
+/// `:9-3`, `:7+0`).
+pub fn split_line_range(arg: &str) -> Result<Option<(&str, LineRange)>> {
+    let Some((path, suffix)) = arg.rsplit_once(':') else {
+        return Ok(None);
+    };
+    let drive = path.len() == 1 && path.as_bytes()[0].is_ascii_alphabetic();
+    let Some(at) = suffix.find(['-', '+']) else {
+        return Ok(None);
+    };
+    let (first, second) = (&suffix[..at], &suffix[at + 1..]);
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if path.is_empty() || drive || !digits(first) || !digits(second) {
+        return Ok(None);
+    }
+    let invalid = || {
+        anyhow!(
+            "invalid line range in {arg}: lines count from 1 and the range must select at least one"
+        )
+    };
+    let start: usize = first.parse().map_err(|_| invalid())?;
+    let n: usize = second.parse().map_err(|_| invalid())?;
+    let end = if suffix.as_bytes()[at] == b'-' {
+        Some(n)
+    } else {
+        (start + n).checked_sub(1).filter(|_| n > 0)
+    };
+    match end {
+        Some(end) if start >= 1 && end >= start => Ok(Some((path, LineRange { start, end }))),
+        _ => Err(invalid()),
+    }
+}
+
+/// The lines of `contents` that `range` selects, with the range actually
+/// kept: one running past the last line is clamped to it. `None` when
+/// `contents` has no lines.
+pub fn slice_lines(
+    contents: &str,
+    range: LineRange,
+) -> Option<(String, LineRange)> {
+    let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let end = range.end.min(lines.len());
+    let start = range.start.min(end);
+    (end > 0).then(|| (lines[start - 1..end].concat(), LineRange { start, end }))
+}
+
+/// Ignore file for context-only exclusions, read like `.gitignore` even
+/// outside a git repository.
+pub const CGIGNORE: &str = ".cgignore";
+
+/// Options for the directory walk.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Worker threads for the parallel walker; 0 lets `ignore` choose.
+    pub threads: usize,
+    /// Maximum depth below each directory argument; `Some(0)` walks nothing,
+    /// so only explicitly named files are gathered.
+    pub max_depth: Option<usize>,
+    /// Traverse symlinked files and directories. Each canonical directory is
+    /// walked at most once, so link cycles are skipped rather than followed.
+    pub follow_links: bool,
+    /// Include hidden (dot) files and directories.
+    pub hidden: bool,
+    /// Ignore `.gitignore`, `.ignore`, `.cgignore`, and git exclude files.
+    pub no_ignore: bool,
+    /// Ignore `.cgignore` files only.
+    pub no_cgignore: bool,
+    /// List directories inside a git work tree with `git ls-files` instead
+    /// of walking them (see `Pipeline::build_candidates`).
+    pub git_ls_files: bool,
+    /// With `git_ls_files`, also list untracked files that are not ignored.
+    pub git_others: bool,
+    /// Keep only candidates modified at or after this time (see
+    /// `Pipeline::build_candidates`).
+    pub modified_since: Option<std::time::SystemTime>,
+    /// Keep only candidates modified before this time.
+    pub modified_before: Option<std::time::SystemTime>,
+}
+
 /// Returns all file paths (recursively) if any of them are directories.
 pub fn gather_all_file_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
-    let mut results = Vec::new();
+    gather_all_file_paths_with(paths, &WalkOptions::default(), &|_| {})
+}
+
+/// Walks `paths` in parallel and returns every file found, sorted and
+/// deduplicated so the result is identical to a serial walk.
+/// `on_progress` receives the running file count as entries stream in.
+pub fn gather_all_file_paths_with(
+    paths: &[PathBuf],
+    options: &WalkOptions,
+    on_progress: &(dyn Fn(usize) + Sync),
+) -> Result<Vec<PathBuf>> {
+    let results: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+    let errors: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    let visited_dirs: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
 
     for path in paths {
+        if !options.no_ignore && !options.no_cgignore && cgignored(path) {
+            tracing::debug!("skipping {} excluded by {CGIGNORE}", path.display());
+            continue;
+        }
         // Recursively gather files, letting WalkBuilder handle ignore files
-        let walker = WalkBuilder::new(path)
-            .follow_links(false) // Adjust if you want to follow symlinks
-            .standard_filters(true) // Respects hidden files and default filters
-            .add_custom_ignore_filename(".gitignore")
-            .build();
-
-        for entry_result in walker {
-            match entry_result {
-                Ok(entry) => {
-                    if entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
-                        results.push(entry.path().to_path_buf());
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("Could not process entry in {:?}: {:?}", path, e);
-                }
+        let mut builder = WalkBuilder::new(path);
+        builder
+            .follow_links(options.follow_links)
+            .standard_filters(!options.no_ignore)
+            // Set after standard_filters, which also toggles hidden-file skipping
+            .hidden(!options.hidden)
+            .threads(options.threads)
+            .max_depth(options.max_depth);
+        if !options.no_ignore {
+            builder.add_custom_ignore_filename(".gitignore");
+            if !options.no_cgignore {
+                builder.add_custom_ignore_filename(CGIGNORE);
             }
         }
+        let walker = builder.build_parallel();
+
+        walker.run(|| {
+            Box::new(|entry_result| {
+                match entry_result {
+                    Ok(entry) => {
+                        let is_dir = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+                        if options.follow_links && is_dir {
+                            let canon = dunce::canonicalize(entry.path())
+                                .unwrap_or_else(|_| entry.path().to_path_buf());
+                            let mut visited = visited_dirs.lock().expect("visited dirs poisoned");
+                            if !visited.insert(canon) {
+                                tracing::debug!(
+                                    "skipping already walked directory {:?}",
+                                    entry.path()
+                                );
+                                return WalkState::Skip;
+                            }
+                        }
+                        if entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+                            let mut found = results.lock().expect("walk results poisoned");
+                            found.push(entry.path().to_path_buf());
+                            on_progress(found.len());
+                        }
+                    }
+                    Err(e) if is_symlink_loop(&e) => {
+                        tracing::debug!("skipping symlink loop: {e}");
+                    }
+                    Err(e) => {
+                        errors
+                            .lock()
+                            .expect("walk errors poisoned")
+                            .push(format!("Could not process entry in {:?}: {:?}", path, e));
+                    }
+                }
+                WalkState::Continue
+            })
+        });
     }
 
+    for err in errors.into_inner().expect("walk errors poisoned") {
+        tracing::warn!(event_code = events::WALK_ERROR, "{err}");
+    }
+    let mut results = results.into_inner().expect("walk results poisoned");
     results.sort();
     results.dedup();
     Ok(results)
 }
 
+/// Whether a `.cgignore` above directory `dir` excludes it, or one of its
+/// parents. The walker never filters the directory it starts from, so this
+/// keeps context-only exclusions in force for directory arguments too. The
+/// nearest `.cgignore` with a matching rule decides.
+fn cgignored(dir: &Path) -> bool {
+    let Ok(dir) = dunce::canonicalize(dir) else {
+        return false;
+    };
+    dir.ancestors()
+        .skip(1)
+        .map(|ancestor| ancestor.join(CGIGNORE))
+        .filter(|file| file.is_file())
+        .find_map(|file| {
+            let (matcher, _) = Gitignore::new(&file);
+            match matcher.matched_path_or_any_parents(&dir, true) {
+                Match::None => None,
+                matched => Some(matched.is_ignore()),
+            }
+        })
+        .unwrap_or(false)
+}
+
+/// Whether a walk error is the walker refusing to re-enter an ancestor through
+/// a symlink.
+fn is_symlink_loop(err: &ignore::Error) -> bool {
+    match err {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => {
+            is_symlink_loop(err)
+        }
+        _ => false,
+    }
+}
+
 /// Reads the contents of each file path into `FileContents`, enforcing a
 /// maximum size.
 pub fn collect_file_data(
@@ -84,23 +506,76 @@ pub fn collect_file_data(
     max_size: u64,
     root: &Path,
 ) -> Result<Vec<FileContents>> {
-    Ok(collect_file_data_with_skips(file_paths, max_size, root)?.files)
+    let options = ReadOptions {
+        max_size,
+        hashes: true,
+        ..ReadOptions::default()
+    };
+    Ok(collect_file_data_with_skips(file_paths, root, &HashSet::new(), &options)?.files)
 }
 
+/// Like `collect_file_data`, but also reports skipped files and reads with
+/// `options`. Files in `explicit` (named on the command line) are read as
+/// `ReadOptions::explicit`, so binary ones become placeholders.
 pub fn collect_file_data_with_skips(
     file_paths: &[PathBuf],
-    max_size: u64,
     root: &Path,
+    explicit: &HashSet<PathBuf>,
+    options: &ReadOptions,
+) -> Result<FileCollection> {
+    collect_with(
+        file_paths,
+        |_| options.max_size,
+        |path| {
+            let options = ReadOptions {
+                explicit: explicit.contains(path),
+                ..*options
+            };
+            read_file(path, root, &options)
+        },
+    )
+}
+
+/// Read each of `file_paths` with `read`, warning about and recording the
+/// files it skips, and sort the results by folder then file name.
+/// `max_size` gives each file's limit for the truncation warning.
+pub fn collect_with(
+    file_paths: &[PathBuf],
+    max_size: impl Fn(&Path) -> u64,
+    read: impl Fn(&Path) -> Result<FileContents>,
 ) -> Result<FileCollection> {
     let mut results = Vec::new();
     let mut skipped = Vec::new();
     for path in file_paths {
-        match read_file(path, max_size, root) {
-            Ok(fc) => results.push(fc),
+        match read(path) {
+            Ok(fc) => {
+                if fc.truncated {
+                    tracing::warn!(
+                        event_code = events::FILE_TRUNCATED,
+                        path = %path.display(),
+                        "{:?} exceeds {} bytes; keeping only the first lines",
+                        path,
+                        max_size(path)
+                    );
+                }
+                results.push(fc);
+            }
             Err(e) => {
                 let message = e.to_string();
-                eprintln!("{message}");
-                skipped.push(message);
+                let reason = e
+                    .downcast_ref::<SkipError>()
+                    .map_or(SkipReason::Unreadable, |s| s.reason);
+                tracing::warn!(
+                    event_code = reason.event_code(),
+                    path = %path.display(),
+                    "{message}"
+                );
+                skipped.push(SkippedFile {
+                    path: path.clone(),
+                    reason,
+                    message,
+                    size: fs::metadata(path).ok().map(|m| m.len()),
+                });
             }
         }
     }
@@ -124,25 +599,324 @@ pub fn count_tokens(text: &str) -> usize {
     crate::tokenizer::count(text)
 }
 
-pub fn read_file(
+/// SHA-256 of `bytes` as lowercase hex.
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// An extension as `--max-size-ext` keys it: lowercase, without a leading
+/// dot. `None` when nothing is left.
+pub fn normalize_ext(ext: &str) -> Option<String> {
+    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+    (!ext.is_empty()).then_some(ext)
+}
+
+/// The size limit for `path`: the `--max-size-ext` entry for the longest
+/// extension its file name ends with (`min.js` before `js`), else `max_size`.
+pub fn max_size_for(
     path: &Path,
     max_size: u64,
+    by_ext: &BTreeMap<String, u64>,
+) -> u64 {
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_lowercase()) else {
+        return max_size;
+    };
+    by_ext
+        .iter()
+        .filter(|(ext, _)| {
+            name.strip_suffix(ext.as_str())
+                .is_some_and(|stem| stem.ends_with('.'))
+        })
+        .max_by_key(|(ext, _)| ext.len())
+        .map_or(max_size, |(_, &limit)| limit)
+}
+
+/// Where to cut the first `max_size` bytes of a longer file: after the last
+/// complete line, or at the last UTF-8 boundary when there is no newline.
+/// Bytes that are not UTF-8 before that point are left for binary detection.
+/// UTF-16 (by its byte order mark) is cut after the last newline code unit,
+/// or at the last whole code unit.
+fn truncation_cut(prefix: &[u8]) -> usize {
+    if let Some((encoding, bom)) = Encoding::for_bom(prefix)
+        && encoding != UTF_8
+    {
+        let newline = if encoding == UTF_16LE {
+            [b'\n', 0]
+        } else {
+            [0, b'\n']
+        };
+        let units = &prefix[bom..];
+        return match units.chunks_exact(2).rposition(|unit| unit == newline) {
+            Some(idx) => bom + 2 * idx + 2,
+            None => bom + units.len() / 2 * 2,
+        };
+    }
+    match prefix.iter().rposition(|&b| b == b'\n') {
+        Some(newline) => newline + 1,
+        None => match std::str::from_utf8(prefix) {
+            Ok(_) => prefix.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => prefix.len(),
+        },
+    }
+}
+
+/// The line `--truncate-oversize` appends to a cut file.
+pub fn truncation_marker(
+    shown: usize,
+    size: u64,
+) -> String {
+    format!("<!-- truncated: showing first {shown} of {size} bytes -->\n")
+}
+
+/// The line `--truncate-oversize` appends to a file cut to `--max-file-tokens`.
+pub fn token_truncation_marker(
+    shown: usize,
+    total: usize,
+) -> String {
+    format!("<!-- truncated: showing first {shown} of {total} tokens -->\n")
+}
+
+/// The longest run of whole lines from the start of `contents` that fits in
+/// `limit` tokens together with its `token_truncation_marker`, followed by
+/// that marker. `total` is the token count of all of `contents`.
+pub fn truncate_to_tokens(
+    contents: &str,
+    limit: usize,
+    total: usize,
+) -> String {
+    let ends: Vec<usize> = contents
+        .split_inclusive('\n')
+        .scan(0, |end, line| {
+            *end += line.len();
+            Some(*end)
+        })
+        .collect();
+    let with_marker = |end: usize| {
+        let shown = count_tokens(&contents[..end]);
+        (
+            shown,
+            shown + count_tokens(&token_truncation_marker(shown, total)),
+        )
+    };
+    let kept = ends.partition_point(|&end| with_marker(end).1 <= limit);
+    let end = kept.checked_sub(1).map_or(0, |last| ends[last]);
+    let (shown, _) = with_marker(end);
+    format!(
+        "{}{}",
+        &contents[..end],
+        token_truncation_marker(shown, total)
+    )
+}
+
+/// Convert CRLF line endings in `contents` to LF and drop a leading UTF-8
+/// byte order mark (`--normalize-eol`). Returns whether any CRLF was found.
+pub fn normalize_eol(contents: &mut String) -> bool {
+    if let Some(rest) = contents.strip_prefix('\u{feff}') {
+        *contents = rest.to_string();
+    }
+    let crlf = contents.contains("\r\n");
+    if crlf {
+        *contents = contents.replace("\r\n", "\n");
+    }
+    crlf
+}
+
+/// Read one file. Text that is not UTF-8 is decoded from UTF-16 or
+/// windows-1252, recording the encoding; what `detect::decode` finds binary
+/// is skipped, unless the file was named `explicit`ly, in
+/// which case it is kept as a `FileKind::Binary` placeholder with its size
+/// and SHA-256. The on-disk size and mtime are
+/// recorded for the file map, along with a SHA-256 of the bytes when
+/// `hashes` is set (binary placeholders are always hashed).
+///
+/// A file over `max_size` is skipped, or with `truncate_oversize` cut to its
+/// first `max_size` bytes (see `truncation_cut`) and marked `truncated`,
+/// with `truncation_marker` appended. Only that prefix is read, so a
+/// truncated file has no SHA-256.
+///
+/// With `eol`, text goes through `normalize_eol`; the SHA-256 and size stay
+/// those of the bytes on disk.
+pub fn read_file(
+    path: &Path,
     root: &Path,
+    options: &ReadOptions,
 ) -> Result<FileContents> {
+    let max_size = options.max_size;
     // Enforce the maximum file size
     let metadata = fs::metadata(path)?;
-    if metadata.len() > max_size {
-        return Err(anyhow!(
-            "Warning: {:?} exceeds {} bytes. Skipping.",
-            path,
-            max_size
-        ));
-    }
-    // Read the entire file into memory
-    let content_bytes = fs::read(path)?;
-    // Convert to UTF-8; treat invalid UTF-8 as binary
-    let contents = String::from_utf8(content_bytes)
-        .map_err(|_| anyhow!("Warning: {:?} appears to be a binary file. Skipping.", path))?;
+    let truncated = metadata.len() > max_size;
+    if truncated && !options.truncate_oversize {
+        return Err(too_large(path, max_size));
+    }
+    let (content_bytes, size, sha256) = if truncated {
+        let mut prefix = Vec::new();
+        fs::File::open(path)?
+            .take(max_size)
+            .read_to_end(&mut prefix)?;
+        prefix.truncate(truncation_cut(&prefix));
+        (prefix, metadata.len(), None)
+    } else {
+        // Read the entire file into memory
+        let content_bytes = fs::read(path)?;
+        let size = content_bytes.len() as u64;
+        let sha256 = options.hashes.then(|| sha256_hex(&content_bytes));
+        (content_bytes, size, sha256)
+    };
+    let loaded = Loaded {
+        bytes: content_bytes,
+        size,
+        sha256,
+        mtime: metadata.modified().ok(),
+        truncated,
+    };
+    file_contents(path, root, loaded, options, || {
+        Ok(sha256_hex(&fs::read(path)?))
+    })
+}
+
+/// How `read_file` and `read_bytes` treat a file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOptions {
+    /// Files larger than this are skipped, or cut with `truncate_oversize`.
+    pub max_size: u64,
+    /// Named on the command line: binary files become placeholders.
+    pub explicit: bool,
+    /// Record a SHA-256 of text files (off with `--no-hashes`).
+    pub hashes: bool,
+    /// Cut files over `max_size` instead of skipping them.
+    pub truncate_oversize: bool,
+    /// Convert CRLF line endings (`--normalize-eol`).
+    pub eol: bool,
+}
+
+/// Like `read_file`, for a file already in memory (an archive entry) that
+/// is reported at `path`. Size limits, truncation, hashing, decoding and
+/// binary detection are the same. `size` is the full size, which `bytes`
+/// may stop short of when it is over `max_size`: they then need only hold
+/// the part kept by `truncate_oversize`.
+pub fn read_bytes(
+    path: &Path,
+    bytes: &[u8],
+    size: u64,
+    mtime: Option<std::time::SystemTime>,
+    root: &Path,
+    options: &ReadOptions,
+) -> Result<FileContents> {
+    let truncated = size > options.max_size;
+    if truncated && !options.truncate_oversize {
+        return Err(too_large(path, options.max_size));
+    }
+    let (shown, sha256) = if truncated {
+        let prefix = &bytes[..bytes.len().min(options.max_size as usize)];
+        (&prefix[..truncation_cut(prefix)], None)
+    } else {
+        (bytes, options.hashes.then(|| sha256_hex(bytes)))
+    };
+    let whole = bytes.len() as u64 == size;
+    let loaded = Loaded {
+        bytes: shown.to_vec(),
+        size,
+        sha256,
+        mtime,
+        truncated,
+    };
+    file_contents(path, root, loaded, options, || {
+        if whole {
+            Ok(sha256_hex(bytes))
+        } else {
+            Err(too_large(path, options.max_size))
+        }
+    })
+}
+
+/// The skip error for a file over `--max-size`.
+fn too_large(
+    path: &Path,
+    max_size: u64,
+) -> anyhow::Error {
+    anyhow!(SkipError {
+        reason: SkipReason::TooLarge,
+        message: format!("Warning: {:?} exceeds {} bytes. Skipping.", path, max_size),
+    })
+}
+
+/// The bytes read for one file: all of them, or with `truncated` the prefix
+/// `truncation_cut` kept. `size` is always the full size.
+struct Loaded {
+    bytes: Vec<u8>,
+    size: u64,
+    sha256: Option<String>,
+    mtime: Option<std::time::SystemTime>,
+    truncated: bool,
+}
+
+/// Decode `loaded` into the `FileContents` for `path`. `whole_sha256` hashes
+/// the full file, for a truncated binary placeholder.
+fn file_contents(
+    path: &Path,
+    root: &Path,
+    loaded: Loaded,
+    options: &ReadOptions,
+    whole_sha256: impl FnOnce() -> Result<String>,
+) -> Result<FileContents> {
+    let Loaded {
+        bytes: content_bytes,
+        size,
+        sha256,
+        mtime,
+        truncated,
+    } = loaded;
+    let (explicit, eol) = (options.explicit, options.eol);
+    let mtime = mtime.map(|t| DateTime::<Utc>::from(t).to_rfc3339_opts(SecondsFormat::Secs, true));
+    // Convert to UTF-8, decoding other text encodings; the rest is binary
+    let shown = content_bytes.len();
+    let mut crlf = false;
+    let decoded = detect::decode(content_bytes).map(|(mut contents, encoding)| {
+        if eol {
+            crlf = normalize_eol(&mut contents);
+        }
+        (contents, encoding.map(str::to_string))
+    });
+    let (contents, encoding, kind, truncated) = match decoded {
+        Ok((mut contents, encoding)) if truncated => {
+            let marker = truncation_marker(shown, size);
+            // A cut without a newline still gets the marker on its own line
+            if !contents.is_empty() && !contents.ends_with('\n') {
+                contents.push('\n');
+            }
+            (contents + &marker, encoding, FileKind::Text, true)
+        }
+        Ok((contents, encoding)) => (contents, encoding, FileKind::Text, false),
+        // A placeholder shows no contents, so an oversize one is hashed whole
+        Err(_) if explicit && truncated => {
+            let sha256 = whole_sha256()?;
+            (
+                String::new(),
+                None,
+                FileKind::Binary { size, sha256 },
+                false,
+            )
+        }
+        Err(bytes) if explicit => {
+            let sha256 = sha256.clone().unwrap_or_else(|| sha256_hex(&bytes));
+            (
+                String::new(),
+                None,
+                FileKind::Binary { size, sha256 },
+                false,
+            )
+        }
+        Err(_) => {
+            return Err(anyhow!(SkipError {
+                reason: SkipReason::Binary,
+                message: format!("Warning: {:?} appears to be a binary file. Skipping.", path),
+            }));
+        }
+    };
     let rel_path = path.strip_prefix(root).unwrap_or(path).to_path_buf();
     let folder = rel_path
         .parent()
@@ -152,6 +926,24 @@ pub fn read_file(
         folder,
         path: rel_path,
         contents,
+        stubbed: false,
+        duplicate_of: None,
+        external: false,
+        via_readme: false,
+        priority: false,
+        truncated,
+        encoding,
+        crlf,
+        lines: None,
+        tokens: None,
+        original_tokens: None,
+        size: Some(size),
+        mtime,
+        sha256: match &kind {
+            FileKind::Binary { sha256, .. } => Some(sha256.clone()),
+            FileKind::Text => sha256,
+        },
+        kind,
     })
 }
 
@@ -160,6 +952,128 @@ mod tests {
     use super::*;
     use std::{env, fs};
 
+    #[test]
+    fn brace_alternatives_expand_in_order() {
+        assert_eq!(
+            expand_braces("src/**/*.{rs,toml}"),
+            ["src/**/*.rs", "src/**/*.toml"]
+        );
+        assert_eq!(
+            expand_braces("{src,tests}/*.{rs,md}"),
+            ["src/*.rs", "src/*.md", "tests/*.rs", "tests/*.md"]
+        );
+        assert_eq!(expand_braces("a{,b}c"), ["ac", "abc"]);
+        assert_eq!(expand_braces("plain/*.rs"), ["plain/*.rs"]);
+    }
+
+    #[test]
+    fn nested_brace_groups_expand() {
+        assert_eq!(
+            expand_braces("src/{lib,bin/{a,b}}.rs"),
+            ["src/lib.rs", "src/bin/a.rs", "src/bin/b.rs"]
+        );
+    }
+
+    #[test]
+    fn literal_and_escaped_braces_are_kept() {
+        assert_eq!(expand_braces("pages/[id]{x}.tsx"), ["pages/[id]{x}.tsx"]);
+        assert_eq!(expand_braces("unclosed{a,b"), ["unclosed{a,b"]);
+        assert_eq!(expand_braces(r"lit\{a,b\}.rs"), ["lit{a,b}.rs"]);
+        assert_eq!(expand_braces(r"{a\,b,c}"), ["a,b", "c"]);
+    }
+
+    #[test]
+    fn home_and_variables_expand() {
+        let home = Path::new("/home/dev");
+        let lookup = |name: &str| (name == "PROJ").then(|| "work/app".to_string());
+        let expand = |arg| expand_with(arg, Some(home), lookup);
+        assert_eq!(expand("~/src"), "/home/dev/src");
+        assert_eq!(expand("~"), "/home/dev");
+        assert_eq!(expand("$PROJ/src/*.rs"), "work/app/src/*.rs");
+        assert_eq!(expand("${PROJ}_old/lib"), "work/app_old/lib");
+        assert_eq!(expand("~/${PROJ}"), "/home/dev/work/app");
+        // Only a leading tilde is special
+        assert_eq!(expand("a/~/b"), "a/~/b");
+    }
+
+    #[test]
+    fn unexpandable_text_is_left_as_written() {
+        let lookup = |_: &str| None::<String>;
+        assert_eq!(expand_with("$MISSING/src", None, lookup), "$MISSING/src");
+        assert_eq!(expand_with("${MISSING}x", None, lookup), "${MISSING}x");
+        assert_eq!(expand_with("${unclosed", None, lookup), "${unclosed");
+        assert_eq!(expand_with("cost$5 $ {x}", None, lookup), "cost$5 $ {x}");
+        assert_eq!(expand_with("~/src", None, lookup), "~/src");
+        assert_eq!(
+            expand_with("~nobody-here/src", Some(Path::new("/home/dev")), lookup),
+            "~nobody-here/src"
+        );
+        // Windows-style references are never expanded
+        let lookup = |_: &str| Some("x".to_string());
+        assert_eq!(
+            expand_with("%USERPROFILE%/src", None, lookup),
+            "%USERPROFILE%/src"
+        );
+    }
+
+    #[test]
+    fn line_range_suffixes_parse() {
+        let range = |start, end| LineRange { start, end };
+        assert_eq!(
+            split_line_range("src/big.rs:100-250").unwrap(),
+            Some(("src/big.rs", range(100, 250)))
+        );
+        assert_eq!(
+            split_line_range("big.rs:100+10").unwrap(),
+            Some(("big.rs", range(100, 109)))
+        );
+        assert_eq!(
+            split_line_range("a:b.rs:3-3").unwrap(),
+            Some(("a:b.rs", range(3, 3)))
+        );
+        for plain in [
+            "src/big.rs",
+            "big.rs:12",
+            "notes:todo-list",
+            "big.rs:-5",
+            "big.rs:1-",
+        ] {
+            assert_eq!(split_line_range(plain).unwrap(), None, "{plain}");
+        }
+        for invalid in ["big.rs:0-5", "big.rs:9-3", "big.rs:7+0"] {
+            assert!(split_line_range(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn windows_drive_letters_are_not_line_ranges() {
+        assert_eq!(split_line_range(r"C:\src\main.rs").unwrap(), None);
+        assert_eq!(split_line_range("C:10-20").unwrap(), None);
+        assert_eq!(
+            split_line_range(r"C:\src\main.rs:10-20").unwrap(),
+            Some((r"C:\src\main.rs", LineRange { start: 10, end: 20 }))
+        );
+    }
+
+    #[test]
+    fn slices_clamp_to_the_last_line() {
+        let text = "one\ntwo\nthree\n";
+        let range = |start, end| LineRange { start, end };
+        assert_eq!(
+            slice_lines(text, range(2, 3)),
+            Some(("two\nthree\n".into(), range(2, 3)))
+        );
+        assert_eq!(
+            slice_lines(text, range(2, 99)),
+            Some(("two\nthree\n".into(), range(2, 3)))
+        );
+        assert_eq!(
+            slice_lines(text, range(7, 9)),
+            Some(("three\n".into(), range(3, 3)))
+        );
+        assert_eq!(slice_lines("", range(1, 2)), None);
+    }
+
     #[test]
     fn utf8_non_ascii_is_not_binary() -> anyhow::Result<()> {
         let dir = env::temp_dir();
@@ -0,0 +1,225 @@
+//! Spotting generated files for `--skip-generated`: lockfiles, minified
+//! bundles and source maps by name, minified output by shape (one line
+//! over `GENERATED_LINE_BYTES`), and whatever `.gitattributes` marks
+//! `linguist-generated`. An explicit `-linguist-generated` keeps a file the
+//! name heuristics would drop.
+
+use crate::constants::GENERATED_LINE_BYTES;
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Lockfiles written by package managers, matched by exact file name.
+const LOCKFILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "composer.lock",
+    "Gemfile.lock",
+    "Pipfile.lock",
+    "poetry.lock",
+    "uv.lock",
+    "go.sum",
+    "flake.lock",
+    "mix.lock",
+    "pubspec.lock",
+    "Podfile.lock",
+    "packages.lock.json",
+];
+
+/// File name suffixes of minified bundles.
+const MINIFIED: &[&str] = &[".min.js", ".min.mjs", ".min.css"];
+
+/// One `.gitattributes` line that sets or unsets `linguist-generated`.
+struct Rule {
+    glob: GlobMatcher,
+    /// A pattern without a `/` matches the file name at any depth.
+    basename: bool,
+    generated: bool,
+}
+
+/// The `linguist-generated` value an attribute sets, if it is that one.
+fn linguist_generated(attr: &str) -> Option<bool> {
+    match attr {
+        "linguist-generated" | "linguist-generated=true" => Some(true),
+        "-linguist-generated" | "!linguist-generated" | "linguist-generated=false" => Some(false),
+        _ => None,
+    }
+}
+
+/// The `linguist-generated` rules of a `.gitattributes` file's text.
+/// Lines with other attributes only, and patterns git would reject
+/// (negated or directory-only), are left out.
+fn parse_rules(text: &str) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let Some(pattern) = fields.next() else {
+            continue;
+        };
+        let Some(generated) = fields.rev().find_map(linguist_generated) else {
+            continue;
+        };
+        if pattern.starts_with('!') || pattern.ends_with('/') {
+            continue;
+        }
+        let basename = !pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+        if let Ok(glob) = GlobBuilder::new(pattern).literal_separator(true).build() {
+            rules.push(Rule {
+                glob: glob.compile_matcher(),
+                basename,
+                generated,
+            });
+        }
+    }
+    rules
+}
+
+/// Why a file counts as generated, for the skip message.
+pub fn name_category(name: &str) -> Option<&'static str> {
+    if LOCKFILES.contains(&name) {
+        Some("lockfile")
+    } else if MINIFIED.iter().any(|suffix| name.ends_with(suffix)) {
+        Some("minified")
+    } else if name.len() > ".map".len() && name.ends_with(".map") {
+        Some("source map")
+    } else {
+        None
+    }
+}
+
+/// Whether `text` is a single line longer than `GENERATED_LINE_BYTES`.
+pub fn single_long_line(text: &str) -> bool {
+    text.len() > GENERATED_LINE_BYTES && !text.trim_end().contains('\n')
+}
+
+/// Classifies files as generated, caching each directory's
+/// `.gitattributes` rules.
+#[derive(Default)]
+pub struct Detector {
+    rules: HashMap<PathBuf, Vec<Rule>>,
+}
+
+impl Detector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn rules_in(
+        &mut self,
+        dir: &Path,
+    ) -> &[Rule] {
+        self.rules.entry(dir.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(dir.join(".gitattributes"))
+                .map(|text| parse_rules(&text))
+                .unwrap_or_default()
+        })
+    }
+
+    /// What the `.gitattributes` files above `path` say about its
+    /// `linguist-generated` attribute. Directories are read up to the one
+    /// holding `.git`; deeper files and later lines take precedence.
+    pub fn attribute(
+        &mut self,
+        path: &Path,
+    ) -> Option<bool> {
+        let mut dirs: Vec<&Path> = Vec::new();
+        for dir in path.ancestors().skip(1) {
+            dirs.push(dir);
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        let name = path.file_name()?;
+        let mut generated = None;
+        for dir in dirs.into_iter().rev() {
+            let Ok(rel) = path.strip_prefix(dir) else {
+                continue;
+            };
+            for rule in self.rules_in(dir) {
+                let subject = if rule.basename { Path::new(name) } else { rel };
+                if rule.glob.is_match(subject) {
+                    generated = Some(rule.generated);
+                }
+            }
+        }
+        generated
+    }
+
+    /// Why the file at absolute `path` with `text` counts as generated, or
+    /// `None` to keep it.
+    pub fn category(
+        &mut self,
+        path: &Path,
+        text: &str,
+    ) -> Option<&'static str> {
+        match self.attribute(path) {
+            Some(true) => return Some("linguist-generated"),
+            Some(false) => return None,
+            None => {}
+        }
+        let name = path.file_name()?.to_string_lossy();
+        name_category(&name).or_else(|| single_long_line(text).then_some("single long line"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_of_lockfiles_bundles_and_maps() {
+        assert_eq!(name_category("Cargo.lock"), Some("lockfile"));
+        assert_eq!(name_category("yarn.lock"), Some("lockfile"));
+        assert_eq!(name_category("app.min.js"), Some("minified"));
+        assert_eq!(name_category("app.js.map"), Some("source map"));
+        assert_eq!(name_category("cargo.lock.md"), None);
+        assert_eq!(name_category("main.rs"), None);
+        assert_eq!(name_category(".map"), None);
+    }
+
+    #[test]
+    fn single_line_needs_length() {
+        let long = "x".repeat(GENERATED_LINE_BYTES + 1);
+        assert!(single_long_line(&long));
+        assert!(single_long_line(&format!("{long}\n")));
+        assert!(!single_long_line(&format!("{long}\nmore")));
+        assert!(!single_long_line("short"));
+    }
+
+    #[test]
+    fn gitattributes_rules_and_precedence() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::create_dir_all(dir.path().join("gen/keep")).unwrap();
+        std::fs::write(
+            dir.path().join(".gitattributes"),
+            "# comment\n*.pb.go linguist-generated\n/gen/** linguist-generated=true\n\
+             Cargo.lock -linguist-generated\n*.rs text eol=lf\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("gen/keep/.gitattributes"),
+            "*.rs linguist-generated=false\n",
+        )
+        .unwrap();
+
+        let mut detector = Detector::new();
+        let at = |rel: &str| dir.path().join(rel);
+        assert_eq!(detector.attribute(&at("api/x.pb.go")), Some(true));
+        assert_eq!(detector.attribute(&at("gen/a.rs")), Some(true));
+        assert_eq!(detector.attribute(&at("gen/keep/b.rs")), Some(false));
+        assert_eq!(detector.attribute(&at("gen/keep/c.txt")), Some(true));
+        assert_eq!(detector.attribute(&at("src/gen/d.rs")), None);
+        assert_eq!(detector.category(&at("Cargo.lock"), ""), None);
+        assert_eq!(detector.category(&at("sub/Cargo.lock"), ""), None);
+        assert_eq!(detector.category(&at("yarn.lock"), ""), Some("lockfile"));
+    }
+}
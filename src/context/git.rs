@@ -0,0 +1,165 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Branch, recent commits, and changed files of the repository containing
+/// the working directory.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GitSnapshot {
+    pub branch: Option<String>,
+    /// Subjects of the last five commits, newest first.
+    pub commits: Vec<String>,
+    /// Ref the changed files were diffed against, if one was found.
+    pub base: Option<String>,
+    /// Files changed against `base`; `None` when the diff failed.
+    pub changed: Option<Vec<String>>,
+}
+
+/// Snapshot the repository state, or `None` outside a git work tree.
+pub fn snapshot() -> Option<GitSnapshot> {
+    if git_stdout(&["rev-parse", "--is-inside-work-tree"]).as_deref() != Some("true") {
+        return None;
+    }
+    let commits = git_stdout(&["log", "-5", "--pretty=format:%s"]).unwrap_or_default();
+    let base = detect_changed_files_base();
+    let changed = base.as_ref().and_then(|base| {
+        let out = Command::new("git")
+            .args(["diff", "--name-only", base])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        let stdout = String::from_utf8(out.stdout).ok().unwrap_or_default();
+        Some(stdout.lines().map(str::to_string).collect())
+    });
+    Some(GitSnapshot {
+        branch: git_stdout(&["rev-parse", "--abbrev-ref", "HEAD"]),
+        commits: commits.lines().map(str::to_string).collect(),
+        base,
+        changed,
+    })
+}
+
+pub(crate) fn git_stdout(args: &[&str]) -> Option<String> {
+    Command::new("git").args(args).output().ok().and_then(|o| {
+        if o.status.success() {
+            String::from_utf8(o.stdout)
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        } else {
+            None
+        }
+    })
+}
+
+fn git_ref_exists(refname: &str) -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", refname])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+pub(crate) fn detect_changed_files_base() -> Option<String> {
+    if let Some(upstream) = git_stdout(&[
+        "rev-parse",
+        "--abbrev-ref",
+        "--symbolic-full-name",
+        "@{upstream}",
+    ]) {
+        return Some(upstream);
+    }
+    if let Some(origin_head) = git_stdout(&[
+        "symbolic-ref",
+        "--quiet",
+        "--short",
+        "refs/remotes/origin/HEAD",
+    ]) {
+        return Some(origin_head);
+    }
+    for (refname, display) in [
+        ("refs/heads/main", "main"),
+        ("refs/heads/master", "master"),
+        ("refs/remotes/origin/main", "origin/main"),
+        ("refs/remotes/origin/master", "origin/master"),
+    ] {
+        if git_ref_exists(refname) {
+            return Some(display.to_string());
+        }
+    }
+    None
+}
+
+/// Files git lists under `dir`, as paths joined onto `dir`: those tracked in
+/// the index, plus with `others` the untracked ones that `.gitignore` and the
+/// exclude files do not rule out. `None` outside a work tree or when git fails.
+pub fn ls_files(
+    dir: &Path,
+    others: bool,
+) -> Option<Vec<PathBuf>> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(dir).args(["ls-files", "-z", "--cached"]);
+    if others {
+        cmd.args(["--others", "--exclude-standard"]);
+    }
+    let out = cmd.output().ok().filter(|o| o.status.success())?;
+    let listed = String::from_utf8_lossy(&out.stdout);
+    Some(
+        listed
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| dir.join(path))
+            .collect(),
+    )
+}
+
+/// Files staged in the index of the work tree containing `dir`, joined onto
+/// its top level; staged deletions are left out. `None` outside a work tree
+/// or when git fails.
+pub fn staged_files(dir: &Path) -> Option<Vec<PathBuf>> {
+    let top = toplevel(dir)?;
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(&top)
+        .args(["diff", "--cached", "--name-only", "-z", "--diff-filter=d"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let listed = String::from_utf8_lossy(&out.stdout);
+    Some(
+        listed
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| top.join(path))
+            .collect(),
+    )
+}
+
+/// The staged contents of `path` (`git show :path`), or `None` when it is
+/// not in the index.
+pub fn index_contents(path: &Path) -> Option<Vec<u8>> {
+    let dir = path.parent()?;
+    let name = path.file_name()?.to_string_lossy();
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!(":./{name}"))
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    Some(out.stdout)
+}
+
+/// Top-level directory of the git work tree containing `dir`, canonicalized.
+pub fn toplevel(dir: &Path) -> Option<PathBuf> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let top = String::from_utf8(out.stdout).ok()?;
+    dunce::canonicalize(top.trim()).ok()
+}
@@ -1,190 +1,565 @@
-use crate::chunker::FileMeta;
+use crate::chunker::{self, FileMeta};
+use crate::config::ChunkUnit;
+use crate::context::gather::SkippedFile;
+#[cfg(feature = "git")]
+use crate::context::git::snapshot;
 use crate::context::xml::{maybe_escape_attr, maybe_escape_text};
+use crate::tokenizer::count as count_tokens;
 use chrono::{SecondsFormat, Utc};
-use path_slash::PathBufExt;
+use path_slash::{PathBufExt, PathExt};
 use std::fmt::Write;
-use std::process::Command;
-
-fn git_stdout(args: &[&str]) -> Option<String> {
-    Command::new("git").args(args).output().ok().and_then(|o| {
-        if o.status.success() {
-            String::from_utf8(o.stdout)
-                .ok()
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-        } else {
-            None
-        }
-    })
+
+/// Renders one `<file .../>` entry of a file map, indented to sit inside its
+/// `<folder>`.
+pub(crate) fn file_map_entry(
+    meta: &FileMeta,
+    escape_xml: bool,
+) -> String {
+    let path = meta.path.to_slash_lossy().to_string();
+    let path_attr = maybe_escape_attr(&path, escape_xml);
+    let mut entry = format!(
+        "      <file id=\"{}\" path=\"{}\" tokens=\"{}\" parts=\"{}\" size=\"{}\"",
+        meta.id, path_attr, meta.tokens, meta.parts, meta.size
+    );
+    if let Some(mtime) = &meta.mtime {
+        let _ = write!(entry, " mtime=\"{mtime}\"");
+    }
+    if let Some(sha256) = &meta.sha256 {
+        let _ = write!(entry, " sha256=\"{sha256}\"");
+    }
+    if let Some(original) = &meta.duplicate_of {
+        let original = original.to_slash_lossy().to_string();
+        let _ = write!(
+            entry,
+            " duplicate-of=\"{}\"",
+            maybe_escape_attr(&original, escape_xml)
+        );
+    } else if meta.stubbed {
+        entry.push_str(" stubbed=\"true\"");
+    } else if meta.binary {
+        entry.push_str(" binary=\"true\"");
+    }
+    if let Some(encoding) = &meta.encoding {
+        let _ = write!(entry, " encoding=\"{encoding}\"");
+    }
+    if meta.crlf {
+        entry.push_str(" eol=\"crlf\"");
+    }
+    if let Some(lines) = meta.lines {
+        let _ = write!(entry, " lines=\"{lines}\"");
+    }
+    if meta.truncated {
+        entry.push_str(" truncated=\"true\"");
+    }
+    if meta.external {
+        entry.push_str(" external=\"true\"");
+    }
+    if meta.via_readme {
+        entry.push_str(" included-via=\"readme\"");
+    }
+    if meta.priority {
+        entry.push_str(" priority=\"true\"");
+    }
+    if !meta.chunks.is_empty() {
+        let chunks: Vec<String> = meta.chunks.iter().map(usize::to_string).collect();
+        let _ = write!(entry, " chunk=\"{}\"", chunks.join(","));
+    }
+    entry.push_str("/>\n");
+    entry
+}
+
+/// Renders a `<skipped-file .../>` entry for a file left out of the output,
+/// indented to sit at the end of a file map.
+pub(crate) fn skipped_file_entry(
+    skip: &SkippedFile,
+    escape_xml: bool,
+) -> String {
+    let path = skip.path.to_slash_lossy().to_string();
+    let mut entry = format!(
+        "    <skipped-file path=\"{}\" reason=\"{}\"",
+        maybe_escape_attr(&path, escape_xml),
+        skip.reason.name()
+    );
+    if let Some(size) = skip.size {
+        let _ = write!(entry, " size=\"{size}\"");
+    }
+    entry.push_str("/>\n");
+    entry
 }
 
-fn git_ref_exists(refname: &str) -> bool {
-    Command::new("git")
-        .args(["rev-parse", "--verify", "--quiet", refname])
-        .status()
-        .map(|status| status.success())
-        .unwrap_or(false)
+/// File-map entries grouped by folder. Folders appear in the order of their
+/// first file and files keep their relative order within a folder, so ids
+/// can be out of sequence when the files were sorted.
+struct FolderedMap {
+    /// Folder index and rendered `<file>` entry, in map order.
+    entries: Vec<(usize, String)>,
+    /// Opening `<folder>` tag of each folder, carrying its token subtotal.
+    folders: Vec<String>,
+    total_tokens: usize,
 }
 
-fn detect_changed_files_base() -> Option<String> {
-    if let Some(upstream) = git_stdout(&[
-        "rev-parse",
-        "--abbrev-ref",
-        "--symbolic-full-name",
-        "@{upstream}",
-    ]) {
-        return Some(upstream);
-    }
-    if let Some(origin_head) = git_stdout(&[
-        "symbolic-ref",
-        "--quiet",
-        "--short",
-        "refs/remotes/origin/HEAD",
-    ]) {
-        return Some(origin_head);
-    }
-    for (refname, display) in [
-        ("refs/heads/main", "main"),
-        ("refs/heads/master", "master"),
-        ("refs/remotes/origin/main", "origin/main"),
-        ("refs/remotes/origin/master", "origin/master"),
-    ] {
-        if git_ref_exists(refname) {
-            return Some(display.to_string());
+impl FolderedMap {
+    fn new(
+        files: &[FileMeta],
+        escape_xml: bool,
+    ) -> Self {
+        let mut groups: Vec<(String, Vec<&FileMeta>)> = Vec::new();
+        for meta in files {
+            let folder = meta
+                .path
+                .parent()
+                .map(|p| p.to_slash_lossy().to_string())
+                .filter(|p| !p.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            match groups.iter_mut().find(|(name, _)| *name == folder) {
+                Some((_, members)) => members.push(meta),
+                None => groups.push((folder, vec![meta])),
+            }
+        }
+        let folders = groups
+            .iter()
+            .map(|(name, members)| {
+                let tokens: usize = members.iter().map(|m| m.tokens).sum();
+                format!(
+                    "    <folder path=\"{}\" tokens=\"{tokens}\">\n",
+                    maybe_escape_attr(name, escape_xml)
+                )
+            })
+            .collect();
+        let entries = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, (_, members))| {
+                members
+                    .iter()
+                    .map(move |m| (idx, file_map_entry(m, escape_xml)))
+            })
+            .collect();
+        Self {
+            entries,
+            folders,
+            total_tokens: files.iter().map(|m| m.tokens).sum(),
+        }
+    }
+
+    /// Render the entries in `range`, wrapping each run from one folder in
+    /// its `<folder>` element. A folder split across parts is reopened, with
+    /// its full subtotal, in the next part.
+    fn render(
+        &self,
+        range: std::ops::Range<usize>,
+    ) -> String {
+        let mut out = String::new();
+        let mut open = None;
+        for (folder, entry) in &self.entries[range] {
+            if open != Some(*folder) {
+                if open.is_some() {
+                    out.push_str("    </folder>\n");
+                }
+                out.push_str(&self.folders[*folder]);
+                open = Some(*folder);
+            }
+            out.push_str(entry);
         }
+        if open.is_some() {
+            out.push_str("    </folder>\n");
+        }
+        out
     }
-    None
 }
 
-/// Builds the shared-context-header XML for LLM consumption.
-pub fn make_header(
-    total_chunks: usize,
-    limit: usize,
+/// Renders a complete `<file-map>` with entries grouped by folder, followed
+/// by an entry for each `skipped` file.
+pub(crate) fn render_file_map(
     files: &[FileMeta],
-    multi_step: bool,
+    skipped: &[SkippedFile],
     escape_xml: bool,
-    include_git: bool,
 ) -> String {
-    // Timestamp in RFC3339 with seconds precision
-    let ts = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
-    // Build file-map entries
-    let mut map = String::new();
-    for f in files {
-        let path = f.path.to_slash_lossy().to_string();
-        let path_attr = maybe_escape_attr(&path, escape_xml);
-        let _ = writeln!(
-            &mut map,
-            "    <file id=\"{}\" path=\"{}\" tokens=\"{}\" parts=\"{}\"/>",
-            f.id, path_attr, f.tokens, f.parts
-        );
+    let map = FolderedMap::new(files, escape_xml);
+    let skipped: String = skipped
+        .iter()
+        .map(|skip| skipped_file_entry(skip, escape_xml))
+        .collect();
+    format!(
+        "{}{}{skipped}  </file-map>\n",
+        file_map_open(files.len(), map.total_tokens, None),
+        map.render(0..map.entries.len())
+    )
+}
+
+/// Inputs for `make_header`. Fields default to an empty, single-chunk header;
+/// set only what differs with struct-update syntax.
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderSpec<'a> {
+    /// Total chunks, including the header chunk itself.
+    pub total_chunks: usize,
+    /// Limit per chunk in `chunk_unit` (0 when chunking is off).
+    pub chunk_size: usize,
+    /// Percent of `chunk_size` kept free in every chunk; chunks are built to
+    /// the smaller budget while the header still declares `chunk_size`.
+    pub headroom: u8,
+    /// What `chunk_size` and `chunk_tokens` count.
+    pub chunk_unit: ChunkUnit,
+    pub files: &'a [FileMeta],
+    /// Files left out of the output, listed at the end of the file map (its
+    /// first part when split).
+    pub skipped: &'a [SkippedFile],
+    /// Use the multi-step (fetch on demand) instructions.
+    pub multi_step: bool,
+    pub escape_xml: bool,
+    /// Include branch, recent commits, and changed files.
+    pub include_git: bool,
+    /// Render the `<file-map>`; the file metadata is still used for planning.
+    pub include_file_map: bool,
+    /// The header chunk is emitted after the content chunks.
+    pub header_last: bool,
+    /// File bodies are wrapped in CDATA sections.
+    pub cdata: bool,
+    /// Fixed `generated-at` value for reproducible output; the current time
+    /// when `None`.
+    pub generated_at: Option<&'a str>,
+    /// Size of every chunk in emission order, header included, declared as
+    /// `total-tokens` and `chunk-tokens` (`total-bytes` and so on for other
+    /// units) once the chunk plan is final.
+    pub chunk_tokens: Option<&'a [usize]>,
+    /// Custom instructions text (`--instructions`), escaped like file contents.
+    pub instructions: Option<&'a str>,
+    /// Put `instructions` after the built-in text instead of in place of it.
+    pub append_instructions: bool,
+}
+
+impl Default for HeaderSpec<'_> {
+    fn default() -> Self {
+        Self {
+            total_chunks: 1,
+            chunk_size: 0,
+            headroom: 0,
+            chunk_unit: ChunkUnit::Tokens,
+            files: &[],
+            skipped: &[],
+            multi_step: false,
+            escape_xml: false,
+            include_git: false,
+            include_file_map: true,
+            header_last: false,
+            cdata: false,
+            generated_at: None,
+            chunk_tokens: None,
+            instructions: None,
+            append_instructions: false,
+        }
+    }
+}
+
+/// Builds the shared-context-header XML for LLM consumption.
+pub fn make_header(spec: &HeaderSpec) -> String {
+    let map = FolderedMap::new(spec.files, spec.escape_xml);
+    let git = if spec.include_git {
+        git_sections(spec.escape_xml)
+    } else {
+        String::new()
+    };
+    render_header(
+        spec,
+        &map.render(0..map.entries.len()),
+        1,
+        map.total_tokens,
+        &git,
+    )
+}
+
+/// Render the multi-step header as chunks of at most `spec.chunk_size` tokens
+/// less the headroom (unbounded when 0). The first chunk opens `<shared-context>` and carries the
+/// header with the first file-map part; each later chunk is a
+/// `<file-map part="i/N">` continuation. An entry that cannot fit on its own
+/// still gets a chunk, which may exceed the limit.
+pub fn make_multi_step_header_chunks(spec: &HeaderSpec) -> Vec<String> {
+    let limit = chunker::chunk_budget(spec.chunk_size, spec.headroom);
+    let map = FolderedMap::new(spec.files, spec.escape_xml);
+    let entries = &map.entries;
+    let git = if spec.include_git {
+        git_sections(spec.escape_xml)
+    } else {
+        String::new()
+    };
+    let first_chunk = |body: &str, parts: usize| {
+        let spec = HeaderSpec {
+            total_chunks: parts,
+            ..*spec
+        };
+        format!(
+            "<shared-context>\n{}\n",
+            render_header(&spec, body, parts, map.total_tokens, &git)
+        )
+    };
+    let continuation = |body: &str, idx: usize, parts: usize| {
+        format!(
+            "{}{body}  </file-map>\n",
+            file_map_open(spec.files.len(), map.total_tokens, Some((idx, parts)))
+        )
+    };
+
+    let whole = first_chunk(&map.render(0..entries.len()), 1);
+    if !spec.include_file_map || limit == 0 || count_tokens(&whole) <= limit {
+        return vec![whole];
+    }
+    // The first entry of each folder also carries its `<folder>` tags
+    let folder_close = count_tokens("    </folder>\n");
+    let entry_tokens: Vec<usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, (folder, entry))| {
+            let opens = i == 0 || entries[i - 1].0 != *folder;
+            count_tokens(entry)
+                + if opens {
+                    count_tokens(&map.folders[*folder]) + folder_close
+                } else {
+                    0
+                }
+        })
+        .collect();
+    let mut parts = 2usize;
+    // Entry counts are estimates of the rendered total; slack absorbs the drift
+    let mut slack = 0usize;
+    let mut chunks = Vec::new();
+    for _ in 0..8 {
+        let first_overhead = count_tokens(&first_chunk("", parts));
+        let cont_overhead = count_tokens(&continuation("", parts, parts));
+        let mut groups: Vec<std::ops::Range<usize>> = Vec::new();
+        let (mut start, mut used) = (0usize, 0usize);
+        for (i, &tokens) in entry_tokens.iter().enumerate() {
+            let overhead = if groups.is_empty() {
+                first_overhead
+            } else {
+                cont_overhead
+            };
+            let budget = limit.saturating_sub(overhead + slack);
+            if i > start && used + tokens > budget {
+                groups.push(start..i);
+                (start, used) = (i, 0);
+            }
+            used += tokens;
+        }
+        groups.push(start..entries.len());
+
+        let count = groups.len();
+        chunks = groups
+            .iter()
+            .enumerate()
+            .map(|(idx, range)| {
+                let body = map.render(range.clone());
+                if idx == 0 {
+                    first_chunk(&body, count)
+                } else {
+                    continuation(&body, idx + 1, count)
+                }
+            })
+            .collect();
+        let over = chunks
+            .iter()
+            .zip(&groups)
+            .filter(|(_, range)| range.len() > 1)
+            .map(|(chunk, _)| count_tokens(chunk).saturating_sub(limit))
+            .max()
+            .unwrap_or(0);
+        if count == parts && over == 0 {
+            break;
+        }
+        parts = count;
+        slack += over;
+    }
+    chunks
+}
+
+/// Opening tag of a `<file-map>` element; `part` is set when the map is split.
+fn file_map_open(
+    total_files: usize,
+    total_tokens: usize,
+    part: Option<(usize, usize)>,
+) -> String {
+    match part {
+        Some((idx, total)) => format!(
+            "  <file-map total-files=\"{total_files}\" total-tokens=\"{total_tokens}\" part=\"{idx}/{total}\">\n"
+        ),
+        None => {
+            format!("  <file-map total-files=\"{total_files}\" total-tokens=\"{total_tokens}\">\n")
+        }
     }
+}
+
+/// Render the header around `map` (the first of `map_parts` file-map parts,
+/// covering `map_tokens` tokens in all) and the pre-rendered git sections.
+fn render_header(
+    spec: &HeaderSpec,
+    map: &str,
+    map_parts: usize,
+    map_tokens: usize,
+    git: &str,
+) -> String {
+    let HeaderSpec {
+        total_chunks,
+        chunk_size: limit,
+        chunk_unit,
+        files,
+        skipped,
+        multi_step,
+        escape_xml,
+        header_last,
+        cdata,
+        generated_at,
+        chunk_tokens,
+        include_file_map,
+        instructions: custom,
+        append_instructions,
+        ..
+    } = *spec;
+    // Timestamp in RFC3339 with seconds precision
+    let ts = generated_at.map_or_else(
+        || Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        str::to_string,
+    );
+    let unit = chunk_unit.name();
+    let payload = chunk_tokens.map_or_else(String::new, |sizes| {
+        let list: Vec<String> = sizes.iter().map(usize::to_string).collect();
+        format!(
+            " total-{unit}=\"{}\" chunk-{unit}=\"{}\"",
+            sizes.iter().sum::<usize>(),
+            list.join(",")
+        )
+    });
+    let unit_attr = if chunk_unit == ChunkUnit::Tokens {
+        String::new()
+    } else {
+        format!(" chunk-unit=\"{unit}\"")
+    };
+    let file_map = if include_file_map {
+        let skipped: String = skipped
+            .iter()
+            .map(|skip| skipped_file_entry(skip, escape_xml))
+            .collect();
+        format!(
+            "{}{map}{skipped}  </file-map>\n",
+            file_map_open(
+                files.len(),
+                map_tokens,
+                (map_parts > 1).then_some((1, map_parts))
+            )
+        )
+    } else {
+        String::new()
+    };
     // Build instructions section
-    let escape_note = if escape_xml {
+    // Header prose is escaped whenever the contents are parseable XML
+    let escape_note = if cdata {
+        "    File contents are wrapped in CDATA sections; a literal ]]&gt; is split across two sections.\n"
+    } else if escape_xml {
         "    File contents are XML-escaped; angle brackets and ampersands are encoded.\n"
     } else {
         "    File contents are unescaped; header metadata remains escaped.\n"
     };
-    let instructions = if multi_step {
+    let continuation_note = if map_parts > 1 {
+        format!(
+            "    The file map is split into {map_parts} parts; this is part 1. The remaining parts follow as separate {} messages; wait for all of them before requesting files.\n",
+            maybe_escape_text("<file-map>", escape_xml || cdata)
+        )
+    } else {
+        String::new()
+    };
+    let builtin = if multi_step {
         // Multi-step mode instructions
         format!(
-            "  <instructions>\n    This header lists {total_files} files available for context retrieval. To fetch file contents, enter a file id (e.g., '2'), a file path (e.g., 'src/main.rs'), or a glob pattern (e.g., '*.rs'); glob patterns may match multiple files, and the tool will return those contents in the next message.\n{escape_note}  </instructions>\n",
-            total_files = files.len(),
-            escape_note = escape_note
+            "    This header lists {} files available for context retrieval. To fetch file contents, enter a file id (e.g., '2'), a file path (e.g., 'src/main.rs'), or a glob pattern (e.g., '*.rs'); glob patterns may match multiple files, and the tool will return those contents in the next message.\n",
+            files.len()
+        )
+    } else if header_last && total_chunks > 1 {
+        // Chunked mode, header after the content
+        format!(
+            "    The shared context was split into {total_chunks} chunks, and this header is the last of them; you have already seen every file's contents. Use the file map above to orient yourself in what you read, then reply \"READY\" to confirm you have understood the context.\n"
         )
     } else {
         // Chunked mode instructions
         format!(
-            "  <instructions>\n    The shared context is split into {total_chunks} chunks (including this header). Review each chunk carefully. Acknowledge that you've studied this each chunk. After reading the final chunk, reply \"READY\" to confirm you have understood the context.\n{escape_note}  </instructions>\n",
-            total_chunks = total_chunks,
-            escape_note = escape_note
+            "    The shared context is split into {total_chunks} chunks (including this header). Review each chunk carefully. Acknowledge that you've studied this each chunk. After reading the final chunk, reply \"READY\" to confirm you have understood the context.\n"
         )
     };
-    // Gather git info: branch, recent commits, and changed files
-    let mut git_info = String::new();
-    let mut changed_files_xml = String::new();
-    if include_git {
-        let git_available = git_stdout(&["rev-parse", "--is-inside-work-tree"])
-            .map(|s| s == "true")
-            .unwrap_or(false);
-
-        if git_available {
-            let branch = git_stdout(&["rev-parse", "--abbrev-ref", "HEAD"]);
-            let commits = git_stdout(&["log", "-5", "--pretty=format:%s"]).unwrap_or_default();
-            let commits: Vec<String> = commits.lines().map(|l| l.to_string()).collect();
-
-            if let Some(branch) = branch {
-                let branch_attr = maybe_escape_attr(&branch, escape_xml);
-                let _ = writeln!(&mut git_info, "  <git-info branch=\"{}\">", branch_attr);
-                for msg in commits {
-                    let msg_text = maybe_escape_text(&msg, true);
-                    let _ = writeln!(&mut git_info, "    <commit>{}</commit>", msg_text);
-                }
-                let _ = writeln!(&mut git_info, "  </git-info>");
-            } else {
-                let _ = writeln!(&mut git_info, "  <!-- git info unavailable -->");
-            }
-
-            if let Some(base) = detect_changed_files_base() {
-                let diff_out = Command::new("git")
-                    .args(["diff", "--name-only", &base])
-                    .output();
-                let diff_ok = diff_out
-                    .as_ref()
-                    .map(|o| o.status.success())
-                    .unwrap_or(false);
-                let diff_output = diff_out
-                    .ok()
-                    .and_then(|o| {
-                        if o.status.success() {
-                            String::from_utf8(o.stdout).ok()
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or_default();
-                let changed: Vec<String> = diff_output.lines().map(|l| l.to_string()).collect();
-                if !changed.is_empty() {
-                    let base_attr = maybe_escape_attr(&base, escape_xml);
-                    let _ = writeln!(
-                        &mut changed_files_xml,
-                        "  <changed-files diffed-against=\"{}\">",
-                        base_attr
-                    );
-                    for file in &changed {
-                        let file_text = maybe_escape_text(file, true);
-                        let _ = writeln!(&mut changed_files_xml, "    <file>{}</file>", file_text);
+    // Custom text replaces or follows the built-in prose; the notes on the
+    // file-map split and escaping always stay
+    let prose = match custom {
+        Some(text) => {
+            let text: String = maybe_escape_text(text.trim_end(), escape_xml || cdata)
+                .lines()
+                .map(|line| {
+                    if line.is_empty() {
+                        "\n".to_string()
+                    } else {
+                        format!("    {line}\n")
                     }
-                    let _ = writeln!(&mut changed_files_xml, "  </changed-files>");
-                } else if !diff_ok {
-                    let _ = writeln!(
-                        &mut changed_files_xml,
-                        "  <!-- changed files unavailable -->"
-                    );
-                }
+                })
+                .collect();
+            if append_instructions {
+                builtin + &text
             } else {
-                let _ = writeln!(
-                    &mut changed_files_xml,
-                    "  <!-- changed files unavailable: no git base found -->"
-                );
+                text
             }
-        } else {
-            let _ = writeln!(&mut git_info, "  <!-- git info unavailable -->");
-            let _ = writeln!(
-                &mut changed_files_xml,
-                "  <!-- changed files unavailable: not a git repository -->"
-            );
         }
-    }
+        None => builtin,
+    };
+    let instructions =
+        format!("  <instructions>\n{prose}{continuation_note}{escape_note}  </instructions>\n");
+    let header_index = if header_last {
+        total_chunks.saturating_sub(1)
+    } else {
+        0
+    };
     // Compose full header with closing tag
     format!(
-        "<shared-context-header version=\"1\" total-chunks=\"{total_chunks}\" chunk-size=\"{limit}\" generated-at=\"{ts}\">\n  <file-map total-files=\"{total}\">\n{map}  </file-map>\n{instructions}{git_info}{changed_files_xml}</shared-context-header>\n",
-        total_chunks = total_chunks,
-        limit = limit,
-        ts = ts,
-        total = files.len(),
-        map = map,
-        instructions = instructions,
-        git_info = git_info,
-        changed_files_xml = changed_files_xml
+        "<shared-context-header version=\"1\" index=\"{header_index}\" total=\"{total_chunks}\" kind=\"header\" total-chunks=\"{total_chunks}\" chunk-size=\"{limit}\"{unit_attr}{payload} generated-at=\"{ts}\">\n{file_map}{instructions}{git}</shared-context-header>\n",
     )
 }
+
+/// Placeholder git sections when built without the `git` feature.
+#[cfg(not(feature = "git"))]
+fn git_sections(_escape_xml: bool) -> String {
+    "  <!-- git info unavailable: built without git support -->\n".to_string()
+}
+
+/// Git info (branch, recent commits) and changed-files sections.
+#[cfg(feature = "git")]
+fn git_sections(escape_xml: bool) -> String {
+    let mut out = String::new();
+    let Some(git) = snapshot() else {
+        out.push_str("  <!-- git info unavailable -->\n");
+        out.push_str("  <!-- changed files unavailable: not a git repository -->\n");
+        return out;
+    };
+    if let Some(branch) = &git.branch {
+        let branch_attr = maybe_escape_attr(branch, escape_xml);
+        let _ = writeln!(&mut out, "  <git-info branch=\"{}\">", branch_attr);
+        for msg in &git.commits {
+            let msg_text = maybe_escape_text(msg, true);
+            let _ = writeln!(&mut out, "    <commit>{}</commit>", msg_text);
+        }
+        out.push_str("  </git-info>\n");
+    } else {
+        out.push_str("  <!-- git info unavailable -->\n");
+    }
+    match (&git.base, &git.changed) {
+        (None, _) => out.push_str("  <!-- changed files unavailable: no git base found -->\n"),
+        (Some(_), None) => out.push_str("  <!-- changed files unavailable -->\n"),
+        (Some(base), Some(changed)) if !changed.is_empty() => {
+            let base_attr = maybe_escape_attr(base, escape_xml);
+            let _ = writeln!(
+                &mut out,
+                "  <changed-files diffed-against=\"{}\">",
+                base_attr
+            );
+            for file in changed {
+                let file_text = maybe_escape_text(file, true);
+                let _ = writeln!(&mut out, "    <file>{}</file>", file_text);
+            }
+            out.push_str("  </changed-files>\n");
+        }
+        (Some(_), Some(_)) => {}
+    }
+    out
+}
@@ -0,0 +1,186 @@
+use crate::chunker::{ChunkBody, FileMeta, build_file_meta};
+use crate::context::types::{FileContents, FileKind};
+use anyhow::Result;
+use path_slash::PathBufExt;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct FileMapEntry {
+    id: usize,
+    path: String,
+    tokens: usize,
+    parts: usize,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stubbed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    external: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    included_via: Option<&'static str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    binary: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eol: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    priority: bool,
+}
+
+/// Size and digest of a binary attachment, which has no `contents`.
+#[derive(Serialize)]
+struct BinaryEntry<'a> {
+    size: u64,
+    sha256: &'a str,
+}
+
+#[derive(Serialize)]
+struct FileEntry<'a> {
+    folder: String,
+    path: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<String>,
+    /// Leading lines of `contents` repeated from the previous part.
+    #[serde(skip_serializing_if = "is_zero")]
+    overlap: usize,
+    contents: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    binary: Option<BinaryEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    file_map: Vec<FileMapEntry>,
+    files: Vec<FileEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ChunkDocument<'a> {
+    index: usize,
+    total: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    file_map: Vec<FileMapEntry>,
+    files: Vec<FileEntry<'a>>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn file_map(metas: &[FileMeta]) -> Vec<FileMapEntry> {
+    metas
+        .iter()
+        .map(|m| FileMapEntry {
+            id: m.id,
+            path: m.path.to_slash_lossy().to_string(),
+            tokens: m.tokens,
+            parts: m.parts,
+            stubbed: m.stubbed && m.duplicate_of.is_none(),
+            duplicate_of: m
+                .duplicate_of
+                .as_ref()
+                .map(|p| p.to_slash_lossy().to_string()),
+            external: m.external,
+            included_via: m.via_readme.then_some("readme"),
+            binary: m.binary,
+            truncated: m.truncated,
+            encoding: m.encoding.clone(),
+            eol: m.crlf.then_some("crlf"),
+            lines: m.lines.map(|range| range.to_string()),
+            priority: m.priority,
+        })
+        .collect()
+}
+
+fn file_entry<'a>(
+    file: &'a FileContents,
+    contents: &'a str,
+    part: Option<(usize, usize)>,
+    overlap: usize,
+) -> FileEntry<'a> {
+    let folder = file.folder.to_slash_lossy().to_string();
+    FileEntry {
+        folder: if folder.is_empty() {
+            ".".to_string()
+        } else {
+            folder
+        },
+        path: file.path.to_slash_lossy().to_string(),
+        name: file
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        part: part.map(|(idx, total)| format!("{idx}/{total}")),
+        overlap,
+        contents,
+        binary: match &file.kind {
+            FileKind::Binary { size, sha256 } => Some(BinaryEntry {
+                size: *size,
+                sha256,
+            }),
+            FileKind::Text => None,
+        },
+    }
+}
+
+/// Builds a JSON document with the same file map and contents as the XML output.
+pub fn build_json(files: &[FileContents]) -> Result<String> {
+    build_json_with_meta(files, &build_file_meta(files, false))
+}
+
+/// Builds the JSON document using precomputed file metadata for the file map.
+pub fn build_json_with_meta(
+    files: &[FileContents],
+    metas: &[FileMeta],
+) -> Result<String> {
+    let doc = Document {
+        file_map: file_map(metas),
+        files: files
+            .iter()
+            .filter(|f| !f.stubbed)
+            .map(|f| file_entry(f, &f.contents, None, 0))
+            .collect(),
+    };
+    let mut out = serde_json::to_string_pretty(&doc)?;
+    out.push('\n');
+    Ok(out)
+}
+
+/// Renders one JSON object per line for each chunk: the file map first, then
+/// one object per chunk body. Chunk boundaries follow the XML chunk plan.
+pub fn build_json_chunks(
+    files: &[FileContents],
+    bodies: &[ChunkBody],
+    metas: &[FileMeta],
+) -> Result<Vec<String>> {
+    let total = bodies.len() + 1;
+    let mut out = Vec::with_capacity(total);
+    let header = ChunkDocument {
+        index: 0,
+        total,
+        file_map: file_map(metas),
+        files: Vec::new(),
+    };
+    out.push(serde_json::to_string(&header)? + "\n");
+    for (idx, body) in bodies.iter().enumerate() {
+        let doc = ChunkDocument {
+            index: idx + 1,
+            total,
+            file_map: Vec::new(),
+            files: body
+                .blocks
+                .iter()
+                .map(|b| file_entry(&files[b.file_id], &b.body, b.part, b.overlap))
+                .collect(),
+        };
+        out.push(serde_json::to_string(&doc)? + "\n");
+    }
+    Ok(out)
+}
@@ -1,5 +1,18 @@
+#[cfg(feature = "archive")]
+pub mod archive;
 pub mod chunker;
+pub mod detect;
 pub mod gather;
+pub mod generated;
+#[cfg(feature = "git")]
+pub mod git;
 pub mod header;
+pub mod json;
+pub mod plain;
+pub mod redact;
+pub mod sanitize;
+pub mod template;
+pub mod transform;
 pub mod types;
+pub mod validate;
 pub mod xml;
@@ -0,0 +1,85 @@
+use crate::chunker::PartMarks;
+use crate::constants::PLAIN_PART_SEPARATOR;
+use crate::context::types::{FileContents, FileKind};
+use crate::context::xml::human_size;
+use path_slash::PathExt;
+use std::path::Path;
+
+/// Append a newline unless `body` is empty or already ends with one, so the
+/// next marker starts on its own line.
+fn terminated(body: &str) -> String {
+    if body.is_empty() || body.ends_with('\n') {
+        body.to_string()
+    } else {
+        format!("{body}\n")
+    }
+}
+
+/// A whole file: `===== path =====` and its raw contents.
+pub(crate) fn file_block(
+    path: &Path,
+    body: &str,
+    separator: &str,
+) -> String {
+    let path = path.to_slash_lossy();
+    format!("{separator} {path} {separator}\n{}", terminated(body))
+}
+
+/// One part of a split file: `----- path (part 2/3) -----` and the part's
+/// lines. Concatenating the parts' bodies, less the first `overlap` lines of
+/// each (noted as `(part 2/3, overlap 5)`), gives back the file. A `wrapped`
+/// part stops mid-line; the newline after its body is not in the file.
+pub(crate) fn part_block(
+    path: &Path,
+    idx: usize,
+    total: usize,
+    marks: PartMarks,
+    body: &str,
+) -> String {
+    let path = path.to_slash_lossy();
+    let mut notes = String::new();
+    if marks.overlap > 0 {
+        notes.push_str(&format!(", overlap {}", marks.overlap));
+    }
+    if marks.wrapped {
+        notes.push_str(", wrapped");
+    }
+    if marks.truncated {
+        notes.push_str(", truncated");
+    }
+    format!(
+        "{PLAIN_PART_SEPARATOR} {path} (part {idx}/{total}{notes}) {PLAIN_PART_SEPARATOR}\n{}",
+        terminated(body)
+    )
+}
+
+/// The marker and one-line note standing in for a binary file, or `None`
+/// for text files.
+pub(crate) fn binary_block(
+    file: &FileContents,
+    separator: &str,
+) -> Option<String> {
+    let FileKind::Binary { size, sha256 } = &file.kind else {
+        return None;
+    };
+    Some(file_block(
+        &file.path,
+        &format!("[binary file, {}, sha256 {sha256}]", human_size(*size)),
+        separator,
+    ))
+}
+
+/// Concatenate every non-stubbed file under a `separator`-fenced path line.
+pub fn build_plain(
+    files: &[FileContents],
+    separator: &str,
+) -> String {
+    files
+        .iter()
+        .filter(|f| !f.stubbed)
+        .map(|f| {
+            binary_block(f, separator)
+                .unwrap_or_else(|| file_block(&f.path, &f.contents, separator))
+        })
+        .collect()
+}
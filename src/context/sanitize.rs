@@ -0,0 +1,121 @@
+//! `--sanitize`: C0 control characters in mostly-text files, such as ANSI
+//! colour codes in captured logs or stray NULs in snapshots. Files that are
+//! not text at all are still handled by binary detection in `detect`.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A C0 control character other than tab, newline and carriage return.
+fn is_stray_control(c: char) -> bool {
+    c < ' ' && !matches!(c, '\t' | '\n' | '\r')
+}
+
+/// After an ESC, consume the rest of a CSI (`ESC [ … final`) or OSC
+/// (`ESC ] … BEL` or `ESC ] … ESC \`) sequence. A truncated sequence stops
+/// at the first character that cannot belong to it; an OSC never runs past
+/// the end of its line.
+fn skip_escape_sequence(chars: &mut Peekable<Chars>) {
+    match chars.peek() {
+        Some('[') => {
+            chars.next();
+            while chars
+                .next_if(|c| ('\u{30}'..='\u{3f}').contains(c))
+                .is_some()
+            {}
+            while chars
+                .next_if(|c| ('\u{20}'..='\u{2f}').contains(c))
+                .is_some()
+            {}
+            chars.next_if(|c| ('\u{40}'..='\u{7e}').contains(c));
+        }
+        Some(']') => {
+            chars.next();
+            while let Some(c) = chars.next_if(|&c| c != '\n') {
+                if c == '\u{7}' {
+                    break;
+                }
+                if c == '\u{1b}' {
+                    chars.next_if_eq(&'\\');
+                    break;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Put `with` in place of each stray control character; an ANSI escape
+/// sequence counts as one. `None` when `text` has nothing to rewrite.
+fn rewrite(
+    text: &str,
+    with: &str,
+) -> Option<String> {
+    if !text.chars().any(is_stray_control) {
+        return None;
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !is_stray_control(c) {
+            out.push(c);
+            continue;
+        }
+        if c == '\u{1b}' {
+            skip_escape_sequence(&mut chars);
+        }
+        out.push_str(with);
+    }
+    Some(out)
+}
+
+/// `text` without its stray control characters and ANSI escape sequences,
+/// or `None` if it has none.
+pub fn strip(text: &str) -> Option<String> {
+    rewrite(text, "")
+}
+
+/// `text` with each stray control character or ANSI escape sequence
+/// replaced by U+FFFD, or `None` if it has none.
+pub fn replace(text: &str) -> Option<String> {
+    rewrite(text, "\u{FFFD}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_text_is_left_alone() {
+        assert_eq!(strip("fn main() {\n\tok();\r\n}\n"), None);
+        assert_eq!(replace("héllo ✓\n"), None);
+    }
+
+    #[test]
+    fn ansi_colour_sequences_are_removed_whole() {
+        let log = "\u{1b}[1;31mERROR\u{1b}[0m disk full\n";
+        assert_eq!(strip(log).unwrap(), "ERROR disk full\n");
+        assert_eq!(replace(log).unwrap(), "\u{FFFD}ERROR\u{FFFD} disk full\n");
+    }
+
+    #[test]
+    fn osc_titles_and_truncated_sequences() {
+        assert_eq!(strip("\u{1b}]0;title\u{7}done\n").unwrap(), "done\n");
+        assert_eq!(strip("\u{1b}]8;;url\u{1b}\\link\n").unwrap(), "link\n");
+        // No final byte: the sequence ends at the first character outside it
+        assert_eq!(strip("a\u{1b}[12\nb").unwrap(), "a\nb");
+        // A lone ESC only takes itself
+        assert_eq!(strip("a\u{1b}b").unwrap(), "ab");
+    }
+
+    #[test]
+    fn nul_bytes_and_other_c0_controls() {
+        let snapshot = "key\u{0}value\u{7}\u{8}\n";
+        assert_eq!(strip(snapshot).unwrap(), "keyvalue\n");
+        assert_eq!(
+            replace(snapshot).unwrap(),
+            "key\u{FFFD}value\u{FFFD}\u{FFFD}\n"
+        );
+        // DEL and C1 controls are outside C0 and stay
+        assert_eq!(strip("a\u{7f}\u{85}b"), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! User templates (`--template`) that replace the built-in XML envelope.
+//!
+//! Templates use Jinja syntax via `minijinja` and see four variables:
+//! `files` (every gathered file with its contents), `file_map` (the same
+//! entries as the XML file map), `chunks` (the chunk plan, one chunk when
+//! chunking is off) and `git` (branch, commits and changed files, or none).
+
+use crate::chunker::{ChunkBody, FileMeta};
+use crate::context::types::{FileContents, FileKind};
+use anyhow::{Result, anyhow};
+use minijinja::{Environment, UndefinedBehavior, Value, context};
+use path_slash::PathBufExt;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct TemplateFile<'a> {
+    id: usize,
+    path: String,
+    folder: String,
+    name: String,
+    contents: &'a str,
+    tokens: usize,
+    stubbed: bool,
+    external: bool,
+    binary: bool,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct TemplateMapEntry<'a> {
+    id: usize,
+    path: String,
+    tokens: usize,
+    parts: usize,
+    size: usize,
+    mtime: Option<&'a str>,
+    sha256: Option<&'a str>,
+    stubbed: bool,
+    external: bool,
+    binary: bool,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct TemplatePart<'a> {
+    id: usize,
+    path: String,
+    /// `"i/n"` when the file is split across chunks.
+    part: Option<String>,
+    /// Leading lines of `contents` repeated from the previous part.
+    overlap: usize,
+    contents: &'a str,
+}
+
+#[derive(Serialize)]
+struct TemplateChunk<'a> {
+    index: usize,
+    total: usize,
+    tokens: usize,
+    files: Vec<TemplatePart<'a>>,
+}
+
+/// What a template is rendered from.
+pub struct TemplateInput<'a> {
+    pub files: &'a [FileContents],
+    pub metas: &'a [FileMeta],
+    /// The chunk plan; empty renders all non-stubbed files as one chunk.
+    pub chunks: &'a [ChunkBody],
+    /// Repository state (a serialized `GitSnapshot`), or none.
+    pub git: Option<Value>,
+}
+
+/// The `git` variable: the repository snapshot, or none outside a work tree.
+#[cfg(feature = "git")]
+pub fn git_context() -> Option<Value> {
+    crate::context::git::snapshot().map(|snapshot| Value::from_serialize(&snapshot))
+}
+
+/// The `git` variable, always none when built without git support.
+#[cfg(not(feature = "git"))]
+pub fn git_context() -> Option<Value> {
+    None
+}
+
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    // A typo in a variable name should fail loudly, not render as empty
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_keep_trailing_newline(true);
+    env
+}
+
+/// Template error with its file name and line, followed by the offending
+/// source line. Referenced values are left out: they include file contents.
+fn describe(
+    err: &minijinja::Error,
+    source: &str,
+) -> anyhow::Error {
+    match err
+        .line()
+        .and_then(|n| Some((n, source.lines().nth(n.checked_sub(1)?)?)))
+    {
+        Some((n, line)) => anyhow!("{err}\n{n:>4} | {line}"),
+        None => anyhow!("{err}"),
+    }
+}
+
+/// Parse `source` so syntax errors surface before any files are gathered.
+pub fn check(
+    name: &str,
+    source: &str,
+) -> Result<()> {
+    let mut env = environment();
+    env.add_template(name, source)
+        .map_err(|e| describe(&e, source))?;
+    Ok(())
+}
+
+/// Render `source` (named `name` in error messages) with `input`.
+pub fn render(
+    name: &str,
+    source: &str,
+    input: &TemplateInput,
+) -> Result<String> {
+    let mut env = environment();
+    env.add_template(name, source)
+        .map_err(|e| describe(&e, source))?;
+    let files: Vec<TemplateFile> = input
+        .files
+        .iter()
+        .enumerate()
+        .map(|(id, f)| TemplateFile {
+            id,
+            path: f.path.to_slash_lossy().to_string(),
+            folder: f.folder.to_slash_lossy().to_string(),
+            name: f
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            contents: &f.contents,
+            tokens: input.metas.get(id).map_or(0, |m| m.tokens),
+            stubbed: f.stubbed,
+            external: f.external,
+            binary: matches!(f.kind, FileKind::Binary { .. }),
+            truncated: f.truncated,
+        })
+        .collect();
+    let file_map: Vec<TemplateMapEntry> = input
+        .metas
+        .iter()
+        .map(|m| TemplateMapEntry {
+            id: m.id,
+            path: m.path.to_slash_lossy().to_string(),
+            tokens: m.tokens,
+            parts: m.parts,
+            size: m.size,
+            mtime: m.mtime.as_deref(),
+            sha256: m.sha256.as_deref(),
+            stubbed: m.stubbed,
+            external: m.external,
+            binary: m.binary,
+            truncated: m.truncated,
+        })
+        .collect();
+    let chunks: Vec<TemplateChunk> = if input.chunks.is_empty() {
+        vec![TemplateChunk {
+            index: 1,
+            total: 1,
+            tokens: input.metas.iter().map(|m| m.tokens).sum(),
+            files: input
+                .files
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| !f.stubbed)
+                .map(|(id, f)| TemplatePart {
+                    id,
+                    path: f.path.to_slash_lossy().to_string(),
+                    part: None,
+                    overlap: 0,
+                    contents: &f.contents,
+                })
+                .collect(),
+        }]
+    } else {
+        let total = input.chunks.len();
+        input
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(idx, chunk)| TemplateChunk {
+                index: idx + 1,
+                total,
+                tokens: chunk.tokens,
+                files: chunk
+                    .blocks
+                    .iter()
+                    .map(|b| TemplatePart {
+                        id: b.file_id,
+                        path: input.files[b.file_id].path.to_slash_lossy().to_string(),
+                        part: b.part.map(|(i, n)| format!("{i}/{n}")),
+                        overlap: b.overlap,
+                        contents: &b.body,
+                    })
+                    .collect(),
+            })
+            .collect()
+    };
+    let template = env.get_template(name).map_err(|e| describe(&e, source))?;
+    template
+        .render(context! { files, file_map, chunks, git => input.git.clone() })
+        .map_err(|e| describe(&e, source))
+}
@@ -0,0 +1,566 @@
+//! Content rewrites that trade fidelity for tokens.
+//!
+//! `--squeeze` minifies whitespace. The whole file is rewritten line by line:
+//! trailing whitespace goes, runs of blank lines collapse to one, and leading
+//! indentation becomes one tab per level. Nesting survives, so Python stays
+//! readable, but alignment inside lines and whitespace that a format treats as
+//! meaningful (YAML indentation, Markdown hard breaks) do not, so Markdown
+//! and YAML files are left alone.
+//!
+//! `--strip-comments` drops line and block comments using per-language rules
+//! looked up by file extension; files in other languages are left alone. The
+//! scanner is a heuristic, not a parser: it follows string and character
+//! literals so comment markers inside them survive, but it does not know about
+//! nested block comments, heredocs or YAML block scalars.
+
+use std::path::Path;
+
+/// A quoted literal whose contents are never taken for comments.
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    /// Runs across newlines; otherwise an unclosed literal ends with its line.
+    multiline: bool,
+    /// A backslash escapes the character after it.
+    escapes: bool,
+}
+
+/// Comment rules for one language.
+struct Syntax {
+    /// Markers that comment out the rest of the line. A `#` only counts at
+    /// the start of a line or after whitespace.
+    line: &'static [&'static str],
+    /// Every `line` marker only counts where a `#` would, so the `//` of an
+    /// unquoted `url(http://…)` in SCSS is kept.
+    spaced_line: bool,
+    /// Opening and closing markers of block comments, checked before `line`.
+    block: &'static [(&'static str, &'static str)],
+    /// Literals, longest opening first.
+    quotes: &'static [Quote],
+    /// `'` opens a character literal (`'x'`, `'\n'`) when one follows, and is
+    /// otherwise plain code (Rust lifetimes, Haskell primes).
+    char_literals: bool,
+    /// Rust raw strings (`r"…"`, `br#"…"#`), closed by a `"` and as many `#`
+    /// as opened them, with no escapes.
+    raw_strings: bool,
+}
+
+const fn quote(
+    open: &'static str,
+    close: &'static str,
+    multiline: bool,
+    escapes: bool,
+) -> Quote {
+    Quote {
+        open,
+        close,
+        multiline,
+        escapes,
+    }
+}
+
+const DOUBLE: Quote = quote("\"", "\"", false, true);
+const SINGLE: Quote = quote("'", "'", false, true);
+const TRIPLE_DOUBLE: Quote = quote("\"\"\"", "\"\"\"", true, true);
+const C_BLOCK: (&str, &str) = ("/*", "*/");
+
+const RUST: Syntax = Syntax {
+    line: &["//"],
+    spaced_line: false,
+    block: &[C_BLOCK],
+    quotes: &[quote("\"", "\"", true, true)],
+    char_literals: true,
+    raw_strings: true,
+};
+const C_LIKE: Syntax = Syntax {
+    line: &["//"],
+    spaced_line: false,
+    block: &[C_BLOCK],
+    quotes: &[DOUBLE],
+    char_literals: true,
+    raw_strings: false,
+};
+const GO: Syntax = Syntax {
+    line: &["//"],
+    spaced_line: false,
+    block: &[C_BLOCK],
+    quotes: &[DOUBLE, quote("`", "`", true, false)],
+    char_literals: true,
+    raw_strings: false,
+};
+const JVM: Syntax = Syntax {
+    line: &["//"],
+    spaced_line: false,
+    block: &[C_BLOCK],
+    quotes: &[TRIPLE_DOUBLE, DOUBLE],
+    char_literals: true,
+    raw_strings: false,
+};
+const JAVASCRIPT: Syntax = Syntax {
+    line: &["//"],
+    spaced_line: false,
+    block: &[C_BLOCK],
+    quotes: &[DOUBLE, SINGLE, quote("`", "`", true, true)],
+    char_literals: false,
+    raw_strings: false,
+};
+const CSS: Syntax = Syntax {
+    line: &[],
+    spaced_line: false,
+    block: &[C_BLOCK],
+    quotes: &[DOUBLE, SINGLE],
+    char_literals: false,
+    raw_strings: false,
+};
+const SCSS: Syntax = Syntax {
+    line: &["//"],
+    spaced_line: true,
+    ..CSS
+};
+const PYTHON: Syntax = Syntax {
+    line: &["#"],
+    spaced_line: false,
+    block: &[],
+    quotes: &[
+        TRIPLE_DOUBLE,
+        quote("'''", "'''", true, true),
+        DOUBLE,
+        SINGLE,
+    ],
+    char_literals: false,
+    raw_strings: false,
+};
+const HASH: Syntax = Syntax {
+    line: &["#"],
+    spaced_line: false,
+    block: &[],
+    quotes: &[DOUBLE, SINGLE],
+    char_literals: false,
+    raw_strings: false,
+};
+const SQL: Syntax = Syntax {
+    line: &["--"],
+    spaced_line: false,
+    block: &[C_BLOCK],
+    quotes: &[
+        quote("'", "'", false, false),
+        quote("\"", "\"", false, false),
+    ],
+    char_literals: false,
+    raw_strings: false,
+};
+const LUA: Syntax = Syntax {
+    line: &["--"],
+    spaced_line: false,
+    block: &[("--[[", "]]")],
+    quotes: &[DOUBLE, SINGLE],
+    char_literals: false,
+    raw_strings: false,
+};
+const HASKELL: Syntax = Syntax {
+    line: &["--"],
+    spaced_line: false,
+    block: &[("{-", "-}")],
+    quotes: &[DOUBLE],
+    char_literals: true,
+    raw_strings: false,
+};
+const MARKUP: Syntax = Syntax {
+    line: &[],
+    spaced_line: false,
+    block: &[("<!--", "-->")],
+    quotes: &[],
+    char_literals: false,
+    raw_strings: false,
+};
+
+/// Languages `--strip-comments` knows, by lowercase file extension. The
+/// crate had no extension-to-language table before this one, so it serves
+/// only `--strip-comments`.
+const LANGUAGES: &[(&[&str], &Syntax)] = &[
+    (&["rs"], &RUST),
+    (
+        &[
+            "c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "cs", "java",
+        ],
+        &C_LIKE,
+    ),
+    (&["go"], &GO),
+    (&["kt", "kts", "scala", "swift"], &JVM),
+    (
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts"],
+        &JAVASCRIPT,
+    ),
+    (&["css"], &CSS),
+    (&["scss", "less"], &SCSS),
+    (&["py", "pyi"], &PYTHON),
+    (&["rb", "toml", "yaml", "yml"], &HASH),
+    (&["sql"], &SQL),
+    (&["lua"], &LUA),
+    (&["hs"], &HASKELL),
+    (&["html", "htm", "xml", "svg", "xhtml"], &MARKUP),
+];
+
+/// Comment rules for the file at `path`, or `None` for an unknown language.
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(exts, _)| exts.contains(&ext.as_str()))
+        .map(|(_, syntax)| *syntax)
+}
+
+/// Byte length of the character literal at the start of `rest` (which
+/// starts with `'`), or 1 when the quote does not open one.
+fn char_literal_len(rest: &str) -> usize {
+    let body = &rest[1..];
+    if let Some(escaped) = body.strip_prefix('\\') {
+        return escaped
+            .char_indices()
+            .skip(1)
+            .take(10)
+            .find(|&(_, c)| c == '\'' || c == '\n')
+            .filter(|&(_, c)| c == '\'')
+            .map_or(1, |(i, _)| i + 3);
+    }
+    match body.chars().next() {
+        Some(c) if c != '\'' && c != '\n' && body[c.len_utf8()..].starts_with('\'') => {
+            c.len_utf8() + 2
+        }
+        _ => 1,
+    }
+}
+
+/// Byte length of the raw string literal at the start of `rest` (`r"…"`,
+/// `br##"…"##`), to the end of `rest` when unclosed, or `None` when `rest`
+/// does not start one.
+fn raw_string_len(rest: &str) -> Option<usize> {
+    let body = rest.strip_prefix('b').unwrap_or(rest).strip_prefix('r')?;
+    let hashes = body.len() - body.trim_start_matches('#').len();
+    let body = body[hashes..].strip_prefix('"')?;
+    let open = rest.len() - body.len();
+    let close = format!("\"{}", "#".repeat(hashes));
+    Some(
+        body.find(&close)
+            .map_or(rest.len(), |i| open + i + close.len()),
+    )
+}
+
+/// Append one output line. A line that held a comment loses the whitespace
+/// before it, and is dropped when nothing else is left.
+fn finish_line(
+    out: &mut String,
+    line: &mut String,
+    had_comment: bool,
+    newline: bool,
+) {
+    if had_comment {
+        let kept = line.trim_end();
+        if kept.trim_start().is_empty() {
+            line.clear();
+            return;
+        }
+        out.push_str(kept);
+    } else {
+        out.push_str(line);
+    }
+    if newline {
+        out.push('\n');
+    }
+    line.clear();
+}
+
+/// `text` without the comments `syntax` describes.
+fn strip_with(
+    syntax: &Syntax,
+    text: &str,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    // A shebang is a directive, not a comment
+    if text.starts_with("#!") {
+        let end = text.find('\n').map_or(text.len(), |i| i + 1);
+        out.push_str(&text[..end]);
+        rest = &text[end..];
+    }
+    let mut line = String::new();
+    let mut had_comment = false;
+    let mut block: Option<&str> = None;
+    let mut string: Option<&Quote> = None;
+    while let Some(c) = rest.chars().next() {
+        let step = if c == '\n' {
+            finish_line(&mut out, &mut line, had_comment, true);
+            had_comment = block.is_some();
+            string = string.filter(|q| q.multiline);
+            1
+        } else if let Some(close) = block {
+            if rest.starts_with(close) {
+                block = None;
+                close.len()
+            } else {
+                c.len_utf8()
+            }
+        } else if let Some(q) = string {
+            let len = if q.escapes && c == '\\' {
+                rest[1..]
+                    .chars()
+                    .next()
+                    .filter(|&next| next != '\n')
+                    .map_or(1, |next| 1 + next.len_utf8())
+            } else if rest.starts_with(q.close) {
+                string = None;
+                q.close.len()
+            } else {
+                c.len_utf8()
+            };
+            line.push_str(&rest[..len]);
+            len
+        } else if let Some((open, close)) =
+            syntax.block.iter().find(|(open, _)| rest.starts_with(open))
+        {
+            had_comment = true;
+            block = Some(close);
+            open.len()
+        } else if syntax.line.iter().any(|marker| {
+            rest.starts_with(marker)
+                && ((*marker != "#" && !syntax.spaced_line)
+                    || line.chars().last().is_none_or(char::is_whitespace))
+        }) {
+            had_comment = true;
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(len) = syntax
+            .raw_strings
+            .then(|| raw_string_len(rest))
+            .flatten()
+            .filter(|_| !line.ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+        {
+            line.push_str(&rest[..len]);
+            len
+        } else if let Some(q) = syntax.quotes.iter().find(|q| rest.starts_with(q.open)) {
+            string = Some(q);
+            line.push_str(q.open);
+            q.open.len()
+        } else {
+            let len = if syntax.char_literals && c == '\'' {
+                char_literal_len(rest)
+            } else {
+                c.len_utf8()
+            };
+            line.push_str(&rest[..len]);
+            len
+        };
+        rest = &rest[step..];
+    }
+    finish_line(&mut out, &mut line, had_comment, false);
+    out
+}
+
+/// `text` without its comments, by the rules for the language of `path`,
+/// or `None` when the language is unknown or there is nothing to strip.
+pub fn strip_comments(
+    path: &Path,
+    text: &str,
+) -> Option<String> {
+    let stripped = strip_with(syntax_for(path)?, text);
+    (stripped != text).then_some(stripped)
+}
+
+/// Whether `--squeeze` rewrites the file at `path`. Markdown is skipped: its
+/// code blocks and list nesting depend on exact spaces. So is YAML, which
+/// does not allow tabs in indentation.
+pub fn squeezable(path: &Path) -> bool {
+    !path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "md" | "markdown" | "mdx" | "yaml" | "yml"
+            )
+        })
+}
+
+/// Leading tabs and spaces of `line`, in that order.
+fn leading(line: &str) -> (usize, usize) {
+    let tabs = line.len() - line.trim_start_matches('\t').len();
+    let rest = &line[tabs..];
+    (tabs, rest.len() - rest.trim_start_matches(' ').len())
+}
+
+/// Spaces per indentation level: the narrowest run of two or more leading
+/// spaces on a non-blank line, or 4 when there is none. Single spaces are
+/// taken as alignment (` * ` in block comments), not a level.
+fn indent_unit(text: &str) -> usize {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| leading(line).1)
+        .filter(|&spaces| spaces >= 2)
+        .min()
+        .unwrap_or(4)
+}
+
+/// `text` with trailing whitespace stripped, blank-line runs collapsed to
+/// one, and leading indentation converted to a tab per `indent_unit` level
+/// (leftover alignment spaces stay), or `None` if nothing changes.
+pub fn squeeze(text: &str) -> Option<String> {
+    let unit = indent_unit(text);
+    let mut out = String::with_capacity(text.len());
+    let mut previous_blank = false;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !previous_blank {
+                out.push('\n');
+            }
+            previous_blank = true;
+            continue;
+        }
+        previous_blank = false;
+        let (tabs, spaces) = leading(line);
+        out.extend(std::iter::repeat_n('\t', tabs + spaces / unit));
+        out.extend(std::iter::repeat_n(' ', spaces % unit));
+        out.push_str(&line[tabs + spaces..]);
+        out.push('\n');
+    }
+    if !text.ends_with('\n') && !text.is_empty() {
+        out.pop();
+    }
+    (out != text).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_indentation_becomes_tabs() {
+        let rust = "fn main() {    \n    let x = 1;\n\n\n\n    if x > 0 {\n        println!(\"{x}\");\n    }\n}\n";
+        assert_eq!(
+            squeeze(rust).unwrap(),
+            "fn main() {\n\tlet x = 1;\n\n\tif x > 0 {\n\t\tprintln!(\"{x}\");\n\t}\n}\n"
+        );
+        // Block comment alignment is not an indentation level
+        let doc = "    /**\n     * Docs.\n     */\n";
+        assert_eq!(squeeze(doc).unwrap(), "\t/**\n\t * Docs.\n\t */\n");
+    }
+
+    #[test]
+    fn python_nesting_survives_with_two_space_indents() {
+        let python = "def f(xs):\n  for x in xs:\n    if x:\n      yield x\n\n\n\ndef g():  \n  return [\n      1,\n  ]\n";
+        let squeezed = squeeze(python).unwrap();
+        assert_eq!(
+            squeezed,
+            "def f(xs):\n\tfor x in xs:\n\t\tif x:\n\t\t\tyield x\n\ndef g():\n\treturn [\n\t\t\t1,\n\t]\n"
+        );
+        // Already squeezed text is left alone
+        assert_eq!(squeeze(&squeezed), None);
+    }
+
+    #[test]
+    fn odd_alignment_and_existing_tabs_are_kept() {
+        let text = "call(a,\n     b)\n\t    mixed\n";
+        assert_eq!(squeeze(text).unwrap(), "call(a,\n\t b)\n\t\tmixed\n");
+        assert_eq!(squeeze("no newline   "), Some("no newline".to_string()));
+        assert_eq!(squeeze(""), None);
+    }
+
+    fn strip(
+        path: &str,
+        text: &str,
+    ) -> Option<String> {
+        strip_comments(Path::new(path), text)
+    }
+
+    #[test]
+    fn rust_comments_go_but_strings_keep_their_markers() {
+        let rust = r##"// Copyright 2024 Example Corp.
+// Licensed under MIT.
+
+/// Adds one.
+fn add<'a>(x: &'a str) -> String { // trailing note
+    let url = "https://example.com // not a comment";
+    let c = '"'; /* block */ let d = '\'';
+    /* spans
+       lines */
+    let raw = r#"/* kept */"#;
+    format!("{x}{url}{c}{d}{raw}")
+}
+"##;
+        assert_eq!(
+            strip("src/lib.rs", rust).unwrap(),
+            r##"
+fn add<'a>(x: &'a str) -> String {
+    let url = "https://example.com // not a comment";
+    let c = '"';  let d = '\'';
+    let raw = r#"/* kept */"#;
+    format!("{x}{url}{c}{d}{raw}")
+}
+"##
+        );
+    }
+
+    #[test]
+    fn rust_raw_strings_have_no_escapes_and_counted_hashes() {
+        let rust = r###"let p = r"C:\"; // dir
+let s = "// kept";
+let q = r##"a"#b // in"##; /* gone */
+let b = br"\"; let id = r#type; // note
+"###;
+        assert_eq!(
+            strip("src/main.rs", rust).unwrap(),
+            r###"let p = r"C:\";
+let s = "// kept";
+let q = r##"a"#b // in"##;
+let b = br"\"; let id = r#type;
+"###
+        );
+    }
+
+    #[test]
+    fn python_hash_comments_skip_strings_and_the_shebang() {
+        let python = "#!/usr/bin/env python3\n# License header\ndef f():\n    \"\"\"Docstring # kept.\"\"\"\n    s = 'a # b'  # why\n    return s\n";
+        assert_eq!(
+            strip("tool.py", python).unwrap(),
+            "#!/usr/bin/env python3\ndef f():\n    \"\"\"Docstring # kept.\"\"\"\n    s = 'a # b'\n    return s\n"
+        );
+    }
+
+    #[test]
+    fn markup_and_sql_comments() {
+        assert_eq!(
+            strip("index.html", "<!-- banner -->\n<p>it's -- fine</p>\n").unwrap(),
+            "<p>it's -- fine</p>\n"
+        );
+        assert_eq!(
+            strip("q.sql", "SELECT '--x' -- note\nFROM t;").unwrap(),
+            "SELECT '--x'\nFROM t;"
+        );
+    }
+
+    #[test]
+    fn scss_line_comments_need_whitespace_before_them() {
+        let scss = "// Theme\n.logo { background: url(http://cdn/x.png); } // why\n";
+        assert_eq!(
+            strip("theme.scss", scss).unwrap(),
+            ".logo { background: url(http://cdn/x.png); }\n"
+        );
+        assert_eq!(strip("a.less", "a { b: url(//cdn/x.png); }\n"), None);
+    }
+
+    #[test]
+    fn unknown_languages_and_clean_files_are_left_alone() {
+        assert_eq!(strip("notes.txt", "// looks like a comment\n"), None);
+        assert_eq!(strip("Makefile", "# target\n"), None);
+        assert_eq!(strip("main.rs", "fn main() {}\n"), None);
+    }
+
+    #[test]
+    fn markdown_is_not_squeezable() {
+        assert!(squeezable(Path::new("src/lib.rs")));
+        assert!(squeezable(Path::new("Makefile")));
+        assert!(!squeezable(Path::new("docs/README.MD")));
+        assert!(!squeezable(Path::new("notes.markdown")));
+        assert!(!squeezable(Path::new(".github/workflows/ci.yml")));
+        assert!(!squeezable(Path::new("config.YAML")));
+    }
+}
@@ -1,9 +1,76 @@
 use std::path::PathBuf;
 
+/// What kind of content a gathered file holds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FileKind {
+    #[default]
+    Text,
+    /// A binary file named explicitly on the command line. It is rendered as
+    /// a `<file-binary>` placeholder and `contents` stays empty.
+    Binary { size: u64, sha256: String },
+}
+
+/// Lines `start..=end` of a file, counted from 1, as selected by a
+/// `path:start-end` or `path:start+count` argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl std::fmt::Display for LineRange {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
 /// Contents of a file with its folder and path metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct FileContents {
     pub folder: PathBuf,
     pub path: PathBuf,
     pub contents: String,
+    /// Listed in the file map with its metadata, but its contents are never rendered.
+    pub stubbed: bool,
+    /// Stubbed by `--dedup-contents` as a copy of the file at this path.
+    pub duplicate_of: Option<PathBuf>,
+    /// Lives outside the git repository that contains the working directory.
+    pub external: bool,
+    /// Added by `--include-readmes` as orientation for its folder.
+    pub via_readme: bool,
+    /// Moved to the front of the ordering by `--first`.
+    pub priority: bool,
+    /// Cut to `--max-size` by `--truncate-oversize`; `contents` ends with
+    /// the truncation marker.
+    pub truncated: bool,
+    /// Encoding the file was decoded from (`UTF-16LE`, `windows-1252`, ...),
+    /// or `None` for UTF-8.
+    pub encoding: Option<String>,
+    /// Had CRLF line endings, converted to LF by `--normalize-eol`.
+    pub crlf: bool,
+    /// The lines kept by a `path:start-end` argument; `contents` holds only
+    /// those lines.
+    pub lines: Option<LineRange>,
+    /// Token count of the raw contents, when already computed (e.g. for `--sort tokens`).
+    pub tokens: Option<usize>,
+    /// Token count before the first content transformation, if any changed it.
+    pub original_tokens: Option<usize>,
+    pub kind: FileKind,
+    /// Byte size on disk, when the file was read from disk.
+    pub size: Option<u64>,
+    /// Last modification time as RFC 3339, when the platform reports one.
+    pub mtime: Option<String>,
+    /// SHA-256 hex digest of the bytes on disk, unless `--no-hashes`.
+    pub sha256: Option<String>,
+}
+
+impl FileContents {
+    /// A text file with no contents, rendered as a self-closing
+    /// `<file-contents empty="true"/>`.
+    pub fn is_empty(&self) -> bool {
+        self.kind == FileKind::Text && self.contents.is_empty()
+    }
 }
@@ -0,0 +1,283 @@
+//! `--validate-xml`: a strict well-formedness pass over the rendered output,
+//! run before anything is printed or copied.
+
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::ops::Range;
+
+const ENVELOPE_OPEN: &str = "<shared-context>\n";
+const ENVELOPE_CLOSE: &str = "</shared-context>\n";
+
+/// The first well-formedness problem found in a rendered output.
+#[derive(Debug)]
+pub struct XmlProblem {
+    /// Byte offset into the checked text.
+    pub offset: usize,
+    /// 1-based line of `offset`.
+    pub line: usize,
+    /// `path` of the file whose contents or attributes hold the problem.
+    pub path: Option<String>,
+    pub message: String,
+    /// The character XML 1.0 does not allow, when that is the problem.
+    pub invalid_char: Option<char>,
+}
+
+impl XmlProblem {
+    /// How to get rid of the problem, as one sentence.
+    pub fn suggestion(&self) -> String {
+        match (self.invalid_char, &self.path) {
+            (Some(c), Some(path)) if c.is_ascii() => format!(
+                "strip it from the file (e.g. `tr -d '\\{:03o}' < {path}`) or leave the file out with --exclude-paths {path}",
+                c as u32
+            ),
+            (Some(_), Some(path)) => {
+                format!(
+                    "remove the character from {path} or leave the file out with --exclude-paths {path}"
+                )
+            }
+            (Some(_), None) => "remove the character from the source it came from".to_string(),
+            (None, _) => "raw file contents can contain markup; rerun with --escape-xml or --cdata"
+                .to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for XmlProblem {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at byte {} (line {})",
+            self.message, self.offset, self.line
+        )?;
+        if let Some(path) = &self.path {
+            write!(f, " in {path}")?;
+        }
+        write!(f, "; {}", self.suggestion())
+    }
+}
+
+impl std::error::Error for XmlProblem {}
+
+/// XML 1.0 `Char`: tab, newline, carriage return and everything from space
+/// up, except the noncharacters U+FFFE and U+FFFF. Surrogates cannot occur
+/// in a Rust string.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// The first character `is_xml_char` rejects and its byte offset in `text`.
+fn first_invalid(text: &[u8]) -> Option<(usize, char)> {
+    String::from_utf8_lossy(text)
+        .char_indices()
+        .find(|&(_, c)| !is_xml_char(c))
+}
+
+/// The `path` attribute of `tag`, unescaped.
+fn path_of(tag: &BytesStart) -> Option<String> {
+    tag.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == b"path")
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+struct Checker<'a> {
+    xml: &'a str,
+    /// Where the parsed range starts in `xml`.
+    base: usize,
+    /// Open elements, each with the file path it belongs to.
+    open: Vec<(Vec<u8>, Option<String>)>,
+}
+
+impl Checker<'_> {
+    fn problem(
+        &self,
+        offset: usize,
+        path: Option<String>,
+        message: String,
+        invalid_char: Option<char>,
+    ) -> XmlProblem {
+        let offset = (self.base + offset).min(self.xml.len());
+        XmlProblem {
+            offset,
+            line: self.xml.as_bytes()[..offset]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count()
+                + 1,
+            path: path.or_else(|| self.current_path()),
+            message,
+            invalid_char,
+        }
+    }
+
+    /// The file the innermost open `<file-contents>` element holds.
+    fn current_path(&self) -> Option<String> {
+        self.open
+            .iter()
+            .rev()
+            .find(|(name, _)| name == b"file-contents")
+            .and_then(|(_, path)| path.clone())
+    }
+
+    fn check_text(
+        &self,
+        text: &[u8],
+        offset: usize,
+    ) -> Result<(), XmlProblem> {
+        if let Some((at, c)) = first_invalid(text) {
+            return Err(self.problem(
+                offset + at,
+                None,
+                format!("character U+{:04X} is not allowed in XML", c as u32),
+                Some(c),
+            ));
+        }
+        match text.windows(3).position(|w| w == b"]]>") {
+            Some(at) => Err(self.problem(
+                offset + at,
+                None,
+                "`]]>` is not allowed in text content".to_string(),
+                None,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn check_attributes(
+        &self,
+        tag: &BytesStart,
+        offset: usize,
+    ) -> Result<(), XmlProblem> {
+        let path = path_of(tag);
+        for attr in tag.attributes() {
+            let attr = attr.map_err(|e| {
+                self.problem(
+                    offset,
+                    path.clone(),
+                    format!("malformed attribute: {e}"),
+                    None,
+                )
+            })?;
+            let value = attr.unescape_value().map_err(|e| {
+                self.problem(
+                    offset,
+                    path.clone(),
+                    format!("malformed attribute value: {e}"),
+                    None,
+                )
+            })?;
+            if let Some((_, c)) = first_invalid(value.as_bytes()) {
+                return Err(self.problem(
+                    offset,
+                    path,
+                    format!(
+                        "character U+{:04X} is not allowed in an XML attribute",
+                        c as u32
+                    ),
+                    Some(c),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse `xml` strictly: tags must nest, attributes and entity references
+/// must be well formed, and text may only hold XML 1.0 characters.
+pub fn check_well_formed(xml: &str) -> Result<(), XmlProblem> {
+    check_range(xml, 0..xml.len())
+}
+
+/// Like `check_well_formed`, for one chunk of a chunked output. The first
+/// chunk opens `<shared-context>` and the last one closes it, so a chunk may
+/// start or end with that tag unmatched.
+pub fn check_chunk(xml: &str) -> Result<(), XmlProblem> {
+    let start = if xml.starts_with(ENVELOPE_OPEN) {
+        ENVELOPE_OPEN.len()
+    } else {
+        0
+    };
+    let end = if xml[start..].ends_with(ENVELOPE_CLOSE) {
+        xml.len() - ENVELOPE_CLOSE.len()
+    } else {
+        xml.len()
+    };
+    check_range(xml, start..end)
+}
+
+fn check_range(
+    xml: &str,
+    range: Range<usize>,
+) -> Result<(), XmlProblem> {
+    let mut reader = Reader::from_str(&xml[range.clone()]);
+    let mut checker = Checker {
+        xml,
+        base: range.start,
+        open: Vec::new(),
+    };
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader.read_event().map_err(|e| {
+            checker.problem(reader.error_position() as usize, None, e.to_string(), None)
+        })?;
+        match event {
+            Event::Start(tag) => {
+                checker.check_attributes(&tag, start)?;
+                let path = if tag.name().as_ref() == b"file-contents" {
+                    path_of(&tag)
+                } else {
+                    None
+                };
+                checker.open.push((tag.name().as_ref().to_vec(), path));
+            }
+            Event::Empty(tag) => checker.check_attributes(&tag, start)?,
+            Event::End(_) => {
+                checker.open.pop();
+            }
+            Event::Text(text) => checker.check_text(&text, start)?,
+            Event::CData(text) => checker.check_text(&text, start + "<![CDATA[".len())?,
+            Event::GeneralRef(reference) => {
+                let resolved = reference.resolve_char_ref().map_err(|e| {
+                    checker.problem(start, None, format!("bad character reference: {e}"), None)
+                })?;
+                match resolved {
+                    Some(c) if !is_xml_char(c) => {
+                        return Err(checker.problem(
+                            start,
+                            None,
+                            format!(
+                                "character reference to U+{:04X} is not allowed in XML",
+                                c as u32
+                            ),
+                            Some(c),
+                        ));
+                    }
+                    Some(_) => {}
+                    None if matches!(&*reference, b"lt" | b"gt" | b"amp" | b"apos" | b"quot") => {}
+                    None => {
+                        return Err(checker.problem(
+                            start,
+                            None,
+                            format!("undefined entity &{};", String::from_utf8_lossy(&reference)),
+                            None,
+                        ));
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    match checker.open.last() {
+        Some((name, _)) => Err(checker.problem(
+            range.len(),
+            None,
+            format!("<{}> is never closed", String::from_utf8_lossy(name)),
+            None,
+        )),
+        None => Ok(()),
+    }
+}
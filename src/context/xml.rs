@@ -1,5 +1,7 @@
-use crate::context::types::FileContents;
-use crate::tokenizer::count as count_tokens;
+use crate::chunker::{FileMeta, build_file_meta};
+use crate::context::gather::SkippedFile;
+use crate::context::header::render_file_map;
+use crate::context::types::{FileContents, FileKind};
 use anyhow::Result;
 use path_slash::PathBufExt;
 use std::borrow::Cow;
@@ -49,6 +51,12 @@ pub(crate) fn maybe_escape_attr<'a>(
     }
 }
 
+/// Wrap `text` in a CDATA section. A literal `]]>` would end the section
+/// early, so it is split across two sections (`]]` then `>`), per the XML spec.
+pub(crate) fn cdata_section(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
 pub(crate) fn raw_content_may_break_structure(s: &str) -> bool {
     [
         "</file-contents>",
@@ -64,6 +72,87 @@ pub(crate) fn raw_content_may_break_structure(s: &str) -> bool {
     .any(|marker| s.contains(marker))
 }
 
+/// Prefix every line of `contents` with its 1-based number, right-aligned
+/// to the widest number, and `: `. Numbering happens before splitting and
+/// escaping, so file parts keep absolute line numbers and escaping never
+/// touches the prefixes.
+pub fn number_lines(contents: &str) -> String {
+    number_lines_from(contents, 1)
+}
+
+/// `number_lines` for a slice of a file whose first line is line `first`.
+pub fn number_lines_from(
+    contents: &str,
+    first: usize,
+) -> String {
+    let lines = contents.split_inclusive('\n').count();
+    let width = (first + lines.saturating_sub(1)).to_string().len();
+    let mut out = String::with_capacity(contents.len() + lines * (width + 2));
+    for (idx, line) in contents.split_inclusive('\n').enumerate() {
+        out.push_str(&format!("{:>width$}: {line}", first + idx));
+    }
+    out
+}
+
+/// Byte size for display: `512B`, `14KB`, `2.3MB`.
+pub(crate) fn human_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    if bytes < KB {
+        format!("{bytes}B")
+    } else if bytes < MB {
+        format!("{}KB", (bytes + KB / 2) / KB)
+    } else {
+        format!("{:.1}MB", bytes as f64 / MB as f64)
+    }
+}
+
+/// The `<file-binary .../>` placeholder rendered in place of a binary file's
+/// contents, or `None` for text files.
+pub(crate) fn binary_placeholder(
+    file: &FileContents,
+    indent: &str,
+    escape_xml: bool,
+) -> Option<String> {
+    let FileKind::Binary { size, sha256 } = &file.kind else {
+        return None;
+    };
+    let path = file.path.to_slash_lossy().to_string();
+    Some(format!(
+        "{indent}<file-binary path=\"{}\" size=\"{}\" sha256=\"{sha256}\"/>\n",
+        maybe_escape_attr(&path, escape_xml),
+        human_size(*size)
+    ))
+}
+
+/// ` truncated="true"` for a file cut by `--truncate-oversize`, else empty.
+pub(crate) fn truncated_attr(file: &FileContents) -> &'static str {
+    if file.truncated {
+        " truncated=\"true\""
+    } else {
+        ""
+    }
+}
+
+/// ` encoding="…"` for a file decoded from UTF-16 or windows-1252, else empty.
+pub(crate) fn encoding_attr(file: &FileContents) -> String {
+    file.encoding
+        .as_ref()
+        .map_or_else(String::new, |encoding| format!(" encoding=\"{encoding}\""))
+}
+
+/// ` eol="crlf"` for a file whose CRLF line endings `--normalize-eol`
+/// converted, else empty.
+pub(crate) fn eol_attr(file: &FileContents) -> &'static str {
+    if file.crlf { " eol=\"crlf\"" } else { "" }
+}
+
+/// ` lines="100-250"` for a file cut to a line range, else empty.
+pub(crate) fn lines_attr(file: &FileContents) -> String {
+    file.lines
+        .map_or_else(String::new, |range| format!(" lines=\"{range}\""))
+}
+
 /// Builds a simple XML-like structure grouping files by folder.
 pub fn build_xml(files: &[FileContents]) -> Result<String> {
     build_xml_with_escape(files, false)
@@ -74,24 +163,78 @@ pub fn build_xml_with_escape(
     files: &[FileContents],
     escape_xml: bool,
 ) -> Result<String> {
+    let metas = build_file_meta(files, escape_xml);
+    let style = XmlStyle {
+        escape_xml,
+        ..XmlStyle::default()
+    };
+    build_xml_with_meta(files, &metas, &[], &style)
+}
+
+/// Builds the folder-grouped XML with each file body in a CDATA section
+/// instead of entity-escaped.
+pub fn build_xml_with_cdata(files: &[FileContents]) -> Result<String> {
+    let metas = build_file_meta(files, false);
+    let style = XmlStyle {
+        cdata: true,
+        ..XmlStyle::default()
+    };
+    build_xml_with_meta(files, &metas, &[], &style)
+}
+
+/// How `build_xml_with_meta` renders. The default escapes nothing and puts
+/// the file map first; set only what differs with struct-update syntax.
+#[derive(Debug, Clone, Copy)]
+pub struct XmlStyle {
+    /// Entity-escape paths and file bodies.
+    pub escape_xml: bool,
+    /// Wrap file bodies in CDATA sections (`escape_xml` should then be off).
+    pub cdata: bool,
+    /// Render the file map; left out entirely when off.
+    pub file_map: bool,
+    /// Put the file map after the folders instead of before them.
+    pub map_last: bool,
+}
+
+impl Default for XmlStyle {
+    fn default() -> Self {
+        Self {
+            escape_xml: false,
+            cdata: false,
+            file_map: true,
+            map_last: false,
+        }
+    }
+}
+
+/// Builds the folder-grouped XML using precomputed file metadata for the file
+/// map, placed as `style` says; `skipped` files are listed at the end of the
+/// map.
+pub fn build_xml_with_meta(
+    files: &[FileContents],
+    metas: &[FileMeta],
+    skipped: &[SkippedFile],
+    style: &XmlStyle,
+) -> Result<String> {
+    let XmlStyle {
+        escape_xml,
+        cdata,
+        file_map,
+        map_last,
+    } = *style;
+    let map = if file_map {
+        render_file_map(metas, skipped, escape_xml)
+    } else {
+        String::new()
+    };
     let mut xml = String::new();
     xml.push_str("<shared-context>\n");
-    // File map section
-    xml.push_str(&format!("  <file-map total-files=\"{}\">\n", files.len()));
-    for (id, file) in files.iter().enumerate() {
-        let path = file.path.to_slash_lossy().to_string();
-        let path_attr = maybe_escape_attr(&path, escape_xml);
-        let contents = maybe_escape_text(&file.contents, escape_xml);
-        let tokens = count_tokens(contents.as_ref());
-        xml.push_str(&format!(
-            "    <file id=\"{id}\" path=\"{path}\" tokens=\"{tokens}\" parts=\"1\"/>\n",
-            path = path_attr
-        ));
+    if !map_last {
+        xml.push_str(&map);
     }
-    xml.push_str("  </file-map>\n");
     // Group by folder
     let mut current_folder: Option<String> = None;
-    for file in files {
+    for file in files.iter().filter(|f| !f.stubbed) {
         let folder = file.folder.to_slash_lossy().to_string();
         let folder_display = if folder.is_empty() {
             ".".to_string()
@@ -109,6 +252,10 @@ pub fn build_xml_with_escape(
                 folder = folder_attr
             ));
         }
+        if let Some(placeholder) = binary_placeholder(file, "    ", escape_xml) {
+            xml.push_str(&placeholder);
+            continue;
+        }
         let path = file.path.to_slash_lossy().to_string();
         let name = file
             .path
@@ -117,11 +264,25 @@ pub fn build_xml_with_escape(
             .unwrap_or_default();
         let path_attr = maybe_escape_attr(&path, escape_xml);
         let name_attr = maybe_escape_attr(&name, escape_xml);
-        let contents = maybe_escape_text(&file.contents, escape_xml);
+        if file.is_empty() {
+            xml.push_str(&format!(
+                "    <file-contents path=\"{path_attr}\" name=\"{name_attr}\" empty=\"true\"/>\n"
+            ));
+            continue;
+        }
+        let contents = if cdata {
+            Cow::Owned(cdata_section(&file.contents))
+        } else {
+            maybe_escape_text(&file.contents, escape_xml)
+        };
         xml.push_str(&format!(
-            "    <file-contents path=\"{path}\" name=\"{name}\">\n",
+            "    <file-contents path=\"{path}\" name=\"{name}\"{encoding}{eol}{lines}{truncated}>\n",
             path = path_attr,
-            name = name_attr
+            name = name_attr,
+            encoding = encoding_attr(file),
+            eol = eol_attr(file),
+            lines = lines_attr(file),
+            truncated = truncated_attr(file)
         ));
         // Raw contents:
         xml.push_str(contents.as_ref());
@@ -131,6 +292,71 @@ pub fn build_xml_with_escape(
     if current_folder.is_some() {
         xml.push_str("  </folder>\n");
     }
+    if map_last {
+        xml.push_str(&map);
+    }
     xml.push_str("</shared-context>\n");
     Ok(xml)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn binary_files_render_as_placeholders() {
+        assert_eq!(human_size(512), "512B");
+        assert_eq!(human_size(14 * 1024 + 100), "14KB");
+        assert_eq!(human_size(5 * 1024 * 1024 / 2), "2.5MB");
+
+        let logo = FileContents {
+            folder: PathBuf::from("assets"),
+            path: PathBuf::from("assets/logo.png"),
+            kind: FileKind::Binary {
+                size: 14 * 1024,
+                sha256: "ab12".into(),
+            },
+            ..Default::default()
+        };
+        let xml = build_xml(&[logo]).unwrap();
+        assert!(xml.contains(
+            "    <file-binary path=\"assets/logo.png\" size=\"14KB\" sha256=\"ab12\"/>\n"
+        ));
+        assert!(!xml.contains("<file-contents"));
+        assert!(xml.contains(
+            r#"path="assets/logo.png" tokens="0" parts="1" size="14336" binary="true"/>"#
+        ));
+    }
+
+    #[test]
+    fn empty_files_render_as_self_closing_tags() {
+        let empty = FileContents {
+            path: PathBuf::from("src/__init__.py"),
+            folder: PathBuf::from("src"),
+            ..Default::default()
+        };
+        let xml = build_xml(&[empty]).unwrap();
+        assert!(xml.contains(
+            "    <file-contents path=\"src/__init__.py\" name=\"__init__.py\" empty=\"true\"/>\n"
+        ));
+        assert!(!xml.contains("</file-contents>"));
+    }
+
+    #[test]
+    fn number_lines_right_aligns_to_the_widest_number() {
+        assert_eq!(number_lines(""), "");
+        assert_eq!(number_lines("a"), "1: a");
+        assert_eq!(number_lines("a\nb\n"), "1: a\n2: b\n");
+        let ten = "x\n".repeat(10);
+        let numbered = number_lines(&ten);
+        assert!(numbered.starts_with(" 1: x\n 2: x\n"));
+        assert!(numbered.ends_with("10: x\n"));
+    }
+
+    #[test]
+    fn cdata_sections_split_terminators() {
+        assert_eq!(cdata_section("a < b"), "<![CDATA[a < b]]>");
+        assert_eq!(cdata_section("x]]>y"), "<![CDATA[x]]]]><![CDATA[>y]]>");
+    }
+}
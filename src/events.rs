@@ -0,0 +1,81 @@
+// Stable `event_code` values attached to warnings and errors. Wrappers match on
+// these (e.g. under `--log-format json`) instead of the message text, which may
+// change between releases. Codes are only ever added, never renamed.
+
+/// Total tokens exceed the model context limit.
+pub const OVERFLOW: &str = "OVERFLOW";
+/// Copying to the clipboard failed; output went to the other sinks.
+pub const CLIPBOARD_UNAVAILABLE: &str = "CLIPBOARD_UNAVAILABLE";
+/// A file was skipped because it is not valid UTF-8.
+pub const FILE_SKIPPED_BINARY: &str = "FILE_SKIPPED_BINARY";
+/// A file was skipped because it exceeds `--max-size`.
+pub const FILE_SKIPPED_TOO_LARGE: &str = "FILE_SKIPPED_TOO_LARGE";
+/// A file was skipped because it could not be read.
+pub const FILE_SKIPPED_UNREADABLE: &str = "FILE_SKIPPED_UNREADABLE";
+/// A file was skipped because its contents exceed `--max-file-tokens`.
+pub const FILE_SKIPPED_TOO_MANY_TOKENS: &str = "FILE_SKIPPED_TOO_MANY_TOKENS";
+/// A file over `--max-size` or `--max-file-tokens` was cut by
+/// `--truncate-oversize` (`path` field).
+pub const FILE_TRUNCATED: &str = "FILE_TRUNCATED";
+/// A file split into more than `--max-parts` parts was cut after the last
+/// part kept (`path` field).
+pub const FILE_PARTS_TRUNCATED: &str = "FILE_PARTS_TRUNCATED";
+/// An empty file was left out by `--skip-empty` (`path` field; logged at
+/// debug level).
+pub const FILE_SKIPPED_EMPTY: &str = "FILE_SKIPPED_EMPTY";
+/// A generated file was left out by `--skip-generated` (`path` and `category`
+/// fields; logged at debug level).
+pub const FILE_SKIPPED_GENERATED: &str = "FILE_SKIPPED_GENERATED";
+/// A file with the same contents as another was stubbed by
+/// `--dedup-contents` (`path` and `original` fields; logged at debug level).
+pub const FILE_DUPLICATE: &str = "FILE_DUPLICATE";
+/// `--git-ls-files` could not list a directory outside a git work tree,
+/// which was walked instead (`path` field).
+pub const GIT_LS_FILES_FALLBACK: &str = "GIT_LS_FILES_FALLBACK";
+/// A `path:start-end` line range ran past the end of the file and was
+/// clamped to its last line (`path` field).
+pub const LINE_RANGE_CLAMPED: &str = "LINE_RANGE_CLAMPED";
+/// A candidate's modification time could not be read, so
+/// `--modified-since` / `--modified-before` kept it (`path` field).
+pub const MTIME_UNAVAILABLE: &str = "MTIME_UNAVAILABLE";
+/// An archive entry whose name is absolute or leads outside the archive
+/// was left out (`path` field names the archive).
+pub const ARCHIVE_ENTRY_SKIPPED: &str = "ARCHIVE_ENTRY_SKIPPED";
+/// The directory walk could not process an entry.
+pub const WALK_ERROR: &str = "WALK_ERROR";
+/// One glob pattern failed to parse and was ignored.
+pub const INVALID_PATTERN: &str = "INVALID_PATTERN";
+/// Every glob pattern given to a flag failed to parse (exit code 2).
+pub const ALL_PATTERNS_INVALID: &str = "ALL_PATTERNS_INVALID";
+/// `!` exclude patterns were given without any other excludes.
+pub const EXCLUDE_NEGATION_UNUSED: &str = "EXCLUDE_NEGATION_UNUSED";
+/// No candidate matched the `--only` patterns.
+pub const ONLY_NO_MATCH: &str = "ONLY_NO_MATCH";
+/// A `--first` pattern matched no gathered file.
+pub const FIRST_NO_MATCH: &str = "FIRST_NO_MATCH";
+/// Raw file contents contain the output's own wrapper markers.
+pub const RAW_CONTENT_MARKERS: &str = "RAW_CONTENT_MARKERS";
+/// The header chunk exceeds `--chunk-size`.
+pub const HEADER_OVERSIZE: &str = "HEADER_OVERSIZE";
+/// A chunk or file part exceeds `--chunk-size`.
+pub const CHUNK_OVERSIZE: &str = "CHUNK_OVERSIZE";
+/// `--chunk-index` names a chunk that does not exist (exit code 3).
+pub const CHUNK_INDEX_OUT_OF_RANGE: &str = "CHUNK_INDEX_OUT_OF_RANGE";
+/// Nothing was gathered (exit code 4 unless `--allow-empty`).
+pub const NO_FILES: &str = "NO_FILES";
+/// More candidate files than `--max-files` allows (exit code 6).
+pub const TOO_MANY_FILES: &str = "TOO_MANY_FILES";
+/// `--max-files-truncate` dropped files past the cap.
+pub const FILES_TRUNCATED: &str = "FILES_TRUNCATED";
+/// A file's token count exceeds `--warn-file-tokens` (`path` field).
+pub const FILE_OVER_TOKEN_LIMIT: &str = "FILE_OVER_TOKEN_LIMIT";
+/// `--suggest-chunk-size` or `--auto-chunk-size` needs more chunks than is comfortable.
+pub const MANY_CHUNKS: &str = "MANY_CHUNKS";
+/// A flag was given that has no effect in this mode.
+pub const FLAG_IGNORED: &str = "FLAG_IGNORED";
+/// Invalid combination of command-line arguments (exit code 2).
+pub const INVALID_ARGUMENTS: &str = "INVALID_ARGUMENTS";
+/// `--validate-xml` found output that is not well-formed XML (exit code 7).
+pub const INVALID_XML: &str = "INVALID_XML";
+/// The file-selection TUI failed (exit code 1).
+pub const TUI_ERROR: &str = "TUI_ERROR";
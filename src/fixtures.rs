@@ -0,0 +1,158 @@
+use crate::config::OutputFormat;
+use crate::constants::DEFAULT_PLAIN_SEPARATOR;
+use crate::context::types::FileContents;
+use crate::header::HeaderSpec;
+use crate::pipeline::Pipeline;
+use crate::xml_output::XmlStyle;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Tokenizer used for every fixture, whatever `--tokenizer-model` says, so
+/// chunk boundaries and token counts in the golden files never drift.
+pub const FIXTURE_TOKENIZER: &str = "gpt-4o";
+
+/// `generated-at` stamped on fixture headers in place of the current time.
+pub const FIXTURE_GENERATED_AT: &str = "2000-01-01T00:00:00Z";
+
+/// A built-in render scenario for `--render-fixture`: the synthetic corpus
+/// rendered with one combination of output settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub format: OutputFormat,
+    pub escape_xml: bool,
+    /// Token limit per chunk; 0 renders a single unchunked document.
+    pub chunk_size: usize,
+    pub multi_step: bool,
+}
+
+/// Every fixture; each has a golden file at `tests/golden/<name>.golden`.
+pub const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "plain-xml",
+        format: OutputFormat::Xml,
+        escape_xml: false,
+        chunk_size: 0,
+        multi_step: false,
+    },
+    Fixture {
+        name: "escaped-chunked",
+        format: OutputFormat::Xml,
+        escape_xml: true,
+        chunk_size: 400,
+        multi_step: false,
+    },
+    Fixture {
+        name: "multi-step-header",
+        format: OutputFormat::Xml,
+        escape_xml: false,
+        chunk_size: 0,
+        multi_step: true,
+    },
+    Fixture {
+        name: "json-chunked",
+        format: OutputFormat::Json,
+        escape_xml: false,
+        chunk_size: 400,
+        multi_step: false,
+    },
+    Fixture {
+        name: "plain-chunked",
+        format: OutputFormat::Plain,
+        escape_xml: false,
+        chunk_size: 400,
+        multi_step: false,
+    },
+];
+
+/// Clap value parser for `--render-fixture`; unknown names list the fixtures.
+pub fn parse_fixture(name: &str) -> Result<&'static Fixture, String> {
+    FIXTURES.iter().find(|f| f.name == name).ok_or_else(|| {
+        let known: Vec<&str> = FIXTURES.iter().map(|f| f.name).collect();
+        format!(
+            "unknown fixture '{name}'; known fixtures: {}",
+            known.join(", ")
+        )
+    })
+}
+
+fn file(
+    path: &str,
+    contents: String,
+) -> FileContents {
+    let path = PathBuf::from(path);
+    FileContents {
+        folder: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        path,
+        contents,
+        ..Default::default()
+    }
+}
+
+/// The synthetic corpus, in gather order (folder, then path). Contents are
+/// generated here rather than read from disk: markup characters to exercise
+/// escaping, and one long file that has to be split when chunked.
+pub fn corpus() -> Vec<FileContents> {
+    vec![
+        file(
+            "README.md",
+            "# Fixture\n\nA synthetic project for golden tests.\n".to_string(),
+        ),
+        file(
+            "docs/guide.md",
+            (1..=40)
+                .map(|n| {
+                    format!("Step {n}: run `cargo test` & compare <output> with \"golden\".\n")
+                })
+                .collect(),
+        ),
+        file(
+            "src/lib.rs",
+            "/// Adds two numbers.\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\n\
+             pub fn between(x: i32) -> bool {\n    0 < x && x > -1 && x != 'q' as i32\n}\n"
+                .to_string(),
+        ),
+        file(
+            "src/main.rs",
+            "fn main() {\n    println!(\"{}\", fixture::add(1, 2));\n}\n".to_string(),
+        ),
+    ]
+}
+
+/// Render `fixture` over the corpus exactly as the main pipeline would print
+/// it to stdout, with every chunk concatenated in order. Git info is never
+/// included, so the output has no timestamps or repository state.
+pub fn render(fixture: &Fixture) -> Result<String> {
+    let mut pipeline = Pipeline::from_files(corpus());
+    let needs_chunks = fixture.multi_step || fixture.chunk_size > 0;
+    match fixture.format {
+        OutputFormat::Json if needs_chunks => pipeline.build_json_chunks(fixture.chunk_size)?,
+        OutputFormat::Json => pipeline.build_json()?,
+        OutputFormat::Plain if needs_chunks => {
+            pipeline.build_plain_chunks(fixture.chunk_size, DEFAULT_PLAIN_SEPARATOR)
+        }
+        OutputFormat::Plain => pipeline.build_plain(DEFAULT_PLAIN_SEPARATOR),
+        OutputFormat::Xml if needs_chunks => pipeline.build_chunks_with_header(
+            HeaderSpec {
+                chunk_size: fixture.chunk_size,
+                multi_step: fixture.multi_step,
+                escape_xml: fixture.escape_xml,
+                generated_at: Some(FIXTURE_GENERATED_AT),
+                ..Default::default()
+            },
+            false,
+        )?,
+        OutputFormat::Xml => pipeline.build_xml(&XmlStyle {
+            escape_xml: fixture.escape_xml,
+            ..XmlStyle::default()
+        })?,
+    }
+    Ok(match pipeline.output() {
+        Some(output) => output.to_string(),
+        None => pipeline
+            .rendered_chunks()
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect(),
+    })
+}
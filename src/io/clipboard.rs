@@ -1,5 +1,8 @@
+use crate::io::filter_cmd::shell;
 use anyhow::{Result, anyhow};
 use arboard::Clipboard;
+use std::io::Write;
+use std::process::Stdio;
 
 fn try_copy(text: &str) -> Result<()> {
     let mut ctx = Clipboard::new().map_err(|e| anyhow!("init clipboard: {e}"))?;
@@ -7,13 +10,43 @@ fn try_copy(text: &str) -> Result<()> {
         .map_err(|e| anyhow!("set clipboard contents: {e}"))
 }
 
-/// Copy text to clipboard, warning on failure if `fail_hard` is false.
-/// Returns true when the clipboard copy succeeds.
+/// Pipe `text` to `cmd`'s stdin; a non-zero exit counts as a failed copy.
+fn try_copy_cmd(
+    cmd: &str,
+    text: &str,
+) -> Result<()> {
+    let mut child = shell(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| anyhow!("--clipboard-cmd {cmd:?} failed to start: {e}"))?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .expect("clipboard command stdin is piped");
+    let written = stdin.write_all(text.as_bytes());
+    // Close stdin so the command sees EOF before we wait on it
+    drop(stdin);
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("--clipboard-cmd {cmd:?} failed ({status})"));
+    }
+    written.map_err(|e| anyhow!("--clipboard-cmd {cmd:?}: write to stdin: {e}"))
+}
+
+/// Copy text to clipboard (or through `cmd` when given), warning on failure
+/// if `fail_hard` is false. Returns true when the copy succeeds.
 pub fn copy_to_clipboard(
     text: &str,
+    cmd: Option<&str>,
     fail_hard: bool,
 ) -> Result<bool> {
-    match try_copy(text) {
+    let copied = match cmd {
+        Some(cmd) => try_copy_cmd(cmd, text),
+        None => try_copy(text),
+    };
+    match copied {
         Ok(()) => Ok(true),
         Err(err) => {
             if fail_hard {
@@ -21,7 +54,10 @@ pub fn copy_to_clipboard(
                     "clipboard unavailable ({err}); re-run with --stdout or --no-clipboard"
                 ));
             }
-            tracing::warn!("clipboard unavailable: {err}");
+            tracing::warn!(
+                event_code = crate::events::CLIPBOARD_UNAVAILABLE,
+                "clipboard unavailable: {err}"
+            );
             Ok(false)
         }
     }
@@ -0,0 +1,63 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Target path for chunk `idx`: `out.xml` becomes `out-<idx>.xml`.
+/// Paths without an extension get `default_ext`.
+pub fn chunk_path(
+    base: &Path,
+    idx: usize,
+    default_ext: &str,
+) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = base
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| default_ext.to_string());
+    base.with_file_name(format!("{stem}-{idx}.{ext}"))
+}
+
+/// Write each `(path, contents)` pair, creating parent directories.
+/// Existing files are refused unless `force` is set; every target is checked
+/// before anything is written so a refusal leaves the disk untouched.
+pub fn write_outputs(
+    outputs: &[(PathBuf, &str)],
+    force: bool,
+) -> Result<()> {
+    if !force && let Some((path, _)) = outputs.iter().find(|(path, _)| path.exists()) {
+        return Err(anyhow!(
+            "{} already exists; pass --force to overwrite",
+            path.display()
+        ));
+    }
+    for (path, contents) in outputs {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Write selected paths one per line (or as a JSON array) to `target`,
+/// or to stdout when `target` is `-`. Existing files are replaced.
+pub fn write_selection(
+    target: &Path,
+    paths: &[String],
+    json: bool,
+) -> Result<()> {
+    let text = if json {
+        serde_json::to_string_pretty(paths)? + "\n"
+    } else {
+        paths.iter().map(|p| format!("{p}\n")).collect()
+    };
+    if target == Path::new("-") {
+        print!("{text}");
+        return Ok(());
+    }
+    write_outputs(&[(target.to_path_buf(), text.as_str())], true)
+}
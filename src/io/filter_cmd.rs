@@ -0,0 +1,89 @@
+use anyhow::{Result, anyhow};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Build a command that runs `cmd` through the platform shell.
+pub(crate) fn shell(cmd: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    }
+}
+
+/// Split filter output on NUL or newline, dropping empty entries.
+fn parse_paths(output: &[u8]) -> Vec<String> {
+    output
+        .split(|&b| b == 0 || b == b'\n')
+        .map(|p| {
+            String::from_utf8_lossy(p)
+                .trim_end_matches('\r')
+                .to_string()
+        })
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// Run `cmd` through the shell with `paths` on stdin (NUL-separated, one
+/// batch) and return the paths it echoes on stdout (NUL- or
+/// newline-separated). A non-zero exit or running past `timeout` is an error.
+pub fn run_filter(
+    cmd: &str,
+    paths: &[String],
+    timeout: Duration,
+) -> Result<Vec<String>> {
+    let mut child = shell(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| anyhow!("--filter-cmd {cmd:?} failed to start: {e}"))?;
+
+    // Feed stdin and drain stdout on their own threads so a large batch
+    // cannot deadlock against the child's pipe buffers
+    let mut stdin = child.stdin.take().expect("filter stdin is piped");
+    let input: Vec<u8> = paths
+        .iter()
+        .flat_map(|p| p.bytes().chain(std::iter::once(0)))
+        .collect();
+    std::thread::spawn(move || {
+        // A filter may stop reading early; that is not an error here
+        let _ = stdin.write_all(&input);
+    });
+    let mut stdout = child.stdout.take().expect("filter stdout is piped");
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = tx.send(stdout.read_to_end(&mut buf).map(|_| buf));
+    });
+
+    let timed_out = || anyhow!("--filter-cmd {cmd:?} timed out after {timeout:?}");
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(timed_out());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    if !status.success() {
+        return Err(anyhow!("--filter-cmd {cmd:?} failed ({status})"));
+    }
+    // Output can still be held open by a background grandchild
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let output = rx
+        .recv_timeout(remaining.max(Duration::from_millis(100)))
+        .map_err(|_| timed_out())?
+        .map_err(|e| anyhow!("--filter-cmd {cmd:?}: failed to read output: {e}"))?;
+    Ok(parse_paths(&output))
+}
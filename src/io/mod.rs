@@ -1,2 +1,6 @@
 // IO utilities
+#[cfg(feature = "clipboard")]
 pub mod clipboard;
+pub mod file_sink;
+pub mod filter_cmd;
+pub mod safe_path;
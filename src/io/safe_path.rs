@@ -0,0 +1,212 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Resolve a bundle-provided relative path `rel` to a writable target under
+/// `root`, creating any missing parent directories.
+///
+/// Anything that writes files named by a bundle (rather than by the user)
+/// must go through here. Absolute paths, `..` components, Windows drive and
+/// UNC prefixes and NUL bytes are rejected up front. Parent directories are
+/// created one level at a time and canonicalized after each step, so a
+/// symlink pointing out of `root` is caught before anything is created
+/// beyond it. An existing leaf symlink must also resolve inside `root`.
+///
+/// Both `/` and `\` are treated as separators so a bundle written on
+/// Windows cannot smuggle a traversal past a Unix check, or vice versa.
+pub fn resolve_under(
+    root: &Path,
+    rel: &str,
+) -> Result<PathBuf> {
+    let parts = relative_components(rel)?;
+    let root = dunce::canonicalize(root)
+        .with_context(|| format!("output root {} is not accessible", root.display()))?;
+    let Some((leaf, dirs)) = parts.split_last() else {
+        bail!("refusing empty path {rel:?}");
+    };
+
+    let mut dir = root.clone();
+    for part in dirs {
+        let next = dir.join(part);
+        match fs::create_dir(&next) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to create {}", next.display()));
+            }
+        }
+        dir = dunce::canonicalize(&next)
+            .with_context(|| format!("failed to resolve {}", next.display()))?;
+        if !dir.starts_with(&root) {
+            bail!(
+                "{rel:?} escapes {} through {}",
+                root.display(),
+                next.display()
+            );
+        }
+        if !dir.is_dir() {
+            bail!("{} is not a directory", next.display());
+        }
+    }
+
+    let target = dir.join(leaf);
+    if let Ok(meta) = fs::symlink_metadata(&target)
+        && meta.file_type().is_symlink()
+    {
+        let resolved = dunce::canonicalize(&target)
+            .map_err(|_| anyhow!("{rel:?} is a dangling symlink under {}", root.display()))?;
+        if !resolved.starts_with(&root) {
+            bail!("{rel:?} is a symlink that escapes {}", root.display());
+        }
+    }
+    Ok(target)
+}
+
+/// Split `rel` into plain path components, rejecting anything that could
+/// name a location outside the directory it is joined onto.
+fn relative_components(rel: &str) -> Result<Vec<&str>> {
+    if rel.contains('\0') {
+        bail!("refusing path with a NUL byte: {rel:?}");
+    }
+    if rel.starts_with(['/', '\\']) {
+        bail!("refusing absolute path {rel:?}");
+    }
+    let bytes = rel.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        bail!("refusing path with a drive prefix: {rel:?}");
+    }
+    let mut parts = Vec::new();
+    for part in rel.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => bail!("refusing path with a `..` component: {rel:?}"),
+            // A component such as `C:` or `file:stream` is a drive or an
+            // alternate data stream on Windows.
+            _ if cfg!(windows) && part.contains(':') => {
+                bail!("refusing path with `:` in a component: {rel:?}")
+            }
+            _ => parts.push(part),
+        }
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> assert_fs::TempDir {
+        assert_fs::TempDir::new().unwrap()
+    }
+
+    #[test]
+    fn plain_relative_paths_resolve_under_root() {
+        let dir = root();
+        let canon = dunce::canonicalize(dir.path()).unwrap();
+        for (rel, expected) in [
+            ("a.txt", "a.txt"),
+            ("src/lib.rs", "src/lib.rs"),
+            ("./src/./main.rs", "src/main.rs"),
+            ("src//nested///deep.rs", "src/nested/deep.rs"),
+            ("src\\win\\style.rs", "src/win/style.rs"),
+            ("..hidden/file..txt", "..hidden/file..txt"),
+        ] {
+            let got = resolve_under(dir.path(), rel).unwrap();
+            assert_eq!(got, canon.join(expected), "{rel}");
+            assert!(got.parent().unwrap().is_dir(), "{rel}");
+        }
+    }
+
+    #[test]
+    fn hostile_inputs_are_rejected() {
+        let dir = root();
+        for rel in [
+            "",
+            ".",
+            "./",
+            "/etc/passwd",
+            "\\etc\\passwd",
+            "//server/share/x",
+            "\\\\server\\share\\x",
+            "\\\\?\\C:\\x",
+            "C:\\Windows\\x",
+            "C:x",
+            "c:/x",
+            "..",
+            "../escape.txt",
+            "a/../../escape.txt",
+            "a/b/../c",
+            "..\\escape.txt",
+            "a\\..\\..\\escape.txt",
+            "nul\0byte",
+        ] {
+            assert!(
+                resolve_under(dir.path(), rel).is_err(),
+                "{rel:?} was accepted"
+            );
+        }
+        // Nothing was created while rejecting.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        let dir = root();
+        assert!(resolve_under(&dir.path().join("missing"), "a.txt").is_err());
+    }
+
+    #[test]
+    fn file_in_place_of_a_directory_is_an_error() {
+        let dir = root();
+        fs::write(dir.path().join("a"), "").unwrap();
+        assert!(resolve_under(dir.path(), "a/b.txt").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directory_escape_is_rejected_before_creating_anything() {
+        let outside = root();
+        let dir = root();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+
+        assert!(resolve_under(dir.path(), "link/new/x.txt").is_err());
+        assert!(resolve_under(dir.path(), "link/x.txt").is_err());
+        assert!(!outside.path().join("new").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn leaf_symlinks_must_stay_inside_root() {
+        let outside = root();
+        let dir = root();
+        fs::write(outside.path().join("secret"), "").unwrap();
+        fs::write(dir.path().join("inside"), "").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret"), dir.path().join("out")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("inside"), dir.path().join("in")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("gone"), dir.path().join("dangling")).unwrap();
+
+        assert!(resolve_under(dir.path(), "out").is_err());
+        assert!(resolve_under(dir.path(), "dangling").is_err());
+        assert!(resolve_under(dir.path(), "in").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_inside_root_is_followed() {
+        let dir = root();
+        fs::create_dir(dir.path().join("real")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("real"), dir.path().join("alias")).unwrap();
+        let got = resolve_under(dir.path(), "alias/x.txt").unwrap();
+        let canon = dunce::canonicalize(dir.path()).unwrap();
+        assert_eq!(got, canon.join("real/x.txt"));
+    }
+
+    #[test]
+    fn dotted_root_is_canonicalized() {
+        let dir = root();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let dotted = dir.path().join("sub").join("..");
+        let got = resolve_under(&dotted, "x.txt").unwrap();
+        assert_eq!(got, dunce::canonicalize(dir.path()).unwrap().join("x.txt"));
+    }
+}
@@ -1,10 +1,16 @@
 pub mod cli;
 pub mod config;
+pub mod config_env;
+pub mod config_file;
 pub mod constants;
 pub mod context;
+pub mod events;
+pub mod fixtures;
 pub mod io;
+pub mod models;
 pub mod output;
 pub mod pipeline;
+pub mod report;
 pub mod tokenizer;
 pub mod ui;
 
@@ -1,120 +1,911 @@
 // Import modules from the library crate
-use context_gather::config::{ChunkCopy, Config};
-use context_gather::io::clipboard;
-use context_gather::pipeline::{InvalidExcludePatterns, Pipeline};
+use context_gather::config::{
+    ChunkCopy, ChunkUnit, Config, HeaderPosition, InstructionsMode, LogFormat, OutputFormat,
+    SummaryTarget,
+};
+use context_gather::constants::{DEFAULT_EXCLUDES, SUGGEST_MAX_CHUNKS, SUGGEST_PASTE_LIMIT};
+use context_gather::context::{redact, template, validate};
+use context_gather::header::HeaderSpec;
+use context_gather::io::{clipboard, file_sink};
+use context_gather::pipeline::{InvalidPatterns, Pipeline};
 use context_gather::ui::select_files_tui;
 use context_gather::ui::stream::{multi_step_mode, streaming_mode};
-use context_gather::{gather, tokenizer};
+use context_gather::xml_output::XmlStyle;
+use context_gather::{chunker, events, fixtures, gather, output, report, tokenizer};
 
 use anyhow::Result;
-use tracing::{error, warn};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
-fn copied_label(copied_idx: Option<usize>) -> String {
-    copied_idx
-        .map(|idx| idx.to_string())
-        .unwrap_or_else(|| "none".into())
-}
-
-fn summary_line(
+/// End-of-run summary, rendered as the `OK ...` line or as `--summary-json`.
+#[derive(Debug, Serialize)]
+struct Summary {
     files: usize,
-    tokens: Option<usize>,
+    /// Total tokens; `None` when neither a model context nor JSON was requested.
+    total_tokens: Option<usize>,
     chunks: usize,
-    copied_idx: Option<usize>,
+    /// What `--chunk-size` counted.
+    chunk_unit: ChunkUnit,
+    copied: Vec<usize>,
     skipped: usize,
+    stubbed: usize,
+    /// Copies stubbed by `--dedup-contents`.
+    duplicates: usize,
+    /// Files changed by `--sanitize`.
+    sanitized: usize,
+    /// Files dropped by `--max-files-truncate`.
+    truncated: usize,
+    /// Tokens removed by content transformations.
+    saved_tokens: usize,
+    /// Files over `--warn-file-tokens`.
+    files_over_tokens: usize,
+    written: Vec<PathBuf>,
+    model_context: Option<usize>,
+    over_budget: bool,
+}
+
+impl Summary {
+    fn new(
+        pipeline: &Pipeline,
+        config: &Config,
+        files_over_tokens: usize,
+        total_tokens: Option<usize>,
+        chunks: usize,
+        copied: Vec<usize>,
+        written: Vec<PathBuf>,
+    ) -> Self {
+        let over_budget = matches!(
+            (total_tokens, config.model_context),
+            (Some(tokens), Some(limit)) if tokens > limit
+        );
+        Self {
+            files: pipeline.included_count(),
+            total_tokens,
+            chunks,
+            chunk_unit: config.chunk_unit,
+            copied,
+            skipped: pipeline.skipped_count(),
+            stubbed: pipeline.stubbed_count(),
+            duplicates: pipeline.duplicate_count(),
+            sanitized: pipeline.sanitized_count(),
+            truncated: pipeline.truncated_count(),
+            saved_tokens: pipeline.transform_savings().iter().map(|s| s.saved).sum(),
+            files_over_tokens,
+            written,
+            model_context: config.model_context,
+            over_budget,
+        }
+    }
+
+    fn copied_label(&self) -> String {
+        if self.copied.is_empty() {
+            return "none".into();
+        }
+        self.copied
+            .iter()
+            .map(|idx| idx.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// The human-readable `OK ...` line; token totals only show with a model context.
+    fn line(&self) -> String {
+        let chunks = self.chunks;
+        let plural = if chunks == 1 { "" } else { "s" };
+        let unit = match self.chunk_unit {
+            ChunkUnit::Tokens => String::new(),
+            unit => format!(" ({})", unit.name()),
+        };
+        let mut summary = match self.total_tokens.filter(|_| self.model_context.is_some()) {
+            Some(tokens) => format!(
+                "OK {} files • {tokens} tokens • {chunks} chunk{plural}{unit} • copied={}",
+                self.files,
+                self.copied_label()
+            ),
+            None => format!(
+                "OK {} files • {chunks} chunk{plural}{unit} • copied={}",
+                self.files,
+                self.copied_label()
+            ),
+        };
+        if self.skipped > 0 {
+            summary.push_str(&format!(" • skipped={}", self.skipped));
+        }
+        if self.stubbed > 0 {
+            summary.push_str(&format!(" • stubbed={}", self.stubbed));
+        }
+        if self.duplicates > 0 {
+            summary.push_str(&format!(" • duplicates={}", self.duplicates));
+        }
+        if self.sanitized > 0 {
+            summary.push_str(&format!(" • sanitized={}", self.sanitized));
+        }
+        if self.truncated > 0 {
+            summary.push_str(&format!(" • truncated={}", self.truncated));
+        }
+        if self.saved_tokens > 0 {
+            summary.push_str(&format!(" • saved={}", self.saved_tokens));
+        }
+        if self.files_over_tokens > 0 {
+            summary.push_str(&format!(" • over-file-tokens={}", self.files_over_tokens));
+        }
+        if !self.written.is_empty() {
+            let paths: Vec<String> = self
+                .written
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            summary.push_str(&format!(" • wrote={}", paths.join(",")));
+        }
+        summary
+    }
+
+    /// Print the summary line to stderr, or the JSON object to `json`'s stream.
+    fn emit(
+        &self,
+        json: Option<SummaryTarget>,
+    ) -> Result<()> {
+        match json {
+            None => eprintln!("{}", self.line()),
+            Some(SummaryTarget::Stderr) => eprintln!("{}", serde_json::to_string(self)?),
+            Some(SummaryTarget::Stdout) => println!("{}", serde_json::to_string(self)?),
+        }
+        Ok(())
+    }
+}
+
+fn dry_run_summary(
+    pipeline: &Pipeline,
+    model_context: Option<usize>,
 ) -> String {
-    let mut summary = match tokens {
-        Some(tokens) => format!(
-            "OK {files} files • {tokens} tokens • {chunks} chunk{} • copied={}",
-            if chunks == 1 { "" } else { "s" },
-            copied_label(copied_idx)
-        ),
-        None => format!(
-            "OK {files} files • {chunks} chunk{} • copied={}",
-            if chunks == 1 { "" } else { "s" },
-            copied_label(copied_idx)
-        ),
-    };
-    if skipped > 0 {
-        summary.push_str(&format!(" • skipped={skipped}"));
+    let tokens: usize = pipeline
+        .file_meta()
+        .iter()
+        .filter(|m| !m.stubbed)
+        .map(|m| m.tokens)
+        .sum();
+    let mut summary = format!(
+        "DRY RUN {} files • {tokens} tokens",
+        pipeline.included_count()
+    );
+    if let Some(limit) = model_context {
+        let verdict = if tokens > limit { "exceeds" } else { "within" };
+        summary.push_str(&format!(" • {verdict} model context {limit}"));
     }
     summary
 }
 
+fn print_composition(
+    pipeline: &Pipeline,
+    full_report: bool,
+) {
+    let shares = report::extension_composition(pipeline.file_meta());
+    if let Some(line) = report::composition_line(&shares, 4) {
+        eprintln!("composition: {line}");
+    }
+    let original = report::original_total(pipeline.file_meta());
+    for line in report::savings_lines(pipeline.transform_savings(), original) {
+        eprintln!("{line}");
+    }
+    if full_report {
+        eprint!("{}", report::render_composition_table(&shares));
+        if let Some(deltas) = report::render_token_deltas(pipeline.file_meta()) {
+            eprint!("{deltas}");
+        }
+    }
+}
+
+/// Exit with a usage error when every pattern given to a glob flag was invalid.
+fn exit_on_invalid_patterns(err: &anyhow::Error) {
+    if let Some(invalid) = err.downcast_ref::<InvalidPatterns>() {
+        error!(event_code = events::ALL_PATTERNS_INVALID, "{invalid}");
+        std::process::exit(2);
+    }
+}
+
+/// Shell command that replays an interactive selection saved to `target`.
+fn reproduce_command(
+    target: &std::path::Path,
+    args: &[String],
+) -> String {
+    let quote = |arg: &str| {
+        if !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c))
+        {
+            arg.to_string()
+        } else {
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        }
+    };
+    let mut parts = vec![
+        "context-gather".to_string(),
+        "--paths-from".to_string(),
+        quote(&target.to_string_lossy()),
+    ];
+    parts.extend(args.iter().map(|a| quote(a)));
+    parts.join(" ")
+}
+
+fn print_external_note(pipeline: &Pipeline) {
+    let external = pipeline.external_count();
+    if external > 0 {
+        eprintln!(
+            "note: {external} file{} from outside the repository",
+            if external == 1 { "" } else { "s" }
+        );
+    }
+}
+
+/// The `--redact` report: matches replaced in each file, per rule.
+fn print_redactions(pipeline: &Pipeline) {
+    let redactions = pipeline.redactions();
+    let total: usize = redactions
+        .iter()
+        .flat_map(|(_, counts)| counts.iter().map(|(_, n)| n))
+        .sum();
+    eprintln!(
+        "redacted {total} secret{} in {} file{}",
+        if total == 1 { "" } else { "s" },
+        redactions.len(),
+        if redactions.len() == 1 { "" } else { "s" }
+    );
+    for (path, counts) in redactions {
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(rule, n)| format!("{rule}={n}"))
+            .collect();
+        eprintln!("  {}: {}", path.display(), counts.join(", "));
+    }
+}
+
+/// How many generated files the `--skip-generated` note names.
+const LISTED_GENERATED: usize = 5;
+
+fn print_generated_note(pipeline: &Pipeline) {
+    let generated = pipeline.generated_paths();
+    if generated.is_empty() {
+        return;
+    }
+    let mut names: Vec<String> = generated
+        .iter()
+        .take(LISTED_GENERATED)
+        .map(|p| p.display().to_string())
+        .collect();
+    if generated.len() > LISTED_GENERATED {
+        names.push(format!("and {} more", generated.len() - LISTED_GENERATED));
+    }
+    eprintln!(
+        "note: left out {} generated file{}: {} (--keep-generated keeps them)",
+        generated.len(),
+        if generated.len() == 1 { "" } else { "s" },
+        names.join(", ")
+    );
+}
+
+/// Composition, summary, and notes printed after output. `--quiet` keeps
+/// only what was asked for explicitly (`--report`, `--summary-json`).
+fn print_run_footer(
+    pipeline: &Pipeline,
+    summary: &Summary,
+    config: &Config,
+) -> Result<()> {
+    if config.quiet {
+        if config.report {
+            print_composition(pipeline, true);
+        }
+        if config.summary_json.is_some() {
+            summary.emit(config.summary_json)?;
+        }
+        return Ok(());
+    }
+    print_composition(pipeline, config.report);
+    summary.emit(config.summary_json)?;
+    print_external_note(pipeline);
+    print_generated_note(pipeline);
+    Ok(())
+}
+
+/// Warn when `tokens` exceeds the model context limit; with
+/// `--fail-over-budget`, exit with code 5 instead of succeeding.
+fn check_model_context(
+    tokens: Option<usize>,
+    limit: Option<usize>,
+    fail_over_budget: bool,
+) {
+    if let (Some(tokens), Some(limit)) = (tokens, limit)
+        && tokens > limit
+    {
+        warn!(
+            event_code = events::OVERFLOW,
+            "token count {} exceeds model context limit {} by {}",
+            tokens,
+            limit,
+            tokens - limit
+        );
+        if fail_over_budget {
+            std::process::exit(5);
+        }
+    }
+}
+
+/// With `--validate-xml`, exit with code 7 before anything is printed or
+/// copied if the output (every chunk, when chunked) is not well-formed XML.
+fn validate_xml_output(pipeline: &Pipeline) {
+    let texts: Vec<(Option<usize>, &str)> = match pipeline.output() {
+        Some(output) => vec![(None, output)],
+        None => pipeline
+            .rendered_chunks()
+            .iter()
+            .enumerate()
+            .map(|(idx, chunk)| (Some(idx), chunk.text.as_str()))
+            .collect(),
+    };
+    for (idx, text) in texts {
+        let checked = match idx {
+            Some(_) => validate::check_chunk(text),
+            None => validate::check_well_formed(text),
+        };
+        if let Err(problem) = checked {
+            match idx {
+                Some(idx) => error!(
+                    event_code = events::INVALID_XML,
+                    "chunk {idx} is not well-formed XML: {problem}"
+                ),
+                None => error!(
+                    event_code = events::INVALID_XML,
+                    "output is not well-formed XML: {problem}"
+                ),
+            }
+            std::process::exit(7);
+        }
+    }
+}
+
 fn warn_if_no_files(files: usize) {
     if files == 0 {
-        warn!("no files were included in output");
+        warn!(
+            event_code = events::NO_FILES,
+            "no files were included in output"
+        );
+    }
+}
+
+/// Log which config files were loaded and the settings taken from them.
+fn log_config_sources(config: &Config) {
+    if config.config_sources.is_empty() {
+        info!("config: no config file loaded");
     }
+    for source in &config.config_sources {
+        info!("config: loaded {}", source.path.display());
+    }
+    for (key, path) in &config.config_origins {
+        info!("config: {key} from {}", path.display());
+    }
+}
+
+/// Dump the resolved configuration to stderr: pretty for humans, one line
+/// under `--log-format json` so the stream stays line-delimited.
+fn print_effective_config(config: &Config) {
+    let value = config.to_masked_json();
+    let text = match config.log_format {
+        LogFormat::Human => serde_json::to_string_pretty(&value),
+        LogFormat::Json => serde_json::to_string(&value),
+    };
+    eprintln!("{}", text.expect("JSON value serializes"));
+}
+
+/// The `--format` value as typed on the command line.
+fn format_name(format: OutputFormat) -> String {
+    format
+        .to_possible_value()
+        .map_or_else(String::new, |v| v.get_name().to_string())
 }
 
 fn main() -> Result<()> {
+    let config = Config::from_cli()?;
+
     // Initialize tracing for structured logging, with RUST_LOG support
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
-    tracing_subscriber::fmt()
+    let default_level = match (config.quiet, config.verbose) {
+        (true, _) => "error",
+        (false, 0) => "warn",
+        (false, 1) => "info",
+        (false, _) => "debug",
+    };
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+    let subscriber = tracing_subscriber::fmt()
         .with_env_filter(filter)
-        .with_writer(std::io::stderr)
-        .init();
+        .with_writer(std::io::stderr);
+    match config.log_format {
+        LogFormat::Human => subscriber.init(),
+        LogFormat::Json => subscriber.json().flatten_event(true).init(),
+    }
+    log_config_sources(&config);
 
-    let config = Config::from_cli()?;
+    if let Some(fixture) = config.render_fixture {
+        tokenizer::init(Some(fixtures::FIXTURE_TOKENIZER))?;
+        print!("{}", fixtures::render(fixture)?);
+        return Ok(());
+    }
     tokenizer::init(config.tokenizer_model.as_deref())?;
 
     // Pre-validate CLI arg combos: chunk-index requires chunk-size > 0
     if matches!(config.chunk_size, Some(0)) {
-        error!("--chunk-size must be > 0 (omit it to disable chunking)");
+        error!(
+            event_code = events::INVALID_ARGUMENTS,
+            "--chunk-size must be > 0 (omit it to disable chunking)"
+        );
+        std::process::exit(2);
+    }
+    let mut chunk_limit = config.chunk_size.unwrap_or(0);
+    if config.format != OutputFormat::Xml && config.multi_step {
+        error!(
+            event_code = events::INVALID_ARGUMENTS,
+            "--format {} cannot be combined with --multi-step",
+            format_name(config.format)
+        );
         std::process::exit(2);
     }
-    let chunk_limit = config.chunk_size.unwrap_or(0);
 
-    // 1) Expand user-specified paths (globs, etc.) and build candidates
-    let mut pipeline = Pipeline::new();
-    pipeline.expand_paths(&config.paths)?;
-    pipeline.build_candidates()?;
+    if config.chunk_unit != ChunkUnit::Tokens {
+        // These size chunks from token counts and model context windows
+        let token_only = [
+            ("--multi-step", config.multi_step),
+            ("--suggest-chunk-size", config.suggest_chunk_size),
+            ("--chunk-size auto", config.auto_chunk_size),
+        ];
+        if let Some((flag, _)) = token_only.iter().find(|(_, set)| *set) {
+            error!(
+                event_code = events::INVALID_ARGUMENTS,
+                "--chunk-unit {} cannot be combined with {flag}",
+                config.chunk_unit.name()
+            );
+            std::process::exit(2);
+        }
+    }
 
-    // 2) Exclude patterns: abort if all provided globs are invalid
-    if let Err(err) = pipeline.apply_excludes(&config.exclude) {
-        if let Some(invalid) = err.downcast_ref::<InvalidExcludePatterns>() {
+    if config.paths_from.as_deref() == Some(std::path::Path::new("-"))
+        && (config.multi_step || config.stream)
+    {
+        error!(
+            event_code = events::INVALID_ARGUMENTS,
+            "--paths-from - reads stdin, which the multi-step and stream prompts also need"
+        );
+        std::process::exit(2);
+    }
+    let read_stdin = config.paths.iter().any(|p| p == "-");
+    if read_stdin {
+        let conflict = [
+            (
+                "--paths-from -",
+                config.paths_from.as_deref() == Some(std::path::Path::new("-")),
+            ),
+            ("--multi-step", config.multi_step),
+            ("--stream", config.stream),
+            ("--select", config.select),
+        ];
+        if let Some((flag, _)) = conflict.iter().find(|(_, set)| *set) {
             error!(
-                "Every --exclude pattern was invalid: {:?}",
-                invalid.patterns
+                event_code = events::INVALID_ARGUMENTS,
+                "the `-` path reads stdin, which {flag} also needs"
             );
             std::process::exit(2);
         }
+    }
+
+    let instructions = match &config.instructions_file {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(text) => Some(text),
+            Err(err) => {
+                error!(
+                    event_code = events::INVALID_ARGUMENTS,
+                    "cannot read --instructions-file {}: {err}",
+                    path.display()
+                );
+                std::process::exit(2);
+            }
+        },
+        None => config.instructions.clone(),
+    };
+
+    let redact_rules = if config.redact {
+        match redact::rules(&config.redact_patterns) {
+            Ok(rules) => rules,
+            Err(err) => {
+                error!(event_code = events::INVALID_ARGUMENTS, "{err:#}");
+                std::process::exit(2);
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Read and parse the template up front so its errors come before any work
+    let template = match &config.template {
+        Some(path) => {
+            let source = match std::fs::read_to_string(path) {
+                Ok(source) => source,
+                Err(err) => {
+                    error!(
+                        event_code = events::INVALID_ARGUMENTS,
+                        "cannot read --template {}: {err}",
+                        path.display()
+                    );
+                    std::process::exit(2);
+                }
+            };
+            let name = path.display().to_string();
+            if let Err(err) = template::check(&name, &source) {
+                error!(
+                    event_code = events::INVALID_ARGUMENTS,
+                    "invalid template: {err}"
+                );
+                std::process::exit(2);
+            }
+            Some((name, source))
+        }
+        None => None,
+    };
+
+    if config.print_default_excludes {
+        for pattern in DEFAULT_EXCLUDES {
+            println!("{pattern}");
+        }
+        return Ok(());
+    }
+    if config.print_config || config.verbose > 0 {
+        print_effective_config(&config);
+        if config.print_config {
+            return Ok(());
+        }
+    }
+
+    // 1) Expand user-specified paths (globs, etc.) and build candidates
+    let mut pipeline = Pipeline::new();
+    let mut paths = config.paths.clone();
+    if let Some(source) = &config.paths_from {
+        paths.extend(gather::load_path_list(source)?);
+    }
+    pipeline.set_size_limits(
+        config.max_size,
+        config.max_size_ext.clone(),
+        config.truncate_oversize,
+    );
+    pipeline.expand_paths(&paths)?;
+    if config.staged {
+        if let Err(err) = pipeline.use_staged_files() {
+            error!(event_code = events::INVALID_ARGUMENTS, "{err}");
+            std::process::exit(2);
+        }
+    } else {
+        pipeline.build_candidates(
+            &gather::WalkOptions {
+                threads: config.walk_threads,
+                max_depth: config.max_depth,
+                follow_links: config.follow_symlinks,
+                hidden: config.hidden,
+                no_ignore: config.no_ignore,
+                no_cgignore: config.no_cgignore,
+                git_ls_files: config.git_ls_files,
+                git_others: config.git_others,
+                modified_since: config.modified_since,
+                modified_before: config.modified_before,
+            },
+            &config.only_dirs,
+        )?;
+    }
+    if read_stdin {
+        pipeline.read_stdin(&config.stdin_name)?;
+    }
+
+    // 2) Exclude and --only patterns: abort if all provided globs are invalid
+    if let Err(err) = pipeline.apply_excludes(
+        &config.exclude,
+        &config.exclude_ext,
+        config.default_excludes,
+    ) {
+        exit_on_invalid_patterns(&err);
         return Err(err);
     }
+    if let Err(err) = pipeline.apply_only(&config.only) {
+        exit_on_invalid_patterns(&err);
+        return Err(err);
+    }
+    if config.include_readmes {
+        pipeline.add_readmes();
+    }
 
     pipeline.compute_preselected();
 
+    if config.emit_selection.is_some() && !config.select {
+        warn!(
+            event_code = events::FLAG_IGNORED,
+            "--emit-selection has no effect without --select or --interactive"
+        );
+    }
+
     // 3) If selection UI requested, open the TUI
     if config.select {
         let selected = match select_files_tui(
             pipeline.candidate_files().to_vec(),
             pipeline.preselected_paths(),
+            config.ext_group_below,
         ) {
             Ok(selected) => selected,
             Err(e) => {
-                error!("Error in interactive TUI: {}", e);
+                error!(
+                    event_code = events::TUI_ERROR,
+                    "Error in interactive TUI: {}", e
+                );
                 std::process::exit(1);
             }
         };
         pipeline.set_candidate_files(selected);
+        if let Some(target) = &config.emit_selection {
+            file_sink::write_selection(
+                target,
+                &pipeline.candidate_display_paths(),
+                config.emit_selection_json,
+            )?;
+            if !config.emit_selection_json {
+                eprintln!(
+                    "reproduce: {}",
+                    reproduce_command(target, &config.reproduce_args)
+                );
+            }
+        }
+    }
+
+    if let Some(max) = config.max_files
+        && !config.max_files_truncate
+        && pipeline.candidate_files().len() > max
+    {
+        error!(
+            event_code = events::TOO_MANY_FILES,
+            "{} candidate files exceed --max-files {max}; narrow the paths or pass --max-files-truncate",
+            pipeline.candidate_files().len()
+        );
+        std::process::exit(6);
     }
 
     // 4) Read file data
-    pipeline.collect_file_data(config.max_size)?;
+    pipeline.collect_file_data(!config.no_hashes, config.normalize_eol)?;
+    if config.staged_content {
+        pipeline.read_staged_contents(config.normalize_eol);
+    }
+    if config.skip_generated {
+        pipeline.skip_generated_files();
+    }
+    if config.skip_empty {
+        pipeline.skip_empty_files();
+    }
+    pipeline.set_list_skipped(config.list_skipped);
+    if let Some(cmd) = &config.filter_cmd {
+        pipeline.apply_filter_cmd(cmd, std::time::Duration::from_secs(config.filter_timeout))?;
+    }
+    // Redact only the files the filter kept, so the report counts no others
+    if config.redact {
+        pipeline.redact_secrets(&redact_rules);
+        if !config.quiet {
+            print_redactions(&pipeline);
+        }
+    }
+    if config.dedup_contents {
+        pipeline.dedup_contents();
+    }
+    if let Err(err) = pipeline.apply_stubs(&config.stub_paths) {
+        exit_on_invalid_patterns(&err);
+        return Err(err);
+    }
+    pipeline.mark_external();
+    pipeline.sanitize_contents(config.sanitize);
+    if config.strip_comments {
+        pipeline.strip_comments();
+    }
+    if config.squeeze {
+        pipeline.squeeze_contents();
+    }
+    if config.line_numbers {
+        pipeline.number_lines();
+    }
+    if let Some(limit) = config.max_file_tokens {
+        pipeline.limit_file_tokens(limit, config.truncate_oversize);
+    }
+    let mut files_over_tokens = 0;
+    if let Some(limit) = config.warn_file_tokens {
+        let over = pipeline.files_over_tokens(limit);
+        for (path, tokens) in &over {
+            warn!(
+                event_code = events::FILE_OVER_TOKEN_LIMIT,
+                path = %path.display(),
+                "{} has {tokens} tokens, over --warn-file-tokens {limit}",
+                path.display()
+            );
+        }
+        files_over_tokens = over.len();
+    }
+    pipeline.sort_files(config.sort, config.reverse);
+    if let Err(err) = pipeline.prioritize(&config.first) {
+        exit_on_invalid_patterns(&err);
+        return Err(err);
+    }
+    pipeline.set_chunk_options(chunker::ChunkOptions {
+        strategy: config.split_strategy,
+        overlap: config.part_overlap,
+        group_by_folder: config.group_by_folder,
+        unit: config.chunk_unit,
+        max_parts: config.max_parts,
+    });
+    if let Some(max) = config.max_files {
+        let dropped = pipeline.truncate_files(max);
+        if dropped > 0 {
+            warn!(
+                event_code = events::FILES_TRUNCATED,
+                "--max-files kept the first {max} files in sort order and dropped {dropped}"
+            );
+        }
+    }
+    if let Some(style) = config.path_style {
+        pipeline.apply_path_style(style);
+    }
+    if config.suggest_chunk_size || config.auto_chunk_size {
+        let total = pipeline.content_tokens();
+        let suggestion =
+            chunker::suggest_chunk_size(total, config.model_context, SUGGEST_PASTE_LIMIT);
+        let line = format!(
+            "--chunk-size {} ({} chunk{} for {total} tokens; ceiling {})",
+            suggestion.chunk_size,
+            suggestion.chunks,
+            if suggestion.chunks == 1 { "" } else { "s" },
+            suggestion.ceiling
+        );
+        if suggestion.too_many_chunks() {
+            warn!(
+                event_code = events::MANY_CHUNKS,
+                "{} chunks is more than {SUGGEST_MAX_CHUNKS}; consider narrowing the selection with --only or --exclude-paths",
+                suggestion.chunks
+            );
+        }
+        if config.suggest_chunk_size {
+            println!("suggested: {line}");
+            return Ok(());
+        }
+        eprintln!("auto: {line}");
+        chunk_limit = suggestion.chunk_size;
+    }
+    // What plain, JSON and template chunks are built to; XML chunking applies
+    // the headroom itself so its header can still declare `chunk_limit`
+    let chunk_budget = chunker::chunk_budget(chunk_limit, config.chunk_headroom);
+    if config.dry_run {
+        let xml = config.format == OutputFormat::Xml;
+        if config.format == OutputFormat::Plain {
+            pipeline.plan_plain_files(chunk_budget, &config.plain_separator);
+        } else {
+            pipeline.plan_files(chunk_budget, config.escape_xml && xml, config.cdata && xml);
+        }
+        print!(
+            "{}",
+            output::render_dry_run(pipeline.file_meta(), chunk_budget)
+        );
+        eprintln!("{}", dry_run_summary(&pipeline, config.model_context));
+        return Ok(());
+    }
+    if pipeline.file_data().is_empty() && !config.allow_empty {
+        let cwd = std::env::current_dir()?;
+        error!(
+            event_code = events::NO_FILES,
+            "{}",
+            report::empty_gather_diagnostic(
+                &pipeline.gather_stats(),
+                &cwd,
+                &pipeline.user_path_display(),
+                pipeline.filters()
+            )
+        );
+        std::process::exit(4);
+    }
     warn_if_no_files(pipeline.file_data().len());
-    pipeline.warn_raw_structure_risks(config.escape_xml);
+    if config.format == OutputFormat::Xml {
+        pipeline.warn_raw_structure_risks(config.escape_xml || config.cdata);
+    }
 
     // 5) Build outputs
     let needs_chunks = config.multi_step || chunk_limit > 0;
-    if needs_chunks {
+    let header_last = config.header_position == HeaderPosition::Last;
+    if header_last && (config.multi_step || config.format != OutputFormat::Xml) {
+        warn!(
+            event_code = events::FLAG_IGNORED,
+            "--header-position last has no effect with --multi-step or --format {}",
+            format_name(config.format)
+        );
+    }
+    if config.cdata && config.format != OutputFormat::Xml {
+        warn!(
+            event_code = events::FLAG_IGNORED,
+            "--cdata has no effect with --format {}",
+            format_name(config.format)
+        );
+    }
+    if config.escape_xml && config.format == OutputFormat::Plain {
+        warn!(
+            event_code = events::FLAG_IGNORED,
+            "--escape-xml has no effect with --format plain; contents are written raw"
+        );
+    }
+    let validate_xml =
+        config.validate_xml && template.is_none() && config.format == OutputFormat::Xml;
+    if config.validate_xml && !validate_xml {
+        warn!(
+            event_code = events::FLAG_IGNORED,
+            "--validate-xml only applies to the built-in XML output"
+        );
+    }
+    if config.max_parts.is_some() && chunk_limit == 0 {
+        warn!(
+            event_code = events::FLAG_IGNORED,
+            "--max-parts has no effect without --chunk-size"
+        );
+    }
+    if config.reverse_chunk_output && (config.multi_step || chunk_limit == 0) {
+        warn!(
+            event_code = events::FLAG_IGNORED,
+            "--reverse-chunk-output has no effect without --chunk-size or with --multi-step"
+        );
+    }
+    if let Some((name, source)) = &template {
+        if let Err(err) = pipeline.build_template(name, source, chunk_budget, config.git_info) {
+            error!(
+                event_code = events::INVALID_ARGUMENTS,
+                "template error: {err}"
+            );
+            std::process::exit(2);
+        }
+    } else if config.format == OutputFormat::Plain {
+        if needs_chunks {
+            pipeline.build_plain_chunks(chunk_budget, &config.plain_separator);
+        } else {
+            pipeline.build_plain(&config.plain_separator);
+        }
+    } else if config.format == OutputFormat::Json {
+        if needs_chunks {
+            pipeline.build_json_chunks(chunk_budget)?;
+        } else {
+            pipeline.build_json()?;
+        }
+    } else if needs_chunks {
         pipeline.build_chunks_with_header(
-            chunk_limit,
-            config.escape_xml,
-            config.multi_step,
-            config.git_info,
+            HeaderSpec {
+                chunk_size: chunk_limit,
+                headroom: config.chunk_headroom,
+                chunk_unit: config.chunk_unit,
+                multi_step: config.multi_step,
+                escape_xml: config.escape_xml,
+                cdata: config.cdata,
+                include_git: config.git_info,
+                include_file_map: !config.no_file_map,
+                header_last: header_last && !config.multi_step,
+                instructions: instructions.as_deref(),
+                append_instructions: config.instructions_mode == InstructionsMode::Append,
+                ..Default::default()
+            },
+            config.legacy_snippets,
         )?;
     } else {
-        pipeline.build_xml(config.escape_xml)?;
+        pipeline.build_xml(&XmlStyle {
+            escape_xml: config.escape_xml,
+            cdata: config.cdata,
+            file_map: !config.no_file_map,
+            map_last: header_last,
+        })?;
+    }
+
+    if validate_xml {
+        validate_xml_output(&pipeline);
     }
 
     // Multi-step mode: REPL for fetching files on demand
@@ -125,138 +916,145 @@ fn main() -> Result<()> {
 
     // Chunked mode interactive REPL: only when streaming is requested
     if chunk_limit > 0 && config.stream {
-        streaming_mode(pipeline.rendered_chunks(), &config)?;
+        streaming_mode(pipeline.rendered_chunks(), pipeline.file_meta(), &config)?;
         return Ok(());
     }
 
-    // If chunking disabled (no --chunk-size), output full XML as a single chunk
+    // Clipboard failure is only fatal when it is the sole sink
+    let clipboard_fail_hard = !config.stdout && config.output.is_none();
+
+    // If chunking disabled (no --chunk-size), output the full context as a single chunk
     if chunk_limit == 0 {
-        let xml_output = pipeline
-            .xml_output()
-            .expect("xml output should be built when chunking is disabled");
-        // Print XML on stdout if requested
+        let output = pipeline
+            .output()
+            .expect("output should be built when chunking is disabled");
+        let written: Vec<PathBuf> = config.output.iter().cloned().collect();
+        let targets: Vec<(PathBuf, &str)> = written.iter().map(|p| (p.clone(), output)).collect();
+        file_sink::write_outputs(&targets, config.force)?;
+        // Print output on stdout if requested
         if config.stdout {
-            print!("{xml_output}");
+            print!("{output}");
         }
         // Copy to clipboard
-        let mut copied_idx: Option<usize> = None;
-        if !config.no_clipboard {
-            let copied = clipboard::copy_to_clipboard(xml_output, !config.stdout)?;
-            if copied {
-                copied_idx = Some(0);
-            }
+        let mut copied = Vec::new();
+        if !config.no_clipboard
+            && clipboard::copy_to_clipboard(
+                output,
+                config.clipboard_cmd.as_deref(),
+                clipboard_fail_hard,
+            )?
+        {
+            copied.push(0);
         }
         // Summary: one chunk (index 0)
-        let token_count = config
-            .model_context
-            .map(|_| gather::count_tokens(xml_output));
-        let summary = summary_line(
-            pipeline.file_data().len(),
+        let token_count = (config.model_context.is_some() || config.summary_json.is_some())
+            .then(|| gather::count_tokens(output));
+        let summary = Summary::new(
+            &pipeline,
+            &config,
+            files_over_tokens,
             token_count,
             1,
-            copied_idx,
-            pipeline.skipped_count(),
+            copied,
+            written,
         );
-        eprintln!("{summary}");
-        if let (Some(limit), Some(total_token_count)) = (config.model_context, token_count)
-            && total_token_count > limit
-        {
-            warn!(
-                "token count {} exceeds model context limit {}",
-                total_token_count, limit
-            );
-        }
+        print_run_footer(&pipeline, &summary, &config)?;
+        check_model_context(token_count, config.model_context, config.fail_over_budget);
         return Ok(());
     }
 
-    // Determine default copy index: default to first chunk when unset and clipboard enabled
+    // Determine which chunks to print/copy: default to the first chunk when unset
     let chunks = pipeline.rendered_chunks();
     let total_chunks = chunks.len();
-    if let ChunkCopy::Index(idx) = config.chunk_copy
-        && idx >= total_chunks
+    if let ChunkCopy::Indices(ranges) = &config.chunk_copy
+        && let Some(range) = ranges.iter().find(|r| *r.end() >= total_chunks)
     {
+        let idx = (*range.start()).max(total_chunks);
         warn!(
+            event_code = events::CHUNK_INDEX_OUT_OF_RANGE,
             "--chunk-index {} out of range (0..{})",
             idx,
             total_chunks.saturating_sub(1)
         );
         std::process::exit(3);
     }
-    let copy_idx = if config.no_clipboard {
-        None
-    } else {
-        match config.chunk_copy {
-            ChunkCopy::Default => Some(0),
-            ChunkCopy::Index(idx) => Some(idx),
-            ChunkCopy::None => None,
+    let selected: Vec<usize> = match &config.chunk_copy {
+        ChunkCopy::Default => vec![0],
+        ChunkCopy::Indices(ranges) => ranges.iter().cloned().flatten().collect(),
+        ChunkCopy::None => Vec::new(),
+    };
+    let written: Vec<PathBuf> = match &config.output {
+        Some(base) => (0..total_chunks)
+            .map(|i| file_sink::chunk_path(base, i, config.format.extension()))
+            .collect(),
+        None => Vec::new(),
+    };
+    let targets: Vec<(PathBuf, &str)> = written
+        .iter()
+        .zip(chunks)
+        .map(|(p, c)| (p.clone(), c.text.as_str()))
+        .collect();
+    file_sink::write_outputs(&targets, config.force)?;
+    // Physical emission order; the chunks themselves keep their logical indices
+    let emission_order = |mut indices: Vec<usize>| {
+        if config.reverse_chunk_output {
+            indices.reverse();
         }
+        indices
     };
-    let mut copied_idx: Option<usize> = None;
-    // Non-interactive: print/copy requested chunk(s)
+    // Non-interactive: print requested chunk(s); all of them unless -k was given
     if config.stdout {
-        match config.chunk_copy {
-            ChunkCopy::Default => {
-                for (i, chunk) in chunks.iter().take(total_chunks).enumerate() {
-                    let snippet = chunk.xml.as_str();
-                    print!("{snippet}");
-                    if copy_idx == Some(i) && !config.no_clipboard {
-                        let copied = clipboard::copy_to_clipboard(snippet, !config.stdout)?;
-                        if copied {
-                            copied_idx = Some(i);
-                        }
-                    }
-                }
-            }
-            ChunkCopy::Index(idx) => {
-                let snippet = chunks[idx].xml.as_str();
-                print!("{snippet}");
-                if copy_idx == Some(idx) && !config.no_clipboard {
-                    let copied = clipboard::copy_to_clipboard(snippet, !config.stdout)?;
-                    if copied {
-                        copied_idx = Some(idx);
-                    }
-                }
-            }
-            ChunkCopy::None => {
-                if let Some(idx) = copy_idx {
-                    let snippet = chunks[idx].xml.as_str();
-                    let copied = clipboard::copy_to_clipboard(snippet, !config.stdout)?;
-                    if copied {
-                        copied_idx = Some(idx);
-                    }
-                }
-            }
+        let printed: Vec<usize> = match config.chunk_copy {
+            ChunkCopy::Default => (0..total_chunks).collect(),
+            _ => selected.clone(),
+        };
+        for idx in emission_order(printed) {
+            print!("{}", chunks[idx].text);
         }
-    } else if let Some(idx) = copy_idx {
-        let snippet = chunks[idx].xml.as_str();
-        let copied = clipboard::copy_to_clipboard(snippet, !config.stdout)?;
-        if copied {
-            copied_idx = Some(idx);
+    }
+    // Copy the selected chunks as one clipboard entry
+    let mut copied: Vec<usize> = Vec::new();
+    if !config.no_clipboard && !selected.is_empty() {
+        let text: String = emission_order(selected.clone())
+            .into_iter()
+            .map(|idx| chunks[idx].text.as_str())
+            .collect();
+        if clipboard::copy_to_clipboard(
+            &text,
+            config.clipboard_cmd.as_deref(),
+            clipboard_fail_hard,
+        )? {
+            copied = selected;
         }
     }
-    // 8) Summary
-    let total_token_count: usize = chunks.iter().map(|c| c.tokens).sum();
-    let summary = summary_line(
-        pipeline.file_data().len(),
-        config.model_context.map(|_| total_token_count),
+    // 8) Summary; chunks measured in chars or bytes are only tokenized for
+    // the budget check and the JSON summary
+    let total_token_count = if config.chunk_unit == ChunkUnit::Tokens {
+        Some(chunks.iter().map(|c| c.tokens).sum())
+    } else {
+        (config.model_context.is_some() || config.summary_json.is_some())
+            .then(|| chunks.iter().map(|c| gather::count_tokens(&c.text)).sum())
+    };
+    let summary = Summary::new(
+        &pipeline,
+        &config,
+        files_over_tokens,
+        total_token_count,
         total_chunks,
-        copied_idx,
-        pipeline.skipped_count(),
+        copied,
+        written,
     );
-    eprintln!("{summary}");
-    if config.no_clipboard && !config.stdout {
+    print_run_footer(&pipeline, &summary, &config)?;
+    if config.no_clipboard && !config.stdout && config.output.is_none() && !config.quiet {
         eprintln!("Note: neither --stdout nor clipboard copy requested; nothing visible.");
     }
 
-    // 9) Warn if token count exceeds model context limit
-    if let Some(limit) = config.model_context
-        && total_token_count > limit
-    {
-        warn!(
-            "token count {} exceeds model context limit {}",
-            total_token_count, limit
-        );
-    }
+    // 9) Warn (or fail) if token count exceeds model context limit
+    check_model_context(
+        total_token_count,
+        config.model_context,
+        config.fail_over_budget,
+    );
 
     Ok(())
 }
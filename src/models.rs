@@ -0,0 +1,141 @@
+/// A named model with its context window and the tokenizer used to count for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelPreset {
+    pub name: &'static str,
+    /// Context window in tokens.
+    pub context: usize,
+    /// Tokenizer model name passed to `tokenizer::init`. Non-OpenAI models
+    /// have no public tiktoken encoding and are approximated with o200k.
+    pub tokenizer: &'static str,
+}
+
+/// Built-in presets for `--model`.
+pub const MODEL_PRESETS: &[ModelPreset] = &[
+    ModelPreset {
+        name: "gpt-5.2",
+        context: 400_000,
+        tokenizer: "gpt-5.2",
+    },
+    ModelPreset {
+        name: "gpt-5",
+        context: 400_000,
+        tokenizer: "gpt-5",
+    },
+    ModelPreset {
+        name: "gpt-4.1",
+        context: 1_047_576,
+        tokenizer: "gpt-4.1",
+    },
+    ModelPreset {
+        name: "gpt-4o",
+        context: 128_000,
+        tokenizer: "gpt-4o",
+    },
+    ModelPreset {
+        name: "gpt-4-turbo",
+        context: 128_000,
+        tokenizer: "gpt-4",
+    },
+    ModelPreset {
+        name: "o3",
+        context: 200_000,
+        tokenizer: "o3",
+    },
+    ModelPreset {
+        name: "o4-mini",
+        context: 200_000,
+        tokenizer: "o4-mini",
+    },
+    ModelPreset {
+        name: "claude-sonnet",
+        context: 200_000,
+        tokenizer: "gpt-4o",
+    },
+    ModelPreset {
+        name: "claude-opus",
+        context: 200_000,
+        tokenizer: "gpt-4o",
+    },
+    ModelPreset {
+        name: "gemini-2.5-pro",
+        context: 1_048_576,
+        tokenizer: "gpt-4o",
+    },
+    ModelPreset {
+        name: "gemini-2.5-flash",
+        context: 1_048_576,
+        tokenizer: "gpt-4o",
+    },
+];
+
+/// Look up a preset by name, ignoring case.
+pub fn find_preset(name: &str) -> Option<&'static ModelPreset> {
+    MODEL_PRESETS
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Clap value parser for `--model`; unknown names list the known presets.
+pub fn parse_preset(name: &str) -> Result<&'static ModelPreset, String> {
+    find_preset(name).ok_or_else(|| {
+        let known: Vec<&str> = MODEL_PRESETS.iter().map(|p| p.name).collect();
+        format!("unknown model '{name}'; known models: {}", known.join(", "))
+    })
+}
+
+impl ModelPreset {
+    /// Context limit and tokenizer for this preset, keeping any value the user
+    /// set explicitly with the more specific flags.
+    pub fn fill(
+        &self,
+        context: Option<usize>,
+        tokenizer: Option<String>,
+    ) -> (usize, String) {
+        (
+            context.unwrap_or(self.context),
+            tokenizer.unwrap_or_else(|| self.tokenizer.to_string()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_ignores_case_and_lists_known_presets() {
+        assert_eq!(find_preset("GPT-4o").unwrap().context, 128_000);
+        let err = parse_preset("gpt-2").unwrap_err();
+        assert!(err.contains("unknown model 'gpt-2'"), "{err}");
+        assert!(
+            err.contains("gpt-4o") && err.contains("gemini-2.5-pro"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn explicit_values_override_preset() {
+        let preset = find_preset("gpt-4o").unwrap();
+        assert_eq!(preset.fill(None, None), (128_000, "gpt-4o".to_string()));
+        assert_eq!(
+            preset.fill(Some(50_000), None),
+            (50_000, "gpt-4o".to_string())
+        );
+        assert_eq!(
+            preset.fill(None, Some("gpt-4".into())),
+            (128_000, "gpt-4".to_string())
+        );
+    }
+
+    #[test]
+    fn every_preset_tokenizer_loads() {
+        for preset in MODEL_PRESETS {
+            assert!(
+                crate::tokenizer::supports_model(preset.tokenizer),
+                "{} uses unknown tokenizer {}",
+                preset.name,
+                preset.tokenizer
+            );
+        }
+    }
+}
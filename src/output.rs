@@ -1,39 +1,259 @@
+use crate::chunker::FileMeta;
+use crate::config::ChunkUnit;
+use path_slash::PathBufExt;
+use std::fmt::Write;
+
 #[derive(Debug, Clone)]
 pub struct RenderedChunk {
     /// Fully rendered snippet as printed/copied.
-    pub xml: String,
-    /// Token count for the rendered snippet.
+    pub text: String,
+    /// Size of the rendered snippet in the chunk unit (tokens by default).
     pub tokens: usize,
 }
 
+/// Opening tag for body chunk `idx` of `total`. `id` is kept for older
+/// consumers; `index`, `total`, and `kind` are the stable attributes.
+pub(crate) fn chunk_open_tag(
+    idx: usize,
+    total: usize,
+) -> String {
+    format!("<context-chunk id=\"{idx}/{total}\" index=\"{idx}\" total=\"{total}\" kind=\"body\">")
+}
+
+/// Render the chunk emitted at position `idx`. The header chunk comes first,
+/// or last with `header_last`; `header_xml` is the bare header element. The
+/// first snippet opens `<shared-context>`, the last closes it, and when more
+/// follow the first ends with a `<more>` marker. `remaining` is the size of
+/// the chunks after `idx` and its unit (`remaining-tokens`, or
+/// `remaining-bytes` and so on); when `None` the marker only carries the
+/// chunk count, as in the legacy snippet format.
 pub(crate) fn render_chunk_snippet(
     header_xml: &str,
     body_xmls: &[String],
     idx: usize,
+    remaining: Option<(usize, ChunkUnit)>,
+    header_last: bool,
 ) -> String {
     let total = body_xmls.len() + 1;
     let rem = total.saturating_sub(idx + 1);
+    let header_idx = if header_last { total - 1 } else { 0 };
+    let mut s = String::new();
     if idx == 0 {
-        let mut s = header_xml.to_string();
-        if rem > 0 {
-            s.push_str(&format!("<more remaining=\"{rem}\"/>\n"));
-        } else {
-            s.push_str("</shared-context>\n");
+        s.push_str("<shared-context>\n");
+    }
+    if idx == header_idx {
+        s.push_str(header_xml);
+        s.push('\n');
+    } else {
+        let body = if header_last { idx } else { idx - 1 };
+        let _ = write!(
+            s,
+            "{}\n{}</context-chunk>\n",
+            chunk_open_tag(idx, total),
+            body_xmls[body]
+        );
+    }
+    if rem == 0 {
+        s.push_str("</shared-context>\n");
+    } else if idx == 0 {
+        push_more_marker(&mut s, rem, remaining);
+    }
+    s
+}
+
+fn push_more_marker(
+    s: &mut String,
+    rem: usize,
+    remaining: Option<(usize, ChunkUnit)>,
+) {
+    match remaining {
+        Some((size, unit)) => {
+            let _ = writeln!(
+                s,
+                "<more remaining=\"{rem}\" remaining-{}=\"{size}\"/>",
+                unit.name()
+            );
+        }
+        None => {
+            let _ = writeln!(s, "<more remaining=\"{rem}\"/>");
         }
-        s
-    } else if rem > 0 {
-        format!(
-            "<context-chunk id=\"{}/{}\">\n{}</context-chunk>\n",
-            idx,
-            total,
-            body_xmls[idx - 1]
-        )
+    }
+}
+
+/// Everything `render_chunk_snippet` adds around a body chunk's blocks, for
+/// the body chunk that gets the most: the first one when the header comes
+/// last (it also opens `<shared-context>` and carries the `<more>` marker),
+/// otherwise the last one (it closes `<shared-context>`). Chunk numbers are
+/// written `digits` wide and the remaining size `remaining_digits` wide, so
+/// the envelope is at least as large as any real one with numbers that fit.
+/// `unit` is `None` for the legacy marker without a remaining size.
+pub(crate) fn body_snippet_envelope(
+    digits: usize,
+    remaining_digits: usize,
+    unit: Option<ChunkUnit>,
+    header_last: bool,
+) -> String {
+    let widest = |width: usize| {
+        "9".repeat(width.min(19))
+            .parse::<usize>()
+            .unwrap_or(usize::MAX)
+    };
+    let n = widest(digits);
+    let mut s = String::new();
+    if header_last {
+        s.push_str("<shared-context>\n");
+    }
+    let _ = write!(s, "{}\n</context-chunk>\n", chunk_open_tag(n, n));
+    if header_last {
+        push_more_marker(&mut s, n, unit.map(|unit| (widest(remaining_digits), unit)));
     } else {
-        format!(
-            "<context-chunk id=\"{}/{}\">\n{}</context-chunk>\n</shared-context>\n",
-            idx,
-            total,
-            body_xmls[idx - 1]
-        )
+        s.push_str("</shared-context>\n");
+    }
+    s
+}
+
+/// Per-file table for `--dry-run`: path, byte size, tokens, and whether the
+/// file would be split into parts under `chunk_limit` (0 means no chunking).
+pub fn render_dry_run(
+    metas: &[FileMeta],
+    chunk_limit: usize,
+) -> String {
+    let paths: Vec<String> = metas
+        .iter()
+        .map(|m| m.path.to_slash_lossy().to_string())
+        .collect();
+    let width = paths
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("path".len());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>10}  {:>8}  split",
+        "path", "bytes", "tokens"
+    );
+    for (meta, path) in metas.iter().zip(&paths) {
+        let split = if meta.stubbed {
+            "stubbed".to_string()
+        } else if chunk_limit > 0 && meta.parts > 1 {
+            format!("{} parts", meta.parts)
+        } else {
+            "no".to_string()
+        };
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>10}  {:>8}  {split}",
+            path, meta.size, meta.tokens
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn meta(
+        path: &str,
+        tokens: usize,
+        parts: usize,
+    ) -> FileMeta {
+        FileMeta {
+            path: PathBuf::from(path),
+            tokens,
+            parts,
+            size: tokens * 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn more_marker_carries_remaining_tokens_unless_legacy() {
+        let bodies = vec!["a".to_string(), "b".to_string()];
+        let hinted = render_chunk_snippet(
+            "<hdr/>\n",
+            &bodies,
+            0,
+            Some((41200, ChunkUnit::Tokens)),
+            false,
+        );
+        assert!(hinted.starts_with("<shared-context>\n<hdr/>\n"));
+        assert!(hinted.ends_with("<more remaining=\"2\" remaining-tokens=\"41200\"/>\n"));
+        let legacy = render_chunk_snippet("<hdr/>\n", &bodies, 0, None, false);
+        assert!(legacy.ends_with("<more remaining=\"2\"/>\n"));
+        let last =
+            render_chunk_snippet("<hdr/>\n", &bodies, 2, Some((0, ChunkUnit::Tokens)), false);
+        assert!(last.contains("index=\"2\""));
+        assert!(last.ends_with("b</context-chunk>\n</shared-context>\n"));
+    }
+
+    #[test]
+    fn header_last_moves_wrappers_and_marker() {
+        let bodies = vec!["a".to_string(), "b".to_string()];
+        let first =
+            render_chunk_snippet("<hdr/>\n", &bodies, 0, Some((7, ChunkUnit::Tokens)), true);
+        assert!(first.starts_with("<shared-context>\n<context-chunk id=\"0/3\""));
+        assert!(
+            first.ends_with("a</context-chunk>\n<more remaining=\"2\" remaining-tokens=\"7\"/>\n")
+        );
+        let middle = render_chunk_snippet("<hdr/>\n", &bodies, 1, None, true);
+        assert!(middle.starts_with("<context-chunk id=\"1/3\""));
+        assert!(middle.ends_with("b</context-chunk>\n"));
+        let last = render_chunk_snippet("<hdr/>\n", &bodies, 2, None, true);
+        assert_eq!(last, "<hdr/>\n\n</shared-context>\n");
+    }
+
+    #[test]
+    fn envelope_matches_widest_real_body_snippet() {
+        let bodies = vec![String::new(); 11];
+        let first = render_chunk_snippet(
+            "<hdr/>\n",
+            &bodies,
+            0,
+            Some((4100, ChunkUnit::Tokens)),
+            true,
+        );
+        let envelope = body_snippet_envelope(2, 4, Some(ChunkUnit::Tokens), true);
+        // Chunk 0 of 12 has a one-digit index
+        assert_eq!(envelope.len(), first.len() + 2);
+        assert!(envelope.ends_with("<more remaining=\"99\" remaining-tokens=\"9999\"/>\n"));
+
+        let last = render_chunk_snippet("<hdr/>\n", &bodies, 11, None, false);
+        let envelope = body_snippet_envelope(2, 4, None, false);
+        assert_eq!(envelope.len(), last.len());
+        assert!(envelope.ends_with("</shared-context>\n"));
+    }
+
+    #[test]
+    fn dry_run_table_marks_split_and_stubbed_files() {
+        let mut stub = meta("schema.sql", 9, 0);
+        stub.stubbed = true;
+        let table = render_dry_run(
+            &[meta("src/main.rs", 10, 1), meta("big.txt", 900, 3), stub],
+            300,
+        );
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("path"));
+        assert!(lines[1].starts_with("src/main.rs") && lines[1].ends_with("no"));
+        assert!(lines[2].contains("3600") && lines[2].ends_with("3 parts"));
+        assert!(lines[3].ends_with("stubbed"));
+    }
+
+    #[test]
+    fn dry_run_without_chunking_never_splits() {
+        let table = render_dry_run(&[meta("a.rs", 5, 1)], 0);
+        assert!(table.lines().nth(1).unwrap().ends_with("no"));
+    }
+
+    #[test]
+    fn dry_run_columns_align_to_longest_path() {
+        let table = render_dry_run(&[meta("a", 1, 1), meta("much/longer/path.rs", 2, 1)], 0);
+        let split_cols: Vec<usize> = table.lines().map(|l| l.rfind("  ").unwrap()).collect();
+        assert!(split_cols.iter().all(|&c| c == split_cols[0]));
+        assert!(split_cols[0] > "much/longer/path.rs".len());
     }
 }
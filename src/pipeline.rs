@@ -1,34 +1,172 @@
 use crate::chunker;
-use crate::context::gather;
-use crate::context::types::FileContents;
-use crate::header;
+use crate::config::{ChunkUnit, PathStyle, SanitizeMode, SortKey};
+use crate::constants::DEFAULT_EXCLUDES;
+#[cfg(feature = "archive")]
+use crate::context::archive;
+#[cfg(feature = "git")]
+use crate::context::git;
+use crate::context::template::{self, TemplateInput};
+use crate::context::types::{FileContents, FileKind};
+use crate::context::{gather, generated, json, plain, redact, sanitize, transform};
+use crate::events;
+use crate::header::{self, HeaderSpec};
+use crate::io::filter_cmd;
 use crate::output::{self, RenderedChunk};
+use crate::report;
+use crate::tokenizer;
 use crate::xml_output;
 use anyhow::{Result, anyhow};
-use globset::{Glob, GlobSetBuilder};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use path_slash::{PathBufExt, PathExt};
 use std::path::{Path, PathBuf};
-use tracing::warn;
+use tracing::{debug, warn};
 
+/// Every pattern passed to a glob flag failed to parse.
 #[derive(Debug)]
-pub struct InvalidExcludePatterns {
+pub struct InvalidPatterns {
+    /// The flag the patterns were given to, e.g. `--exclude-paths`.
+    pub flag: &'static str,
     pub patterns: Vec<String>,
 }
 
-impl std::fmt::Display for InvalidExcludePatterns {
+impl std::fmt::Display for InvalidPatterns {
     fn fmt(
         &self,
         f: &mut std::fmt::Formatter<'_>,
     ) -> std::fmt::Result {
         write!(
             f,
-            "Every --exclude pattern was invalid: {:?}",
-            self.patterns
+            "Every {} pattern was invalid: {:?}",
+            self.flag, self.patterns
         )
     }
 }
 
-impl std::error::Error for InvalidExcludePatterns {}
+impl std::error::Error for InvalidPatterns {}
+
+/// Build a glob matcher from user patterns, warning about invalid ones.
+/// Returns `None` when no patterns were given; otherwise the matcher and the
+/// valid patterns in matcher index order.
+fn build_matcher(
+    flag: &'static str,
+    patterns: &[String],
+) -> Result<Option<(GlobSet, Vec<String>)>> {
+    let raw_patterns: Vec<String> = patterns.iter().map(|p| p.replace('\\', "/")).collect();
+    let mut builder = GlobSetBuilder::new();
+    let mut valid = Vec::new();
+    for pattern in &raw_patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+                valid.push(pattern.clone());
+            }
+            Err(err) => {
+                warn!(
+                    event_code = events::INVALID_PATTERN,
+                    "invalid {flag} pattern: {pattern} ({err})"
+                );
+            }
+        }
+    }
+    if !raw_patterns.is_empty() && valid.is_empty() {
+        return Err(anyhow!(InvalidPatterns {
+            flag,
+            patterns: raw_patterns,
+        }));
+    }
+    if valid.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((builder.build()?, valid)))
+}
+
+/// Match a path (absolute, or relative to `root`) against both its
+/// root-relative and absolute forms.
+fn matches_path(
+    matcher: &GlobSet,
+    root: &Path,
+    path: &Path,
+) -> bool {
+    !matching_rules(matcher, root, path).is_empty()
+}
+
+/// Indices of every pattern in `matcher` that matches `path` in either form.
+fn matching_rules(
+    matcher: &GlobSet,
+    root: &Path,
+    path: &Path,
+) -> Vec<usize> {
+    let abs = root.join(path);
+    let rel = abs.strip_prefix(root).unwrap_or(&abs).to_slash_lossy();
+    let mut hits = matcher.matches(rel.as_ref());
+    hits.extend(matcher.matches(abs.to_slash_lossy().as_ref()));
+    hits.sort_unstable();
+    hits.dedup();
+    hits
+}
+
+/// `path` relative to the deepest path argument above it, for matching
+/// `DEFAULT_EXCLUDES`: naming `node_modules/pkg` keeps its files, while
+/// `node_modules` found under an argument is excluded. Files outside every
+/// argument (stdin, staged files) are taken relative to `root`; `None` for
+/// a file named by an argument itself or outside `root` too.
+fn below_argument(
+    user_paths: &[PathBuf],
+    root: &Path,
+    path: &Path,
+) -> Option<String> {
+    let rel = user_paths
+        .iter()
+        .filter_map(|arg| path.strip_prefix(arg).ok())
+        .min_by_key(|rel| rel.components().count())
+        .or_else(|| path.strip_prefix(root).ok())?;
+    match rel.as_os_str().is_empty() {
+        true => None,
+        false => Some(rel.to_slash_lossy().into_owned()),
+    }
+}
+
+/// Normalize `--exclude-ext` values to lowercase suffixes such as `.min.js`.
+fn extension_suffixes(exts: &[String]) -> Vec<String> {
+    exts.iter()
+        .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty())
+        .map(|e| format!(".{e}"))
+        .collect()
+}
+
+/// Replace each root with its `names` subdirectories that exist.
+/// Fails if some name exists under none of the roots.
+fn restrict_to_dirs(
+    roots: &[PathBuf],
+    names: &[String],
+) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for name in names {
+        let name = name.trim().trim_end_matches(['/', '\\']);
+        let found: Vec<PathBuf> = roots
+            .iter()
+            .map(|root| root.join(name))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        if found.is_empty() {
+            let roots: Vec<String> = roots
+                .iter()
+                .map(|r| r.to_slash_lossy().to_string())
+                .collect();
+            return Err(anyhow!(
+                "--only-dirs: directory {name:?} does not exist under any root ({})",
+                if roots.is_empty() {
+                    "no directory arguments".to_string()
+                } else {
+                    roots.join(", ")
+                }
+            ));
+        }
+        dirs.extend(found);
+    }
+    Ok(dirs)
+}
 
 /// Pipeline for gathering and assembling context outputs.
 #[derive(Default)]
@@ -37,10 +175,45 @@ pub struct Pipeline {
     user_paths_raw: Vec<PathBuf>,
     user_paths_canon: Vec<PathBuf>,
     candidate_files: Vec<PathBuf>,
+    /// Candidates added by `add_readmes`.
+    readme_paths: std::collections::HashSet<PathBuf>,
+    /// Exclude and `--only` rules applied to the candidates, as flags.
+    filters: Vec<String>,
+    /// Lines selected by `path:start-end` arguments, by canonical path.
+    line_ranges: std::collections::HashMap<PathBuf, gather::LineRange>,
+    /// Where a `-` argument's stdin is reported, and its bytes.
+    stdin_file: Option<(PathBuf, Vec<u8>)>,
+    /// `--max-size`, `--max-size-ext` and `--truncate-oversize`, from
+    /// `set_size_limits`.
+    max_size: u64,
+    max_size_ext: std::collections::BTreeMap<String, u64>,
+    truncate_oversize: bool,
+    /// Canonical paths of archive arguments.
+    #[cfg(feature = "archive")]
+    archives: Vec<PathBuf>,
+    /// Files read out of archive arguments, by their path under the archive
+    /// (`/work/code.zip/src/main.rs`); emptied by `collect_file_data`.
+    #[cfg(feature = "archive")]
+    archive_entries: std::collections::HashMap<PathBuf, archive::ArchiveEntry>,
     preselected_paths: Vec<PathBuf>,
     file_data: Vec<FileContents>,
-    skipped_messages: Vec<String>,
-    xml_output: Option<String>,
+    file_meta: Vec<chunker::FileMeta>,
+    skipped: Vec<gather::SkippedFile>,
+    /// Render skipped files as `<skipped-file>` entries (`--list-skipped`).
+    list_skipped: bool,
+    discovered_count: usize,
+    excluded_count: usize,
+    /// Files dropped by `truncate_files`.
+    truncated_count: usize,
+    /// Files changed by `sanitize_contents`.
+    sanitized_count: usize,
+    /// How `--chunk-size` splits oversize files and packs chunks.
+    chunking: chunker::ChunkOptions,
+    /// Tokens removed by each `transform_contents` pass, in run order.
+    transform_savings: Vec<report::TransformSavings>,
+    /// Matches replaced by `redact_secrets`, per rule, by file.
+    redactions: Vec<(PathBuf, Vec<(String, usize)>)>,
+    output: Option<String>,
     rendered_chunks: Vec<RenderedChunk>,
 }
 
@@ -49,35 +222,138 @@ impl Pipeline {
         Self::default()
     }
 
-    /// Expand user-provided paths and cache canonical versions for preselection.
+    /// A pipeline over already-gathered files, skipping discovery and reads.
+    /// Used to render the built-in `--render-fixture` corpora.
+    pub fn from_files(files: Vec<FileContents>) -> Self {
+        Self {
+            file_data: files,
+            ..Self::default()
+        }
+    }
+
+    /// Expand user-provided paths and cache canonical versions for
+    /// preselection. A `path:start-end` or `path:start+count` argument is
+    /// expanded without its suffix, and the files it names are later cut to
+    /// those lines by `collect_file_data`; a path that exists as written is
+    /// never split. Archive arguments are read into memory here. A `-`
+    /// argument stands for stdin (see `read_stdin`) and is skipped.
     pub fn expand_paths(
         &mut self,
         paths: &[String],
     ) -> Result<()> {
         let root = std::env::current_dir()?;
         self.root = dunce::canonicalize(root)?;
-        self.user_paths_raw = gather::expand_paths(paths.to_vec())?;
+        let mut plain = Vec::with_capacity(paths.len());
+        for arg in paths.iter().filter(|arg| *arg != "-") {
+            let ranged = match Path::new(arg).exists() {
+                true => None,
+                false => gather::split_line_range(arg)?,
+            };
+            let Some((path, range)) = ranged else {
+                plain.push(arg.clone());
+                continue;
+            };
+            plain.push(path.to_string());
+            for expanded in gather::expand_paths(vec![path.to_string()])? {
+                if expanded.is_dir() {
+                    warn!(
+                        event_code = events::FLAG_IGNORED,
+                        "line range {range} ignored for directory {}",
+                        expanded.display()
+                    );
+                } else if let Ok(canon) = dunce::canonicalize(&expanded) {
+                    self.line_ranges.insert(canon, range);
+                }
+            }
+        }
+        self.user_paths_raw = gather::expand_paths(plain)?;
         self.user_paths_canon = self
             .user_paths_raw
             .iter()
             .filter_map(|p| dunce::canonicalize(p).ok())
             .collect();
+        #[cfg(feature = "archive")]
+        for path in &self.user_paths_raw {
+            if !archive::is_archive(path) {
+                continue;
+            }
+            let canon = dunce::canonicalize(path)?;
+            if self.archives.contains(&canon) {
+                continue;
+            }
+            let limit = |rel: &Path| gather::max_size_for(rel, self.max_size, &self.max_size_ext);
+            for (rel, entry) in archive::read_archive(path, limit, self.truncate_oversize)? {
+                self.archive_entries.insert(canon.join(rel), entry);
+            }
+            self.archives.push(canon);
+        }
         Ok(())
     }
 
     /// Build candidate file list (explicit files + files under directories).
-    pub fn build_candidates(&mut self) -> Result<()> {
+    /// With `only_dirs`, directory roots are narrowed to those named subtrees;
+    /// explicit file arguments are unaffected. An archive argument stands
+    /// for the files in it that the walk would accept by depth and
+    /// hiddenness; ignore files inside archives are not read.
+    pub fn build_candidates(
+        &mut self,
+        walk: &gather::WalkOptions,
+        only_dirs: &[String],
+    ) -> Result<()> {
         let mut candidate_files: Vec<PathBuf> = Vec::new();
         let mut dirs_to_scan: Vec<PathBuf> = Vec::new();
         for up in &self.user_paths_raw {
+            #[cfg(feature = "archive")]
+            if let Ok(canon) = dunce::canonicalize(up)
+                && self.archives.contains(&canon)
+            {
+                candidate_files.extend(
+                    self.archive_entries
+                        .keys()
+                        .filter(|path| {
+                            path.strip_prefix(&canon)
+                                .is_ok_and(|rel| walk_visible(rel, walk))
+                        })
+                        .cloned(),
+                );
+                continue;
+            }
             if up.is_dir() {
                 dirs_to_scan.push(up.clone());
             } else {
                 candidate_files.push(up.clone());
             }
         }
+        if !only_dirs.is_empty() {
+            dirs_to_scan = restrict_to_dirs(&dirs_to_scan, only_dirs)?;
+        }
+        if walk.git_ls_files {
+            dirs_to_scan.retain(|dir| match git_listed_files(dir, walk) {
+                Some(listed) => {
+                    candidate_files.extend(listed);
+                    false
+                }
+                None => {
+                    warn!(
+                        event_code = events::GIT_LS_FILES_FALLBACK,
+                        path = %dir.display(),
+                        "{} is not inside a git work tree; walking it instead of using --git-ls-files",
+                        dir.display()
+                    );
+                    true
+                }
+            });
+        }
         if !dirs_to_scan.is_empty() {
-            candidate_files.extend(gather::gather_all_file_paths(&dirs_to_scan)?);
+            candidate_files.extend(gather::gather_all_file_paths_with(
+                &dirs_to_scan,
+                walk,
+                &|found| {
+                    if found % 10_000 == 0 {
+                        debug!("discovered {found} files so far");
+                    }
+                },
+            )?);
         }
 
         // Canonicalize and deduplicate explicit and discovered files
@@ -89,16 +365,129 @@ impl Pipeline {
         candidate_files.sort();
         candidate_files.dedup();
 
+        self.discovered_count = candidate_files.len();
+        if walk.modified_since.is_some() || walk.modified_before.is_some() {
+            candidate_files.retain(|path| modified_within(path, self.mtime(path), walk));
+            self.excluded_count += self.discovered_count - candidate_files.len();
+        }
         self.candidate_files = candidate_files;
         Ok(())
     }
 
-    /// Compute which candidates are preselected (under user paths).
+    /// Build the candidate list from the files staged in the git index
+    /// (`--staged`) instead of walking, keeping those under the user paths.
+    /// Fails outside a git work tree.
+    pub fn use_staged_files(&mut self) -> Result<()> {
+        #[cfg(feature = "git")]
+        {
+            let staged = git::staged_files(&self.root).ok_or_else(|| {
+                anyhow!(
+                    "--staged needs a git repository, but {} is not inside a work tree",
+                    self.root.display()
+                )
+            })?;
+            let mut candidate_files: Vec<PathBuf> = staged
+                .into_iter()
+                .filter(|path| path.is_file() && is_preselected(path, &self.user_paths_canon))
+                .collect();
+            candidate_files.sort();
+            self.discovered_count = candidate_files.len();
+            self.candidate_files = candidate_files;
+            Ok(())
+        }
+        #[cfg(not(feature = "git"))]
+        {
+            Err(anyhow!("--staged needs the `git` feature"))
+        }
+    }
+
+    /// Replace the contents read from the working tree with what is staged
+    /// in the git index (`--staged-content`). Files cut by
+    /// `--truncate-oversize` keep their working-tree prefix, and a staged
+    /// version that looks binary leaves the file as it was. With
+    /// `normalize_eol`, the staged text's CRLF line endings become LF.
+    pub fn read_staged_contents(
+        &mut self,
+        normalize_eol: bool,
+    ) {
+        #[cfg(not(feature = "git"))]
+        let _ = normalize_eol;
+        #[cfg(feature = "git")]
+        for file in &mut self.file_data {
+            if file.truncated || file.kind != FileKind::Text {
+                continue;
+            }
+            let Some(bytes) = git::index_contents(&self.root.join(&file.path)) else {
+                continue;
+            };
+            let size = bytes.len() as u64;
+            let sha256 = file.sha256.as_ref().map(|_| gather::sha256_hex(&bytes));
+            if let Ok((mut contents, encoding)) = crate::context::detect::decode(bytes) {
+                file.crlf = normalize_eol && gather::normalize_eol(&mut contents);
+                file.contents = contents;
+                file.encoding = encoding.map(str::to_string);
+                file.size = Some(size);
+                file.sha256 = sha256;
+                file.tokens = None;
+            }
+        }
+    }
+
+    /// Read stdin to EOF and add it as a candidate named `name` under the
+    /// root, for a `-` path argument. Call after the candidates are built.
+    /// It is then read, filtered, and rendered like a file named on the
+    /// command line.
+    pub fn read_stdin(
+        &mut self,
+        name: &Path,
+    ) -> Result<()> {
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin().lock(), &mut bytes)?;
+        let path = self.root.join(name);
+        if let Err(at) = self.candidate_files.binary_search(&path) {
+            self.candidate_files.insert(at, path.clone());
+            self.discovered_count += 1;
+        }
+        self.stdin_file = Some((path, bytes));
+        Ok(())
+    }
+
+    /// Modification time of a candidate, on disk or recorded in its archive.
+    /// Stdin counts as modified now.
+    fn mtime(
+        &self,
+        path: &Path,
+    ) -> std::io::Result<std::time::SystemTime> {
+        if self
+            .stdin_file
+            .as_ref()
+            .is_some_and(|(stdin, _)| stdin == path)
+        {
+            return Ok(std::time::SystemTime::now());
+        }
+        #[cfg(feature = "archive")]
+        if let Some(entry) = self.archive_entries.get(path) {
+            return entry
+                .mtime
+                .ok_or_else(|| std::io::Error::other("the archive records no modification time"));
+        }
+        std::fs::metadata(path).and_then(|m| m.modified())
+    }
+
+    /// Compute which candidates are preselected (under user paths, or added
+    /// by `add_readmes`).
     pub fn compute_preselected(&mut self) {
         self.preselected_paths = self
             .candidate_files
             .iter()
-            .filter(|cand| is_preselected(cand, &self.user_paths_canon))
+            .filter(|cand| {
+                is_preselected(cand, &self.user_paths_canon)
+                    || self.readme_paths.contains(*cand)
+                    || self
+                        .stdin_file
+                        .as_ref()
+                        .is_some_and(|(stdin, _)| stdin == *cand)
+            })
             .cloned()
             .collect();
     }
@@ -110,6 +499,42 @@ impl Pipeline {
         self.candidate_files = files;
     }
 
+    /// Candidate paths relative to the root (absolute when outside it),
+    /// in the form accepted by `--paths-from`.
+    pub fn candidate_display_paths(&self) -> Vec<String> {
+        self.candidate_files
+            .iter()
+            .map(|p| {
+                p.strip_prefix(&self.root)
+                    .unwrap_or(p)
+                    .to_slash_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// The expanded user paths relative to the root (absolute when outside
+    /// it), as searched for candidates.
+    pub fn user_path_display(&self) -> Vec<String> {
+        self.user_paths_raw
+            .iter()
+            .map(|p| {
+                let canon = dunce::canonicalize(p).unwrap_or_else(|_| p.clone());
+                match canon.strip_prefix(&self.root) {
+                    Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+                    Ok(rel) => rel.to_slash_lossy().to_string(),
+                    Err(_) => p.to_slash_lossy().to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Exclude and `--only` rules applied to the candidates, as the flags
+    /// that set them.
+    pub fn filters(&self) -> &[String] {
+        &self.filters
+    }
+
     pub fn candidate_files(&self) -> &[PathBuf] {
         &self.candidate_files
     }
@@ -122,75 +547,915 @@ impl Pipeline {
         &self.file_data
     }
 
+    /// Per-file metadata from the most recent output build.
+    pub fn file_meta(&self) -> &[chunker::FileMeta] {
+        &self.file_meta
+    }
+
     pub fn skipped_count(&self) -> usize {
-        self.skipped_messages.len()
+        self.skipped.len()
+    }
+
+    pub fn skipped_files(&self) -> &[gather::SkippedFile] {
+        &self.skipped
+    }
+
+    /// Limit file reads to `max_size` bytes, or the `max_size_ext` limit of
+    /// a file's extension (see `gather::max_size_for`). Larger files are
+    /// skipped, or cut to the limit with `truncate_oversize`. Set before
+    /// `expand_paths`, which reads archive entries within these limits.
+    pub fn set_size_limits(
+        &mut self,
+        max_size: u64,
+        max_size_ext: std::collections::BTreeMap<String, u64>,
+        truncate_oversize: bool,
+    ) {
+        self.max_size = max_size;
+        self.max_size_ext = max_size_ext;
+        self.truncate_oversize = truncate_oversize;
     }
 
-    pub fn skipped_messages(&self) -> &[String] {
-        &self.skipped_messages
+    /// List skipped files in the file map and header so the model knows
+    /// they exist.
+    pub fn set_list_skipped(
+        &mut self,
+        on: bool,
+    ) {
+        self.list_skipped = on;
     }
 
-    pub fn xml_output(&self) -> Option<&str> {
-        self.xml_output.as_deref()
+    /// Skipped files to render, with paths relative to the root (absolute
+    /// when outside it); empty unless `set_list_skipped` is on.
+    fn listed_skips(&self) -> Vec<gather::SkippedFile> {
+        if !self.list_skipped {
+            return Vec::new();
+        }
+        self.skipped
+            .iter()
+            .map(|skip| gather::SkippedFile {
+                path: skip
+                    .path
+                    .strip_prefix(&self.root)
+                    .unwrap_or(&skip.path)
+                    .to_path_buf(),
+                ..skip.clone()
+            })
+            .collect()
+    }
+
+    /// Counts explaining how candidates were narrowed down, for diagnostics.
+    pub fn gather_stats(&self) -> report::GatherStats {
+        let count = |reason| self.skipped.iter().filter(|s| s.reason == reason).count();
+        report::GatherStats {
+            discovered: self.discovered_count,
+            excluded: self.excluded_count,
+            too_large: count(gather::SkipReason::TooLarge),
+            too_many_tokens: count(gather::SkipReason::TooManyTokens),
+            binary: count(gather::SkipReason::Binary),
+            unreadable: count(gather::SkipReason::Unreadable),
+            empty: count(gather::SkipReason::Empty),
+            generated: count(gather::SkipReason::Generated),
+        }
+    }
+
+    /// Non-chunked output built by `build_xml` or `build_json`.
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
     }
 
     pub fn rendered_chunks(&self) -> &[RenderedChunk] {
         &self.rendered_chunks
     }
 
-    /// Apply exclude patterns to candidate files.
+    /// How the gathered files would be laid out in chunk bodies of at most
+    /// `chunk_limit`, without rendering any of them. JSON, plain-text and
+    /// template chunks follow it as is; `build_chunks_with_header` plans
+    /// against the limit less the chunk wrapper, so its layout can differ.
+    pub fn chunk_plan(
+        &self,
+        chunk_limit: usize,
+        escape_xml: bool,
+        cdata: bool,
+    ) -> Vec<chunker::ChunkPlan> {
+        chunker::plan_chunk_bodies(
+            &self.file_data,
+            chunk_limit,
+            escape_xml,
+            cdata,
+            self.chunking,
+        )
+        .0
+    }
+
+    /// Apply exclude globs and `--exclude-ext` extensions to candidate files,
+    /// plus `DEFAULT_EXCLUDES` when `defaults` is set. A file matching any of
+    /// these rules is dropped, unless it matches an exclude pattern starting
+    /// with `!`, which re-includes it.
     pub fn apply_excludes(
         &mut self,
         exclude: &[String],
+        exclude_ext: &[String],
+        defaults: bool,
+    ) -> Result<()> {
+        let (negated, positive): (Vec<String>, Vec<String>) = exclude
+            .iter()
+            .map(|p| match p.strip_prefix('!') {
+                Some(negated) => format!("!{}", gather::expand_home_and_vars(negated)),
+                None => gather::expand_home_and_vars(p),
+            })
+            .partition(|p| p.starts_with('!'));
+        let negated: Vec<String> = negated.iter().map(|p| p[1..].to_string()).collect();
+        self.filters
+            .extend(exclude.iter().map(|p| format!("--exclude-paths {p}")));
+        self.filters
+            .extend(exclude_ext.iter().map(|ext| format!("--exclude-ext {ext}")));
+        let matcher = build_matcher("--exclude-paths", &positive)?;
+        let suffixes = extension_suffixes(exclude_ext);
+        let default_patterns: Vec<String> = match defaults {
+            true => DEFAULT_EXCLUDES.iter().map(|p| p.to_string()).collect(),
+            false => Vec::new(),
+        };
+        let default_matcher = build_matcher("default exclude", &default_patterns)?;
+        if matcher.is_none() && suffixes.is_empty() && default_matcher.is_none() {
+            if !negated.is_empty() {
+                warn!(
+                    event_code = events::EXCLUDE_NEGATION_UNUSED,
+                    "negated --exclude-paths patterns have no effect without other excludes: {:?}",
+                    negated
+                );
+            }
+            return Ok(());
+        }
+        let allow = build_matcher("--exclude-paths negation", &negated)?;
+        let mut reincluded = 0usize;
+        let glob_count = matcher.as_ref().map_or(0, |(_, p)| p.len());
+        let default_start = glob_count + suffixes.len();
+        let mut removed_by = vec![0usize; default_start + default_patterns.len()];
+        let before = self.candidate_files.len();
+        let user_paths = &self.user_paths_canon;
+        self.candidate_files.retain(|path| {
+            let mut hits = matcher
+                .as_ref()
+                .map(|(set, _)| matching_rules(set, &self.root, path))
+                .unwrap_or_default();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            hits.extend(
+                suffixes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, suffix)| name.len() > suffix.len() && name.ends_with(*suffix))
+                    .map(|(i, _)| glob_count + i),
+            );
+            if let Some((set, _)) = &default_matcher
+                && let Some(rel) = below_argument(user_paths, &self.root, path)
+            {
+                hits.extend(
+                    set.matches(rel.as_str())
+                        .into_iter()
+                        .map(|i| default_start + i),
+                );
+            }
+            if !hits.is_empty()
+                && allow
+                    .as_ref()
+                    .is_some_and(|(set, _)| matches_path(set, &self.root, path))
+            {
+                reincluded += 1;
+                return true;
+            }
+            for &hit in &hits {
+                removed_by[hit] += 1;
+            }
+            hits.is_empty()
+        });
+        if let Some((_, patterns)) = &matcher {
+            for (pattern, n) in patterns.iter().zip(&removed_by) {
+                debug!("--exclude-paths {pattern:?} matched {n} files");
+            }
+        }
+        for (suffix, n) in suffixes.iter().zip(&removed_by[glob_count..]) {
+            debug!("--exclude-ext {:?} matched {n} files", &suffix[1..]);
+        }
+        for (pattern, n) in default_patterns.iter().zip(&removed_by[default_start..]) {
+            debug!("default exclude {pattern:?} matched {n} files");
+        }
+        if removed_by[default_start..].iter().any(|&n| n > 0) {
+            self.filters.push("(default excludes)".to_string());
+        }
+        if allow.is_some() {
+            debug!("negated --exclude-paths patterns re-included {reincluded} files");
+        }
+        self.excluded_count = before - self.candidate_files.len();
+        Ok(())
+    }
+
+    /// Keep only candidate files matching at least one `--only` pattern.
+    pub fn apply_only(
+        &mut self,
+        only: &[String],
     ) -> Result<()> {
-        let raw_patterns: Vec<String> = exclude.iter().map(|p| p.replace('\\', "/")).collect();
-        let mut builder = GlobSetBuilder::new();
-        let mut valid = 0usize;
-        for pattern in &raw_patterns {
-            match Glob::new(pattern) {
-                Ok(glob) => {
-                    builder.add(glob);
-                    valid += 1;
+        let Some((matcher, _)) = build_matcher("--only", only)? else {
+            return Ok(());
+        };
+        self.filters
+            .extend(only.iter().map(|p| format!("--only {p}")));
+        let before = self.candidate_files.len();
+        self.candidate_files
+            .retain(|path| matches_path(&matcher, &self.root, path));
+        if before > 0 && self.candidate_files.is_empty() {
+            warn!(
+                event_code = events::ONLY_NO_MATCH,
+                "no files matched --only patterns: {:?}", only
+            );
+        }
+        self.excluded_count += before - self.candidate_files.len();
+        Ok(())
+    }
+
+    /// Add the `README*` files of every folder that contributes a candidate,
+    /// unless already a candidate. Call after excludes and `--only`, so only
+    /// folders that still contribute files get one. Returns the number added.
+    pub fn add_readmes(&mut self) -> usize {
+        let mut folders: Vec<&Path> = self
+            .candidate_files
+            .iter()
+            .filter_map(|path| path.parent())
+            .collect();
+        folders.sort();
+        folders.dedup();
+        let mut readmes = Vec::new();
+        for folder in folders {
+            let Ok(entries) = std::fs::read_dir(folder) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let is_readme = entry
+                    .file_name()
+                    .to_string_lossy()
+                    .to_ascii_lowercase()
+                    .starts_with("readme");
+                if is_readme && entry.file_type().is_ok_and(|t| t.is_file()) {
+                    let path = entry.path();
+                    if self.candidate_files.binary_search(&path).is_err() {
+                        readmes.push(path);
+                    }
                 }
-                Err(err) => {
-                    warn!("invalid --exclude-paths pattern: {pattern} ({err})");
+            }
+        }
+        let added = readmes.len();
+        debug!("--include-readmes added {added} files");
+        self.candidate_files.extend(readmes.iter().cloned());
+        self.candidate_files.sort();
+        self.readme_paths.extend(readmes);
+        added
+    }
+
+    /// Keep only gathered files for which `keep` returns true. Call after
+    /// `collect_file_data` and before building output; dropped files count as
+    /// excluded.
+    pub fn retain_files(
+        &mut self,
+        mut keep: impl FnMut(&FileContents) -> bool,
+    ) {
+        let before = self.file_data.len();
+        self.file_data.retain(|file| keep(file));
+        self.excluded_count += before - self.file_data.len();
+    }
+
+    /// Drop gathered files with no contents (`--skip-empty`), recording
+    /// them as skipped.
+    pub fn skip_empty_files(&mut self) {
+        let (empty, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.file_data)
+            .into_iter()
+            .partition(FileContents::is_empty);
+        self.file_data = kept;
+        for file in empty {
+            let path = self.root.join(&file.path);
+            debug!(
+                event_code = events::FILE_SKIPPED_EMPTY,
+                path = %path.display(),
+                "skipping empty file"
+            );
+            self.skipped.push(gather::SkippedFile {
+                message: format!("skipping empty file {}", path.display()),
+                path,
+                reason: gather::SkipReason::Empty,
+                size: Some(0),
+            });
+        }
+    }
+
+    /// Drop gathered files that look generated (`--skip-generated`; see
+    /// `generated::Detector`), recording them as skipped.
+    pub fn skip_generated_files(&mut self) {
+        let mut detector = generated::Detector::new();
+        let mut dropped = Vec::new();
+        self.file_data.retain(|file| {
+            let path = self.root.join(&file.path);
+            let text = match file.kind {
+                FileKind::Text => file.contents.as_str(),
+                _ => "",
+            };
+            match detector.category(&path, text) {
+                Some(category) => {
+                    dropped.push((path, category, file.size));
+                    false
                 }
+                None => true,
             }
+        });
+        for (path, category, size) in dropped {
+            debug!(
+                event_code = events::FILE_SKIPPED_GENERATED,
+                path = %path.display(),
+                category,
+                "skipping generated file"
+            );
+            self.skipped.push(gather::SkippedFile {
+                message: format!("skipping generated file {} ({category})", path.display()),
+                path,
+                reason: gather::SkipReason::Generated,
+                size,
+            });
         }
-        if !raw_patterns.is_empty() && valid == 0 {
-            return Err(anyhow!(InvalidExcludePatterns {
-                patterns: raw_patterns,
-            }));
+    }
+
+    /// Files left out by `skip_generated_files`, relative to the root.
+    pub fn generated_paths(&self) -> Vec<PathBuf> {
+        self.skipped
+            .iter()
+            .filter(|skip| skip.reason == gather::SkipReason::Generated)
+            .map(|skip| {
+                skip.path
+                    .strip_prefix(&self.root)
+                    .unwrap_or(&skip.path)
+                    .to_path_buf()
+            })
+            .collect()
+    }
+
+    /// Stub every gathered text file whose contents match another's
+    /// (`--dedup-contents`). The copy with the lexicographically smallest
+    /// path is kept; the others point at it through `duplicate_of`. Empty
+    /// files are left alone.
+    pub fn dedup_contents(&mut self) {
+        let mut groups: std::collections::HashMap<String, Vec<usize>> = Default::default();
+        for (idx, file) in self.file_data.iter().enumerate() {
+            if file.kind == FileKind::Text && !file.contents.is_empty() {
+                let digest = gather::sha256_hex(file.contents.as_bytes());
+                groups.entry(digest).or_default().push(idx);
+            }
+        }
+        for group in groups.into_values().filter(|g| g.len() > 1) {
+            let slash = |idx: &usize| self.file_data[*idx].path.to_slash_lossy().into_owned();
+            let canonical = *group.iter().min_by_key(|idx| slash(idx)).unwrap();
+            let original = self.file_data[canonical].path.clone();
+            for idx in group.into_iter().filter(|&idx| idx != canonical) {
+                let file = &mut self.file_data[idx];
+                debug!(
+                    event_code = events::FILE_DUPLICATE,
+                    path = %file.path.display(),
+                    original = %original.display(),
+                    "{} duplicates {}",
+                    file.path.display(),
+                    original.display()
+                );
+                file.stubbed = true;
+                file.duplicate_of = Some(original.clone());
+            }
         }
-        if valid == 0 {
+    }
+
+    /// Keep only gathered files whose relative path `cmd` echoes back.
+    pub fn apply_filter_cmd(
+        &mut self,
+        cmd: &str,
+        timeout: std::time::Duration,
+    ) -> Result<()> {
+        let paths: Vec<String> = self
+            .file_data
+            .iter()
+            .map(|f| f.path.to_slash_lossy().to_string())
+            .collect();
+        let kept: std::collections::HashSet<String> = filter_cmd::run_filter(cmd, &paths, timeout)?
+            .into_iter()
+            .collect();
+        self.retain_files(|f| kept.contains(f.path.to_slash_lossy().as_ref()));
+        debug!(
+            "--filter-cmd kept {} of {} files",
+            self.file_data.len(),
+            paths.len()
+        );
+        Ok(())
+    }
+
+    /// Mark gathered files matching `--stub-paths` patterns as stubbed.
+    pub fn apply_stubs(
+        &mut self,
+        stubs: &[String],
+    ) -> Result<()> {
+        let Some((matcher, _)) = build_matcher("--stub-paths", stubs)? else {
             return Ok(());
+        };
+        for file in &mut self.file_data {
+            file.stubbed |= matches_path(&matcher, &self.root, &file.path);
         }
+        Ok(())
+    }
 
-        let matcher = builder.build()?;
-        self.candidate_files.retain(|path| {
-            let abs = path.to_slash_lossy();
-            let rel = path
-                .strip_prefix(&self.root)
-                .ok()
-                .map(|p| p.to_slash_lossy());
-            let rel = rel.as_deref().unwrap_or(abs.as_ref());
-            !matcher.is_match(rel) && !matcher.is_match(abs.as_ref())
-        });
+    /// Move gathered files matching `--first` patterns to the front, keeping
+    /// the relative order within both groups. Call after `sort_files`.
+    pub fn prioritize(
+        &mut self,
+        patterns: &[String],
+    ) -> Result<()> {
+        let Some((matcher, valid)) = build_matcher("--first", patterns)? else {
+            return Ok(());
+        };
+        let mut hits = vec![0usize; valid.len()];
+        for file in &mut self.file_data {
+            let rules = matching_rules(&matcher, &self.root, &file.path);
+            for &rule in &rules {
+                hits[rule] += 1;
+            }
+            file.priority = !rules.is_empty();
+        }
+        for (pattern, _) in valid.iter().zip(&hits).filter(|(_, n)| **n == 0) {
+            warn!(
+                event_code = events::FIRST_NO_MATCH,
+                "--first pattern matched no files: {pattern}"
+            );
+        }
+        self.file_data.sort_by_key(|f| !f.priority);
         Ok(())
     }
 
-    /// Read file data into memory.
+    /// Flag files that live outside the git repository containing the root.
+    /// Does nothing when the root is not inside a git work tree, or when
+    /// built without the `git` feature.
+    pub fn mark_external(&mut self) {
+        #[cfg(feature = "git")]
+        {
+            let Some(top) = git::toplevel(&self.root) else {
+                return;
+            };
+            for file in &mut self.file_data {
+                file.external = !self.root.join(&file.path).starts_with(&top);
+            }
+        }
+    }
+
+    /// Number of gathered files from outside the git repository.
+    pub fn external_count(&self) -> usize {
+        self.file_data.iter().filter(|f| f.external).count()
+    }
+
+    /// Tokens removed by each content transformation pass.
+    pub fn transform_savings(&self) -> &[report::TransformSavings] {
+        &self.transform_savings
+    }
+
+    /// Number of gathered files dropped by `truncate_files`.
+    pub fn truncated_count(&self) -> usize {
+        self.truncated_count
+    }
+
+    /// Choose how chunked builds split files that exceed the chunk size and
+    /// pack blocks into chunks.
+    pub fn set_chunk_options(
+        &mut self,
+        options: chunker::ChunkOptions,
+    ) {
+        self.chunking = options;
+    }
+
+    /// Number of gathered files changed by `sanitize_contents`.
+    pub fn sanitized_count(&self) -> usize {
+        self.sanitized_count
+    }
+
+    /// Number of gathered files whose contents are rendered in the output.
+    pub fn included_count(&self) -> usize {
+        self.file_data.iter().filter(|f| !f.stubbed).count()
+    }
+
+    /// Number of gathered files listed in the file map without contents,
+    /// other than duplicates.
+    pub fn stubbed_count(&self) -> usize {
+        self.file_data
+            .iter()
+            .filter(|f| f.stubbed && f.duplicate_of.is_none())
+            .count()
+    }
+
+    /// Number of gathered files stubbed by `dedup_contents`.
+    pub fn duplicate_count(&self) -> usize {
+        self.file_data
+            .iter()
+            .filter(|f| f.duplicate_of.is_some())
+            .count()
+    }
+
+    /// Read file data into memory, hashing each file unless `!hashes`, within
+    /// the limits of `set_size_limits`. With `normalize_eol`, CRLF line
+    /// endings become LF.
     pub fn collect_file_data(
         &mut self,
-        max_size: u64,
+        hashes: bool,
+        normalize_eol: bool,
     ) -> Result<()> {
-        let collection =
-            gather::collect_file_data_with_skips(&self.candidate_files, max_size, &self.root)?;
+        // Files named directly (not found by walking a directory)
+        let explicit: std::collections::HashSet<PathBuf> = self
+            .user_paths_canon
+            .iter()
+            .filter(|p| p.is_file())
+            .cloned()
+            .collect();
+        #[cfg(feature = "archive")]
+        let archived = std::mem::take(&mut self.archive_entries);
+        let limit = |path: &Path| gather::max_size_for(path, self.max_size, &self.max_size_ext);
+        let stdin = self.stdin_file.take();
+        let collection = gather::collect_with(&self.candidate_files, limit, |path| {
+            let max_size = limit(path);
+            let options = gather::ReadOptions {
+                max_size,
+                explicit: false,
+                hashes,
+                truncate_oversize: self.truncate_oversize,
+                eol: normalize_eol,
+            };
+            if let Some((stdin_path, bytes)) = &stdin
+                && stdin_path == path
+            {
+                let options = gather::ReadOptions {
+                    explicit: true,
+                    ..options
+                };
+                return gather::read_bytes(
+                    path,
+                    bytes,
+                    bytes.len() as u64,
+                    None,
+                    &self.root,
+                    &options,
+                );
+            }
+            #[cfg(feature = "archive")]
+            if let Some(entry) = archived.get(path) {
+                return gather::read_bytes(
+                    path,
+                    &entry.bytes,
+                    entry.size,
+                    entry.mtime,
+                    &self.root,
+                    &options,
+                );
+            }
+            let options = gather::ReadOptions {
+                explicit: explicit.contains(path),
+                ..options
+            };
+            gather::read_file(path, &self.root, &options)
+        })?;
         self.file_data = collection.files;
-        self.skipped_messages = collection.skipped;
+        if !self.readme_paths.is_empty() {
+            for file in &mut self.file_data {
+                file.via_readme = self.readme_paths.contains(&self.root.join(&file.path));
+            }
+        }
+        self.skipped = collection.skipped;
+        self.apply_line_ranges();
         Ok(())
     }
 
+    /// Cut files named with a line range down to those lines, clamping
+    /// ranges that run past the end of the file.
+    fn apply_line_ranges(&mut self) {
+        if self.line_ranges.is_empty() {
+            return;
+        }
+        for file in &mut self.file_data {
+            let path = self.root.join(&file.path);
+            let Some(&range) = self.line_ranges.get(&path) else {
+                continue;
+            };
+            if file.kind != FileKind::Text {
+                continue;
+            }
+            let Some((contents, kept)) = gather::slice_lines(&file.contents, range) else {
+                warn!(
+                    event_code = events::LINE_RANGE_CLAMPED,
+                    path = %path.display(),
+                    "{} is empty; ignoring line range {range}",
+                    path.display()
+                );
+                continue;
+            };
+            if kept != range {
+                warn!(
+                    event_code = events::LINE_RANGE_CLAMPED,
+                    path = %path.display(),
+                    "{} has no lines past {}; showing lines {kept} instead of {range}",
+                    path.display(),
+                    kept.end
+                );
+            }
+            file.contents = contents;
+            file.lines = Some(kept);
+            file.tokens = None;
+        }
+    }
+
+    /// Count each gathered file's tokens once, in parallel; sorting and
+    /// chunking reuse the cached counts.
+    pub fn count_file_tokens(&mut self) {
+        let counts = tokenizer::par_map(&self.file_data, |_, file| {
+            file.tokens
+                .is_none()
+                .then(|| gather::count_tokens(&file.contents))
+        });
+        for (file, count) in self.file_data.iter_mut().zip(counts) {
+            if let Some(count) = count {
+                file.tokens = Some(count);
+            }
+        }
+    }
+
+    /// Enforce `--max-file-tokens`: rendered files over `limit` tokens are
+    /// skipped, or with `truncate` cut by `gather::truncate_to_tokens` and
+    /// marked truncated. Counts are cached as by `count_file_tokens`, so
+    /// building output reuses them.
+    pub fn limit_file_tokens(
+        &mut self,
+        limit: usize,
+        truncate: bool,
+    ) {
+        self.count_file_tokens();
+        let mut kept = Vec::with_capacity(self.file_data.len());
+        for mut file in std::mem::take(&mut self.file_data) {
+            let tokens = file.tokens.unwrap_or(0);
+            if file.stubbed || tokens <= limit {
+                kept.push(file);
+                continue;
+            }
+            let path = self.root.join(&file.path);
+            if truncate {
+                file.contents = gather::truncate_to_tokens(&file.contents, limit, tokens);
+                file.tokens = Some(gather::count_tokens(&file.contents));
+                file.truncated = true;
+                warn!(
+                    event_code = events::FILE_TRUNCATED,
+                    path = %path.display(),
+                    "{} has {tokens} tokens, over --max-file-tokens {limit}; keeping only the first lines",
+                    path.display()
+                );
+                kept.push(file);
+            } else {
+                let message = format!(
+                    "{} has {tokens} tokens, over --max-file-tokens {limit}. Skipping.",
+                    path.display()
+                );
+                warn!(
+                    event_code = events::FILE_SKIPPED_TOO_MANY_TOKENS,
+                    path = %path.display(),
+                    "{message}"
+                );
+                self.skipped.push(gather::SkippedFile {
+                    path,
+                    reason: gather::SkipReason::TooManyTokens,
+                    message,
+                    size: file.size,
+                });
+            }
+        }
+        self.file_data = kept;
+    }
+
+    /// Rendered files whose token count exceeds `limit`, with their counts.
+    /// Counts are cached as by `count_file_tokens`.
+    pub fn files_over_tokens(
+        &mut self,
+        limit: usize,
+    ) -> Vec<(PathBuf, usize)> {
+        self.count_file_tokens();
+        self.file_data
+            .iter()
+            .filter(|f| !f.stubbed)
+            .filter_map(|f| {
+                let tokens = f.tokens.unwrap_or(0);
+                (tokens > limit).then(|| (f.path.clone(), tokens))
+            })
+            .collect()
+    }
+
+    /// Prefix each line of every rendered text file with its line number,
+    /// counting from the start of its line range if it has one. Cached token
+    /// counts are dropped since the contents grew.
+    pub fn number_lines(&mut self) {
+        for file in &mut self.file_data {
+            if file.stubbed || file.kind != FileKind::Text {
+                continue;
+            }
+            let first = file.lines.map_or(1, |range| range.start);
+            file.contents = xml_output::number_lines_from(&file.contents, first);
+            file.tokens = None;
+        }
+    }
+
+    /// Total tokens of the rendered (non-stubbed) file contents, caching
+    /// counts as `count_file_tokens` does.
+    pub fn content_tokens(&mut self) -> usize {
+        self.count_file_tokens();
+        self.file_data
+            .iter()
+            .filter(|f| !f.stubbed)
+            .map(|f| f.tokens.unwrap_or(0))
+            .sum()
+    }
+
+    /// Reorder gathered files by `key`. Ties keep folder-then-path order; for
+    /// `SortKey::Tokens` the counts are stashed so building output reuses them.
+    /// READMEs added by `add_readmes` lead each run of same-folder files.
+    pub fn sort_files(
+        &mut self,
+        key: SortKey,
+        reverse: bool,
+    ) {
+        if key == SortKey::Tokens {
+            self.count_file_tokens();
+        }
+        let root = &self.root;
+        let rank = |file: &FileContents| -> u128 {
+            match key {
+                SortKey::Path => 0,
+                SortKey::Tokens => file.tokens.unwrap_or(0) as u128,
+                SortKey::Size => file.contents.len() as u128,
+                SortKey::Mtime => std::fs::metadata(root.join(&file.path))
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_nanos()),
+            }
+        };
+        if key == SortKey::Path {
+            if reverse {
+                self.file_data.reverse();
+            }
+        } else if reverse {
+            self.file_data
+                .sort_by_cached_key(|f| std::cmp::Reverse(rank(f)));
+        } else {
+            self.file_data.sort_by_cached_key(rank);
+        }
+        for group in self.file_data.chunk_by_mut(|a, b| a.folder == b.folder) {
+            group.sort_by_key(|f| !f.via_readme);
+        }
+    }
+
+    /// Rewrite gathered contents with `rewrite` (returning `None` keeps a file
+    /// as is). Stubbed files are skipped. Each changed file keeps its token
+    /// count from before the first pass, and the tokens this pass removed are
+    /// recorded under `pass`, with the changed files' totals before and after
+    /// it, for the summary and `--report`.
+    /// Returns how many files changed.
+    pub fn transform_contents(
+        &mut self,
+        pass: &'static str,
+        mut rewrite: impl FnMut(&FileContents) -> Option<String>,
+    ) -> usize {
+        let mut totals = (0, 0);
+        let mut changed = 0;
+        for file in self.file_data.iter_mut().filter(|f| !f.stubbed) {
+            let Some(contents) = rewrite(file).filter(|c| *c != file.contents) else {
+                continue;
+            };
+            let before = file
+                .tokens
+                .unwrap_or_else(|| gather::count_tokens(&file.contents));
+            let after = gather::count_tokens(&contents);
+            file.original_tokens.get_or_insert(before);
+            file.contents = contents;
+            file.tokens = Some(after);
+            totals.0 += before;
+            totals.1 += after;
+            changed += 1;
+        }
+        self.transform_savings.push(report::TransformSavings {
+            pass,
+            saved: totals.0.saturating_sub(totals.1),
+            before: totals.0,
+            after: totals.1,
+        });
+        changed
+    }
+
+    /// Strip or replace stray control characters in text files, as a
+    /// `transform_contents` pass; `SanitizeMode::Keep` does nothing.
+    pub fn sanitize_contents(
+        &mut self,
+        mode: SanitizeMode,
+    ) {
+        let (pass, rewrite): (&'static str, fn(&str) -> Option<String>) = match mode {
+            SanitizeMode::Keep => return,
+            SanitizeMode::Strip => ("control-character stripping", sanitize::strip),
+            SanitizeMode::Replace => ("control-character replacement", sanitize::replace),
+        };
+        self.sanitized_count += self.transform_contents(pass, |file| {
+            (file.kind == FileKind::Text)
+                .then(|| rewrite(&file.contents))
+                .flatten()
+        });
+    }
+
+    /// Replace secrets matching `rules` in every text file (`--redact`), as
+    /// a `transform_contents` pass. Call before `apply_stubs` and the output
+    /// modes, so nothing rendered, copied, or served still holds them.
+    pub fn redact_secrets(
+        &mut self,
+        rules: &[redact::Rule],
+    ) {
+        let mut redactions = Vec::new();
+        self.transform_contents("redaction", |file| {
+            if file.kind != FileKind::Text {
+                return None;
+            }
+            let (contents, counts) = redact::redact(&file.contents, rules)?;
+            redactions.push((file.path.clone(), counts));
+            Some(contents)
+        });
+        self.redactions = redactions;
+    }
+
+    /// Files changed by `redact_secrets`, with how many matches each rule
+    /// replaced.
+    pub fn redactions(&self) -> &[(PathBuf, Vec<(String, usize)>)] {
+        &self.redactions
+    }
+
+    /// Strip comments from text files in a known language
+    /// (`--strip-comments`), as a `transform_contents` pass.
+    pub fn strip_comments(&mut self) {
+        self.transform_contents("comment stripping", |file| {
+            (file.kind == FileKind::Text)
+                .then(|| transform::strip_comments(&file.path, &file.contents))
+                .flatten()
+        });
+    }
+
+    /// Squeeze whitespace in text files (`--squeeze`), as a
+    /// `transform_contents` pass; Markdown and YAML are left alone.
+    pub fn squeeze_contents(&mut self) {
+        self.transform_contents("whitespace squeezing", |file| {
+            (file.kind == FileKind::Text && transform::squeezable(&file.path))
+                .then(|| transform::squeeze(&file.contents))
+                .flatten()
+        });
+    }
+
+    /// Keep only the first `max` gathered files; call after `sort_files` so
+    /// the cap follows the active sort order. Returns how many were dropped.
+    pub fn truncate_files(
+        &mut self,
+        max: usize,
+    ) -> usize {
+        let dropped = self.file_data.len().saturating_sub(max);
+        self.file_data.truncate(max);
+        self.truncated_count += dropped;
+        dropped
+    }
+
+    /// Rewrite each file's path (and folder) in `style` for rendering. Call
+    /// last, after every step that resolves file paths against the root.
+    /// Files outside the base directory of `PathStyle::Relative` stay absolute.
+    pub fn apply_path_style(
+        &mut self,
+        style: PathStyle,
+    ) {
+        #[cfg(feature = "git")]
+        let base = git::toplevel(&self.root).unwrap_or_else(|| self.root.clone());
+        #[cfg(not(feature = "git"))]
+        let base = self.root.clone();
+        for file in &mut self.file_data {
+            let abs = self.root.join(&file.path);
+            let rel = abs.strip_prefix(&base).unwrap_or(&abs).to_path_buf();
+            let path = match style {
+                PathStyle::Relative => rel.clone(),
+                PathStyle::Absolute => abs.clone(),
+                PathStyle::FileName => abs.file_name().map(PathBuf::from).unwrap_or(rel.clone()),
+            };
+            file.folder = match style {
+                PathStyle::Absolute => abs.parent(),
+                PathStyle::Relative | PathStyle::FileName => rel.parent(),
+            }
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+            file.path = path;
+        }
+    }
+
     pub fn warn_raw_structure_risks(
         &self,
         escape_xml: bool,
@@ -201,6 +1466,7 @@ impl Pipeline {
         for file in &self.file_data {
             if xml_output::raw_content_may_break_structure(&file.contents) {
                 warn!(
+                    event_code = events::RAW_CONTENT_MARKERS,
                     "raw contents in {} contain context wrapper markers; output is XML-like, not parseable XML; use --escape-xml for escaped content",
                     file.path.to_slash_lossy()
                 );
@@ -208,182 +1474,498 @@ impl Pipeline {
         }
     }
 
-    /// Build the full XML output (folder-grouped) for non-chunked mode.
-    pub fn build_xml(
+    /// Compute per-file metadata (tokens, parts) without rendering any output.
+    pub fn plan_files(
         &mut self,
+        chunk_limit: usize,
         escape_xml: bool,
+        cdata: bool,
+    ) {
+        self.file_meta = if chunk_limit > 0 {
+            chunker::plan_chunk_bodies(
+                &self.file_data,
+                chunk_limit,
+                escape_xml,
+                cdata,
+                self.chunking,
+            )
+            .1
+        } else {
+            chunker::build_file_meta(&self.file_data, escape_xml)
+        };
+    }
+
+    /// Like `plan_files`, for `--format plain` blocks.
+    pub fn plan_plain_files(
+        &mut self,
+        chunk_limit: usize,
+        separator: &str,
+    ) {
+        self.file_meta = if chunk_limit > 0 {
+            chunker::plan_plain_chunk_bodies(&self.file_data, chunk_limit, separator, self.chunking)
+                .1
+        } else {
+            chunker::build_file_meta(&self.file_data, false)
+        };
+    }
+
+    /// Build the full XML output (folder-grouped) for non-chunked mode,
+    /// rendered as `style` says.
+    pub fn build_xml(
+        &mut self,
+        style: &xml_output::XmlStyle,
     ) -> Result<()> {
-        self.xml_output = Some(xml_output::build_xml_with_escape(
+        self.file_meta = chunker::build_file_meta(&self.file_data, style.escape_xml);
+        self.output = Some(xml_output::build_xml_with_meta(
             &self.file_data,
-            escape_xml,
+            &self.file_meta,
+            &self.listed_skips(),
+            style,
         )?);
         Ok(())
     }
 
-    /// Build chunked output with header (for chunked/multi-step modes).
-    pub fn build_chunks_with_header(
+    /// Render the output through a user template. The result is one
+    /// document; with a `chunk_limit` the template sees the chunk plan as
+    /// `chunks` and the document is also the only rendered chunk.
+    pub fn build_template(
         &mut self,
+        name: &str,
+        source: &str,
         chunk_limit: usize,
-        escape_xml: bool,
-        multi_step: bool,
         include_git: bool,
     ) -> Result<()> {
-        if multi_step {
-            let metas = chunker::build_file_meta(&self.file_data, escape_xml);
-            let header_xml = format!(
-                "<shared-context>\n{}\n",
-                header::make_header(1, chunk_limit, &metas, multi_step, escape_xml, include_git,)
-            );
-            let header_tokens = gather::count_tokens(&header_xml);
+        let (bodies, metas) = if chunk_limit > 0 {
+            chunker::build_chunk_bodies(&self.file_data, chunk_limit, false, false, self.chunking)
+        } else {
+            (Vec::new(), chunker::build_file_meta(&self.file_data, false))
+        };
+        let git = if include_git {
+            template::git_context()
+        } else {
+            None
+        };
+        let text = template::render(
+            name,
+            source,
+            &TemplateInput {
+                files: &self.file_data,
+                metas: &metas,
+                chunks: &bodies,
+                git,
+            },
+        )?;
+        if chunk_limit > 0 {
             self.rendered_chunks = vec![RenderedChunk {
-                tokens: header_tokens,
-                xml: header_xml,
+                tokens: tokenizer::measure(&text, self.chunking.unit),
+                text: text.clone(),
             }];
+        }
+        self.output = Some(text);
+        self.file_meta = metas;
+        Ok(())
+    }
+
+    /// Build the `--format plain` output for non-chunked mode.
+    pub fn build_plain(
+        &mut self,
+        separator: &str,
+    ) {
+        self.file_meta = chunker::build_file_meta(&self.file_data, false);
+        self.output = Some(plain::build_plain(&self.file_data, separator));
+    }
+
+    /// Build `--format plain` chunks: bodies only, with no header chunk.
+    pub fn build_plain_chunks(
+        &mut self,
+        chunk_limit: usize,
+        separator: &str,
+    ) {
+        let (bodies, mut metas) = chunker::build_plain_chunk_bodies(
+            &self.file_data,
+            chunk_limit,
+            separator,
+            self.chunking,
+        );
+        chunker::assign_chunks(&mut metas, &bodies, 0);
+        self.rendered_chunks = bodies
+            .into_iter()
+            .map(|body| {
+                let text: String = body.blocks.into_iter().map(|b| b.xml).collect();
+                RenderedChunk {
+                    tokens: tokenizer::measure(&text, self.chunking.unit),
+                    text,
+                }
+            })
+            .collect();
+        self.file_meta = metas;
+    }
+
+    /// Build the full JSON output for non-chunked mode.
+    pub fn build_json(&mut self) -> Result<()> {
+        self.file_meta = chunker::build_file_meta(&self.file_data, false);
+        self.output = Some(json::build_json_with_meta(
+            &self.file_data,
+            &self.file_meta,
+        )?);
+        Ok(())
+    }
+
+    /// Build one JSON object per chunk, reusing the XML chunk plan for boundaries.
+    pub fn build_json_chunks(
+        &mut self,
+        chunk_limit: usize,
+    ) -> Result<()> {
+        let (bodies, mut metas) =
+            chunker::build_chunk_bodies(&self.file_data, chunk_limit, false, false, self.chunking);
+        chunker::assign_chunks(&mut metas, &bodies, 0);
+        self.rendered_chunks = json::build_json_chunks(&self.file_data, &bodies, &metas)?
+            .into_iter()
+            .map(|text| RenderedChunk {
+                tokens: tokenizer::measure(&text, self.chunking.unit),
+                text,
+            })
+            .collect();
+        self.file_meta = metas;
+        Ok(())
+    }
+
+    /// Build chunked output with header (for chunked/multi-step modes).
+    /// `spec.chunk_size` less `spec.headroom` is the chunk limit; `files` and
+    /// `total_chunks` are filled in from the chunk plan. `legacy_snippets`
+    /// drops the `remaining-tokens` hint from the `<more>` marker.
+    pub fn build_chunks_with_header(
+        &mut self,
+        spec: HeaderSpec<'_>,
+        legacy_snippets: bool,
+    ) -> Result<()> {
+        let listed = self.listed_skips();
+        let spec = HeaderSpec {
+            skipped: &listed,
+            ..spec
+        };
+        let chunk_limit = chunker::chunk_budget(spec.chunk_size, spec.headroom);
+        let escape_xml = spec.escape_xml;
+        let header_last = spec.header_last;
+        if spec.multi_step {
+            let metas = chunker::build_file_meta(&self.file_data, escape_xml);
+            let header_chunks = header::make_multi_step_header_chunks(&HeaderSpec {
+                files: &metas,
+                ..spec
+            });
+            self.rendered_chunks = header_chunks
+                .into_iter()
+                .map(|text| RenderedChunk {
+                    tokens: tokenizer::measure(&text, self.chunking.unit),
+                    text,
+                })
+                .collect();
+            if chunk_limit > 0 && self.rendered_chunks.iter().any(|c| c.tokens > chunk_limit) {
+                warn!(
+                    event_code = events::HEADER_OVERSIZE,
+                    "a multi-step header chunk exceeds chunk size {}; increase --chunk-size or disable git info",
+                    chunk_limit
+                );
+            }
+            self.file_meta = metas;
             return Ok(());
         }
 
-        let mut effective_limit = chunk_limit;
-        for attempt in 0..8 {
-            let (mut bodies, metas) =
-                chunker::build_chunk_bodies(&self.file_data, effective_limit, escape_xml);
-            let max_blocks: usize = bodies.iter().map(|b| b.blocks.len()).sum();
-            let mut splits = 0usize;
-            let mut header_oversize = false;
-            loop {
-                let total_chunks = bodies.len() + 1;
-                let header_xml = format!(
-                    "<shared-context>\n{}\n",
-                    header::make_header(
-                        total_chunks,
-                        chunk_limit,
-                        &metas,
-                        multi_step,
-                        escape_xml,
-                        include_git,
-                    )
+        let unit = self.chunking.unit;
+        // Tokens may merge differently where the envelope meets the blocks
+        // than when each is measured alone
+        let seam = if unit == ChunkUnit::Tokens { 2 } else { 0 };
+        // Budget blocks against the limit less the widest envelope a body
+        // snippet gets, with chunk numbers `digits` wide. A plan with more
+        // chunks than that allows is planned again with wider numbers
+        let mut digits = (self.file_data.len() + 1).to_string().len().max(3);
+        let (mut bodies, mut metas) = loop {
+            let budget = if chunk_limit > 0 {
+                let envelope = output::body_snippet_envelope(
+                    digits,
+                    digits + chunk_limit.to_string().len(),
+                    (!legacy_snippets).then_some(unit),
+                    header_last,
                 );
-                let wrapper_floor = if chunk_limit > 0 {
-                    let last_id = total_chunks.saturating_sub(1);
-                    let wrapper = format!(
-                        "<context-chunk id=\"{}/{}\">\n</context-chunk>\n</shared-context>\n",
-                        last_id, total_chunks
-                    );
-                    let wrapper_tokens = gather::count_tokens(&wrapper);
-                    chunk_limit.saturating_sub(wrapper_tokens.saturating_add(2))
-                } else {
-                    0
-                };
-                let body_xmls: Vec<String> = bodies
-                    .iter()
-                    .map(|body| body.blocks.iter().map(|b| b.xml.as_str()).collect())
-                    .collect();
-                let mut snippet_tokens = Vec::with_capacity(total_chunks);
-                let mut snippet_xmls = Vec::with_capacity(total_chunks);
-                let mut split_body_idx = None;
-                let mut oversize_single = Vec::new();
-                let mut required_limit: Option<usize> = None;
-                let mut max_over_limit = 0usize;
-                let mut has_unavoidable = false;
-                for idx in 0..total_chunks {
-                    let snippet = output::render_chunk_snippet(&header_xml, &body_xmls, idx);
-                    let tokens = gather::count_tokens(&snippet);
-                    snippet_tokens.push(tokens);
-                    snippet_xmls.push(snippet);
-                    if chunk_limit > 0 && tokens > chunk_limit {
-                        if idx == 0 {
-                            header_oversize = true;
-                        } else {
-                            let body_idx = idx - 1;
-                            if bodies[body_idx].blocks.len() > 1 {
-                                split_body_idx = Some(body_idx);
-                                break;
-                            } else {
-                                oversize_single.push(idx);
-                                let block_tokens = bodies[body_idx].blocks[0].tokens;
-                                if block_tokens > chunk_limit {
-                                    has_unavoidable = true;
-                                    continue;
-                                }
-                                let overhead = tokens.saturating_sub(block_tokens);
-                                let limit = chunk_limit.saturating_sub(overhead).max(wrapper_floor);
-                                required_limit = Some(match required_limit {
-                                    Some(prev) => prev.min(limit),
-                                    None => limit,
-                                });
-                            }
-                            max_over_limit = max_over_limit.max(tokens.saturating_sub(chunk_limit));
-                        }
-                    }
-                }
+                chunk_limit
+                    .saturating_sub(tokenizer::measure(&envelope, unit) + seam)
+                    .max(1)
+            } else {
+                0
+            };
+            let (bodies, metas) = chunker::build_chunk_bodies(
+                &self.file_data,
+                budget,
+                escape_xml,
+                spec.cdata,
+                self.chunking,
+            );
+            let needed = (bodies.len() + 1).to_string().len();
+            if chunk_limit == 0 || needed <= digits {
+                break (bodies, metas);
+            }
+            digits = needed;
+        };
 
-                if let Some(body_idx) = split_body_idx {
-                    let last_block = bodies[body_idx]
-                        .blocks
-                        .pop()
-                        .expect("chunk should contain at least one block");
-                    bodies[body_idx].tokens =
-                        bodies[body_idx].tokens.saturating_sub(last_block.tokens);
-                    let last_tokens = last_block.tokens;
-                    bodies.insert(
-                        body_idx + 1,
-                        chunker::ChunkBody {
-                            blocks: vec![last_block],
-                            tokens: last_tokens,
-                        },
+        // Render and measure every snippet. Should one still come out over
+        // the limit, its chunk gives its last block to a new chunk after it
+        // and the snippets are measured again; a chunk of one block is left
+        // as it is. Most snippets need less than the widest envelope, so
+        // once everything fits, neighbours that fit in one snippet are
+        // merged (a single time, so merging and splitting cannot alternate)
+        let mut merged = false;
+        let (body_xmls, emitted, remaining_after, mut snippet_tokens, mut snippet_xmls) = loop {
+            let total_chunks = bodies.len() + 1;
+            chunker::assign_chunks(&mut metas, &bodies, usize::from(!header_last));
+            let header_xml = header::make_header(&HeaderSpec {
+                total_chunks,
+                files: &metas,
+                ..spec
+            });
+            let body_xmls: Vec<String> = bodies
+                .iter()
+                .map(|body| body.blocks.iter().map(|b| b.xml.as_str()).collect())
+                .collect();
+            // Tokens per chunk in emission order; the header only counts
+            // when it follows the bodies
+            let body_tokens = bodies.iter().map(|b| b.tokens);
+            let emitted: Vec<usize> = if header_last {
+                body_tokens
+                    .chain([tokenizer::measure(&header_xml, unit)])
+                    .collect()
+            } else {
+                std::iter::once(0).chain(body_tokens).collect()
+            };
+            // remaining_after[idx] = tokens of every chunk emitted after idx
+            let mut remaining_after = vec![0usize; total_chunks];
+            for idx in (0..total_chunks - 1).rev() {
+                remaining_after[idx] = remaining_after[idx + 1] + emitted[idx + 1];
+            }
+            let snippets: Vec<(usize, String)> = remaining_after
+                .iter()
+                .enumerate()
+                .map(|(idx, &remaining)| {
+                    let snippet = output::render_chunk_snippet(
+                        &header_xml,
+                        &body_xmls,
+                        idx,
+                        (!legacy_snippets).then_some((remaining, unit)),
+                        header_last,
                     );
-                    splits += 1;
-                    if splits > max_blocks {
-                        return Err(anyhow!("chunk splitting did not converge"));
-                    }
+                    (tokenizer::measure(&snippet, unit), snippet)
+                })
+                .collect();
+            let over = (0..bodies.len()).find(|&body_idx| {
+                let idx = if header_last { body_idx } else { body_idx + 1 };
+                chunk_limit > 0
+                    && snippets[idx].0 > chunk_limit
+                    && bodies[body_idx].blocks.len() > 1
+            });
+            if let Some(body_idx) = over {
+                let last_block = bodies[body_idx]
+                    .blocks
+                    .pop()
+                    .expect("chunk should contain at least one block");
+                bodies[body_idx].tokens = bodies[body_idx].tokens.saturating_sub(last_block.tokens);
+                let last_tokens = last_block.tokens;
+                bodies.insert(
+                    body_idx + 1,
+                    chunker::ChunkBody {
+                        blocks: vec![last_block],
+                        tokens: last_tokens,
+                    },
+                );
+                continue;
+            }
+            if !merged && chunk_limit > 0 && bodies.len() > 1 {
+                merged = true;
+                let position = |body_idx: usize| if header_last { body_idx } else { body_idx + 1 };
+                let sizes: Vec<usize> =
+                    (0..bodies.len()).map(|b| snippets[position(b)].0).collect();
+                let envelopes: Vec<usize> = (0..bodies.len())
+                    .map(|b| {
+                        let tag = output::chunk_open_tag(position(b), total_chunks);
+                        tokenizer::measure(&format!("{tag}\n</context-chunk>\n"), unit)
+                    })
+                    .collect();
+                let before = bodies.len();
+                bodies = merge_adjacent_bodies(
+                    bodies,
+                    &sizes,
+                    &envelopes,
+                    chunk_limit.saturating_sub(seam),
+                );
+                if bodies.len() < before {
                     continue;
                 }
+            }
+            let (tokens, xmls): (Vec<usize>, Vec<String>) = snippets.into_iter().unzip();
+            break (body_xmls, emitted, remaining_after, tokens, xmls);
+        };
+        let header_idx = if header_last { bodies.len() } else { 0 };
+        let total_chunks = bodies.len() + 1;
 
-                if let Some(limit) = required_limit
-                    && limit > 0
-                    && limit < effective_limit
-                {
-                    let adjusted = limit.saturating_sub(2).max(1);
-                    if adjusted < effective_limit {
-                        effective_limit = adjusted;
-                        break;
-                    }
-                    effective_limit = limit;
-                    break;
-                }
-                if max_over_limit > 0
-                    && !has_unavoidable
-                    && effective_limit > wrapper_floor.saturating_add(1)
-                {
-                    effective_limit = effective_limit.saturating_sub(1).max(wrapper_floor);
-                    break;
-                }
+        // Declare the payload size in the header. Writing the numbers in
+        // changes the header's own size, so re-render it (and, when it comes
+        // last, the first chunk's remaining-tokens hint) until the counts
+        // settle
+        let planned_header_tokens = emitted[header_idx];
+        let rerendered: &[usize] = if header_last { &[header_idx, 0] } else { &[0] };
+        for _ in 0..4 {
+            let header_xml = header::make_header(&HeaderSpec {
+                total_chunks,
+                files: &metas,
+                chunk_tokens: Some(&snippet_tokens),
+                ..spec
+            });
+            let header_tokens = tokenizer::measure(&header_xml, unit);
+            let mut settled = true;
+            for &idx in rerendered {
+                let remaining = if header_last && idx != header_idx {
+                    remaining_after[idx] - planned_header_tokens + header_tokens
+                } else {
+                    remaining_after[idx]
+                };
+                let snippet = output::render_chunk_snippet(
+                    &header_xml,
+                    &body_xmls,
+                    idx,
+                    (!legacy_snippets).then_some((remaining, unit)),
+                    header_last,
+                );
+                let tokens = tokenizer::measure(&snippet, unit);
+                settled &= tokens == snippet_tokens[idx];
+                snippet_tokens[idx] = tokens;
+                snippet_xmls[idx] = snippet;
+            }
+            if settled {
+                break;
+            }
+        }
+        if chunk_limit > 0 && snippet_tokens[header_idx] > chunk_limit {
+            warn!(
+                event_code = events::HEADER_OVERSIZE,
+                "header exceeds chunk size {}; increase --chunk-size or disable git info",
+                chunk_limit
+            );
+        }
+        if chunk_limit > 0
+            && snippet_tokens
+                .iter()
+                .enumerate()
+                .any(|(idx, &tokens)| idx != header_idx && tokens > chunk_limit)
+        {
+            warn!(
+                event_code = events::CHUNK_OVERSIZE,
+                "one or more chunks exceed the chunk size {} due to oversize file parts",
+                chunk_limit
+            );
+        }
 
-                if header_oversize {
-                    warn!(
-                        "header exceeds chunk size {}; increase --chunk-size or disable git info",
-                        chunk_limit
-                    );
-                }
-                if !oversize_single.is_empty() {
-                    warn!(
-                        "one or more chunks exceed the chunk size {} due to oversize file parts",
-                        chunk_limit
-                    );
-                }
+        self.rendered_chunks = snippet_xmls
+            .into_iter()
+            .zip(snippet_tokens)
+            .map(|(text, tokens)| RenderedChunk { text, tokens })
+            .collect();
+        self.file_meta = metas;
+        Ok(())
+    }
+}
 
-                self.rendered_chunks = snippet_xmls
-                    .into_iter()
-                    .zip(snippet_tokens)
-                    .map(|(xml, tokens)| RenderedChunk { xml, tokens })
-                    .collect();
-                return Ok(());
+/// Merge runs of adjacent chunk bodies whose snippets fit in `limit`
+/// together. `sizes[i]` is the measured snippet of body `i` and
+/// `envelopes[i]` the part of it that is its own `<context-chunk>` wrapper,
+/// which a body merged into the one before it no longer needs. Blocks keep
+/// their order.
+fn merge_adjacent_bodies(
+    bodies: Vec<chunker::ChunkBody>,
+    sizes: &[usize],
+    envelopes: &[usize],
+    limit: usize,
+) -> Vec<chunker::ChunkBody> {
+    let mut merged: Vec<chunker::ChunkBody> = Vec::with_capacity(bodies.len());
+    let mut merged_size = 0;
+    for (idx, body) in bodies.into_iter().enumerate() {
+        let joined = merged_size + sizes[idx].saturating_sub(envelopes[idx]);
+        match merged.last_mut() {
+            Some(last) if joined <= limit => {
+                last.tokens += body.tokens;
+                last.blocks.extend(body.blocks);
+                merged_size = joined;
             }
-            if attempt == 7 {
-                return Err(anyhow!("chunk splitting did not converge"));
+            _ => {
+                merged.push(body);
+                merged_size = sizes[idx];
             }
         }
-        Err(anyhow!("chunk splitting did not converge"))
     }
+    merged
+}
+
+/// Whether `path`, last modified at `mtime`, was modified within
+/// `--modified-since` and `--modified-before`. Files whose mtime cannot be
+/// read are kept.
+fn modified_within(
+    path: &Path,
+    mtime: std::io::Result<std::time::SystemTime>,
+    walk: &gather::WalkOptions,
+) -> bool {
+    let mtime = match mtime {
+        Ok(mtime) => mtime,
+        Err(err) => {
+            warn!(
+                event_code = events::MTIME_UNAVAILABLE,
+                path = %path.display(),
+                "cannot read the modification time of {}; keeping it: {err}",
+                path.display()
+            );
+            return true;
+        }
+    };
+    walk.modified_since.is_none_or(|since| mtime >= since)
+        && walk.modified_before.is_none_or(|before| mtime < before)
+}
+
+/// Files `git ls-files` lists under `dir`, kept to those the walk would
+/// accept by depth and hiddenness; ignore files are git's business here.
+/// `None` when `dir` is outside a work tree, or without the `git` feature.
+fn git_listed_files(
+    dir: &Path,
+    walk: &gather::WalkOptions,
+) -> Option<Vec<PathBuf>> {
+    #[cfg(feature = "git")]
+    {
+        let listed = git::ls_files(dir, walk.git_others)?;
+        Some(
+            listed
+                .into_iter()
+                .filter(|path| {
+                    walk_visible(path.strip_prefix(dir).unwrap_or(path), walk) && path.is_file()
+                })
+                .collect(),
+        )
+    }
+    #[cfg(not(feature = "git"))]
+    {
+        let _ = (dir, walk);
+        None
+    }
+}
+
+/// Whether the walk would reach `rel`, a path below one of its roots, by
+/// `--max-depth` and `--hidden`.
+#[cfg_attr(not(any(feature = "git", feature = "archive")), allow(dead_code))]
+fn walk_visible(
+    rel: &Path,
+    walk: &gather::WalkOptions,
+) -> bool {
+    let depth_ok = walk
+        .max_depth
+        .is_none_or(|max| rel.components().count() <= max);
+    let hidden = rel
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+    depth_ok && (walk.hidden || !hidden)
 }
 
 // Helper: check if `candidate` is "under" any user-specified path (including exact matches).
@@ -0,0 +1,465 @@
+use crate::chunker::FileMeta;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Label used for files without an extension.
+pub const NO_EXTENSION: &str = "(none)";
+
+/// Token share of one file extension in the final bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionShare {
+    pub ext: String,
+    pub files: usize,
+    pub tokens: usize,
+}
+
+/// Group file token counts by lowercase extension, largest share first.
+/// Ties are broken by extension name so the ordering is deterministic.
+/// Stubbed files contribute no tokens to the bundle and are left out.
+pub fn extension_composition(metas: &[FileMeta]) -> Vec<ExtensionShare> {
+    let mut by_ext: HashMap<String, ExtensionShare> = HashMap::new();
+    for meta in metas.iter().filter(|m| !m.stubbed) {
+        let ext = meta
+            .path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| NO_EXTENSION.to_string());
+        let entry = by_ext.entry(ext.clone()).or_insert(ExtensionShare {
+            ext,
+            files: 0,
+            tokens: 0,
+        });
+        entry.files += 1;
+        entry.tokens += meta.tokens;
+    }
+    let mut shares: Vec<ExtensionShare> = by_ext.into_values().collect();
+    shares.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.ext.cmp(&b.ext)));
+    shares
+}
+
+fn percent(
+    part: usize,
+    total: usize,
+) -> usize {
+    if total == 0 {
+        0
+    } else {
+        ((part as f64 / total as f64) * 100.0).round() as usize
+    }
+}
+
+/// One-line breakdown such as `rs 62% • md 21% • toml 9% • other 8%`.
+/// Shows the `top` largest extensions and folds the rest into "other".
+/// Returns `None` when there are no tokens to break down.
+pub fn composition_line(
+    shares: &[ExtensionShare],
+    top: usize,
+) -> Option<String> {
+    let total: usize = shares.iter().map(|s| s.tokens).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut parts: Vec<String> = shares
+        .iter()
+        .take(top)
+        .map(|s| format!("{} {}%", s.ext, percent(s.tokens, total)))
+        .collect();
+    if shares.len() > top {
+        let other: usize = shares[top..].iter().map(|s| s.tokens).sum();
+        parts.push(format!("other {}%", percent(other, total)));
+    }
+    Some(parts.join(" • "))
+}
+
+/// Full per-extension table for `--report`.
+pub fn render_composition_table(shares: &[ExtensionShare]) -> String {
+    let total: usize = shares.iter().map(|s| s.tokens).sum();
+    let width = shares
+        .iter()
+        .map(|s| s.ext.len())
+        .max()
+        .unwrap_or(0)
+        .max("extension".len());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>6}  {:>10}  {:>5}",
+        "extension", "files", "tokens", "share"
+    );
+    for s in shares {
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>6}  {:>10}  {:>4}%",
+            s.ext,
+            s.files,
+            s.tokens,
+            percent(s.tokens, total)
+        );
+    }
+    out
+}
+
+/// Tokens removed by one content transformation pass across all files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransformSavings {
+    /// Human-readable pass name, e.g. "comment stripping".
+    pub pass: &'static str,
+    pub saved: usize,
+    /// Tokens of the files the pass changed, before and after it.
+    pub before: usize,
+    pub after: usize,
+}
+
+/// Format `n` with comma thousands separators.
+fn with_commas(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Bundle tokens before any transformation; stubbed files are left out.
+pub fn original_total(metas: &[FileMeta]) -> usize {
+    metas
+        .iter()
+        .filter(|m| !m.stubbed)
+        .map(|m| m.original_tokens.unwrap_or(m.tokens))
+        .sum()
+}
+
+/// One line per pass, e.g. `comment stripping saved 18,220 tokens (40,000 →
+/// 21,780), 9.4%`, with the share taken of the untransformed bundle.
+pub fn savings_lines(
+    savings: &[TransformSavings],
+    original_total: usize,
+) -> Vec<String> {
+    savings
+        .iter()
+        .filter(|s| s.saved > 0)
+        .map(|s| {
+            let share = if original_total == 0 {
+                0.0
+            } else {
+                s.saved as f64 / original_total as f64 * 100.0
+            };
+            format!(
+                "{} saved {} tokens ({} → {}), {share:.1}%",
+                s.pass,
+                with_commas(s.saved),
+                with_commas(s.before),
+                with_commas(s.after)
+            )
+        })
+        .collect()
+}
+
+/// Original and final tokens for each transformed file, for `--report`.
+/// Returns `None` when no file was transformed.
+pub fn render_token_deltas(metas: &[FileMeta]) -> Option<String> {
+    let rows: Vec<(String, usize, usize)> = metas
+        .iter()
+        .filter(|m| !m.stubbed)
+        .filter_map(|m| {
+            let original = m.original_tokens?;
+            Some((m.path.display().to_string(), original, m.tokens))
+        })
+        .collect();
+    if rows.is_empty() {
+        return None;
+    }
+    let width = rows
+        .iter()
+        .map(|(path, ..)| path.len())
+        .max()
+        .unwrap_or(0)
+        .max("transformed file".len());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>10}    {:>10}  {:>7}",
+        "transformed file", "original", "final", "saved"
+    );
+    for (path, original, tokens) in rows {
+        let _ = writeln!(
+            out,
+            "{path:<width$}  {original:>10} → {tokens:>10}  {:>6}%",
+            percent(original.saturating_sub(tokens), original)
+        );
+    }
+    Some(out)
+}
+
+/// How the candidate list was narrowed down before output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GatherStats {
+    /// Files found by path expansion and directory walks (after ignore rules).
+    pub discovered: usize,
+    /// Candidates removed by `--exclude-paths` or `--only`.
+    pub excluded: usize,
+    pub too_large: usize,
+    /// Files over `--max-file-tokens`.
+    pub too_many_tokens: usize,
+    pub binary: usize,
+    pub unreadable: usize,
+    /// Files left out by `--skip-empty`.
+    pub empty: usize,
+    /// Files left out by `--skip-generated`.
+    pub generated: usize,
+}
+
+/// How many expanded paths `empty_gather_diagnostic` lists before eliding.
+const LISTED_PATHS: usize = 10;
+
+/// Explain why nothing was gathered: the expanded `paths` searched, the
+/// exclude and `--only` `filters` in force, and the likely causes that apply.
+pub fn empty_gather_diagnostic(
+    stats: &GatherStats,
+    cwd: &std::path::Path,
+    paths: &[String],
+    filters: &[String],
+) -> String {
+    let mut out = format!(
+        "no gatherable files found (searched from {})\n",
+        cwd.display()
+    );
+    if !paths.is_empty() {
+        let shown = paths[..paths.len().min(LISTED_PATHS)].join(", ");
+        match paths
+            .len()
+            .checked_sub(LISTED_PATHS)
+            .filter(|&more| more > 0)
+        {
+            Some(more) => {
+                let _ = writeln!(out, "  paths: {shown}, and {more} more");
+            }
+            None => {
+                let _ = writeln!(out, "  paths: {shown}");
+            }
+        }
+    }
+    if !filters.is_empty() {
+        let _ = writeln!(out, "  filters: {}", filters.join(" "));
+    }
+    if stats.discovered == 0 {
+        out.push_str(
+            "  - no files were discovered: the paths may be wrong, or everything is \
+             hidden or matched by .gitignore or .cgignore\n",
+        );
+    }
+    if stats.excluded > 0 {
+        let _ = writeln!(
+            out,
+            "  - {} file(s) removed by --exclude-paths/--only; the patterns may be too broad",
+            stats.excluded
+        );
+    }
+    if stats.binary > 0 {
+        let _ = writeln!(out, "  - {} file(s) skipped as binary", stats.binary);
+    }
+    if stats.too_large > 0 {
+        let _ = writeln!(
+            out,
+            "  - {} file(s) skipped as larger than --max-size",
+            stats.too_large
+        );
+    }
+    if stats.too_many_tokens > 0 {
+        let _ = writeln!(
+            out,
+            "  - {} file(s) skipped as over --max-file-tokens",
+            stats.too_many_tokens
+        );
+    }
+    if stats.unreadable > 0 {
+        let _ = writeln!(
+            out,
+            "  - {} path(s) could not be read (missing or no permission)",
+            stats.unreadable
+        );
+    }
+    if stats.empty > 0 {
+        let _ = writeln!(
+            out,
+            "  - {} empty file(s) left out by --skip-empty",
+            stats.empty
+        );
+    }
+    if stats.generated > 0 {
+        let _ = writeln!(
+            out,
+            "  - {} generated file(s) left out by --skip-generated",
+            stats.generated
+        );
+    }
+    out.push_str("pass --allow-empty to emit an empty context anyway");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn meta(
+        path: &str,
+        tokens: usize,
+    ) -> FileMeta {
+        FileMeta {
+            id: 0,
+            path: PathBuf::from(path),
+            tokens,
+            parts: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn groups_by_extension_and_handles_missing_extension() {
+        let metas = vec![
+            meta("src/a.rs", 60),
+            meta("src/B.RS", 2),
+            meta("Makefile", 10),
+            meta(".gitignore", 3),
+            meta("README.md", 25),
+        ];
+        let shares = extension_composition(&metas);
+        assert_eq!(shares[0].ext, "rs");
+        assert_eq!(shares[0].files, 2);
+        assert_eq!(shares[0].tokens, 62);
+        let none = shares.iter().find(|s| s.ext == NO_EXTENSION).unwrap();
+        assert_eq!(none.files, 2);
+        assert_eq!(none.tokens, 13);
+    }
+
+    #[test]
+    fn ties_are_ordered_by_name() {
+        let metas = vec![meta("b.toml", 10), meta("a.md", 10), meta("c.rs", 10)];
+        let shares = extension_composition(&metas);
+        let order: Vec<&str> = shares.iter().map(|s| s.ext.as_str()).collect();
+        assert_eq!(order, ["md", "rs", "toml"]);
+    }
+
+    #[test]
+    fn line_folds_tail_into_other() {
+        let metas = vec![
+            meta("a.rs", 62),
+            meta("a.md", 21),
+            meta("a.toml", 9),
+            meta("a.yml", 4),
+            meta("a.json", 3),
+            meta("a.txt", 1),
+        ];
+        let line = composition_line(&extension_composition(&metas), 4).unwrap();
+        assert_eq!(line, "rs 62% • md 21% • toml 9% • yml 4% • other 4%");
+    }
+
+    #[test]
+    fn line_is_none_without_tokens() {
+        assert_eq!(composition_line(&[], 4), None);
+        let shares = extension_composition(&[meta("empty.rs", 0)]);
+        assert_eq!(composition_line(&shares, 4), None);
+    }
+
+    #[test]
+    fn empty_diagnostic_lists_only_applicable_causes() {
+        let stats = GatherStats {
+            discovered: 3,
+            excluded: 1,
+            binary: 2,
+            ..Default::default()
+        };
+        let msg = empty_gather_diagnostic(&stats, std::path::Path::new("/work"), &[], &[]);
+        assert!(msg.contains("/work"));
+        assert!(!msg.contains("paths:") && !msg.contains("filters:"));
+        assert!(msg.contains("1 file(s) removed by --exclude-paths/--only"));
+        assert!(msg.contains("2 file(s) skipped as binary"));
+        assert!(!msg.contains("no files were discovered"));
+        assert!(!msg.contains("--max-size"));
+        assert!(msg.ends_with("--allow-empty to emit an empty context anyway"));
+    }
+
+    #[test]
+    fn empty_diagnostic_lists_paths_and_filters() {
+        let paths: Vec<String> = (0..12).map(|i| format!("src/m{i}.rs")).collect();
+        let filters = [
+            "--exclude-paths *.rs".to_string(),
+            "--only docs/**".to_string(),
+        ];
+        let stats = GatherStats {
+            discovered: 12,
+            excluded: 12,
+            ..Default::default()
+        };
+        let msg = empty_gather_diagnostic(&stats, std::path::Path::new("/work"), &paths, &filters);
+        let lines: Vec<&str> = msg.lines().collect();
+        assert_eq!(
+            lines[1],
+            "  paths: src/m0.rs, src/m1.rs, src/m2.rs, src/m3.rs, src/m4.rs, src/m5.rs, src/m6.rs, src/m7.rs, src/m8.rs, src/m9.rs, and 2 more"
+        );
+        assert_eq!(lines[2], "  filters: --exclude-paths *.rs --only docs/**");
+
+        let msg = empty_gather_diagnostic(&stats, std::path::Path::new("/work"), &paths[..1], &[]);
+        assert!(msg.contains("  paths: src/m0.rs\n"));
+    }
+
+    #[test]
+    fn savings_lines_use_the_untransformed_total() {
+        let mut stripped = meta("a.rs", 800);
+        stripped.original_tokens = Some(1000);
+        let metas = vec![stripped, meta("b.md", 1000)];
+        assert_eq!(original_total(&metas), 2000);
+
+        let savings = [
+            TransformSavings {
+                pass: "comment stripping",
+                saved: 18_220,
+                before: 40_000,
+                after: 21_780,
+            },
+            TransformSavings {
+                pass: "redaction",
+                saved: 0,
+                before: 0,
+                after: 0,
+            },
+        ];
+        assert_eq!(
+            savings_lines(&savings, 193_830),
+            vec!["comment stripping saved 18,220 tokens (40,000 → 21,780), 9.4%"]
+        );
+        assert_eq!(with_commas(999), "999");
+        assert_eq!(with_commas(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn token_deltas_list_only_transformed_files() {
+        assert_eq!(render_token_deltas(&[meta("a.rs", 5)]), None);
+
+        let mut stripped = meta("src/lib.rs", 750);
+        stripped.original_tokens = Some(1000);
+        let mut stub = meta("big.json", 0);
+        stub.stubbed = true;
+        stub.original_tokens = Some(50);
+        let table = render_token_deltas(&[stripped, meta("a.rs", 5), stub]).unwrap();
+        assert_eq!(table.lines().count(), 2);
+        let row = table.lines().nth(1).unwrap();
+        assert!(row.starts_with("src/lib.rs"));
+        assert!(row.contains("1000 →        750"), "{row}");
+        assert!(row.ends_with("25%"));
+    }
+
+    #[test]
+    fn table_lists_every_extension() {
+        let metas = vec![meta("a.rs", 3), meta("b", 1)];
+        let table = render_composition_table(&extension_composition(&metas));
+        assert!(table.starts_with("extension"));
+        assert!(table.contains("rs"));
+        assert!(table.contains(NO_EXTENSION));
+        assert_eq!(table.lines().count(), 3);
+    }
+}
@@ -1,5 +1,7 @@
+use crate::config::ChunkUnit;
 use anyhow::{Result, anyhow};
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tiktoken_rs::{CoreBPE, get_bpe_from_model, o200k_base};
 
 const DEFAULT_MODEL: &str = "gpt-5.2";
@@ -30,6 +32,11 @@ fn try_bpe_for_model(model: &str) -> Result<CoreBPE> {
     ))
 }
 
+/// Whether `model` names a tokenizer `init` accepts.
+pub fn supports_model(model: &str) -> bool {
+    try_bpe_for_model(model).is_ok()
+}
+
 fn bpe_for_model_or_panic(model: &str) -> CoreBPE {
     try_bpe_for_model(model).expect("tokenizer init failed")
 }
@@ -46,6 +53,18 @@ pub fn count(text: &str) -> usize {
     tok.encode_with_special_tokens(text).len()
 }
 
+/// Size of `text` in `unit`; only `ChunkUnit::Tokens` runs the tokenizer.
+pub fn measure(
+    text: &str,
+    unit: ChunkUnit,
+) -> usize {
+    match unit {
+        ChunkUnit::Tokens => count(text),
+        ChunkUnit::Chars => text.chars().count(),
+        ChunkUnit::Bytes => text.len(),
+    }
+}
+
 /// Initialize the tokenizer model (call before any token counting).
 pub fn init(model: Option<&str>) -> Result<()> {
     if TOK.get().is_some() {
@@ -63,3 +82,78 @@ pub fn init(model: Option<&str>) -> Result<()> {
         .map_err(|_| anyhow!("tokenizer already initialized"))?;
     Ok(())
 }
+
+/// `f(index, item)` for each of `items`, spread over the available cores,
+/// with results in the order of `items`. Counting tokens is independent per
+/// file, so large gathers count them this way; the shared tokenizer is safe
+/// to use from every thread.
+pub fn par_map<T: Sync, R: Send>(
+    items: &[T],
+    f: impl Fn(usize, &T) -> R + Sync,
+) -> Vec<R> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    par_map_on(threads, items, f)
+}
+
+fn par_map_on<T: Sync, R: Send>(
+    threads: usize,
+    items: &[T],
+    f: impl Fn(usize, &T) -> R + Sync,
+) -> Vec<R> {
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        return items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| f(idx, item))
+            .collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut done: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut out = Vec::new();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(idx) else {
+                            break out;
+                        };
+                        out.push((idx, f(idx, item)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    });
+    done.sort_unstable_by_key(|&(idx, _)| idx);
+    done.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn par_map_keeps_item_order() {
+        let texts: Vec<String> = (0..200).map(|i| "word ".repeat((i * 37) % 150)).collect();
+        let sequential: Vec<(usize, usize)> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (i, count(t)))
+            .collect();
+        for threads in [1, 4, 16] {
+            assert_eq!(
+                par_map_on(threads, &texts, |i, t| (i, count(t))),
+                sequential
+            );
+        }
+    }
+}
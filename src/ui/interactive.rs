@@ -71,9 +71,12 @@ fn cleanup_terminal<B: Backend>(terminal: &mut Terminal<B>) -> Result<()> {
     Ok(())
 }
 
+/// Run the file-selection TUI. Extensions with fewer than `ext_group_below`
+/// files are collapsed into one "other" entry (0 disables grouping).
 pub fn select_files_tui(
     paths: Vec<PathBuf>,
     preselected: &[PathBuf],
+    ext_group_below: usize,
 ) -> Result<Vec<PathBuf>> {
     // Install panic hook to restore terminal on panic
     let default_hook = panic::take_hook();
@@ -97,6 +100,21 @@ pub fn select_files_tui(
         return Ok(paths);
     }
 
+    // Under tests, CG_TEST_EVENTS replays a scripted key sequence without a terminal
+    if let Ok(script) = std::env::var("CG_TEST_EVENTS") {
+        let events = tui_events::parse_scripted_events(&script).map_err(|e| anyhow!(e))?;
+        let mut state = tui_state::UiState::new(paths, preselected);
+        state.group_rare_exts(ext_group_below);
+        for evt in events {
+            match tui_events::handle_event(&mut state, evt) {
+                Some(tui_events::UiMsg::Quit) => return Ok(vec![]),
+                Some(tui_events::UiMsg::Submit) => return Ok(state.selected_paths()),
+                _ => {}
+            }
+        }
+        return Err(anyhow!("CG_TEST_EVENTS ended without enter or quit"));
+    }
+
     // Ensure we have a TTY to render against; stderr stays attached when stdout is piped.
     if !io::stderr().is_terminal() {
         return Err(anyhow!(
@@ -106,6 +124,7 @@ pub fn select_files_tui(
 
     // Initialize state
     let mut state = tui_state::UiState::new(paths, preselected);
+    state.group_rare_exts(ext_group_below);
 
     // Setup terminal
     enable_raw_mode()?;
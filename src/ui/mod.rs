@@ -1,8 +1,14 @@
+#[cfg(feature = "tui")]
 pub mod interactive;
+#[cfg(feature = "clipboard")]
 pub mod stream;
+#[cfg(feature = "tui")]
 pub mod tui_events;
+#[cfg(feature = "tui")]
 pub mod tui_render;
+#[cfg(feature = "tui")]
 pub mod tui_state;
 
 // Re-export the TUI entrypoint function
+#[cfg(feature = "tui")]
 pub use interactive::select_files_tui;
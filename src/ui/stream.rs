@@ -1,12 +1,39 @@
+use crate::chunker::FileMeta;
 use crate::config::Config;
+use crate::constants::MAX_EMPTY_READS;
 use crate::context::types::FileContents;
-use crate::context::xml::{maybe_escape_attr, maybe_escape_text};
+use crate::context::xml::{
+    binary_placeholder, cdata_section, encoding_attr, eol_attr, lines_attr, maybe_escape_attr,
+    maybe_escape_text, truncated_attr,
+};
 use crate::io::clipboard;
 use crate::output::RenderedChunk;
 use anyhow::Result;
 use globset::{Glob, GlobSetBuilder};
 use path_slash::PathBufExt;
-use std::io::{self, Write};
+use std::io::{self, BufRead, Write};
+
+/// One line read from a REPL prompt.
+enum Input {
+    /// stdin reached EOF.
+    Eof,
+    /// A trimmed line of text.
+    Line(String),
+    /// A line that was not valid UTF-8, converted lossily for display.
+    NotUtf8(String),
+}
+
+/// Read one line without failing on invalid UTF-8.
+fn read_input(reader: &mut impl BufRead) -> io::Result<Input> {
+    let mut buf = Vec::new();
+    if reader.read_until(b'\n', &mut buf)? == 0 {
+        return Ok(Input::Eof);
+    }
+    Ok(match String::from_utf8(buf) {
+        Ok(line) => Input::Line(line.trim().to_string()),
+        Err(e) => Input::NotUtf8(String::from_utf8_lossy(e.as_bytes()).trim().to_string()),
+    })
+}
 
 /// Multi-step mode: initial header then REPL for fetching files by id or glob.
 pub fn multi_step_mode(
@@ -14,34 +41,73 @@ pub fn multi_step_mode(
     file_data: &[FileContents],
     config: &Config,
 ) -> Result<()> {
-    // Header snippet without closing </shared-context>
-    let snippet = chunks.first().map(|c| c.xml.as_str()).unwrap_or("");
-    // Output the header snippet if requested
-    if config.stdout {
-        print!("{}", snippet);
-    }
-    if !config.no_clipboard {
-        let copied = clipboard::copy_to_clipboard(snippet, !config.stdout)?;
+    // Header chunks (without closing </shared-context>): the header, then any
+    // file-map continuation parts, copied one at a time
+    let total = chunks.len();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        if config.stdout {
+            print!("{}", chunk.text);
+        }
+        if config.no_clipboard {
+            continue;
+        }
+        if idx > 0 {
+            {
+                let mut ui = io::stderr();
+                write!(ui, "Press Enter to copy header part {}/{total}: ", idx + 1)?;
+                ui.flush()?;
+            }
+            if let Input::Eof = read_input(&mut io::stdin().lock())? {
+                eprintln!("stdin closed; leaving multi-step mode.");
+                return Ok(());
+            }
+        }
+        let copied = clipboard::copy_to_clipboard(
+            &chunk.text,
+            config.clipboard_cmd.as_deref(),
+            !config.stdout,
+        )?;
         if copied {
-            eprintln!("Copied header");
+            if total == 1 {
+                eprintln!("Copied header");
+            } else {
+                eprintln!("Copied header part {}/{total}", idx + 1);
+            }
         }
     }
     // Display REPL instructions
     eprintln!("Commands: enter file ids, file paths, or glob patterns; type 'q' to quit.");
 
     // REPL for on-demand file requests
+    let mut empty_reads = 0usize;
     loop {
         {
             let mut ui = io::stderr();
             write!(ui, "Request file id or glob (or 'q' to quit): ")?;
             ui.flush()?;
         }
-        let mut cmd = String::new();
-        if io::stdin().read_line(&mut cmd)? == 0 {
-            eprintln!("stdin closed; leaving multi-step mode.");
-            return Ok(());
+        let cmd = match read_input(&mut io::stdin().lock())? {
+            Input::Eof => {
+                eprintln!("stdin closed; leaving multi-step mode.");
+                return Ok(());
+            }
+            Input::NotUtf8(raw) => {
+                empty_reads = 0;
+                eprintln!("Unrecognized input (not valid UTF-8): {raw}");
+                continue;
+            }
+            Input::Line(line) => line,
+        };
+        if cmd.is_empty() {
+            empty_reads += 1;
+            if empty_reads >= MAX_EMPTY_READS {
+                eprintln!("{MAX_EMPTY_READS} empty inputs in a row; leaving multi-step mode.");
+                return Ok(());
+            }
+            continue;
         }
-        let cmd = cmd.trim();
+        empty_reads = 0;
+        let cmd = cmd.as_str();
         if cmd.eq_ignore_ascii_case("q") {
             break;
         }
@@ -80,31 +146,49 @@ pub fn multi_step_mode(
         // Output each requested file
         for &id in &selected {
             let fc = &file_data[id];
-            let path = fc.path.to_slash_lossy().to_string();
-            let folder = fc.folder.to_slash_lossy().to_string();
-            let folder_display = if folder.is_empty() { "." } else { &folder };
-            let name = fc
-                .path
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_default();
-            let path_attr = maybe_escape_attr(&path, config.escape_xml);
-            let folder_attr = maybe_escape_attr(folder_display, config.escape_xml);
-            let name_attr = maybe_escape_attr(&name, config.escape_xml);
-            let contents = maybe_escape_text(&fc.contents, config.escape_xml);
-            let out = format!(
-                "<file-contents id=\"{id}\" path=\"{path}\" name=\"{name}\" folder=\"{folder}\">\n{contents}\n</file-contents>\n",
-                id = id,
-                path = path_attr,
-                name = name_attr,
-                folder = folder_attr,
-                contents = contents
-            );
+            if fc.stubbed {
+                eprintln!("File id {} is stubbed; contents are not available", id);
+                continue;
+            }
+            let out = binary_placeholder(fc, "", config.escape_xml).unwrap_or_else(|| {
+                let path = fc.path.to_slash_lossy().to_string();
+                let folder = fc.folder.to_slash_lossy().to_string();
+                let folder_display = if folder.is_empty() { "." } else { &folder };
+                let name = fc
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let path_attr = maybe_escape_attr(&path, config.escape_xml);
+                let folder_attr = maybe_escape_attr(folder_display, config.escape_xml);
+                let name_attr = maybe_escape_attr(&name, config.escape_xml);
+                let contents = if config.cdata {
+                    cdata_section(&fc.contents).into()
+                } else {
+                    maybe_escape_text(&fc.contents, config.escape_xml)
+                };
+                format!(
+                    "<file-contents id=\"{id}\" path=\"{path}\" name=\"{name}\" folder=\"{folder}\"{encoding}{eol}{lines}{truncated}>\n{contents}\n</file-contents>\n",
+                    id = id,
+                    path = path_attr,
+                    name = name_attr,
+                    folder = folder_attr,
+                    encoding = encoding_attr(fc),
+                    eol = eol_attr(fc),
+                    lines = lines_attr(fc),
+                    truncated = truncated_attr(fc),
+                    contents = contents
+                )
+            });
             if config.stdout {
                 print!("{}", out);
             }
             if !config.no_clipboard {
-                let copied = clipboard::copy_to_clipboard(&out, !config.stdout)?;
+                let copied = clipboard::copy_to_clipboard(
+                    &out,
+                    config.clipboard_cmd.as_deref(),
+                    !config.stdout,
+                )?;
                 if copied {
                     eprintln!("Copied file id {}", id);
                 }
@@ -114,45 +198,96 @@ pub fn multi_step_mode(
     Ok(())
 }
 
+/// The files in chunk `idx` according to `files`, with the part for split
+/// ones, e.g. `src/lib.rs, docs/guide.md (part 2/3)`.
+fn chunk_file_list(
+    files: &[FileMeta],
+    idx: usize,
+) -> String {
+    let mut listed = Vec::new();
+    for meta in files {
+        for (part, _) in meta.chunks.iter().enumerate().filter(|&(_, &c)| c == idx) {
+            let path = meta.path.to_slash_lossy();
+            listed.push(if meta.parts > 1 {
+                format!("{path} (part {}/{})", part + 1, meta.parts)
+            } else {
+                path.to_string()
+            });
+        }
+    }
+    listed.join(", ")
+}
+
 /// Interactive streaming mode: REPL for browsing and copying context chunks.
+/// With `--reverse-chunk-output`, paging starts at the last chunk and walks back.
+/// `files` says which files each chunk holds, listed as each chunk comes up.
 pub fn streaming_mode(
     chunks: &[RenderedChunk],
+    files: &[FileMeta],
     config: &Config,
 ) -> Result<()> {
     let total = chunks.len();
-    let mut idx = 0usize;
+    let reverse = config.reverse_chunk_output;
+    let mut idx = if reverse { total - 1 } else { 0 };
     eprintln!("▲ Streaming {total} chunks (0..{}).", total - 1);
     // Display REPL instructions
     eprintln!("Commands: press Enter for next chunk, number to jump, or 'q' to quit.");
+    // Enter pages forward, so allow at least one full pass before giving up
+    let max_empty_reads = MAX_EMPTY_READS.max(total);
+    let mut empty_reads = 0usize;
     loop {
-        let snippet = &chunks[idx].xml;
+        let snippet = &chunks[idx].text;
         if config.stdout {
             print!("{}", snippet);
         }
         if !config.no_clipboard {
-            let copied = clipboard::copy_to_clipboard(snippet, !config.stdout)?;
+            let copied = clipboard::copy_to_clipboard(
+                snippet,
+                config.clipboard_cmd.as_deref(),
+                !config.stdout,
+            )?;
             if copied {
                 eprintln!("Copied chunk {idx}");
             }
         }
+        let listed = chunk_file_list(files, idx);
+        if !listed.is_empty() {
+            eprintln!("Chunk {idx} files: {listed}");
+        }
         {
             let mut ui = io::stderr();
             write!(ui, "Enter chunk # (0..{}) or 'q' to quit: ", total - 1)?;
             ui.flush()?;
         }
-        let mut cmd = String::new();
-        if io::stdin().read_line(&mut cmd)? == 0 {
-            eprintln!("stdin closed; leaving streaming mode.");
-            return Ok(());
+        let cmd = match read_input(&mut io::stdin().lock())? {
+            Input::Eof => {
+                eprintln!("stdin closed; leaving streaming mode.");
+                return Ok(());
+            }
+            Input::NotUtf8(raw) => {
+                empty_reads = 0;
+                eprintln!("Unrecognized input (not valid UTF-8): {raw}");
+                continue;
+            }
+            Input::Line(line) => line,
+        };
+        if cmd.is_empty() {
+            empty_reads += 1;
+            if empty_reads >= max_empty_reads {
+                eprintln!("{max_empty_reads} empty inputs in a row; leaving streaming mode.");
+                return Ok(());
+            }
+            idx = if reverse {
+                (idx + total - 1) % total
+            } else {
+                (idx + 1) % total
+            };
+            continue;
         }
-        let cmd = cmd.trim();
+        empty_reads = 0;
         if cmd.eq_ignore_ascii_case("q") {
             break;
         }
-        if cmd.is_empty() {
-            idx = (idx + 1) % total;
-            continue;
-        }
         match cmd.parse::<usize>() {
             Ok(n) if n < total => idx = n,
             Ok(_) => eprintln!(
@@ -1,5 +1,5 @@
-use crate::ui::tui_state::{UiState, clamp_selection, filtered_exts, filtered_files};
-use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crate::ui::tui_state::{ExtRow, UiState, clamp_selection, filtered_exts, filtered_files};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use std::collections::HashSet;
 use std::path::PathBuf;
 
@@ -123,10 +123,23 @@ pub fn handle_event(
                         filtered_ext.len(),
                     );
                 }
-                // Space toggles the current extension
+                // Space toggles the current extension (or the whole "other" group)
                 KeyCode::Char(' ') => {
-                    if let Some(&orig) = filtered_ext.get(state.ext_selected_idx) {
-                        state.extension_items[orig].1 = !state.extension_items[orig].1;
+                    if let Some(&row) = filtered_ext.get(state.ext_selected_idx) {
+                        state.toggle_ext_row(row);
+                    }
+                }
+                // Right expands the "other" group, Left collapses it
+                KeyCode::Right
+                    if filtered_ext.get(state.ext_selected_idx) == Some(&ExtRow::Group) =>
+                {
+                    state.ext_group_expanded = true;
+                }
+                KeyCode::Left if state.ext_group_expanded => {
+                    state.ext_group_expanded = false;
+                    filtered_ext = filtered_exts(state);
+                    if let Some(pos) = filtered_ext.iter().position(|r| *r == ExtRow::Group) {
+                        state.ext_selected_idx = state.ext_selected_idx.min(pos);
                     }
                 }
                 // Other chars update the extension search
@@ -259,11 +272,44 @@ pub fn handle_event(
     None
 }
 
+/// Parse a scripted key sequence such as `ctrl+u,down,space,enter` into key
+/// events. Tokens are `enter`, `esc`, `space`, `up`, `down`, `left`, `right`, `backspace`,
+/// `ctrl+<char>`, or a single character. Used by `CG_TEST_EVENTS`.
+pub fn parse_scripted_events(spec: &str) -> Result<Vec<Event>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|token| {
+            let (mods, key) = match token.strip_prefix("ctrl+") {
+                Some(rest) => (KeyModifiers::CONTROL, rest),
+                None => (KeyModifiers::NONE, token),
+            };
+            let code = match key {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "backspace" => KeyCode::Backspace,
+                _ => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => return Err(format!("unknown scripted key: {token}")),
+                    }
+                }
+            };
+            Ok(Event::Key(KeyEvent::new(code, mods)))
+        })
+        .collect()
+}
+
 // Unit tests for handle_event
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
     use std::path::PathBuf;
 
     fn make_state() -> UiState {
@@ -410,4 +456,56 @@ mod tests {
         assert_eq!(msg2, Some(UiMsg::ToggleExtensionMode));
         assert!(!state.extension_mode);
     }
+
+    #[test]
+    fn test_extension_group_expand_collapse_and_apply() {
+        let paths = ["1.rs", "2.rs", "x.a", "x.b"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut state = UiState::new(paths, &[]);
+        state.group_rare_exts(2);
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+
+        handle_event(
+            &mut state,
+            Event::Key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL)),
+        );
+        // rows: .rs, other
+        handle_event(&mut state, key(KeyCode::Down));
+        handle_event(&mut state, key(KeyCode::Right));
+        assert!(state.ext_group_expanded);
+        assert_eq!(filtered_exts(&state).len(), 4);
+
+        // Left from a member collapses back onto the group row
+        handle_event(&mut state, key(KeyCode::Down));
+        handle_event(&mut state, key(KeyCode::Left));
+        assert!(!state.ext_group_expanded);
+        assert_eq!(state.ext_selected_idx, 1);
+
+        // Space on the group checks both rare extensions; Enter applies them
+        handle_event(&mut state, key(KeyCode::Char(' ')));
+        handle_event(&mut state, key(KeyCode::Enter));
+        let selected = state.selected_paths();
+        assert_eq!(selected, vec![PathBuf::from("x.a"), PathBuf::from("x.b")]);
+    }
+
+    #[test]
+    fn test_parse_scripted_events() {
+        let events = parse_scripted_events("ctrl+u, down,space,x,enter").unwrap();
+        assert_eq!(
+            events[0],
+            Event::Key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            events[2],
+            Event::Key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            events[3],
+            Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+        );
+        assert_eq!(events.len(), 5);
+        assert!(parse_scripted_events("pagedown").is_err());
+    }
 }
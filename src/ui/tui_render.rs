@@ -1,5 +1,5 @@
 use crate::ui::tui_state::{
-    UiState, adjust_scroll_and_slice, clamp_selection, filtered_exts, filtered_files,
+    ExtRow, UiState, adjust_scroll_and_slice, clamp_selection, filtered_exts, filtered_files,
 };
 use ratatui::{
     Frame,
@@ -27,7 +27,7 @@ pub fn render(
     // Search bar title and input binding
     let (title, input) = if state.extension_mode {
         (
-            "Extensions (Ctrl+E to exit, Enter to apply)".to_owned(),
+            "Extensions (Ctrl+E to exit, Enter to apply, →/← to expand/collapse other)".to_owned(),
             &state.extension_search,
         )
     } else if state.search_edit_mode {
@@ -69,13 +69,42 @@ pub fn render(
         // Build ListItems
         let items: Vec<ListItem> = window
             .iter()
-            .map(|&idx| {
-                let (text, checked) = &state.extension_items[idx];
-                let mark = if *checked { "[x]" } else { "[ ]" };
+            .map(|&row| {
+                let (mark, text) = match row {
+                    ExtRow::Ext(idx) => {
+                        let (ext, checked) = &state.extension_items[idx];
+                        let mark = if *checked { "[x]" } else { "[ ]" };
+                        // members of the expanded group are indented under it
+                        let nested =
+                            state.extension_search.is_empty() && state.ext_group.contains(&idx);
+                        let text = if nested {
+                            format!("  {ext}")
+                        } else {
+                            ext.clone()
+                        };
+                        (mark, text)
+                    }
+                    ExtRow::Group => {
+                        let checked = state.ext_group_checked();
+                        let mark = if checked == 0 {
+                            "[ ]"
+                        } else if checked == state.ext_group.len() {
+                            "[x]"
+                        } else {
+                            "[-]"
+                        };
+                        let arrow = if state.ext_group_expanded {
+                            "▾"
+                        } else {
+                            "▸"
+                        };
+                        (mark, format!("{arrow} {}", state.ext_group_label()))
+                    }
+                };
                 let spans = Line::from(vec![
                     Span::styled(mark, Style::default().fg(Color::Yellow)),
                     Span::raw(" "),
-                    Span::raw(text.clone()),
+                    Span::raw(text),
                 ]);
                 ListItem::new(spans)
             })
@@ -2,6 +2,16 @@ use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
+/// A row of the extension list: one extension, or the collapsed group of
+/// extensions with few files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtRow {
+    /// Index into `UiState::extension_items`.
+    Ext(usize),
+    /// The "other" entry standing for every index in `UiState::ext_group`.
+    Group,
+}
+
 /// Shared UI state for file selection TUI
 pub struct UiState {
     pub items: Vec<(PathBuf, bool)>,
@@ -13,6 +23,9 @@ pub struct UiState {
     pub extension_search: String,
     pub ext_selected_idx: usize,
     pub ext_scroll_offset: usize,
+    /// Extensions collapsed into the "other" row, as `extension_items` indices.
+    pub ext_group: Vec<usize>,
+    pub ext_group_expanded: bool,
     pub reset_ext_on_toggle: bool,
     pub saved_search_input: String,
     pub search_edit_mode: bool,
@@ -81,6 +94,8 @@ impl UiState {
             extension_search: String::new(),
             ext_selected_idx: 0,
             ext_scroll_offset: 0,
+            ext_group: Vec::new(),
+            ext_group_expanded: false,
             reset_ext_on_toggle: true,
             saved_search_input: String::new(),
             search_edit_mode: false,
@@ -89,6 +104,60 @@ impl UiState {
         }
     }
 
+    /// Collapse extensions with fewer than `below` files into one "other" row.
+    /// Grouping a single extension gains nothing, so that case is left flat.
+    pub fn group_rare_exts(
+        &mut self,
+        below: usize,
+    ) {
+        self.ext_group = self
+            .extension_items
+            .iter()
+            .enumerate()
+            .filter(|(_, (ext, _))| self.ext_counts.get(ext).copied().unwrap_or(0) < below)
+            .map(|(idx, _)| idx)
+            .collect();
+        if self.ext_group.len() < 2 {
+            self.ext_group.clear();
+        }
+        self.ext_group_expanded = false;
+    }
+
+    /// Label for the group row, e.g. `other (23 ext, 31 files)`.
+    pub fn ext_group_label(&self) -> String {
+        let files: usize = self
+            .ext_group
+            .iter()
+            .map(|&idx| self.ext_counts[&self.extension_items[idx].0])
+            .sum();
+        format!("other ({} ext, {files} files)", self.ext_group.len())
+    }
+
+    /// Number of checked extensions in the group.
+    pub fn ext_group_checked(&self) -> usize {
+        self.ext_group
+            .iter()
+            .filter(|&&idx| self.extension_items[idx].1)
+            .count()
+    }
+
+    /// Toggle one row; the group row checks all of its extensions unless they
+    /// are all checked already, in which case it clears them.
+    pub fn toggle_ext_row(
+        &mut self,
+        row: ExtRow,
+    ) {
+        match row {
+            ExtRow::Ext(idx) => self.extension_items[idx].1 = !self.extension_items[idx].1,
+            ExtRow::Group => {
+                let check = self.ext_group_checked() < self.ext_group.len();
+                for &idx in &self.ext_group {
+                    self.extension_items[idx].1 = check;
+                }
+            }
+        }
+    }
+
     /// Return selected `PathBuf`s based on the checkbox state
     pub fn selected_paths(&self) -> Vec<PathBuf> {
         self.items
@@ -119,26 +188,40 @@ pub fn filtered_files(state: &UiState) -> Vec<usize> {
     entries.into_iter().map(|(idx, _)| idx).collect()
 }
 
-pub fn filtered_exts(state: &UiState) -> Vec<usize> {
+/// Rows of the extension list. Without a search, grouped extensions sit behind
+/// the "other" row (listed after it when expanded); a search matches every
+/// extension directly.
+pub fn filtered_exts(state: &UiState) -> Vec<ExtRow> {
+    if state.extension_search.is_empty() {
+        let grouped: HashSet<usize> = state.ext_group.iter().copied().collect();
+        let mut rows: Vec<ExtRow> = (0..state.extension_items.len())
+            .filter(|idx| !grouped.contains(idx))
+            .map(ExtRow::Ext)
+            .collect();
+        if !state.ext_group.is_empty() {
+            rows.push(ExtRow::Group);
+            if state.ext_group_expanded {
+                rows.extend(state.ext_group.iter().copied().map(ExtRow::Ext));
+            }
+        }
+        return rows;
+    }
     let matcher = SkimMatcherV2::default();
-    let mut entries: Vec<(usize, i64)> = if state.extension_search.is_empty() {
-        (0..state.extension_items.len())
-            .map(|idx| (idx, 0))
-            .collect()
-    } else {
-        state
-            .extension_items
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, (ext, _))| {
-                matcher
-                    .fuzzy_match(ext, &state.extension_search)
-                    .map(|score| (idx, score))
-            })
-            .collect()
-    };
+    let mut entries: Vec<(usize, i64)> = state
+        .extension_items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, (ext, _))| {
+            matcher
+                .fuzzy_match(ext, &state.extension_search)
+                .map(|score| (idx, score))
+        })
+        .collect();
     entries.sort_unstable_by_key(|&(_, score)| std::cmp::Reverse(score));
-    entries.into_iter().map(|(idx, _)| idx).collect()
+    entries
+        .into_iter()
+        .map(|(idx, _)| ExtRow::Ext(idx))
+        .collect()
 }
 
 pub fn clamp_selection(
@@ -170,3 +253,81 @@ pub fn adjust_scroll_and_slice(
     let end_idx = (*scroll_offset + max_lines).min(data_len);
     (*scroll_offset, end_idx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Four `.rs` files, two `.md`, and one file each of `.a`, `.b`, `.c`.
+    fn polyglot_state() -> UiState {
+        let paths = [
+            "1.rs", "2.rs", "3.rs", "4.rs", "1.md", "2.md", "x.a", "x.b", "x.c",
+        ]
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+        let mut state = UiState::new(paths, &[]);
+        state.group_rare_exts(2);
+        state
+    }
+
+    fn names(
+        state: &UiState,
+        rows: &[ExtRow],
+    ) -> Vec<String> {
+        rows.iter()
+            .map(|row| match row {
+                ExtRow::Ext(idx) => state.extension_items[*idx].0.clone(),
+                ExtRow::Group => state.ext_group_label(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rare_extensions_collapse_into_other_row() {
+        let state = polyglot_state();
+        assert_eq!(
+            names(&state, &filtered_exts(&state)),
+            vec![".rs", ".md", "other (3 ext, 3 files)"]
+        );
+
+        // A lone rare extension stays in the flat list
+        let paths = ["1.rs", "2.rs", "x.a"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut state = UiState::new(paths, &[]);
+        state.group_rare_exts(2);
+        assert!(state.ext_group.is_empty());
+        assert_eq!(filtered_exts(&state).len(), 2);
+    }
+
+    #[test]
+    fn expanded_group_lists_members_and_search_flattens() {
+        let mut state = polyglot_state();
+        state.ext_group_expanded = true;
+        assert_eq!(
+            names(&state, &filtered_exts(&state)),
+            vec![".rs", ".md", "other (3 ext, 3 files)", ".a", ".b", ".c"]
+        );
+
+        state.ext_group_expanded = false;
+        state.extension_search = "b".into();
+        assert_eq!(names(&state, &filtered_exts(&state)), vec![".b"]);
+    }
+
+    #[test]
+    fn group_row_toggles_all_members() {
+        let mut state = polyglot_state();
+        let b = state.ext_group[1];
+        state.toggle_ext_row(ExtRow::Ext(b));
+        assert_eq!(state.ext_group_checked(), 1);
+
+        // Partially checked: the group toggle checks the rest
+        state.toggle_ext_row(ExtRow::Group);
+        assert_eq!(state.ext_group_checked(), 3);
+        state.toggle_ext_row(ExtRow::Group);
+        assert_eq!(state.ext_group_checked(), 0);
+        assert!(state.extension_items.iter().all(|(_, checked)| !checked));
+    }
+}
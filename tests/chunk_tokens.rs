@@ -0,0 +1,220 @@
+use context_gather::gather::FileContents;
+use context_gather::header::HeaderSpec;
+use context_gather::pipeline::Pipeline;
+use context_gather::tokenizer::count as count_tokens;
+use std::path::PathBuf;
+
+fn files() -> Vec<FileContents> {
+    (0..12)
+        .map(|i| {
+            let path = PathBuf::from(format!("src/file_{i}.rs"));
+            FileContents {
+                folder: PathBuf::from("src"),
+                path,
+                contents: format!("fn f{i}() {{ println!(\"{}\"); }}\n", "x".repeat(40)).repeat(8),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Chunk tokens declared by the header, in emission order.
+fn declared(header: &str) -> (usize, Vec<usize>) {
+    let attr = |name: &str| {
+        let start = header.find(&format!(" {name}=\"")).unwrap() + name.len() + 3;
+        let len = header[start..].find('"').unwrap();
+        header[start..start + len].to_string()
+    };
+    let total = attr("total-tokens").parse().unwrap();
+    let chunks = attr("chunk-tokens")
+        .split(',')
+        .map(|n| n.parse().unwrap())
+        .collect();
+    (total, chunks)
+}
+
+#[test]
+fn header_declares_the_rendered_chunk_tokens() {
+    for header_last in [false, true] {
+        for legacy_snippets in [false, true] {
+            let mut pipeline = Pipeline::from_files(files());
+            pipeline
+                .build_chunks_with_header(
+                    HeaderSpec {
+                        chunk_size: 300,
+                        header_last,
+                        ..Default::default()
+                    },
+                    legacy_snippets,
+                )
+                .unwrap();
+            let chunks = pipeline.rendered_chunks();
+            assert!(chunks.len() > 2);
+            let header = if header_last {
+                chunks.last().unwrap()
+            } else {
+                &chunks[0]
+            };
+            let (total, declared) = declared(&header.text);
+            let actual: Vec<usize> = chunks.iter().map(|c| c.tokens).collect();
+            let sum: usize = actual.iter().sum();
+            assert_eq!(declared.len(), actual.len());
+            assert!(total.abs_diff(sum) <= 2, "declared {total}, rendered {sum}");
+            for (d, a) in declared.iter().zip(&actual) {
+                assert!(
+                    d.abs_diff(*a) <= 2,
+                    "declared {declared:?}, rendered {actual:?}"
+                );
+            }
+            // Every body chunk stays under the limit
+            assert!(
+                chunks
+                    .iter()
+                    .filter(|c| !c.text.contains("<shared-context-header"))
+                    .all(|c| c.tokens <= 300),
+                "{actual:?} (header_last={header_last})"
+            );
+        }
+    }
+}
+
+/// Build with every header position and snippet format, and check that no
+/// chunk but the header exceeds `limit` unless it holds a single block.
+/// Returns the number of chunks.
+fn assert_chunks_within(
+    files: &[FileContents],
+    limit: usize,
+) -> usize {
+    let mut emitted = 0;
+    for header_last in [false, true] {
+        for legacy_snippets in [false, true] {
+            let mut pipeline = Pipeline::from_files(files.to_vec());
+            pipeline
+                .build_chunks_with_header(
+                    HeaderSpec {
+                        chunk_size: limit,
+                        header_last,
+                        ..Default::default()
+                    },
+                    legacy_snippets,
+                )
+                .unwrap();
+            let chunks = pipeline.rendered_chunks();
+            let sizes: Vec<usize> = chunks.iter().map(|c| c.tokens).collect();
+            for chunk in chunks
+                .iter()
+                .filter(|c| !c.text.contains("<shared-context-header"))
+            {
+                assert!(
+                    chunk.tokens <= limit || chunk.text.matches("<file-contents ").count() == 1,
+                    "{sizes:?} over {limit} (header_last={header_last}, legacy={legacy_snippets})"
+                );
+            }
+            emitted = chunks.len();
+        }
+    }
+    emitted
+}
+
+fn file(
+    path: &str,
+    contents: String,
+) -> FileContents {
+    FileContents {
+        folder: PathBuf::from("."),
+        path: PathBuf::from(path),
+        contents,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn hundreds_of_tiny_files_stay_within_the_limit() {
+    let files: Vec<FileContents> = (0..400)
+        .map(|i| file(&format!("f{i}.txt"), format!("x{i}\n")))
+        .collect();
+    // Over 100 chunks, so chunk numbers need more digits than at the start
+    assert!(assert_chunks_within(&files, 100) > 100);
+    // Chunks too small for any two files: each gets its own, or is wrapped
+    // into several
+    assert!(assert_chunks_within(&files[..50], 40) > 50);
+}
+
+#[test]
+fn one_enormous_file_stays_within_the_limit() {
+    let contents: String = (0..4000)
+        .map(|i| {
+            format!(
+                "let value_{i} = compute({i}, \"{}\");\n",
+                "y".repeat(i % 17)
+            )
+        })
+        .collect();
+    assert!(assert_chunks_within(&[file("big.rs", contents)], 500) > 50);
+}
+
+#[test]
+fn zero_files_render_only_the_header() {
+    assert_eq!(assert_chunks_within(&[], 300), 1);
+}
+
+/// The blocks of a body chunk, without its `<context-chunk>` wrapper.
+fn chunk_blocks(text: &str) -> &str {
+    let open = "kind=\"body\">\n";
+    let start = text.find(open).unwrap() + open.len();
+    &text[start..text.rfind("</context-chunk>").unwrap()]
+}
+
+#[test]
+fn neighbouring_chunks_that_fit_together_are_merged() {
+    // Files of uneven sizes, some split into parts, which left neighbouring
+    // chunks that fit in one snippet when each was sized for the widest wrapper
+    let files: Vec<FileContents> = (0..40)
+        .map(|i| {
+            file(
+                &format!("src/f{i}.rs"),
+                format!("fn f{i}() {{ let x = {i}; }}\n").repeat(i * 7 % 23 + 1),
+            )
+        })
+        .collect();
+    let limit = 200;
+    let mut pipeline = Pipeline::from_files(files.clone());
+    pipeline
+        .build_chunks_with_header(
+            HeaderSpec {
+                chunk_size: limit,
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+    let chunks = pipeline.rendered_chunks();
+    let bodies: Vec<&str> = chunks[1..].iter().map(|c| c.text.as_str()).collect();
+    for pair in bodies.windows(2) {
+        let merged = pair[0].replacen(
+            "</context-chunk>",
+            &format!("{}</context-chunk>", chunk_blocks(pair[1])),
+            1,
+        );
+        assert!(
+            count_tokens(&merged) > limit,
+            "could merge:\n{}{}",
+            pair[0],
+            pair[1]
+        );
+    }
+    assert!(chunks[1..].iter().all(|c| c.tokens <= limit));
+
+    // Parts and their chunks still match what the chunks hold
+    for meta in pipeline.file_meta() {
+        let path = format!("path=\"{}\"", meta.path.display());
+        let holding: Vec<usize> = chunks
+            .iter()
+            .enumerate()
+            .skip(1)
+            .flat_map(|(idx, c)| std::iter::repeat_n(idx, c.text.matches(&path).count()))
+            .collect();
+        assert_eq!(holding.len(), meta.parts, "{}", meta.path.display());
+        assert_eq!(holding, meta.chunks, "{}", meta.path.display());
+    }
+}
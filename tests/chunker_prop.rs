@@ -1,8 +1,36 @@
 #![cfg_attr(not(test), allow(dead_code))]
-use context_gather::{chunker::build_chunks, gather::FileContents};
+use context_gather::config::SplitStrategy;
+use context_gather::{
+    chunker::{ChunkOptions, build_chunk_bodies, build_chunks},
+    context::validate::check_well_formed,
+    gather::FileContents,
+    header::HeaderSpec,
+    pipeline::Pipeline,
+    xml_output::number_lines,
+};
 use proptest::prelude::*;
 use std::path::PathBuf;
 
+/// The bodies of every `<file-contents>` block in `xml`, joined. A
+/// `wrapped="true"` part breaks a line, so its closing newline is dropped,
+/// and an `empty="true"` file has no body.
+fn joined_bodies(xml: &str) -> String {
+    let mut out = String::new();
+    for block in xml.split("<file-contents ").skip(1) {
+        let (tag, rest) = block.split_once(">\n").unwrap();
+        if tag.ends_with('/') {
+            continue;
+        }
+        let body = &rest[..rest.find("    </file-contents>").unwrap()];
+        if tag.contains("wrapped=\"true\"") {
+            out.push_str(body.strip_suffix('\n').unwrap());
+        } else {
+            out.push_str(body);
+        }
+    }
+    out
+}
+
 proptest! {
     #[test]
     // This the only piece of the suite that
@@ -25,14 +53,48 @@ proptest! {
             folder: PathBuf::from("."),
             path: PathBuf::from("big.txt"),
             contents: text.clone(),
+            ..Default::default()
         };
         let (chunks, _) = build_chunks(&[file], limit, false);
-        let glued:String = chunks.into_iter().map(|c| c.xml).collect();
+        let glued = joined_bodies(&chunks.into_iter().map(|c| c.xml).collect::<String>());
         for l in &lines {
             prop_assert!(glued.contains(l));
         }
     }
 
+    #[test]
+    // Same reassembly check with `--line-numbers`: once the prefixes are
+    // stripped, every part's lines are numbered consecutively from 1 across
+    // chunk boundaries and carry the original text.
+    fn numbered_reassembly_keeps_absolute_line_numbers(lines in prop::collection::vec("[^<\n]*", 1..100),
+                                                       limit in 10usize..200usize) {
+        let text = lines.join("\n");
+        let file = FileContents {
+            folder: PathBuf::from("."),
+            path: PathBuf::from("big.txt"),
+            contents: number_lines(&text),
+            ..Default::default()
+        };
+        let (chunks, _) = build_chunks(&[file], limit, false);
+        let glued = joined_bodies(&chunks.into_iter().map(|c| c.xml).collect::<String>());
+        let numbered: Vec<(usize, &str)> = glued
+            .split('\n')
+            .filter_map(|l| {
+                let (num, rest) = l.split_once(": ")?;
+                Some((num.trim_start().parse().ok()?, rest))
+            })
+            .collect();
+        let expected: Vec<&str> = text
+            .split_inclusive('\n')
+            .map(|l| l.strip_suffix('\n').unwrap_or(l))
+            .collect();
+        prop_assert_eq!(numbered.len(), expected.len());
+        for (idx, ((num, rest), original)) in numbered.iter().zip(&expected).enumerate() {
+            prop_assert_eq!(*num, idx + 1);
+            prop_assert_eq!(*rest, *original);
+        }
+    }
+
     #[test]
     fn chunks_respect_limit_for_small_lines(repeats in prop::collection::vec(1usize..6, 1..60),
                                             limit in 50usize..200usize) {
@@ -45,10 +107,182 @@ proptest! {
             folder: PathBuf::from("."),
             path: PathBuf::from("small.txt"),
             contents,
+            ..Default::default()
         };
         let (chunks, _) = build_chunks(&[file], limit, false);
         for chunk in chunks {
             prop_assert!(chunk.tokens <= limit);
         }
     }
+
+    #[test]
+    // Both split strategies: the part bodies of a split file concatenate back
+    // to its lines, and no block exceeds the limit. Lines mix code, blank
+    // lines, closing braces and headings so smart cuts actually happen.
+    fn split_parts_reassemble_within_limit(
+        lines in prop::collection::vec(
+            prop_oneof![
+                (1usize..6).prop_map(|n| "tok ".repeat(n)),
+                Just(String::new()),
+                Just("}".to_string()),
+                Just("    }".to_string()),
+                Just("## Heading".to_string()),
+            ],
+            1..80,
+        ),
+        limit in 50usize..200usize,
+        smart in any::<bool>(),
+    ) {
+        let contents = lines.join("\n");
+        let file = FileContents {
+            folder: PathBuf::from("."),
+            path: PathBuf::from("big.txt"),
+            contents: contents.clone(),
+            ..Default::default()
+        };
+        let strategy = if smart { SplitStrategy::Smart } else { SplitStrategy::Lines };
+        let options = ChunkOptions { strategy, ..Default::default() };
+        let (bodies, _) = build_chunk_bodies(&[file], limit, false, false, options);
+        let blocks: Vec<_> = bodies.iter().flat_map(|b| &b.blocks).collect();
+        for block in &blocks {
+            prop_assert!(block.tokens <= limit);
+        }
+        let joined: String = blocks.iter().map(|b| b.body.as_str()).collect();
+        if blocks.len() > 1 {
+            // Split files get a newline after every line, the last included
+            prop_assert_eq!(joined, format!("{contents}\n"));
+        } else {
+            prop_assert_eq!(joined, contents);
+        }
+    }
+
+    #[test]
+    fn overlapping_parts_reassemble_within_limit(
+        lines in prop::collection::vec(
+            prop_oneof![
+                (1usize..6).prop_map(|n| "tok ".repeat(n)),
+                Just(String::new()),
+                Just("}".to_string()),
+            ],
+            1..80,
+        ),
+        limit in 50usize..200usize,
+        overlap in 0usize..8,
+        smart in any::<bool>(),
+    ) {
+        let contents = lines.join("\n");
+        let file = FileContents {
+            folder: PathBuf::from("."),
+            path: PathBuf::from("big.txt"),
+            contents: contents.clone(),
+            ..Default::default()
+        };
+        let strategy = if smart { SplitStrategy::Smart } else { SplitStrategy::Lines };
+        let options = ChunkOptions { strategy, overlap, ..Default::default() };
+        let (bodies, _) = build_chunk_bodies(&[file], limit, false, false, options);
+        for body in &bodies {
+            prop_assert!(body.tokens <= limit);
+        }
+        let blocks: Vec<_> = bodies.iter().flat_map(|b| &b.blocks).collect();
+        prop_assert_eq!(blocks[0].overlap, 0);
+        // Drop the repeated lines at each seam before joining
+        let mut joined = String::new();
+        for block in &blocks {
+            prop_assert!(block.overlap <= overlap);
+            let mut body = block.body.as_str();
+            for _ in 0..block.overlap {
+                let end = body.find('\n').map_or(body.len(), |i| i + 1);
+                body = &body[end..];
+            }
+            joined.push_str(body);
+        }
+        if blocks.len() > 1 {
+            prop_assert_eq!(joined, format!("{contents}\n"));
+        } else {
+            prop_assert_eq!(joined, contents);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(4))]
+
+    #[test]
+    // Minified-style lines far over the limit are broken into wrapped pieces
+    // instead of oversize parts: every block fits, escaped pieces stay well
+    // formed, and the pieces join back into the line.
+    fn long_lines_wrap_within_limit(
+        word in "[ -~é✓&<]{1,40}",
+        short in prop::collection::vec("[a-z ]{0,30}", 0..4),
+        limit in 200usize..400usize,
+        escape_xml in any::<bool>(),
+    ) {
+        let long = word.repeat(50_000 / word.chars().count() + 1);
+        let contents = [short.join("\n"), long, short.join("\n")].join("\n");
+        let file = FileContents {
+            folder: PathBuf::from("."),
+            path: PathBuf::from("bundle.min.js"),
+            contents: contents.clone(),
+            ..Default::default()
+        };
+        let (bodies, _) =
+            build_chunk_bodies(&[file], limit, escape_xml, false, ChunkOptions::default());
+        let blocks: Vec<_> = bodies.iter().flat_map(|b| &b.blocks).collect();
+        prop_assert!(blocks.len() > 1);
+        for block in &blocks {
+            prop_assert!(block.tokens <= limit);
+            if escape_xml {
+                prop_assert!(check_well_formed(&block.xml).is_ok(), "{}", block.xml);
+            }
+        }
+        let xml: String = blocks.iter().map(|b| b.xml.as_str()).collect();
+        let joined = joined_bodies(&xml);
+        let joined = if escape_xml {
+            quick_xml::escape::unescape(&joined).unwrap().into_owned()
+        } else {
+            joined
+        };
+        prop_assert_eq!(joined, format!("{contents}\n"));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    // Every rendered chunk other than the header fits the limit, wrapper and
+    // `<more>` marker included, unless it holds a single block.
+    fn header_chunks_respect_limit(
+        files in prop::collection::vec(prop::collection::vec("[a-z{}();<&]{0,30}", 1..40), 0..30),
+        limit in 60usize..400usize,
+        header_last in any::<bool>(),
+        legacy_snippets in any::<bool>(),
+    ) {
+        let files: Vec<FileContents> = files
+            .into_iter()
+            .enumerate()
+            .map(|(i, lines)| FileContents {
+                folder: PathBuf::from(format!("d{}", i % 3)),
+                path: PathBuf::from(format!("d{}/f{i}.rs", i % 3)),
+                contents: lines.join("\n"),
+                ..Default::default()
+            })
+            .collect();
+        let mut pipeline = Pipeline::from_files(files);
+        pipeline
+            .build_chunks_with_header(
+                HeaderSpec { chunk_size: limit, header_last, ..Default::default() },
+                legacy_snippets,
+            )
+            .unwrap();
+        for chunk in pipeline.rendered_chunks() {
+            if chunk.text.contains("<shared-context-header") {
+                continue;
+            }
+            prop_assert!(
+                chunk.tokens <= limit || chunk.text.matches("<file-contents ").count() == 1,
+                "{} tokens over {limit}:\n{}", chunk.tokens, chunk.text
+            );
+        }
+    }
 }
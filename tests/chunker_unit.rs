@@ -1,4 +1,9 @@
-use context_gather::{chunker::*, gather::FileContents, tokenizer::count as count_tokens};
+use context_gather::config::SplitStrategy;
+use context_gather::{
+    chunker::*,
+    gather::{FileContents, FileKind},
+    tokenizer::count as count_tokens,
+};
 use std::path::PathBuf;
 
 fn make_file(
@@ -9,6 +14,7 @@ fn make_file(
         folder: PathBuf::from("."),
         path: PathBuf::from(format!("file{id}.txt")),
         contents: "tok ".repeat(repeat), // 1 token ~= "tok"
+        ..Default::default()
     }
 }
 
@@ -43,6 +49,7 @@ fn split_across_two_chunks() {
         folder: PathBuf::from("."),
         path,
         contents,
+        ..Default::default()
     };
     let (chunks, meta) = build_chunks(&[file], limit, false);
     assert!(chunks.len() >= 2);
@@ -56,6 +63,7 @@ fn oversize_file_line_split_keeps_order() {
         folder: PathBuf::from("."),
         path: PathBuf::from("big.txt"),
         contents: content.clone(),
+        ..Default::default()
     };
     let (chunks, _) = build_chunks(&[f], 50, false); // tiny token limit
     // Re-assemble lines from all chunks and compare
@@ -72,6 +80,7 @@ fn part_counts_match_output() {
         folder: PathBuf::from("."),
         path: PathBuf::from("big.txt"),
         contents: content,
+        ..Default::default()
     };
     let (chunks, meta) = build_chunks(&[f], 50, false);
     let joined: String = chunks.iter().map(|c| c.xml.clone()).collect();
@@ -101,11 +110,13 @@ fn file_paths_match_meta_parts() {
             folder: PathBuf::from("."),
             path: PathBuf::from("a.txt"),
             contents: "line\n".repeat(5),
+            ..Default::default()
         },
         FileContents {
             folder: PathBuf::from("."),
             path: PathBuf::from("big.txt"),
             contents: "line\n".repeat(200),
+            ..Default::default()
         },
     ];
     let (chunks, metas) = build_chunks(&files, 50, false);
@@ -123,3 +134,316 @@ fn file_paths_match_meta_parts() {
         );
     }
 }
+
+#[test]
+fn cdata_wraps_each_split_part() {
+    let files = vec![FileContents {
+        folder: PathBuf::from("."),
+        path: PathBuf::from("big.xml"),
+        contents: "<a>x]]>y</a>\n".repeat(60),
+        ..Default::default()
+    }];
+    let (bodies, metas) = build_chunk_bodies(
+        &files,
+        80,
+        false,
+        true,
+        ChunkOptions {
+            strategy: SplitStrategy::Lines,
+            ..Default::default()
+        },
+    );
+    assert!(metas[0].parts > 1);
+    for block in bodies.iter().flat_map(|b| &b.blocks) {
+        let start = block.xml.find("<![CDATA[").unwrap() + "<![CDATA[".len();
+        let end = block.xml.rfind("]]>").unwrap();
+        let inner = block.xml[start..end].replace("]]]]><![CDATA[>", "]]>");
+        assert_eq!(inner, block.body);
+        assert!(
+            block.xml.ends_with("]]>\n    </file-contents>\n"),
+            "{}",
+            block.xml
+        );
+    }
+}
+
+/// Part bodies of the only file in `files`, split with `strategy`.
+fn split_bodies(
+    contents: &str,
+    limit: usize,
+    strategy: SplitStrategy,
+) -> Vec<String> {
+    let files = vec![FileContents {
+        folder: PathBuf::from("."),
+        path: PathBuf::from("lib.rs"),
+        contents: contents.to_string(),
+        ..Default::default()
+    }];
+    let options = ChunkOptions {
+        strategy,
+        ..Default::default()
+    };
+    let (bodies, _) = build_chunk_bodies(&files, limit, false, false, options);
+    bodies
+        .into_iter()
+        .flat_map(|b| b.blocks)
+        .map(|b| b.body)
+        .collect()
+}
+
+#[test]
+fn smart_split_keeps_functions_whole() {
+    let contents: String = (0..12)
+        .map(|i| format!("fn f{i}() {{\n    let a = {i};\n    let b = a + 1;\n    b * 2\n}}\n\n"))
+        .collect();
+    let smart = split_bodies(&contents, 100, SplitStrategy::Smart);
+    assert!(smart.len() > 1);
+    for part in &smart {
+        assert!(part.trim_start().starts_with("fn "), "{part:?}");
+    }
+    let lines = split_bodies(&contents, 100, SplitStrategy::Lines);
+    assert!(
+        lines
+            .iter()
+            .any(|part| !part.trim_start().starts_with("fn ")),
+        "{lines:?}"
+    );
+}
+
+#[test]
+fn smart_split_breaks_before_markdown_headings() {
+    let contents: String = (0..8)
+        .map(|i| format!("## Section {i}\nSome prose for section {i}.\nMore prose here.\n"))
+        .collect();
+    for part in split_bodies(&contents, 60, SplitStrategy::Smart) {
+        assert!(part.starts_with("## Section"), "{part:?}");
+    }
+}
+
+#[test]
+fn overlapping_parts_repeat_the_previous_tail() {
+    let files = vec![FileContents {
+        folder: PathBuf::from("."),
+        path: PathBuf::from("log.txt"),
+        contents: (0..40).map(|i| format!("entry {i}\n")).collect(),
+        ..Default::default()
+    }];
+    let options = ChunkOptions {
+        strategy: SplitStrategy::Lines,
+        overlap: 3,
+        ..Default::default()
+    };
+    let (bodies, _) = build_chunk_bodies(&files, 80, false, false, options);
+    let blocks: Vec<_> = bodies.iter().flat_map(|b| &b.blocks).collect();
+    assert!(blocks.len() > 2);
+    assert_eq!(blocks[0].overlap, 0);
+    assert!(!blocks[0].xml.contains("overlap="));
+    for pair in blocks.windows(2) {
+        let mut tail: Vec<_> = pair[0].body.lines().rev().take(3).collect();
+        tail.reverse();
+        let head: Vec<_> = pair[1].body.lines().take(3).collect();
+        assert_eq!(pair[1].overlap, 3);
+        assert_eq!(head, tail);
+        assert!(pair[1].xml.contains(" overlap=\"3\">"), "{}", pair[1].xml);
+    }
+    for body in &bodies {
+        assert!(body.tokens <= 80);
+    }
+}
+
+#[test]
+fn group_by_folder_packs_a_folders_files_together() {
+    // Interleaved as `--sort size` would leave them
+    let files: Vec<FileContents> = ["src/a.rs", "tests/a.rs", "src/b.rs", "tests/b.rs"]
+        .into_iter()
+        .map(|path| {
+            let path = PathBuf::from(path);
+            FileContents {
+                folder: path.parent().unwrap().to_path_buf(),
+                path,
+                contents: "tok ".repeat(30),
+                ..Default::default()
+            }
+        })
+        .collect();
+    // Room for two blocks per chunk
+    let (whole, _) = build_chunk_bodies(&files[..1], 0, false, false, ChunkOptions::default());
+    let limit = 2 * whole[0].tokens + 5;
+    let chunk_paths = |group_by_folder| -> Vec<Vec<String>> {
+        let options = ChunkOptions {
+            group_by_folder,
+            ..Default::default()
+        };
+        let (bodies, _) = build_chunk_bodies(&files, limit, false, false, options);
+        for body in &bodies {
+            assert!(body.tokens <= limit);
+        }
+        bodies
+            .iter()
+            .map(|b| {
+                b.blocks
+                    .iter()
+                    .map(|block| files[block.file_id].path.to_string_lossy().to_string())
+                    .collect()
+            })
+            .collect()
+    };
+    assert_eq!(
+        chunk_paths(false),
+        [["src/a.rs", "tests/a.rs"], ["src/b.rs", "tests/b.rs"]]
+    );
+    assert_eq!(
+        chunk_paths(true),
+        [["src/a.rs", "src/b.rs"], ["tests/a.rs", "tests/b.rs"]]
+    );
+}
+
+#[test]
+fn parallel_counting_matches_sequential_metas() {
+    // Sizes vary so threads finish out of order
+    let files: Vec<FileContents> = (0..400).map(|i| make_file(i, 1 + (i * 37) % 300)).collect();
+    let expected: Vec<(usize, PathBuf, usize)> = files
+        .iter()
+        .enumerate()
+        .map(|(id, f)| (id, f.path.clone(), count_tokens(&f.contents)))
+        .collect();
+    let summary = |metas: Vec<FileMeta>| -> Vec<(usize, PathBuf, usize)> {
+        metas
+            .into_iter()
+            .map(|m| (m.id, m.path, m.tokens))
+            .collect()
+    };
+    assert_eq!(summary(build_file_meta(&files, false)), expected);
+    let (bodies, metas) = build_chunk_bodies(&files, 200, false, false, ChunkOptions::default());
+    assert_eq!(summary(metas), expected);
+    // Blocks are packed in file order, parts in part order
+    let order: Vec<(usize, Option<(usize, usize)>)> = bodies
+        .iter()
+        .flat_map(|b| b.blocks.iter().map(|block| (block.file_id, block.part)))
+        .collect();
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(order, sorted);
+}
+
+/// Files of every kind: small ones, a stub, a binary attachment and one
+/// big enough to split, across two folders.
+fn plan_fixture() -> Vec<FileContents> {
+    let big: String = (0..80)
+        .map(|i| format!("fn f{i}() {{ if a < b && c > d {{ go({i}); }} }}\n"))
+        .collect();
+    let mut files = vec![
+        FileContents {
+            folder: PathBuf::from("src"),
+            path: PathBuf::from("src/big.rs"),
+            contents: big,
+            ..Default::default()
+        },
+        FileContents {
+            folder: PathBuf::from("src"),
+            path: PathBuf::from("src/schema.sql"),
+            contents: "CREATE TABLE t (id INT);\n".into(),
+            stubbed: true,
+            ..Default::default()
+        },
+        FileContents {
+            folder: PathBuf::from("assets"),
+            path: PathBuf::from("assets/logo.png"),
+            kind: FileKind::Binary {
+                size: 2048,
+                sha256: "ab".repeat(32),
+            },
+            ..Default::default()
+        },
+    ];
+    for i in 0..6 {
+        let folder = if i % 2 == 0 { "src" } else { "tests" };
+        files.push(FileContents {
+            folder: PathBuf::from(folder),
+            path: PathBuf::from(format!("{folder}/small_{i}.rs")),
+            contents: "tok ".repeat(20 + 15 * i),
+            ..Default::default()
+        });
+    }
+    files
+}
+
+/// Each chunk's size, with `(file_id, part, tokens)` for each block.
+type Layout = Vec<(usize, Vec<(usize, Option<(usize, usize)>, usize)>)>;
+
+#[test]
+fn plan_matches_built_chunks() {
+    let files = plan_fixture();
+    let layout = |plans: &[ChunkPlan]| -> Layout {
+        plans
+            .iter()
+            .map(|c| {
+                let blocks = c
+                    .blocks
+                    .iter()
+                    .map(|b| (b.file_id, b.part, b.tokens))
+                    .collect();
+                (c.tokens, blocks)
+            })
+            .collect()
+    };
+    let built = |bodies: &[ChunkBody]| -> Layout {
+        bodies
+            .iter()
+            .map(|c| {
+                let blocks = c
+                    .blocks
+                    .iter()
+                    .map(|b| {
+                        assert_eq!(b.tokens, count_tokens(&b.xml));
+                        (b.file_id, b.part, b.tokens)
+                    })
+                    .collect();
+                (c.tokens, blocks)
+            })
+            .collect()
+    };
+    let option_sets = [
+        ChunkOptions::default(),
+        ChunkOptions {
+            strategy: SplitStrategy::Smart,
+            overlap: 2,
+            ..Default::default()
+        },
+        ChunkOptions {
+            group_by_folder: true,
+            ..Default::default()
+        },
+    ];
+    for limit in [0, 150, 400] {
+        for options in option_sets {
+            for (escape_xml, cdata) in [(false, false), (true, false), (false, true)] {
+                let (plans, plan_metas) =
+                    plan_chunk_bodies(&files, limit, escape_xml, cdata, options);
+                let (bodies, metas) = build_chunk_bodies(&files, limit, escape_xml, cdata, options);
+                let split = plans
+                    .iter()
+                    .flat_map(|c| &c.blocks)
+                    .any(|b| b.part.is_some());
+                assert_eq!(split, limit > 0);
+                assert_eq!(layout(&plans), built(&bodies), "limit {limit} {options:?}");
+                let parts =
+                    |m: Vec<FileMeta>| m.into_iter().map(|m| (m.id, m.parts)).collect::<Vec<_>>();
+                assert_eq!(parts(plan_metas), parts(metas));
+            }
+            let (plans, _) = plan_plain_chunk_bodies(&files, limit, "=====", options);
+            let (bodies, _) = build_plain_chunk_bodies(&files, limit, "=====", options);
+            assert_eq!(
+                layout(&plans),
+                built(&bodies),
+                "plain, limit {limit} {options:?}"
+            );
+        }
+    }
+    let plans = plan_chunks(&files, 150, false);
+    let (chunks, _) = build_chunks(&files, 150, false);
+    assert!(plans.len() > 1);
+    let plan_tokens: Vec<usize> = plans.iter().map(|p| p.tokens).collect();
+    let chunk_tokens: Vec<usize> = chunks.iter().map(|c| c.tokens).collect();
+    assert_eq!(plan_tokens, chunk_tokens);
+}
@@ -0,0 +1,152 @@
+#![cfg(feature = "archive")]
+
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+use std::io::Write;
+
+/// A small codebase zipped into `code.zip`.
+fn zipped(dir: &assert_fs::TempDir) {
+    let file = std::fs::File::create(dir.child("code.zip").path()).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, bytes) in [
+        ("src/main.rs", b"fn main() {}\n".as_slice()),
+        ("src/util/mod.rs", b"pub fn util() {}\n"),
+        ("logs/run.log", b"noise\n"),
+        (
+            "assets/logo.png",
+            &[0x89, b'P', b'N', b'G', 0, 0, 0, 0x0d, 0, 0],
+        ),
+        ("big.txt", &[b'x'; 4096]),
+        (".git/config", b"[core]\n"),
+        ("../evil.txt", b"escaped\n"),
+    ] {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(bytes).unwrap();
+    }
+    zip.add_directory("empty/", options).unwrap();
+    zip.finish().unwrap();
+}
+
+fn gather(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> (String, String) {
+    common::run_ok(dir, &[&["--max-size", "1024"], args].concat())
+}
+
+#[test]
+fn zip_entries_are_gathered_under_the_archive_name() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    zipped(&dir);
+    let (stdout, stderr) = gather(&dir, &["code.zip"]);
+    assert!(
+        stdout.contains("<file-contents path=\"code.zip/src/main.rs\""),
+        "{stdout}"
+    );
+    assert!(stdout.contains("fn main() {}"), "{stdout}");
+    assert!(
+        stdout.contains("path=\"code.zip/src/util/mod.rs\""),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("path=\"code.zip/logs/run.log\""),
+        "{stdout}"
+    );
+    // Binary detection and --max-size apply to entries as to files on disk
+    assert!(!stdout.contains("logo.png"), "{stdout}");
+    assert!(stderr.contains("appears to be a binary file"), "{stderr}");
+    assert!(!stdout.contains("big.txt"), "{stdout}");
+    assert!(stderr.contains("exceeds 1024 bytes"), "{stderr}");
+    // Hidden entries are skipped like hidden files, and escaping names never read
+    assert!(!stdout.contains(".git/config"), "{stdout}");
+    assert!(!stdout.contains("escaped"), "{stdout}");
+    assert!(stderr.contains("points outside the archive"), "{stderr}");
+}
+
+#[test]
+fn excludes_apply_to_archive_entries() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    zipped(&dir);
+    let excludes = [
+        "--exclude-paths",
+        "**/*.log",
+        "--exclude-paths",
+        "code.zip/src/util/**",
+    ];
+    let (stdout, _) = gather(&dir, &[&["code.zip"], excludes.as_slice()].concat());
+    assert!(stdout.contains("path=\"code.zip/src/main.rs\""), "{stdout}");
+    assert!(!stdout.contains("run.log"), "{stdout}");
+    assert!(!stdout.contains("util/mod.rs"), "{stdout}");
+}
+
+#[test]
+fn tar_gz_archives_are_read_too() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let file = std::fs::File::create(dir.child("code.tar.gz").path()).unwrap();
+    let gz = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut tar = tar::Builder::new(gz);
+    let body = b"print('hi')\n";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(body.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(1_700_000_000);
+    header.set_cksum();
+    tar.append_data(&mut header, "pkg/app.py", body.as_slice())
+        .unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let (stdout, _) = gather(&dir, &["code.tar.gz"]);
+    assert!(
+        stdout.contains("<file-contents path=\"code.tar.gz/pkg/app.py\""),
+        "{stdout}"
+    );
+    assert!(stdout.contains("print('hi')"), "{stdout}");
+    assert!(
+        stdout.contains("mtime=\"2023-11-14T22:13:20Z\""),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn oversized_entries_are_skipped_or_truncated_without_reading_them_whole() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let file = std::fs::File::create(dir.child("bomb.zip").path()).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+    zip.start_file("small.txt", options).unwrap();
+    zip.write_all(b"small\n").unwrap();
+    zip.start_file("huge.txt", options).unwrap();
+    let line = [b"x".repeat(1023), b"\n".to_vec()].concat();
+    for _ in 0..8 * 1024 {
+        zip.write_all(&line).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let (stdout, stderr) = gather(&dir, &["bomb.zip", "--list-skipped"]);
+    assert!(
+        stdout.contains(r#"<skipped-file path="bomb.zip/huge.txt" reason="too-large"/>"#),
+        "{stdout}"
+    );
+    assert!(stderr.contains("exceeds 1024 bytes"), "{stderr}");
+
+    let (stdout, _) = gather(&dir, &["bomb.zip", "--truncate-oversize"]);
+    assert!(stdout.contains("path=\"bomb.zip/huge.txt\""), "{stdout}");
+    assert!(stdout.contains(&"x".repeat(1023)), "{stdout}");
+    assert!(!stdout.contains(&"x\nx".repeat(2)), "{stdout}");
+}
+
+#[test]
+fn an_unreadable_archive_is_an_error() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("broken.zip").write_str("not a zip").unwrap();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "broken.zip"])
+        .assert()
+        .failure()
+        .stderr(contains("cannot read archive broken.zip"));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use assert_fs::prelude::*;
+use common::run_ok;
+
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/lib.rs")
+        .write_str("pub fn lib() {}\n")
+        .unwrap();
+    dir.child("src/nested/mod.rs")
+        .write_str("pub fn nested() {}\n")
+        .unwrap();
+    dir.child("src/settings.toml")
+        .write_str("[settings]\n")
+        .unwrap();
+    dir.child("src/notes.md").write_str("# notes\n").unwrap();
+    dir
+}
+
+#[test]
+fn one_argument_gathers_every_brace_alternative() {
+    let dir = project();
+    let (stdout, stderr) = run_ok(&dir, &["src/**/*.{rs,toml}"]);
+    assert!(stdout.contains("pub fn lib()"), "{stdout}");
+    assert!(stdout.contains("pub fn nested()"), "{stdout}");
+    assert!(stdout.contains("[settings]"), "{stdout}");
+    assert!(!stdout.contains("# notes"), "{stdout}");
+    assert!(stderr.contains("OK 3 files"), "{stderr}");
+}
+
+#[test]
+fn alternatives_without_matches_are_dropped() {
+    let dir = project();
+    let (stdout, stderr) = run_ok(&dir, &["src/*.{toml,yaml}"]);
+    assert!(stdout.contains("[settings]"), "{stdout}");
+    assert!(stderr.contains("OK 1 files"), "{stderr}");
+    assert!(!stderr.contains("yaml"), "{stderr}");
+}
+
+#[test]
+fn braces_work_in_exclude_patterns() {
+    let dir = project();
+    let (stdout, _) = run_ok(&dir, &["--exclude-paths", "**/*.{md,toml}", "src"]);
+    assert!(stdout.contains("pub fn lib()"), "{stdout}");
+    assert!(!stdout.contains("[settings]"), "{stdout}");
+    assert!(!stdout.contains("# notes"), "{stdout}");
+}
@@ -0,0 +1,95 @@
+mod common;
+use assert_fs::prelude::*;
+use std::process::Command;
+
+/// A git repository whose snapshots are committed but excluded from context
+/// by `.cgignore`, and whose fixtures are excluded by a ripgrep-style
+/// `.ignore`. `None` when git is unavailable.
+fn repo() -> Option<assert_fs::TempDir> {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/lib.rs")
+        .write_str("pub fn lib() {}\n")
+        .unwrap();
+    dir.child("snapshots/render.snap")
+        .write_str("snapshot body\n")
+        .unwrap();
+    dir.child("snapshots/nested/deep.snap")
+        .write_str("deep snapshot\n")
+        .unwrap();
+    dir.child("fixtures/input.json")
+        .write_str("{\"fixture\": 1}\n")
+        .unwrap();
+    dir.child(".cgignore").write_str("snapshots/\n").unwrap();
+    dir.child(".ignore").write_str("fixtures/\n").unwrap();
+    for args in [&["init", "--quiet"][..], &["add", "--all"]] {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .status();
+        if !matches!(status, Ok(status) if status.success()) {
+            return None;
+        }
+    }
+    Some(dir)
+}
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> String {
+    common::run_ok(dir, &[&["--allow-empty"], args].concat()).0
+}
+
+#[test]
+fn cgignore_excludes_files_that_git_still_tracks() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let tracked = Command::new("git")
+        .args(["ls-files", "snapshots"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&tracked.stdout).contains("snapshots/render.snap"));
+
+    let stdout = run(&dir, &["."]);
+    assert!(stdout.contains("path=\"src/lib.rs\""), "{stdout}");
+    assert!(!stdout.contains("snapshot body"), "{stdout}");
+    assert!(!stdout.contains("fixture"), "{stdout}");
+}
+
+#[test]
+fn cgignore_applies_to_directory_arguments() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    for arg in ["snapshots", "snapshots/nested"] {
+        let stdout = run(&dir, &[arg]);
+        assert!(!stdout.contains("snapshot"), "{arg}: {stdout}");
+    }
+}
+
+#[test]
+fn no_cgignore_brings_the_files_back() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let stdout = run(&dir, &["--no-cgignore", "."]);
+    assert!(stdout.contains("snapshot body"), "{stdout}");
+    assert!(stdout.contains("deep snapshot"), "{stdout}");
+    // `.ignore` still applies
+    assert!(!stdout.contains("fixture"), "{stdout}");
+
+    let stdout = run(&dir, &["--no-cgignore", "snapshots/nested"]);
+    assert!(stdout.contains("deep snapshot"), "{stdout}");
+}
+
+#[test]
+fn no_ignore_also_drops_cgignore() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let stdout = run(&dir, &["--no-ignore", "."]);
+    assert!(stdout.contains("snapshot body"), "{stdout}");
+    assert!(stdout.contains("fixture"), "{stdout}");
+}
@@ -0,0 +1,134 @@
+use assert_fs::prelude::*;
+use predicates::str::contains;
+use serde_json::Value;
+
+/// About 10 KB of short lines.
+fn ten_kb() -> String {
+    (0..400)
+        .map(|i| format!("entry {i:04} = \"value\";\n"))
+        .collect()
+}
+
+/// Each emitted chunk: the header before the first `<context-chunk`, then
+/// one snippet per chunk.
+fn snippets(stdout: &str) -> Vec<String> {
+    let mut parts = stdout.split("<context-chunk id=\"");
+    let mut out = vec![parts.next().unwrap().to_string()];
+    out.extend(parts.map(|p| format!("<context-chunk id=\"{p}")));
+    out
+}
+
+#[test]
+fn byte_unit_bounds_every_chunk_in_bytes() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("data.txt").write_str(&ten_kb()).unwrap();
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--chunk-unit",
+            "bytes",
+            "-c",
+            "2000",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stderr(contains(" chunks (bytes) • "));
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    let snippets = snippets(&stdout);
+    assert!(snippets.len() > 5, "{stdout}");
+    for snippet in &snippets {
+        assert!(snippet.len() <= 2000, "{} bytes", snippet.len());
+    }
+    assert!(stdout.contains(" chunk-size=\"2000\" chunk-unit=\"bytes\" total-bytes=\""));
+    assert!(!stdout.contains("chunk-tokens=\""));
+}
+
+#[test]
+fn char_unit_counts_characters_not_bytes() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("notes.txt")
+        .write_str(&"héllo wörld ✓ ünïcödé\n".repeat(200))
+        .unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--chunk-unit",
+            "chars",
+            "-c",
+            "1500",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    let snippets = snippets(&stdout);
+    assert!(snippets.len() > 2);
+    for snippet in &snippets {
+        assert!(snippet.chars().count() <= 1500);
+    }
+    // Multi-byte text: some chunk holds more bytes than the char limit
+    assert!(snippets.iter().any(|s| s.len() > 1500));
+}
+
+#[test]
+fn summary_json_reports_the_unit() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("data.txt").write_str(&ten_kb()).unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--no-clipboard",
+            "--summary-json=stdout",
+            "-k",
+            "-1",
+            "--chunk-unit",
+            "bytes",
+            "-c",
+            "2000",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let summary: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(summary["chunk_unit"], "bytes");
+    assert!(summary["chunks"].as_u64().unwrap() > 5);
+    // Still real tokens, not bytes
+    assert!(summary["total_tokens"].as_u64().unwrap() < 10_000);
+}
+
+#[test]
+fn non_token_units_reject_token_based_sizing() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("data.txt").write_str("hello\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--chunk-unit",
+            "chars",
+            "--multi-step",
+            ".",
+        ])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(contains(
+            "--chunk-unit chars cannot be combined with --multi-step",
+        ));
+}
@@ -54,7 +54,14 @@ fn zero_files_outputs_header_with_closing_tag() {
 
     assert_cmd::cargo::cargo_bin_cmd!("context-gather")
         .current_dir(&dir)
-        .args(["--stdout", "--no-clipboard", "-c", "50", "."])
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--allow-empty",
+            "-c",
+            "50",
+            ".",
+        ])
         .assert()
         .success()
         .stdout(contains("</shared-context>"))
@@ -63,6 +70,91 @@ fn zero_files_outputs_header_with_closing_tag() {
         .stderr(contains("OK"));
 }
 
+#[test]
+fn snippets_carry_stable_index_total_kind_attributes() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..3 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(100))
+            .unwrap();
+    }
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "-c", "200", "."])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8_lossy(&output);
+    let total = stdout.matches("<context-chunk ").count() + 1;
+    assert!(total > 2, "expected several chunks");
+    assert!(stdout.contains(&format!(
+        "<shared-context-header version=\"1\" index=\"0\" total=\"{total}\" kind=\"header\""
+    )));
+    for idx in 1..total {
+        assert!(stdout.contains(&format!(
+            "<context-chunk id=\"{idx}/{total}\" index=\"{idx}\" total=\"{total}\" kind=\"body\">"
+        )));
+    }
+}
+
+/// `index` attribute values of the header and body tags, in emission order.
+fn emitted_indices(stdout: &str) -> Vec<usize> {
+    stdout
+        .lines()
+        .filter(|l| l.starts_with("<shared-context-header ") || l.starts_with("<context-chunk "))
+        .map(|l| {
+            let start = l.find(" index=\"").unwrap() + " index=\"".len();
+            let end = start + l[start..].find('"').unwrap();
+            l[start..end].parse().unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn reverse_chunk_output_emits_last_chunk_first() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..3 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(100))
+            .unwrap();
+    }
+    let run = |extra: &[&str]| {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args(["--stdout", "--no-clipboard", "-c", "200"])
+            .args(extra)
+            .arg(".")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    let forward = run(&[]);
+    let reversed = run(&["--reverse-chunk-output"]);
+    let order = emitted_indices(&forward);
+    assert!(order.len() > 2, "expected several chunks");
+    let mut expected = order.clone();
+    expected.reverse();
+    assert_eq!(emitted_indices(&reversed), expected);
+
+    // Same snippets, only the physical order differs
+    let total = order.len();
+    assert!(reversed.contains(&format!(
+        "<context-chunk id=\"{0}/{total}\" index=\"{0}\" total=\"{total}\" kind=\"body\">",
+        total - 1
+    )));
+    assert_eq!(forward.len(), reversed.len());
+    // The header chunk, with its logical <more> marker, now comes last
+    let more = format!("<more remaining=\"{}\"", total - 1);
+    assert!(reversed.lines().last().unwrap().starts_with(&more));
+}
+
 #[test]
 fn chunk_index_selects_single_stdout_chunk() {
     let dir = assert_fs::TempDir::new().unwrap();
@@ -94,6 +186,53 @@ fn chunk_index_selects_single_stdout_chunk() {
     );
 }
 
+#[test]
+fn chunk_index_accepts_lists_and_ranges() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..10 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(100))
+            .unwrap();
+    }
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "-c", "50", "-k", "1,3-4", "."])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8_lossy(&output);
+    assert_eq!(stdout.matches("<context-chunk id=").count(), 3);
+    for idx in [1, 3, 4] {
+        assert!(stdout.contains(&format!("<context-chunk id=\"{idx}/")));
+    }
+    assert!(!stdout.contains("<context-chunk id=\"2/"));
+}
+
+#[test]
+fn chunk_index_list_rejects_out_of_range_member() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str("hello").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "-c",
+            "500",
+            "-k",
+            "0,97-99",
+            ".",
+        ])
+        .assert()
+        .code(3)
+        .stdout(is_empty())
+        .stderr(contains("--chunk-index 97 out of range"));
+}
+
 #[test]
 fn chunk_index_none_suppresses_stdout() {
     let dir = assert_fs::TempDir::new().unwrap();
@@ -134,3 +273,481 @@ fn streaming_exits_on_stdin_eof() {
         .stdout(contains("<shared-context>"))
         .stderr(contains("stdin closed; leaving streaming mode."));
 }
+
+#[test]
+fn more_marker_hints_remaining_tokens() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..4 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(100))
+            .unwrap();
+    }
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "-c", "200", "."])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::is_match(r#"<more remaining="\d+" remaining-tokens="[1-9]\d*"/>"#)
+                .unwrap(),
+        );
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--legacy-snippets",
+            "-c",
+            "200",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("<more remaining=\""))
+        .stdout(contains("remaining-tokens").not());
+}
+
+#[test]
+fn streaming_survives_binary_stdin() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("foo.txt").write_str("hello world").unwrap();
+
+    let blob: Vec<u8> = (0..=255u8).cycle().take(2048).collect();
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("context-gather");
+    cmd.current_dir(&dir)
+        .args([
+            "--stream",
+            "--chunk-size",
+            "100",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .write_stdin(blob)
+        .timeout(Duration::from_secs(2));
+    cmd.assert()
+        .success()
+        .stderr(contains("Unrecognized input (not valid UTF-8)"))
+        .stderr(contains("stdin closed; leaving streaming mode."));
+}
+
+/// Check that `xml` is one balanced `<shared-context>` element; contents must
+/// be escaped so every `<` starts a tag.
+fn assert_single_balanced_root(xml: &str) {
+    let mut stack: Vec<&str> = Vec::new();
+    let mut roots = 0;
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        let end = start + rest[start..].find('>').expect("unterminated tag");
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            assert_eq!(stack.pop(), Some(name), "mismatched </{name}>");
+        } else if !tag.ends_with('/') {
+            if stack.is_empty() {
+                roots += 1;
+            }
+            stack.push(tag.split_whitespace().next().unwrap());
+        }
+    }
+    assert!(stack.is_empty(), "unclosed tags: {stack:?}");
+    assert_eq!(roots, 1);
+    assert!(xml.starts_with("<shared-context>\n"));
+    assert!(xml.ends_with("</shared-context>\n"));
+}
+
+#[test]
+fn header_position_orders_chunks_and_stays_well_formed() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..4 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok <b>\n".repeat(60))
+            .unwrap();
+    }
+
+    for position in ["first", "last"] {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args([
+                "--stdout",
+                "--no-clipboard",
+                "--escape-xml",
+                "--header-position",
+                position,
+                "-c",
+                "300",
+                ".",
+            ])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let stdout = String::from_utf8_lossy(&output);
+        assert_single_balanced_root(&stdout);
+
+        let bodies = stdout.matches("<context-chunk ").count();
+        let total = bodies + 1;
+        assert!(bodies > 1, "expected several body chunks");
+        let header_index = if position == "first" { 0 } else { bodies };
+        assert!(stdout.contains(&format!(
+            "<shared-context-header version=\"1\" index=\"{header_index}\" total=\"{total}\" kind=\"header\""
+        )));
+        let more = format!("<more remaining=\"{}\"", total - 1);
+        assert_eq!(stdout.matches("<more ").count(), 1);
+        assert!(stdout.contains(&more));
+        let header_at = stdout.find("<shared-context-header").unwrap();
+        let first_body_at = stdout.find("<context-chunk ").unwrap();
+        assert_eq!(position == "first", header_at < first_body_at);
+        assert!(stdout.find(&more).unwrap() < stdout.find("<context-chunk id=\"1/").unwrap());
+    }
+}
+
+#[test]
+fn header_position_last_moves_file_map_without_chunking() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str("alpha").unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--header-position",
+            "last",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8_lossy(&output);
+    assert_single_balanced_root(&stdout);
+    assert!(stdout.find("</folder>").unwrap() < stdout.find("<file-map").unwrap());
+}
+
+#[test]
+fn suggest_chunk_size_prints_recommendation_without_output() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..10 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"word ".repeat(1000))
+            .unwrap();
+    }
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--suggest-chunk-size", "--model-context", "5000", "."])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("suggested: --chunk-size "))
+        .stdout(contains("ceiling 4000"))
+        .stdout(contains("<shared-context").not());
+
+    // 80% of 1250 rounds up to the 1,000-token floor, which needs > 8 chunks.
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--suggest-chunk-size", "--model-context", "1250", "."])
+        .assert()
+        .success()
+        .stdout(contains("ceiling 1000"))
+        .stderr(contains("consider narrowing the selection"));
+}
+
+#[test]
+fn auto_chunk_size_chunks_with_the_suggestion() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..10 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"word ".repeat(1000))
+            .unwrap();
+    }
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--auto-chunk-size",
+            "--model-context",
+            "5000",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout(contains(r#"index="3""#))
+        .stderr(contains("auto: --chunk-size "));
+}
+
+#[test]
+fn line_numbers_stay_absolute_across_split_parts() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let contents: String = (1..=30).map(|n| format!("line {n} & more\n")).collect();
+    dir.child("big.txt").write_str(&contents).unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--line-numbers",
+            "--escape-xml",
+            "-c",
+            "80",
+            "big.txt",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains(" 1: line 1 &amp; more\n"), "{stdout}");
+    assert!(stdout.contains("30: line 30 &amp; more\n"), "{stdout}");
+    let second = stdout
+        .split("part=\"2/")
+        .nth(1)
+        .expect("file should be split into parts");
+    let first_line = second.lines().nth(1).unwrap();
+    let number: usize = first_line
+        .split(':')
+        .next()
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert!(number > 1, "{first_line}");
+}
+
+#[test]
+fn instructions_file_replaces_header_instructions() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.rs").write_str("fn a() {}\n").unwrap();
+    dir.child("prompt.txt")
+        .write_str("Answer in French.\n")
+        .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--chunk-size",
+            "1000",
+            "--instructions-file",
+            "prompt.txt",
+            "--stdout",
+            "--no-clipboard",
+            "a.rs",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("  <instructions>\n    Answer in French.\n"))
+        .stdout(contains("READY").not());
+}
+
+#[test]
+fn minified_line_is_wrapped_instead_of_oversize() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let line: String = (0..400).map(|i| format!("var a{i}=f({i});")).collect();
+    dir.child("bundle.min.js")
+        .write_str(&format!("{line}\n"))
+        .unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "-c", "400", "."])
+        .assert()
+        .success()
+        .stderr(contains("exceed").not())
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains(" wrapped=\"true\">"), "{stdout}");
+    for snippet in stdout.split("<context-chunk id=\"").skip(1) {
+        assert!(count_tokens(snippet) <= 400);
+    }
+    // Wrapped parts end mid-line; their closing newline is not in the file
+    let mut joined = String::new();
+    for block in stdout.split("<file-contents ").skip(1) {
+        let (tag, rest) = block.split_once(">\n").unwrap();
+        let body = &rest[..rest.find("    </file-contents>").unwrap()];
+        joined.push_str(if tag.contains("wrapped=\"true\"") {
+            body.strip_suffix('\n').unwrap()
+        } else {
+            body
+        });
+    }
+    assert_eq!(joined.trim_end(), line);
+}
+
+/// Value of attribute `name` in the tag starting `tag`.
+fn attr_of(
+    tag: &str,
+    name: &str,
+) -> Option<String> {
+    let start = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let len = tag[start..].find('"')?;
+    Some(tag[start..start + len].to_string())
+}
+
+#[test]
+fn file_map_chunk_attributes_match_chunk_contents() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..4 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(40))
+            .unwrap();
+    }
+    let big: String = (0..60)
+        .map(|i| format!("let v{i} = compute({i});\n"))
+        .collect();
+    dir.child("src/big.rs").write_str(&big).unwrap();
+
+    for position in ["first", "last"] {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args([
+                "--stdout",
+                "--no-clipboard",
+                "--header-position",
+                position,
+                "-c",
+                "300",
+                ".",
+            ])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let stdout = String::from_utf8_lossy(&output);
+
+        // (path, part, chunk) as the file map declares them
+        let mut declared = Vec::new();
+        for entry in stdout
+            .lines()
+            .filter(|l| l.trim_start().starts_with("<file id="))
+        {
+            let path = attr_of(entry, "path").unwrap();
+            let parts: usize = attr_of(entry, "parts").unwrap().parse().unwrap();
+            let chunks = attr_of(entry, "chunk").unwrap();
+            let chunks: Vec<usize> = chunks.split(',').map(|c| c.parse().unwrap()).collect();
+            assert_eq!(chunks.len(), parts, "{entry}");
+            for (part, chunk) in chunks.into_iter().enumerate() {
+                declared.push((
+                    path.clone(),
+                    (parts > 1).then(|| format!("{}/{parts}", part + 1)),
+                    chunk,
+                ));
+            }
+        }
+        // ... and as the chunks hold them
+        let mut actual = Vec::new();
+        for chunk in stdout.split("<context-chunk ").skip(1) {
+            let index: usize = attr_of(chunk, "index").unwrap().parse().unwrap();
+            for block in chunk.split("<file-contents ").skip(1) {
+                let tag = format!(" {}", &block[..block.find('>').unwrap()]);
+                actual.push((attr_of(&tag, "path").unwrap(), attr_of(&tag, "part"), index));
+            }
+        }
+        assert!(
+            declared.iter().any(|(_, part, _)| part.is_some()),
+            "{stdout}"
+        );
+        declared.sort();
+        actual.sort();
+        assert_eq!(declared, actual, "header {position}");
+    }
+}
+
+#[test]
+fn streaming_lists_each_chunks_files() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str(&"tok\n".repeat(40)).unwrap();
+    dir.child("b.txt").write_str(&"tok\n".repeat(40)).unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stream",
+            "--chunk-size",
+            "200",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .write_stdin("1\n2\n")
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success()
+        .stderr(contains("Chunk 1 files: a.txt\n"))
+        .stderr(contains("Chunk 2 files: b.txt\n"))
+        .stderr(contains("Chunk 0 files").not());
+}
+
+#[test]
+fn chunk_headroom_builds_smaller_chunks_under_the_same_declared_size() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..8 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(60))
+            .unwrap();
+    }
+    let chunk_sizes = |headroom: &str| {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args([
+                "--stdout",
+                "--no-clipboard",
+                "-c",
+                "400",
+                "--chunk-headroom",
+                headroom,
+                ".",
+            ])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let stdout = String::from_utf8_lossy(&output).into_owned();
+        assert!(stdout.contains(" chunk-size=\"400\""), "{stdout}");
+        let sizes: Vec<usize> = stdout
+            .split("<context-chunk id=\"")
+            .skip(1)
+            .map(|part| count_tokens(&format!("<context-chunk id=\"{part}")))
+            .collect();
+        sizes
+    };
+
+    let full = chunk_sizes("0");
+    let reduced = chunk_sizes("20");
+    assert!(full.iter().all(|&t| t <= 400), "{full:?}");
+    assert!(reduced.iter().all(|&t| t <= 320), "{reduced:?}");
+    assert!(
+        full.iter().max() > reduced.iter().max(),
+        "{full:?} vs {reduced:?}"
+    );
+    assert!(reduced.len() > full.len(), "{full:?} vs {reduced:?}");
+}
+
+#[test]
+fn chunk_headroom_of_100_percent_is_rejected() {
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "-c",
+            "400",
+            "--chunk-headroom",
+            "100",
+            ".",
+        ])
+        .assert()
+        .code(2)
+        .stderr(contains("--chunk-headroom"));
+}
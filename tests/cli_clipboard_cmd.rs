@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+#[test]
+fn clipboard_cmd_receives_the_full_output() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    // Several megabytes, well past any pipe buffer
+    let big = "let x = 1; // filler line for a large payload\n".repeat(80_000);
+    dir.child("big.rs").write_str(&big).unwrap();
+    dir.child("small.rs").write_str("fn small() {}\n").unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-model-context",
+            "--max-size",
+            "10000000",
+            "--clipboard-cmd",
+            "cat > copied.txt",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stderr(contains("copied=0"))
+        .get_output()
+        .clone();
+
+    let copied = std::fs::read(dir.child("copied.txt").path()).unwrap();
+    assert!(copied.len() > 3_000_000);
+    assert_eq!(copied, output.stdout);
+}
+
+#[test]
+fn failing_clipboard_cmd_falls_back_like_a_provider_failure() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str("hello").unwrap();
+
+    // With another sink, a failed copy is only a warning
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--clipboard-cmd", "exit 3", "a.txt"])
+        .assert()
+        .success()
+        .stdout(contains("hello"))
+        .stderr(contains("clipboard unavailable: --clipboard-cmd"));
+
+    // As the only sink, it is an error
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--clipboard-cmd", "exit 3", "a.txt"])
+        .assert()
+        .failure()
+        .stderr(contains("re-run with --stdout or --no-clipboard"));
+}
+
+#[test]
+fn clipboard_cmd_from_config_file() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str("from config").unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("clipboard_cmd = \"cat > copied.txt\"\n")
+        .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("XDG_CONFIG_HOME", dir.path())
+        .args(["a.txt"])
+        .assert()
+        .success();
+    let copied = std::fs::read_to_string(dir.child("copied.txt").path()).unwrap();
+    assert!(copied.contains("from config"));
+}
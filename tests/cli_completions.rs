@@ -0,0 +1,50 @@
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+#[test]
+fn completions_emit_script_for_each_shell() {
+    for (shell, marker) in [
+        ("zsh", "#compdef context-gather"),
+        ("bash", "complete -F _context__gather"),
+        ("fish", "complete -c context-gather -n"),
+        ("powershell", "Register-ArgumentCompleter"),
+    ] {
+        assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .args(["completions", shell])
+            .assert()
+            .success()
+            .stdout(contains(marker))
+            .stdout(contains("--chunk-size"));
+    }
+}
+
+#[test]
+fn completions_require_a_known_shell() {
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .args(["completions", "tcsh"])
+        .assert()
+        .code(2)
+        .stderr(contains("invalid value 'tcsh'"));
+}
+
+#[test]
+fn paths_still_gather_without_a_subcommand() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/lib.rs").write_str("fn lib() {}\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "."])
+        .assert()
+        .success()
+        .stdout(contains("fn lib() {}"));
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["src/", "-c", "50", "--stdout", "--no-clipboard"])
+        .assert()
+        .success()
+        .stdout(contains("fn lib() {}"))
+        .stdout(contains("#compdef").not());
+}
@@ -0,0 +1,220 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("keep.rs")
+        .write_str("fn keep() { 1 < 2; }\n")
+        .unwrap();
+    dir.child("gen.rs")
+        .write_str("fn generated() {}\n")
+        .unwrap();
+    dir
+}
+
+fn run(
+    dir: &assert_fs::TempDir,
+    home: &assert_fs::TempDir,
+    args: &[&str],
+) -> assert_cmd::assert::Assert {
+    common::context_gather(dir)
+        .env("XDG_CONFIG_HOME", home.path())
+        .env_remove("CG_TOKENIZER_MODEL")
+        .args(args)
+        .arg(".")
+        .assert()
+}
+
+fn stdout_of(assert: assert_cmd::assert::Assert) -> String {
+    common::output_of(assert).0
+}
+
+#[test]
+fn partial_project_config_applies_only_its_keys() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("exclude = [\"gen.rs\"]\nescape_xml = true\n")
+        .unwrap();
+
+    let stdout = stdout_of(run(&dir, &home, &[]));
+    assert!(!stdout.contains("generated"));
+    assert!(stdout.contains("1 &lt; 2"));
+    assert!(!stdout.contains("<context-chunk"));
+}
+
+#[test]
+fn cli_values_take_precedence_over_config() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("max_size = 1\nchunk_size = 100000\n")
+        .unwrap();
+
+    // max_size = 1 skips every file unless the CLI overrides it
+    let stdout = stdout_of(run(&dir, &home, &["--allow-empty"]));
+    assert!(stdout.contains("chunk-size=\"100000\""));
+    assert!(!stdout.contains("fn keep()"));
+
+    let stdout = stdout_of(run(&dir, &home, &["--max-size", "4096", "-c", "300"]));
+    assert!(stdout.contains("fn keep()"));
+    assert!(stdout.contains("chunk-size=\"300\""));
+}
+
+#[test]
+fn cli_turns_off_switches_the_config_turns_on() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("escape_xml = true\ngit_info = true\n")
+        .unwrap();
+    let printed = |args: &[&str]| {
+        let args = [args, &["--print-config"]].concat();
+        common::output_of(run(&dir, &home, &args)).1
+    };
+
+    let config = printed(&[]);
+    assert!(config.contains("\"escape_xml\": true"), "{config}");
+    assert!(config.contains("\"git_info\": true"), "{config}");
+    let config = printed(&["--no-escape-xml", "--no-git-info"]);
+    assert!(config.contains("\"escape_xml\": false"), "{config}");
+    assert!(config.contains("\"git_info\": false"), "{config}");
+
+    let stdout = stdout_of(run(&dir, &home, &["--no-escape-xml"]));
+    assert!(stdout.contains("1 < 2"), "{stdout}");
+}
+
+#[test]
+fn project_config_layers_over_user_config() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    home.child("context-gather/config.toml")
+        .write_str("exclude = [\"keep.rs\"]\nescape_xml = true\nmax_size = 1\n")
+        .unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("max_size = 4096\n")
+        .unwrap();
+
+    let stdout = stdout_of(run(&dir, &home, &[]));
+    assert!(!stdout.contains("fn keep()"));
+    assert!(stdout.contains("fn generated()"));
+}
+
+#[test]
+fn unknown_config_key_names_the_file() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("chunk_sise = 10\n")
+        .unwrap();
+
+    run(&dir, &home, &[])
+        .failure()
+        .stderr(contains(".context-gather.toml"))
+        .stderr(contains("chunk_sise"));
+}
+
+#[test]
+fn no_config_skips_loading() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("not valid toml = [\n")
+        .unwrap();
+
+    let stdout = stdout_of(run(&dir, &home, &["--no-config"]));
+    assert!(stdout.contains("fn keep()"));
+    assert!(stdout.contains("fn generated()"));
+}
+
+#[test]
+fn explicit_config_and_verbose_origins() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("exclude = [\"keep.rs\"]\n")
+        .unwrap();
+    dir.child("alt.toml")
+        .write_str("exclude = [\"gen.rs\"]\nmax_size = 4096\n")
+        .unwrap();
+
+    let assert = run(&dir, &home, &["--config", "alt.toml", "-v"])
+        .success()
+        .stderr(contains("config: loaded alt.toml"))
+        .stderr(contains("config: exclude from alt.toml"))
+        .stderr(contains("config: max_size from alt.toml"));
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(stdout.contains("fn keep()"));
+    assert!(!stdout.contains("fn generated()"));
+}
+
+#[test]
+fn configured_paths_apply_only_without_cli_paths() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child("src/lib.rs").write_str("fn lib() {}\n").unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("paths = [\"src\"]\n")
+        .unwrap();
+
+    let defaulted = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("XDG_CONFIG_HOME", home.path())
+        .args(["--stdout", "--no-clipboard", "-v"])
+        .assert()
+        .success()
+        .stderr(contains("config: paths from"));
+    let stdout = String::from_utf8(defaulted.get_output().stdout.clone()).unwrap();
+    assert!(stdout.contains("fn lib()"));
+    assert!(!stdout.contains("fn keep()"));
+
+    let stdout = stdout_of(run(&dir, &home, &[]));
+    assert!(stdout.contains("fn keep()"));
+}
+
+#[test]
+fn print_config_dumps_env_settings_and_exits() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    dir.child(".context-gather.toml")
+        .write_str("exclude = [\"gen.rs\"]\n")
+        .unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("XDG_CONFIG_HOME", home.path())
+        .env("CG_MAX_SIZE", "4096")
+        .env("CG_EXCLUDE", "*.lock")
+        .args(["--stdout", "--print-config", "."])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    // Nothing is gathered
+    assert!(output.stdout.is_empty());
+    let config: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(config["max_size"], 4096);
+    assert_eq!(config["exclude"], serde_json::json!(["gen.rs", "*.lock"]));
+    assert_eq!(config["stdout"], true);
+}
+
+#[test]
+fn verbose_dumps_effective_config_before_gathering() {
+    let dir = project();
+    let home = assert_fs::TempDir::new().unwrap();
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("XDG_CONFIG_HOME", home.path())
+        .env("CG_CHUNK_SIZE", "5000")
+        .args(["--stdout", "--no-clipboard", "-v", "."])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    assert!(String::from_utf8_lossy(&output.stdout).contains("fn keep()"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(r#""chunk_size": 5000"#), "{stderr}");
+}
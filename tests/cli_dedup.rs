@@ -1,3 +1,4 @@
+mod common;
 use assert_fs::prelude::*;
 use predicates::str::contains;
 
@@ -14,3 +15,66 @@ fn dir_and_file_are_deduped() {
         .stdout(contains("<shared-context"))
         .stderr(contains("OK 1 files"));
 }
+
+/// The same helper vendored twice, plus an unrelated file.
+fn vendored() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let helper = "pub fn clamp(x: i32) -> i32 { x.max(0) }\n";
+    dir.child("vendor/b/util.rs").write_str(helper).unwrap();
+    dir.child("vendor/a/util.rs").write_str(helper).unwrap();
+    dir.child("main.rs").write_str("fn main() {}\n").unwrap();
+    dir
+}
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> (String, String) {
+    common::run_ok(dir, &[args, &["."]].concat())
+}
+
+#[test]
+fn dedup_contents_keeps_the_smallest_path_and_notes_the_copy() {
+    let dir = vendored();
+    let (stdout, stderr) = run(&dir, &["--dedup-contents"]);
+    assert_eq!(stdout.matches("x.max(0)").count(), 1, "{stdout}");
+    assert!(stdout.contains("<file-contents path=\"vendor/a/util.rs\""));
+    assert!(!stdout.contains("<file-contents path=\"vendor/b/util.rs\""));
+    let copy = stdout
+        .lines()
+        .find(|l| l.contains("<file id=") && l.contains("path=\"vendor/b/util.rs\""))
+        .unwrap();
+    assert!(copy.contains("parts=\"0\""), "{copy}");
+    assert!(copy.contains("duplicate-of=\"vendor/a/util.rs\""), "{copy}");
+    assert!(!copy.contains("stubbed="), "{copy}");
+    assert!(stderr.contains("OK 2 files"), "{stderr}");
+    assert!(stderr.contains("duplicates=1"), "{stderr}");
+    assert!(!stderr.contains("stubbed="), "{stderr}");
+}
+
+#[test]
+fn identical_files_are_kept_without_dedup_contents() {
+    let dir = vendored();
+    let (stdout, stderr) = run(&dir, &[]);
+    assert_eq!(stdout.matches("x.max(0)").count(), 2);
+    assert!(!stdout.contains("duplicate-of="));
+    assert!(
+        stderr.contains("OK 3 files") && !stderr.contains("duplicates="),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn dedup_contents_lists_the_copy_in_json() {
+    let dir = vendored();
+    let (stdout, _) = run(&dir, &["--dedup-contents", "--format", "json"]);
+    let doc: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let copy = doc["file_map"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["path"] == "vendor/b/util.rs")
+        .unwrap();
+    assert_eq!(copy["duplicate_of"], "vendor/a/util.rs");
+    assert!(copy.get("stubbed").is_none());
+}
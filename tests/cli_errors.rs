@@ -1,4 +1,5 @@
 use assert_fs::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
 
 #[test]
 fn chunk_index_requires_chunk_size() {
@@ -72,6 +73,29 @@ fn chunk_index_out_of_range_errors() {
         .code(3);
 }
 
+#[test]
+fn chunk_index_huge_range_is_out_of_range_not_an_allocation() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("foo.txt").write_str("hello world").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--chunk-size",
+            "10",
+            "--chunk-index",
+            "0-99999999999",
+            "--stdout",
+            "--no-clipboard",
+            "foo.txt",
+        ])
+        .timeout(std::time::Duration::from_secs(30))
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicates::str::contains("out of range"));
+}
+
 #[test]
 fn stream_requires_chunk_size() {
     let dir = assert_fs::TempDir::new().unwrap();
@@ -127,3 +151,83 @@ fn stream_conflicts_with_chunk_index() {
         .failure()
         .code(2);
 }
+
+#[test]
+fn empty_directory_explains_and_exits_4() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "."])
+        .assert()
+        .failure()
+        .code(4)
+        .stdout(predicates::str::is_empty())
+        .stderr(predicates::str::contains("no gatherable files found"))
+        .stderr(predicates::str::contains("no files were discovered"))
+        .stderr(predicates::str::contains("--allow-empty"));
+}
+
+#[test]
+fn excluded_everything_reports_exclude_count() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.rs").write_str("fn a() {}\n").unwrap();
+    dir.child("b.rs").write_str("fn b() {}\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--exclude-paths", "*.rs", "."])
+        .assert()
+        .code(4)
+        .stderr(predicates::str::contains(
+            "2 file(s) removed by --exclude-paths/--only",
+        ));
+}
+
+#[test]
+fn unmatched_paths_list_what_was_searched_and_fail() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/a.rs").write_str("fn a() {}\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--exclude-paths",
+            "**/*.rs",
+            "--exclude-ext",
+            "lock",
+            "src",
+            "docs/*.md",
+        ])
+        .assert()
+        .code(4)
+        .stdout(predicates::str::is_empty())
+        .stderr(predicates::str::contains("paths: src, docs/*.md"))
+        .stderr(predicates::str::contains(
+            "filters: --exclude-paths **/*.rs --exclude-ext lock",
+        ))
+        .stderr(predicates::str::contains("OK 0 files").not());
+}
+
+#[test]
+fn missing_instructions_file_fails_before_gathering() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("foo.txt").write_str("hello world").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--instructions-file",
+            "missing.txt",
+            "--stdout",
+            "--no-clipboard",
+            "foo.txt",
+        ])
+        .assert()
+        .failure()
+        .code(2)
+        .stdout("")
+        .stderr(predicates::str::contains("cannot read --instructions-file"));
+}
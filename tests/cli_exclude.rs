@@ -1,3 +1,4 @@
+mod common;
 use assert_fs::prelude::*;
 use predicates::str::contains;
 
@@ -57,3 +58,200 @@ fn invalid_exclude_patterns_warn_but_valid_apply() {
     assert!(stdout.contains("b.rs"));
     assert!(!stdout.contains("a.rs"));
 }
+
+#[test]
+fn exclude_ext_unions_with_exclude_paths() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/a.rs").write_str("fn a() {}\n").unwrap();
+    dir.child("data.json").write_str("{}\n").unwrap();
+    dir.child("UPPER.JSON").write_str("{}\n").unwrap();
+    dir.child("app.min.js").write_str("x\n").unwrap();
+    dir.child("app.js").write_str("y\n").unwrap();
+    dir.child("Cargo.lock").write_str("lock\n").unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("RUST_LOG", "debug")
+        .args([
+            "--exclude-ext",
+            ".json",
+            "--exclude-ext",
+            "min.js",
+            "--exclude-paths",
+            "*.lock",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stderr(contains(r#"--exclude-ext "json" matched 2 files"#))
+        .stderr(contains(r#"--exclude-paths "*.lock" matched 1 files"#))
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("src/a.rs"));
+    assert!(stdout.contains("app.js"));
+    assert!(!stdout.contains("data.json"));
+    assert!(!stdout.contains("UPPER.JSON"));
+    assert!(!stdout.contains("app.min.js"));
+    assert!(!stdout.contains("Cargo.lock"));
+}
+
+fn negation_fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("target/doc/important.md")
+        .write_str("important_marker\n")
+        .unwrap();
+    dir.child("target/doc/other.md")
+        .write_str("other_marker\n")
+        .unwrap();
+    dir.child("Cargo.lock")
+        .write_str("cargo_lock_marker\n")
+        .unwrap();
+    dir.child("web/yarn.lock")
+        .write_str("yarn_lock_marker\n")
+        .unwrap();
+    dir.child("main.rs").write_str("fn main() {}\n").unwrap();
+    dir
+}
+
+fn stdout_with(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> String {
+    common::run_ok(dir, &[&["--no-config"], args, &["."]].concat()).0
+}
+
+#[test]
+fn negated_exclude_reincludes_from_directory_glob() {
+    let dir = negation_fixture();
+
+    let stdout = stdout_with(
+        &dir,
+        &[
+            "--exclude-paths",
+            "target/**",
+            "--exclude-paths",
+            "!target/doc/important.md",
+        ],
+    );
+    assert!(stdout.contains("important_marker"));
+    assert!(!stdout.contains("other_marker"));
+    assert!(stdout.contains("fn main() {}"));
+}
+
+#[test]
+fn negated_exclude_reincludes_from_extension_glob() {
+    let dir = negation_fixture();
+
+    let stdout = stdout_with(
+        &dir,
+        &["--exclude-paths", "**/*.lock", "--exclude-paths", "!web/**"],
+    );
+    assert!(stdout.contains("yarn_lock_marker"));
+    assert!(!stdout.contains("cargo_lock_marker"));
+
+    let stdout = stdout_with(
+        &dir,
+        &["--exclude-ext", "lock", "--exclude-paths", "!Cargo.lock"],
+    );
+    assert!(stdout.contains("cargo_lock_marker"));
+    assert!(!stdout.contains("yarn_lock_marker"));
+}
+
+#[test]
+fn negation_alone_is_a_noop_with_warning() {
+    let dir = negation_fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--no-config",
+            "--no-default-excludes",
+            "--exclude-paths",
+            "!main.rs",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("other_marker"))
+        .stdout(contains("cargo_lock_marker"))
+        .stderr(contains("no effect without other excludes"));
+}
+
+fn default_excludes_fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("node_modules/foo.js")
+        .write_str("dependency_marker\n")
+        .unwrap();
+    dir.child("src/target/notes.md")
+        .write_str("build_output_marker\n")
+        .unwrap();
+    dir.child("main.rs").write_str("fn main() {}\n").unwrap();
+    dir.child("notes.txt").write_str("notes_marker\n").unwrap();
+    dir
+}
+
+#[test]
+fn default_excludes_drop_dependency_dirs_without_gitignore() {
+    let dir = default_excludes_fixture();
+    assert!(!dir.child(".gitignore").exists());
+
+    let stdout = stdout_with(&dir, &[]);
+    assert!(!stdout.contains("dependency_marker"), "{stdout}");
+    assert!(!stdout.contains("build_output_marker"), "{stdout}");
+    assert!(stdout.contains("fn main() {}"));
+
+    let stdout = stdout_with(&dir, &["--no-default-excludes"]);
+    assert!(stdout.contains("dependency_marker"), "{stdout}");
+    assert!(stdout.contains("build_output_marker"), "{stdout}");
+}
+
+#[test]
+fn default_excludes_combine_with_user_excludes() {
+    let dir = default_excludes_fixture();
+
+    let stdout = stdout_with(&dir, &["--exclude-paths", "*.rs"]);
+    assert!(!stdout.contains("dependency_marker"), "{stdout}");
+    assert!(!stdout.contains("fn main() {}"), "{stdout}");
+    assert!(stdout.contains("notes_marker"), "{stdout}");
+
+    let stdout = stdout_with(&dir, &["--exclude-paths", "!node_modules/foo.js"]);
+    assert!(stdout.contains("dependency_marker"), "{stdout}");
+    assert!(!stdout.contains("build_output_marker"), "{stdout}");
+}
+
+#[test]
+fn default_excludes_spare_a_directory_named_on_the_command_line() {
+    let dir = default_excludes_fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--no-config", "node_modules"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert!(
+        String::from_utf8(output)
+            .unwrap()
+            .contains("dependency_marker")
+    );
+}
+
+#[test]
+fn print_default_excludes_lists_the_patterns() {
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .args(["--print-default-excludes"])
+        .assert()
+        .success()
+        .stdout(contains("**/node_modules/**\n"))
+        .stdout(contains("**/target/**\n"))
+        .stdout(contains("**/.venv/**\n"));
+}
@@ -0,0 +1,45 @@
+use assert_fs::prelude::*;
+use predicates::str::contains;
+use std::process::Command;
+
+#[test]
+fn files_outside_the_repository_are_marked_external() {
+    let parent = assert_fs::TempDir::new().unwrap();
+    let repo = parent.child("repo");
+    repo.child("src/main.rs")
+        .write_str("fn main() {}\n")
+        .unwrap();
+    parent
+        .child("shared-lib/src/lib.rs")
+        .write_str("pub fn shared() {}\n")
+        .unwrap();
+    let init = Command::new("git")
+        .arg("init")
+        .arg("--quiet")
+        .current_dir(repo.path())
+        .status();
+    if !matches!(init, Ok(status) if status.success()) {
+        return;
+    }
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(repo.path())
+        .args(["--stdout", "--no-clipboard", "src", "../shared-lib/src"])
+        .assert()
+        .success()
+        .stderr(contains("note: 1 file from outside the repository"))
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    let entry = |needle: &str| {
+        stdout
+            .lines()
+            .find(|l| l.contains("<file id=") && l.contains(needle))
+            .unwrap_or_else(|| panic!("no file-map entry for {needle}"))
+            .to_string()
+    };
+    assert!(entry("lib.rs").contains(r#"external="true""#));
+    assert!(!entry("main.rs").contains("external="));
+}
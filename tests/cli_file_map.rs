@@ -0,0 +1,49 @@
+mod common;
+use assert_fs::prelude::*;
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+fn run(
+    dir: &assert_fs::TempDir,
+    extra: &[&str],
+) -> String {
+    common::run_ok(dir, &[extra, &["hello.txt"]].concat()).0
+}
+
+fn file_map_line(stdout: &str) -> &str {
+    stdout
+        .lines()
+        .find(|l| l.contains("<file id=\"0\""))
+        .unwrap_or_else(|| panic!("no file map entry in {stdout}"))
+}
+
+#[test]
+fn file_map_entry_has_size_mtime_and_sha256() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("hello.txt").write_str("hello\n").unwrap();
+
+    // A chunked header also says which chunk holds the file
+    for (extra, end) in [
+        (&[][..], "/>"),
+        (&["--chunk-size", "1000"][..], r#" chunk="1"/>"#),
+    ] {
+        let stdout = run(&dir, extra);
+        let entry = file_map_line(&stdout);
+        assert!(entry.contains(r#"parts="1" size="6" mtime=""#), "{entry}");
+        assert!(
+            entry.contains(&format!(r#"Z" sha256="{HELLO_SHA256}"{end}"#)),
+            "{entry}"
+        );
+    }
+}
+
+#[test]
+fn no_hashes_omits_sha256() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("hello.txt").write_str("hello\n").unwrap();
+
+    let stdout = run(&dir, &["--no-hashes"]);
+    let entry = file_map_line(&stdout);
+    assert!(entry.contains(r#"size="6" mtime=""#), "{entry}");
+    assert!(!entry.contains("sha256"), "{entry}");
+}
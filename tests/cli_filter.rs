@@ -0,0 +1,89 @@
+#![cfg(unix)]
+
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/keep.rs")
+        .write_str("fn keep_marker() {}\n")
+        .unwrap();
+    dir.child("src/vendor.rs")
+        .write_str("// Proprietary\nfn vendor_marker() {}\n")
+        .unwrap();
+    dir.child("notes.md").write_str("notes_marker\n").unwrap();
+    dir
+}
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> assert_cmd::assert::Assert {
+    common::context_gather(dir)
+        .args(["--no-config"])
+        .args(args)
+        .arg(".")
+        .assert()
+}
+
+#[test]
+fn filter_script_keeps_only_echoed_paths() {
+    let dir = fixture();
+    // Drop files containing "Proprietary"; echo the rest NUL-separated
+    dir.child("filter.sh")
+        .write_str(
+            "while IFS= read -r -d '' p; do\n  grep -q Proprietary \"$p\" || printf '%s\\0' \"$p\"\ndone\n",
+        )
+        .unwrap();
+
+    let output = run(
+        &dir,
+        &[
+            "--filter-cmd",
+            "bash filter.sh",
+            "--exclude-paths",
+            "filter.sh",
+        ],
+    )
+    .success()
+    .stderr(contains("OK 2 files"))
+    .get_output()
+    .stdout
+    .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("keep_marker"));
+    assert!(stdout.contains("notes_marker"));
+    assert!(!stdout.contains("vendor_marker"));
+}
+
+#[test]
+fn filter_accepts_newline_separated_output() {
+    let dir = fixture();
+
+    let output = run(&dir, &["--filter-cmd", "tr '\\0' '\\n' | grep '^src/'"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("keep_marker"));
+    assert!(!stdout.contains("notes_marker"));
+}
+
+#[test]
+fn filter_failure_and_timeout_abort() {
+    let dir = fixture();
+
+    run(&dir, &["--filter-cmd", "cat >/dev/null; exit 3"])
+        .failure()
+        .stderr(contains("--filter-cmd"))
+        .stderr(contains("exit status: 3"));
+
+    run(
+        &dir,
+        &["--filter-cmd", "exec sleep 10", "--filter-timeout", "1"],
+    )
+    .failure()
+    .stderr(contains("timed out after 1s"));
+}
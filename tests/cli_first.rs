@@ -0,0 +1,68 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+/// Five files of roughly 100 tokens each, so `-c 300` spreads them over
+/// several content chunks.
+fn first_fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for name in ["a.txt", "b.txt", "c.txt", "d.txt", "zz_last.txt"] {
+        dir.child(name).write_str(&"tok\n".repeat(50)).unwrap();
+    }
+    dir
+}
+
+fn run(args: &[&str]) -> (String, String) {
+    let dir = first_fixture();
+    common::run_ok(&dir, &[&["-c", "300"], args, &["."]].concat())
+}
+
+fn first_content_chunk(stdout: &str) -> &str {
+    let chunk = stdout
+        .split("<context-chunk ")
+        .nth(1)
+        .expect("at least one content chunk");
+    &chunk[..chunk.find("</context-chunk>").unwrap()]
+}
+
+#[test]
+fn prioritized_file_lands_in_first_content_chunk() {
+    let (stdout, _) = run(&[]);
+    assert!(!first_content_chunk(&stdout).contains("path=\"zz_last.txt\""));
+
+    let (stdout, _) = run(&["--first", "zz_*"]);
+    assert!(
+        first_content_chunk(&stdout).contains("path=\"zz_last.txt\""),
+        "{stdout}"
+    );
+    let paths: Vec<&str> = stdout
+        .lines()
+        .filter(|l| l.trim_start().starts_with("<file id="))
+        .collect();
+    assert!(paths[0].contains("path=\"zz_last.txt\""));
+    assert!(paths[0].contains("priority=\"true\""));
+    // The rest keep their path order and are not marked.
+    assert!(paths[1].contains("path=\"a.txt\""));
+    assert!(paths[4].contains("path=\"d.txt\""));
+    assert!(paths[1..].iter().all(|l| !l.contains("priority=")));
+}
+
+#[test]
+fn unmatched_first_pattern_warns() {
+    let (stdout, stderr) = run(&["--first", "zz_*", "--first", "missing/**"]);
+    assert!(stderr.contains("FIRST_NO_MATCH"), "{stderr}");
+    assert!(stderr.contains("missing/**"));
+    assert!(!stderr.contains("matched no files: zz_*"));
+    assert!(stdout.contains("priority=\"true\""));
+}
+
+#[test]
+fn all_invalid_first_patterns_exit_2() {
+    let dir = first_fixture();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--first", "[", "."])
+        .assert()
+        .code(2)
+        .stderr(contains("--first"));
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use assert_fs::prelude::*;
+use common::run_ok;
+use std::process::Command;
+
+/// A git repository with one tracked file, one untracked file, and one
+/// ignored file. `None` when git is unavailable.
+fn repo() -> Option<assert_fs::TempDir> {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/tracked.rs")
+        .write_str("fn tracked() {}\n")
+        .unwrap();
+    dir.child(".gitignore").write_str("*.log\n").unwrap();
+    for args in [&["init", "--quiet"][..], &["add", "--all"]] {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .status();
+        if !matches!(status, Ok(status) if status.success()) {
+            return None;
+        }
+    }
+    dir.child("src/untracked.rs~")
+        .write_str("fn backup() {}\n")
+        .unwrap();
+    dir.child("build.log").write_str("build output\n").unwrap();
+    Some(dir)
+}
+
+#[test]
+fn git_ls_files_gathers_only_tracked_files() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let (walked, _) = run_ok(dir.path(), &["."]);
+    assert!(walked.contains("fn backup()"), "{walked}");
+
+    let (stdout, stderr) = run_ok(dir.path(), &["--git-ls-files", "."]);
+    assert!(stdout.contains("fn tracked()"), "{stdout}");
+    assert!(!stdout.contains("fn backup()"), "{stdout}");
+    assert!(!stdout.contains("build output"), "{stdout}");
+    assert!(stderr.contains("OK 1 files"), "{stderr}");
+
+    // Path arguments still narrow the listing
+    let (stdout, _) = run_ok(
+        dir.path(),
+        &["--git-ls-files", "--allow-empty", "src/nothing-here"],
+    );
+    assert!(!stdout.contains("fn tracked()"), "{stdout}");
+}
+
+#[test]
+fn git_others_adds_untracked_files_that_are_not_ignored() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let (stdout, _) = run_ok(dir.path(), &["--git-ls-files", "--git-others", "."]);
+    assert!(stdout.contains("fn tracked()"), "{stdout}");
+    assert!(stdout.contains("fn backup()"), "{stdout}");
+    assert!(!stdout.contains("build output"), "{stdout}");
+}
+
+#[test]
+fn git_ls_files_outside_a_repository_walks_with_a_warning() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("plain.rs").write_str("fn plain() {}\n").unwrap();
+    // Inside a work tree after all (e.g. a temp dir within a checkout)
+    if Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .current_dir(dir.path())
+        .output()
+        .is_ok_and(|o| o.status.success())
+    {
+        return;
+    }
+    let (stdout, stderr) = run_ok(dir.path(), &["--git-ls-files", "."]);
+    assert!(stdout.contains("fn plain()"), "{stdout}");
+    assert!(stderr.contains("GIT_LS_FILES_FALLBACK"), "{stderr}");
+    assert!(stderr.contains("walking it instead"), "{stderr}");
+}
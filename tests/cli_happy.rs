@@ -138,3 +138,44 @@ fn escape_xml_suppresses_raw_wrapper_warning() {
         .stdout(contains("before &lt;/file-contents&gt; after"))
         .stderr(contains("context wrapper markers").not());
 }
+
+#[test]
+fn summary_reports_extension_composition() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.rs")
+        .write_str(&"fn a() {}\n".repeat(20))
+        .unwrap();
+    dir.child("b.md").write_str("# b\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--no-clipboard", "--report", "."])
+        .assert()
+        .success()
+        .stderr(contains("composition: rs "))
+        .stderr(contains("extension"))
+        .stderr(contains("OK 2 files"));
+}
+
+#[test]
+fn dry_run_lists_files_without_copying() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("small.txt").write_str("tok\n").unwrap();
+    dir.child("big.txt")
+        .write_str(&"tok tok tok\n".repeat(200))
+        .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .args(["--dry-run", "-c", "200", "--model-context", "10", "."])
+        .assert()
+        .success()
+        .stdout(contains("path"))
+        .stdout(predicates::str::is_match(r"big\.txt\s+\d+\s+\d+\s+\d+ parts").unwrap())
+        .stdout(predicates::str::is_match(r"small\.txt\s+4\s+\d+\s+no").unwrap())
+        .stdout(contains("<shared-context").not())
+        .stderr(contains("DRY RUN 2 files"))
+        .stderr(contains("exceeds model context 10"));
+}
@@ -0,0 +1,96 @@
+use assert_fs::prelude::*;
+use serde_json::Value;
+
+#[test]
+fn json_format_round_trips_contents() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let contents = "say \"hi\"\n\tline <two> & \\three\n";
+    dir.child("a.txt").write_str(contents).unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--format", "json", "--stdout", "--no-clipboard", "."])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let doc: Value = serde_json::from_slice(&output).expect("stdout should be valid JSON");
+    assert_eq!(doc["file_map"][0]["path"], "a.txt");
+    assert_eq!(doc["file_map"][0]["parts"], 1);
+    assert_eq!(doc["files"][0]["folder"], ".");
+    assert_eq!(doc["files"][0]["name"], "a.txt");
+    assert_eq!(doc["files"][0]["contents"], contents);
+}
+
+#[test]
+fn json_format_emits_one_object_per_chunk() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..5 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(50))
+            .unwrap();
+    }
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--format",
+            "json",
+            "--stdout",
+            "--no-clipboard",
+            "-c",
+            "80",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8(output).unwrap();
+    let docs: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line should be a JSON object"))
+        .collect();
+    assert!(docs.len() > 2, "expected several chunks");
+    let total = docs.len() as u64;
+    for (idx, doc) in docs.iter().enumerate() {
+        assert_eq!(doc["index"], idx as u64);
+        assert_eq!(doc["total"], total);
+    }
+    assert_eq!(docs[0]["file_map"].as_array().unwrap().len(), 5);
+    let gathered: usize = docs[1..]
+        .iter()
+        .map(|d| d["files"].as_array().unwrap().len())
+        .sum();
+    assert!(gathered >= 5);
+}
+
+#[test]
+fn json_format_describes_explicit_binary_files() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("logo.png")
+        .write_binary(&[0, 255, 0, 128])
+        .unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--format", "json", "--stdout", "--no-clipboard", "logo.png"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let doc: Value = serde_json::from_slice(&output).expect("stdout should be valid JSON");
+    assert_eq!(doc["file_map"][0]["binary"], true);
+    assert_eq!(doc["files"][0]["contents"], "");
+    assert_eq!(doc["files"][0]["binary"]["size"], 4);
+    assert_eq!(
+        doc["files"][0]["binary"]["sha256"].as_str().unwrap().len(),
+        64
+    );
+}
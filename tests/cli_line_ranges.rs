@@ -0,0 +1,89 @@
+mod common;
+
+use assert_fs::prelude::*;
+use common::run_ok;
+use predicates::str::contains;
+
+/// `big.rs` has 300 numbered lines, `other.rs` a few.
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let big: String = (1..=300)
+        .map(|i| format!("let line_{i} = {i};\n"))
+        .collect();
+    dir.child("src/big.rs").write_str(&big).unwrap();
+    dir.child("src/other.rs")
+        .write_str("fn other() {}\n")
+        .unwrap();
+    dir
+}
+
+#[test]
+fn line_range_keeps_only_those_lines() {
+    let dir = project();
+    let (stdout, _) = run_ok(&dir, &["src/big.rs:100-250", "src/other.rs"]);
+    assert!(stdout.contains("let line_100 = 100;"), "{stdout}");
+    assert!(stdout.contains("let line_250 = 250;"));
+    assert!(!stdout.contains("let line_99 = 99;"));
+    assert!(!stdout.contains("let line_251 = 251;"));
+    assert!(stdout.contains("fn other()"));
+    assert!(
+        stdout.contains("<file-contents path=\"src/big.rs\" name=\"big.rs\" lines=\"100-250\">"),
+        "{stdout}"
+    );
+    let entry = stdout
+        .lines()
+        .find(|l| l.contains("<file id=") && l.contains("path=\"src/big.rs\""))
+        .unwrap();
+    assert!(entry.contains("lines=\"100-250\""), "{entry}");
+    assert!(!stdout.contains("lines=\"1-"));
+}
+
+#[test]
+fn line_numbers_start_at_the_range() {
+    let dir = project();
+    let (stdout, _) = run_ok(&dir, &["--line-numbers", "src/big.rs:120+3"]);
+    assert!(stdout.contains("120: let line_120 = 120;\n"), "{stdout}");
+    assert!(stdout.contains("122: let line_122 = 122;\n"), "{stdout}");
+    assert!(!stdout.contains("let line_123"));
+    assert!(stdout.contains("lines=\"120-122\""));
+}
+
+#[test]
+fn out_of_bounds_ranges_clamp_with_a_warning() {
+    let dir = project();
+    let (stdout, stderr) = run_ok(&dir, &["src/big.rs:290-400"]);
+    assert!(stdout.contains("let line_300 = 300;"));
+    assert!(!stdout.contains("let line_289 = 289;"));
+    assert!(stdout.contains("lines=\"290-300\""), "{stdout}");
+    assert!(stderr.contains("LINE_RANGE_CLAMPED"), "{stderr}");
+    assert!(
+        stderr.contains("showing lines 290-300 instead of 290-400"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn chunked_parts_keep_the_range() {
+    let dir = project();
+    let (stdout, _) = run_ok(&dir, &["-c", "500", "src/big.rs:1-200"]);
+    let parts: Vec<&str> = stdout
+        .lines()
+        .filter(|l| l.contains("<file-contents "))
+        .collect();
+    assert!(parts.len() > 1, "{stdout}");
+    assert!(
+        parts.iter().all(|l| l.contains("lines=\"1-200\"")),
+        "{parts:?}"
+    );
+}
+
+#[test]
+fn invalid_ranges_are_rejected() {
+    let dir = project();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "src/big.rs:250-100"])
+        .assert()
+        .failure()
+        .stderr(contains("invalid line range in src/big.rs:250-100"));
+}
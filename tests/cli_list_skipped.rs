@@ -0,0 +1,40 @@
+mod common;
+use common::basic_fs;
+
+fn run(args: &[&str]) -> String {
+    let dir = basic_fs();
+    common::run_ok(&dir, &[args, &["."]].concat()).0
+}
+
+/// The `<file-map>` element of `stdout`, up to its closing tag.
+fn file_map(stdout: &str) -> &str {
+    let start = stdout.find("<file-map ").unwrap();
+    let end = start + stdout[start..].find("</file-map>").unwrap();
+    &stdout[start..end]
+}
+
+#[test]
+fn skipped_binary_is_listed_in_the_file_map() {
+    let stdout = run(&["--list-skipped"]);
+    assert!(
+        file_map(&stdout)
+            .contains(r#"<skipped-file path="bin/binary.dat" reason="binary" size="4096"/>"#),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("<file-contents path=\"bin/binary.dat\""));
+    assert!(stdout.contains(r#"total-files="2""#));
+
+    assert!(!run(&[]).contains("<skipped-file"));
+}
+
+#[test]
+fn skipped_files_are_listed_in_the_chunked_header() {
+    let stdout = run(&["--list-skipped", "--max-size", "20", "-c", "500"]);
+    let header = &stdout[..stdout.find("</shared-context-header>").unwrap()];
+    assert!(
+        header.contains(r#"<skipped-file path="bin/binary.dat" reason="too-large" size="4096"/>"#)
+    );
+    assert!(header.contains(r#"<skipped-file path="src/hello.rs" reason="too-large" size="33"/>"#));
+    assert!(header.contains(r#"path="README.md""#));
+    assert_eq!(stdout.matches("<skipped-file ").count(), 2);
+}
@@ -0,0 +1,75 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+/// A JSON blob of about 2,000 tokens and a one-line source file.
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let blob: String = (0..200)
+        .map(|i| format!("{{\"key_{i}\": \"value number {i}\"}},\n"))
+        .collect();
+    dir.child("data/blob.json").write_str(&blob).unwrap();
+    dir.child("src/main.rs")
+        .write_str("fn main() {}\n")
+        .unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> (String, String) {
+    let dir = project();
+    common::run_ok(&dir, &[args, &["."]].concat())
+}
+
+#[test]
+fn files_over_the_token_limit_are_skipped() {
+    let (stdout, stderr) = run(&["--max-file-tokens", "50", "--list-skipped"]);
+    assert!(!stdout.contains("value number"), "{stdout}");
+    assert!(stdout.contains("<file-contents path=\"src/main.rs\""));
+    assert!(stdout.contains(r#"total-files="1""#));
+    assert!(stdout.contains(r#"<skipped-file path="data/blob.json" reason="too-many-tokens""#));
+    assert!(stderr.contains("FILE_SKIPPED_TOO_MANY_TOKENS"), "{stderr}");
+    assert!(stderr.contains("over --max-file-tokens 50"));
+    assert!(stderr.contains("skipped=1"));
+}
+
+#[test]
+fn truncate_oversize_keeps_the_first_lines_within_the_limit() {
+    let (stdout, stderr) = run(&["--max-file-tokens", "100", "--truncate-oversize"]);
+    let start = stdout
+        .find("<file-contents path=\"data/blob.json\"")
+        .unwrap();
+    let block = &stdout[start..start + stdout[start..].find("</file-contents>").unwrap()];
+    assert!(block.contains("truncated=\"true\""), "{block}");
+    assert!(block.contains("value number 0\"}"));
+    assert!(!block.contains("value number 199"));
+    assert!(block.contains("<!-- truncated: showing first "));
+    assert!(stdout.contains("<file-contents path=\"src/main.rs\""));
+    assert!(stderr.contains("FILE_TRUNCATED"), "{stderr}");
+    assert!(!stderr.contains("skipped="));
+}
+
+#[test]
+fn files_within_the_limit_are_untouched() {
+    let (stdout, stderr) = run(&["--max-file-tokens", "100000"]);
+    assert!(stdout.contains("value number 199\"},\n"));
+    assert!(stdout.contains(r#"total-files="2""#));
+    assert!(!stdout.contains("truncated"));
+    assert!(!stderr.contains("--max-file-tokens"));
+}
+
+#[test]
+fn only_oversize_files_exit_4_with_the_reason() {
+    let dir = project();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--max-file-tokens",
+            "50",
+            "data",
+        ])
+        .assert()
+        .code(4)
+        .stderr(contains("1 file(s) skipped as over --max-file-tokens"));
+}
@@ -0,0 +1,67 @@
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    // a.txt is the largest, so --sort size puts it last
+    for (name, body) in [("a.txt", "aaaa"), ("b.txt", "bb"), ("c.txt", "c")] {
+        dir.child(name).write_str(body).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn max_files_aborts_when_exceeded() {
+    let dir = project();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--max-files", "2", "."])
+        .assert()
+        .code(6)
+        .stdout(predicate::str::is_empty())
+        .stderr(contains("3 candidate files exceed --max-files 2"));
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--max-files", "3", "."])
+        .assert()
+        .success();
+}
+
+#[test]
+fn max_files_truncate_keeps_first_in_sort_order() {
+    let dir = project();
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--max-files",
+            "2",
+            "--max-files-truncate",
+            "--sort",
+            "size",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stderr(contains("dropped 1"))
+        .stderr(contains("truncated=1"))
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("c.txt"));
+    assert!(stdout.contains("b.txt"));
+    assert!(!stdout.contains("a.txt"), "largest file should be dropped");
+}
+
+#[test]
+fn max_files_truncate_requires_max_files() {
+    let dir = project();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--max-files-truncate", "."])
+        .assert()
+        .code(2);
+}
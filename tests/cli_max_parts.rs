@@ -0,0 +1,88 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+/// One file that takes many parts at `-c 300`, and one that fits.
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let big: String = (0..400)
+        .map(|i| format!("let value_{i} = compute({i});\n"))
+        .collect();
+    dir.child("big.rs").write_str(&big).unwrap();
+    dir.child("small.rs").write_str("fn small() {}\n").unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> (String, String) {
+    let dir = project();
+    common::run_ok(&dir, &[&["-c", "300"], args, &["."]].concat())
+}
+
+fn map_entry<'a>(
+    stdout: &'a str,
+    path: &str,
+) -> &'a str {
+    stdout
+        .lines()
+        .find(|l| {
+            l.trim_start().starts_with("<file id=") && l.contains(&format!("path=\"{path}\""))
+        })
+        .unwrap()
+}
+
+#[test]
+fn max_parts_truncates_after_the_last_part_kept() {
+    let (full, _) = run(&[]);
+    assert!(full.matches("<file-contents path=\"big.rs\"").count() > 2);
+
+    let (stdout, stderr) = run(&["--max-parts", "2"]);
+    let parts: Vec<&str> = stdout
+        .lines()
+        .filter(|l| l.contains("<file-contents path=\"big.rs\""))
+        .collect();
+    assert_eq!(parts.len(), 2, "{stdout}");
+    assert!(parts[0].contains("part=\"1/2\"") && !parts[0].contains("truncated="));
+    assert!(parts[1].contains("part=\"2/2\"") && parts[1].contains("truncated=\"true\""));
+    // The rest of the file is gone
+    assert!(stdout.contains("value_1 "));
+    assert!(!stdout.contains("value_399 "));
+
+    let entry = map_entry(&stdout, "big.rs");
+    assert!(entry.contains("parts=\"2\""), "{entry}");
+    assert!(entry.contains("truncated=\"true\""), "{entry}");
+    assert!(!map_entry(&stdout, "small.rs").contains("truncated="));
+    assert!(
+        stderr.contains("big.rs needs more than --max-parts 2 parts"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("tokens after part 2"), "{stderr}");
+}
+
+#[test]
+fn max_parts_leaves_files_within_the_cap_alone() {
+    let (full, _) = run(&[]);
+    let (capped, stderr) = run(&["--max-parts", "50"]);
+    assert_eq!(
+        full.matches("<file-contents ").count(),
+        capped.matches("<file-contents ").count()
+    );
+    assert!(!capped.contains("truncated="));
+    assert!(!stderr.contains("--max-parts"));
+}
+
+#[test]
+fn max_parts_rejects_zero() {
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "-c",
+            "300",
+            "--max-parts",
+            "0",
+            ".",
+        ])
+        .assert()
+        .code(2)
+        .stderr(contains("--max-parts"));
+}
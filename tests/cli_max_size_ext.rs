@@ -0,0 +1,88 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> assert_cmd::assert::Assert {
+    let home = assert_fs::TempDir::new().unwrap();
+    common::context_gather(dir)
+        .env("XDG_CONFIG_HOME", home.path())
+        .args(args)
+        .arg(".")
+        .assert()
+}
+
+fn stdout_of(assert: assert_cmd::assert::Assert) -> String {
+    String::from_utf8(assert.success().get_output().stdout.clone()).unwrap()
+}
+
+/// A 60-byte JSON file, a 600-byte Markdown file and a 60-byte Rust file.
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("data.json")
+        .write_str(&format!("{{\"big\": \"{}\"}}\n", "x".repeat(47)))
+        .unwrap();
+    dir.child("guide.md")
+        .write_str(&format!("# guide\n{}\n", "words ".repeat(98)))
+        .unwrap();
+    dir.child("lib.rs")
+        .write_str(&format!("fn lib() {{}}\n// {}\n", "y".repeat(44)))
+        .unwrap();
+    dir
+}
+
+#[test]
+fn extension_limits_win_over_the_global_limit() {
+    let dir = fixture();
+    let assert = run(
+        &dir,
+        &[
+            "--max-size",
+            "100",
+            "--max-size-ext",
+            "json=32",
+            "--max-size-ext",
+            ".MD=1000",
+        ],
+    )
+    .success()
+    .stderr(contains("data.json\" exceeds 32 bytes"));
+    let stdout = stdout_of(assert);
+    // under the global limit, over its own
+    assert!(!stdout.contains("\"big\""), "{stdout}");
+    // over the global limit, under its own
+    assert!(stdout.contains("# guide"), "{stdout}");
+    // no entry: the global limit applies
+    assert!(stdout.contains("fn lib()"), "{stdout}");
+
+    let stdout = stdout_of(run(&dir, &["--max-size", "100"]));
+    assert!(stdout.contains("\"big\""), "{stdout}");
+    assert!(!stdout.contains("# guide"), "{stdout}");
+}
+
+#[test]
+fn config_table_sets_extension_limits() {
+    let dir = fixture();
+    dir.child(".context-gather.toml")
+        .write_str("max_size = 100\n\n[max_size_ext]\njson = 32\nmd = 1000\n")
+        .unwrap();
+    let stdout = stdout_of(run(&dir, &[]));
+    assert!(!stdout.contains("\"big\""), "{stdout}");
+    assert!(stdout.contains("# guide"), "{stdout}");
+
+    // a command-line pair replaces the table's entry for that extension
+    let stdout = stdout_of(run(&dir, &["--max-size-ext", "json=1000"]));
+    assert!(stdout.contains("\"big\""), "{stdout}");
+    assert!(stdout.contains("# guide"), "{stdout}");
+}
+
+#[test]
+fn malformed_pair_is_named_in_the_error() {
+    let dir = fixture();
+    run(&dir, &["--max-size-ext", "json:32"])
+        .failure()
+        .code(2)
+        .stderr(contains("invalid --max-size-ext pair \"json:32\""));
+}
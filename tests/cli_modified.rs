@@ -0,0 +1,82 @@
+mod common;
+use assert_fs::prelude::*;
+use std::time::{Duration, SystemTime};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// `old.rs` last modified 30 days ago, `new.rs` an hour ago.
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let now = SystemTime::now();
+    for (name, age) in [
+        ("old.rs", 30 * DAY),
+        ("new.rs", Duration::from_secs(60 * 60)),
+    ] {
+        let child = dir.child(name);
+        child.write_str(&format!("// {name}\n")).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(child.path())
+            .unwrap()
+            .set_modified(now - age)
+            .unwrap();
+    }
+    dir
+}
+
+fn run(args: &[&str]) -> (String, String) {
+    let dir = project();
+    common::run_ok(&dir, &[&["--allow-empty"], args, &["."]].concat())
+}
+
+#[test]
+fn modified_since_keeps_recent_files() {
+    let (stdout, stderr) = run(&["--modified-since", "7d"]);
+    assert!(stdout.contains("// new.rs"), "{stdout}");
+    assert!(!stdout.contains("// old.rs"), "{stdout}");
+    assert!(stderr.contains("OK 1 files"), "{stderr}");
+}
+
+#[test]
+fn modified_before_keeps_old_files() {
+    let (stdout, _) = run(&["--modified-before", "1w"]);
+    assert!(stdout.contains("// old.rs"), "{stdout}");
+    assert!(!stdout.contains("// new.rs"), "{stdout}");
+}
+
+#[test]
+fn bounds_combine_and_accept_dates() {
+    let (stdout, _) = run(&["--modified-since", "60d", "--modified-before", "1d"]);
+    assert!(
+        stdout.contains("// old.rs") && !stdout.contains("// new.rs"),
+        "{stdout}"
+    );
+
+    let (stdout, _) = run(&["--modified-since", "2000-01-01"]);
+    assert!(
+        stdout.contains("// old.rs") && stdout.contains("// new.rs"),
+        "{stdout}"
+    );
+
+    let future = chrono::Utc::now() + chrono::Duration::days(1);
+    let (stdout, _) = run(&["--modified-since", &future.to_rfc3339()]);
+    assert!(
+        !stdout.contains("// old.rs") && !stdout.contains("// new.rs"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn malformed_times_are_rejected() {
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--modified-since",
+            "last week",
+            ".",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("invalid time: last week"));
+}
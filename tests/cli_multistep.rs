@@ -1,4 +1,5 @@
 use assert_fs::prelude::*;
+use predicates::prelude::*;
 use predicates::str::{contains, is_empty};
 use std::time::Duration;
 
@@ -48,3 +49,92 @@ fn multi_step_exits_on_stdin_eof() {
         .stdout(contains("<shared-context>"))
         .stderr(contains("stdin closed; leaving multi-step mode."));
 }
+
+#[test]
+fn multi_step_reports_non_utf8_input_and_continues() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("d.txt").write_str("bytes").unwrap();
+
+    let mut stdin = vec![0xff, 0xfe, 0x00, b'\n'];
+    stdin.extend_from_slice(b"q\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("context-gather");
+    cmd.current_dir(&dir)
+        .args(["--no-clipboard", "-m", "."])
+        .write_stdin(stdin)
+        .timeout(Duration::from_secs(2));
+    cmd.assert()
+        .success()
+        .stderr(contains("Unrecognized input (not valid UTF-8)"))
+        .stderr(contains("stdin closed").not());
+}
+
+#[test]
+fn multi_step_gives_up_after_repeated_empty_input() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("e.txt").write_str("blank").unwrap();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("context-gather");
+    cmd.current_dir(&dir)
+        .args(["--no-clipboard", "-m", "."])
+        .write_stdin("\n".repeat(50))
+        .timeout(Duration::from_secs(2));
+    cmd.assert().success().stderr(contains(
+        "10 empty inputs in a row; leaving multi-step mode.",
+    ));
+}
+
+#[test]
+fn multi_step_chunk_size_splits_header_file_map() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..200 {
+        dir.child(format!("src/file_{i:03}.rs"))
+            .write_str("fn f() {}\n")
+            .unwrap();
+    }
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("context-gather");
+    cmd.current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "-m", "-c", "1500", "."])
+        .write_stdin("q\n")
+        .timeout(Duration::from_secs(5));
+    cmd.assert()
+        .success()
+        .stdout(contains("<file-map total-files=\"200\" total-tokens=\""))
+        .stdout(contains("\" part=\"1/"))
+        .stdout(contains("\" part=\"2/"))
+        .stdout(contains("src/file_199.rs"));
+}
+
+#[test]
+fn no_file_map_shrinks_multi_step_header_to_one_chunk() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..200 {
+        dir.child(format!("src/file_{i:03}.rs"))
+            .write_str("fn f() {}\n")
+            .unwrap();
+    }
+
+    let run = |extra: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("context-gather");
+        cmd.current_dir(&dir)
+            .args(["--stdout", "--no-clipboard", "-m", "-c", "1500"])
+            .args(extra)
+            .arg(".")
+            .write_stdin("q\n")
+            .timeout(Duration::from_secs(5));
+        let output = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+    // The header and each file-map continuation is a separate chunk
+    let chunks = |stdout: &str| {
+        stdout.matches("<shared-context-header").count() + stdout.matches("\" part=\"").count()
+            - usize::from(stdout.contains("part=\"1/"))
+    };
+
+    let with_map = run(&[]);
+    let without_map = run(&["--no-file-map"]);
+    assert!(chunks(&with_map) > 1, "{with_map}");
+    assert_eq!(chunks(&without_map), 1, "{without_map}");
+    assert!(!without_map.contains("<file-map"), "{without_map}");
+    assert!(without_map.contains("<instructions>"));
+}
@@ -0,0 +1,96 @@
+mod common;
+use assert_fs::prelude::*;
+
+/// A Windows-authored file: UTF-8 BOM and CRLF line endings.
+fn windows_file() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let body: String = (0..40)
+        .map(|i| format!("    let value_{i} = compute({i});\r\n"))
+        .collect();
+    dir.child("win.rs")
+        .write_binary(format!("\u{feff}fn main() {{\r\n{body}}}\r\n").as_bytes())
+        .unwrap();
+    dir.child("unix.rs").write_str("fn unix() {}\n").unwrap();
+    dir
+}
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> String {
+    common::run_ok(dir, &[args, &["."]].concat()).0
+}
+
+fn map_entry<'a>(
+    stdout: &'a str,
+    path: &str,
+) -> &'a str {
+    stdout
+        .lines()
+        .find(|l| l.contains("<file id=") && l.contains(&format!("path=\"{path}\"")))
+        .unwrap()
+}
+
+fn tokens(entry: &str) -> usize {
+    let rest = entry.split("tokens=\"").nth(1).unwrap();
+    rest[..rest.find('"').unwrap()].parse().unwrap()
+}
+
+#[test]
+fn normalize_eol_converts_crlf_and_strips_the_bom() {
+    let dir = windows_file();
+    let stdout = run(&dir, &["--normalize-eol"]);
+    assert!(!stdout.contains('\r'), "{stdout}");
+    assert!(!stdout.contains('\u{feff}'), "{stdout}");
+    assert!(
+        stdout.contains("fn main() {\n    let value_0 = compute(0);\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("<file-contents path=\"win.rs\" name=\"win.rs\" eol=\"crlf\">"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("<file-contents path=\"unix.rs\" name=\"unix.rs\">"),
+        "{stdout}"
+    );
+    assert!(map_entry(&stdout, "win.rs").contains(" eol=\"crlf\""));
+    assert!(!map_entry(&stdout, "unix.rs").contains("eol="));
+}
+
+#[test]
+fn normalize_eol_lowers_the_token_count() {
+    let dir = windows_file();
+    let raw = run(&dir, &[]);
+    let normalized = run(&dir, &["--normalize-eol"]);
+    assert!(raw.contains("\r\n"));
+    assert!(!map_entry(&raw, "win.rs").contains("eol="));
+    let (before, after) = (
+        tokens(map_entry(&raw, "win.rs")),
+        tokens(map_entry(&normalized, "win.rs")),
+    );
+    assert!(after < before, "{after} >= {before}");
+    assert_eq!(
+        tokens(map_entry(&raw, "unix.rs")),
+        tokens(map_entry(&normalized, "unix.rs"))
+    );
+}
+
+#[test]
+fn normalize_eol_is_reported_in_json() {
+    let dir = windows_file();
+    let stdout = run(&dir, &["--normalize-eol", "--format", "json"]);
+    let doc: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let map = doc["file_map"].as_array().unwrap();
+    let win = map.iter().find(|e| e["path"] == "win.rs").unwrap();
+    let unix = map.iter().find(|e| e["path"] == "unix.rs").unwrap();
+    assert_eq!(win["eol"], "crlf");
+    assert!(unix.get("eol").is_none());
+    let file = doc["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["path"] == "win.rs")
+        .unwrap();
+    assert!(!file["contents"].as_str().unwrap().contains('\r'));
+}
@@ -0,0 +1,297 @@
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src").create_dir_all().unwrap();
+    dir.child("src/a.rs").write_str("fn a() {}\n").unwrap();
+    dir.child("Cargo.toml").write_str("[package]\n").unwrap();
+    dir.child("notes.md").write_str("# notes\n").unwrap();
+    dir
+}
+
+#[test]
+fn only_keeps_matching_relative_paths() {
+    let dir = fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--only",
+            "**/*.rs",
+            "--only",
+            "*.toml",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("a.rs"));
+    assert!(stdout.contains("Cargo.toml"));
+    assert!(!stdout.contains("notes.md"));
+}
+
+#[test]
+fn only_applies_after_excludes() {
+    let dir = fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--only",
+            "**/*.rs",
+            "--only",
+            "*.toml",
+            "--exclude-paths",
+            "src/**",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("Cargo.toml"));
+    assert!(!stdout.contains("a.rs"));
+}
+
+#[test]
+fn invalid_only_patterns_warn_but_valid_apply() {
+    let dir = fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--only",
+            "[",
+            "--only",
+            "*.md",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stderr(contains("invalid --only pattern: ["))
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("notes.md"));
+    assert!(!stdout.contains("a.rs"));
+}
+
+#[test]
+fn all_invalid_only_patterns_exit_2() {
+    let dir = fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--only", "[", "--stdout", "--no-clipboard", "."])
+        .assert()
+        .code(2)
+        .stderr(contains("Every --only pattern was invalid"));
+}
+
+#[test]
+fn only_matching_nothing_warns_and_proceeds() {
+    let dir = fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--only",
+            "**/*.py",
+            "--allow-empty",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout(contains(r#"total-files="0""#))
+        .stderr(contains("no files matched --only patterns"));
+}
+
+fn dirs_fixture() -> assert_fs::TempDir {
+    let dir = fixture();
+    dir.child("proto/api.proto")
+        .write_str("syntax = \"proto3\";\n")
+        .unwrap();
+    dir.child("docs/guide.md").write_str("# guide\n").unwrap();
+    dir.child("src/gen/out.rs")
+        .write_str("// generated\n")
+        .unwrap();
+    dir.child(".gitignore").write_str("src/gen/\n").unwrap();
+    dir
+}
+
+#[test]
+fn only_dirs_restricts_walk_and_honors_ignores() {
+    let dir = dirs_fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--only-dirs",
+            "src,proto",
+            "--exclude-paths",
+            "**/*.proto",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("src/a.rs"));
+    assert!(!stdout.contains("api.proto"));
+    assert!(!stdout.contains("guide.md"));
+    assert!(!stdout.contains("notes.md"));
+    assert!(!stdout.contains("out.rs"));
+}
+
+#[test]
+fn only_dirs_leaves_explicit_files_alone() {
+    let dir = dirs_fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--only-dirs",
+            "proto",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+            "notes.md",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("api.proto"));
+    assert!(stdout.contains("notes.md"));
+    assert!(!stdout.contains("src/a.rs"));
+}
+
+#[test]
+fn only_dirs_missing_directory_errors() {
+    let dir = dirs_fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--only-dirs", "src,nope", "--stdout", "--no-clipboard", "."])
+        .assert()
+        .failure()
+        .stderr(contains("\"nope\" does not exist under any root"));
+}
+
+#[test]
+fn max_depth_zero_keeps_only_explicit_files() {
+    let dir = dirs_fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--max-depth",
+            "0",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+            "src/a.rs",
+        ])
+        .assert()
+        .success()
+        .stderr(contains("OK 1 files"))
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("fn a() {}"));
+    assert!(!stdout.contains("notes.md"));
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_symlinks_dedupes_linked_files() {
+    let dir = fixture();
+    let outside = assert_fs::TempDir::new().unwrap();
+    outside
+        .child("shared.rs")
+        .write_str("fn shared() {}\n")
+        .unwrap();
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("vendor")).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("src"), dir.path().join("src-link")).unwrap();
+
+    let run = |extra: &[&str]| {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args(extra)
+            .args(["--stdout", "--no-clipboard", "."])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8_lossy(&output).into_owned()
+    };
+
+    let plain = run(&[]);
+    assert!(!plain.contains("fn shared() {}"));
+
+    let followed = run(&["--follow-symlinks"]);
+    assert!(followed.contains("fn shared() {}"));
+    assert_eq!(followed.matches("fn a() {}").count(), 1);
+}
+
+#[test]
+fn hidden_and_no_ignore_widen_the_walk() {
+    let dir = dirs_fixture();
+    dir.child(".github/workflows/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+
+    let run = |extra: &[&str]| {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args(extra)
+            .args(["--stdout", "--no-clipboard", "."])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8_lossy(&output).into_owned()
+    };
+
+    let plain = run(&[]);
+    assert!(!plain.contains("on: push"));
+    assert!(!plain.contains("// generated"));
+
+    let hidden = run(&["--hidden"]);
+    assert!(hidden.contains("on: push"));
+    assert!(!hidden.contains("// generated"));
+
+    let no_ignore = run(&["--no-ignore"]);
+    assert!(!no_ignore.contains("on: push"));
+    assert!(no_ignore.contains("// generated"));
+}
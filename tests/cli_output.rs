@@ -0,0 +1,68 @@
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+use std::fs;
+
+#[test]
+fn output_writes_bundle_and_creates_parents() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.rs").write_str("fn a() {}\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--no-clipboard", "-w", "out/nested/ctx.xml", "a.rs"])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_empty())
+        .stderr(contains("wrote=out/nested/ctx.xml"))
+        .stderr(contains("nothing visible").not());
+
+    let written = fs::read_to_string(dir.path().join("out/nested/ctx.xml")).unwrap();
+    assert!(written.starts_with("<shared-context>"));
+    assert!(written.contains("fn a() {}"));
+}
+
+#[test]
+fn output_writes_one_file_per_chunk() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..5 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(50))
+            .unwrap();
+    }
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--no-clipboard", "-c", "80", "--output", "ctx.xml", "."])
+        .assert()
+        .success()
+        .stderr(contains("wrote=ctx-0.xml,ctx-1.xml"));
+
+    assert!(!dir.path().join("ctx.xml").exists());
+    let header = fs::read_to_string(dir.path().join("ctx-0.xml")).unwrap();
+    assert!(header.contains("<shared-context-header"));
+    let first = fs::read_to_string(dir.path().join("ctx-1.xml")).unwrap();
+    assert!(first.contains("<context-chunk"));
+}
+
+#[test]
+fn output_refuses_to_overwrite_without_force() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.rs").write_str("fn a() {}\n").unwrap();
+    dir.child("ctx.xml").write_str("keep me").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--no-clipboard", "-w", "ctx.xml", "a.rs"])
+        .assert()
+        .failure()
+        .stderr(contains("already exists; pass --force to overwrite"));
+    dir.child("ctx.xml").assert("keep me");
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--no-clipboard", "-w", "ctx.xml", "--force", "a.rs"])
+        .assert()
+        .success();
+    dir.child("ctx.xml").assert(contains("fn a() {}"));
+}
@@ -0,0 +1,78 @@
+mod common;
+use assert_fs::prelude::*;
+
+/// A home directory with one project, and a working directory elsewhere.
+fn dirs() -> (assert_fs::TempDir, assert_fs::TempDir) {
+    let home = assert_fs::TempDir::new().unwrap();
+    home.child("proj/src/main.rs")
+        .write_str("fn main() {}\n")
+        .unwrap();
+    home.child("proj/src/generated.rs")
+        .write_str("// generated\n")
+        .unwrap();
+    let cwd = assert_fs::TempDir::new().unwrap();
+    cwd.child("local.rs").write_str("fn local() {}\n").unwrap();
+    (home, cwd)
+}
+
+fn run(
+    home: &assert_fs::TempDir,
+    cwd: &assert_fs::TempDir,
+    args: &[&str],
+) -> String {
+    let assert = common::context_gather(cwd)
+        .env("HOME", home.path())
+        .env("USERPROFILE", home.path())
+        .env("CG_TEST_PROJ", home.child("proj").path())
+        .env("CG_TEST_SKIP", "generated")
+        .args(args)
+        .assert();
+    common::output_of(assert).0
+}
+
+#[test]
+fn tilde_expands_to_the_home_directory() {
+    let (home, cwd) = dirs();
+    let stdout = run(&home, &cwd, &["~/proj/src"]);
+    assert!(stdout.contains("fn main()"), "{stdout}");
+    assert!(!stdout.contains("fn local()"));
+}
+
+#[test]
+fn variables_expand_in_paths_and_excludes() {
+    let (home, cwd) = dirs();
+    for arg in ["$CG_TEST_PROJ/src", "${CG_TEST_PROJ}/src/*.rs"] {
+        let stdout = run(&home, &cwd, &[arg]);
+        assert!(stdout.contains("fn main()"), "{arg}: {stdout}");
+        assert!(stdout.contains("// generated"), "{arg}: {stdout}");
+    }
+    let stdout = run(
+        &home,
+        &cwd,
+        &[
+            "$CG_TEST_PROJ/src",
+            "--exclude-paths",
+            "**/${CG_TEST_SKIP}.rs",
+        ],
+    );
+    assert!(stdout.contains("fn main()"), "{stdout}");
+    assert!(!stdout.contains("// generated"), "{stdout}");
+}
+
+#[test]
+fn unset_variables_and_percent_references_stay_literal() {
+    let (home, cwd) = dirs();
+    cwd.child("%CG_TEST_PROJ%/kept.rs")
+        .write_str("fn kept() {}\n")
+        .unwrap();
+    let stdout = run(&home, &cwd, &["%CG_TEST_PROJ%"]);
+    assert!(stdout.contains("fn kept()"), "{stdout}");
+    assert!(!stdout.contains("fn main()"));
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&cwd)
+        .env_remove("CG_TEST_UNSET")
+        .args(["--stdout", "--no-clipboard", "$CG_TEST_UNSET/src"])
+        .assert()
+        .code(4);
+}
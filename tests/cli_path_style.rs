@@ -0,0 +1,61 @@
+mod common;
+use assert_fs::prelude::*;
+use std::path::Path;
+use std::process::Command;
+
+fn rendered_paths(
+    cwd: &Path,
+    style: &str,
+    path: &str,
+) -> (String, String) {
+    let (stdout, _) = common::run_ok(cwd, &["--path-style", style, path]);
+    let attr = |tag: &str| {
+        let line = stdout
+            .lines()
+            .find(|l| l.trim_start().starts_with(tag))
+            .unwrap_or_else(|| panic!("no {tag} in {stdout}"));
+        let start = line.find("path=\"").unwrap() + "path=\"".len();
+        let end = start + line[start..].find('"').unwrap();
+        line[start..end].to_string()
+    };
+    (attr("<file id="), attr("<file-contents"))
+}
+
+#[test]
+fn same_file_rendered_three_ways() {
+    let repo = assert_fs::TempDir::new().unwrap();
+    repo.child("src/lib.rs")
+        .write_str("pub fn lib() {}\n")
+        .unwrap();
+    let init = Command::new("git")
+        .arg("init")
+        .arg("--quiet")
+        .current_dir(repo.path())
+        .status();
+    if !matches!(init, Ok(status) if status.success()) {
+        return;
+    }
+    let src = repo.path().join("src");
+    let abs = dunce::canonicalize(src.join("lib.rs")).unwrap();
+    let abs = abs.to_string_lossy().replace('\\', "/");
+
+    for (style, expected) in [
+        ("relative", "src/lib.rs"),
+        ("absolute", abs.as_str()),
+        ("file-name", "lib.rs"),
+    ] {
+        let (map, contents) = rendered_paths(&src, style, "lib.rs");
+        assert_eq!(map, expected, "{style}");
+        assert_eq!(contents, expected, "{style}");
+    }
+}
+
+#[test]
+fn relative_falls_back_to_the_working_directory() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("docs/guide.md").write_str("# Guide\n").unwrap();
+
+    let (map, contents) = rendered_paths(dir.path(), "relative", "docs");
+    assert_eq!(map, "docs/guide.md");
+    assert_eq!(contents, "docs/guide.md");
+}
@@ -0,0 +1,93 @@
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/a.rs").write_str("fn a() {}\n").unwrap();
+    dir.child("src/b.rs").write_str("fn b() {}\n").unwrap();
+    dir.child("c.lock").write_str("locked\n").unwrap();
+    dir.child("notes.md").write_str("# notes\n").unwrap();
+    dir
+}
+
+#[test]
+fn paths_from_stdin_are_trimmed_deduped_and_excluded() {
+    let dir = fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--paths-from",
+            "-",
+            "--exclude-paths",
+            "*.lock",
+            "--stdout",
+            "--no-clipboard",
+        ])
+        .write_stdin("# changed files\n  src/a.rs  \n\nsrc/a.rs\n./src/a.rs\nc.lock\n")
+        .assert()
+        .success()
+        .stderr(contains("OK 1 files"))
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert_eq!(stdout.matches("<file-contents").count(), 1);
+    assert!(stdout.contains("fn a() {}"));
+    assert!(!stdout.contains("locked"));
+    assert!(!stdout.contains("notes.md"));
+}
+
+#[test]
+fn paths_from_composes_with_explicit_paths() {
+    let dir = fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--paths-from",
+            "-",
+            "--stdout",
+            "--no-clipboard",
+            "notes.md",
+        ])
+        .write_stdin("src/b.rs\n")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("fn b() {}"));
+    assert!(stdout.contains("# notes"));
+    assert!(!stdout.contains("fn a() {}"));
+}
+
+#[test]
+fn paths_from_reads_a_list_file() {
+    let dir = fixture();
+    dir.child("list.txt").write_str("src\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--paths-from", "list.txt", "--stdout", "--no-clipboard"])
+        .assert()
+        .success()
+        .stdout(contains("fn a() {}"))
+        .stdout(contains("fn b() {}"))
+        .stderr(contains("OK 2 files"));
+}
+
+#[test]
+fn paths_from_stdin_conflicts_with_multi_step() {
+    let dir = fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--paths-from", "-", "--multi-step", "--no-clipboard"])
+        .write_stdin("src/a.rs\n")
+        .assert()
+        .code(2);
+}
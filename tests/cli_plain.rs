@@ -0,0 +1,151 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn big_file() -> String {
+    (0..60)
+        .map(|i| format!("let value_{i} = compute({i}, 2, 3);\n"))
+        .collect()
+}
+
+fn fixture_tree() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("README.md").write_str("# Demo\n").unwrap();
+    dir.child("src/big.rs").write_str(&big_file()).unwrap();
+    dir
+}
+
+fn render(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> String {
+    common::run_ok(dir, &[&["--format", "plain"], args, &["."]].concat()).0
+}
+
+/// Map each marker line to the lines that follow it, up to the next marker.
+fn sections(stdout: &str) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    for line in stdout.split_inclusive('\n') {
+        if line.starts_with("===== ") || line.starts_with("----- ") {
+            out.push((line.trim_end().to_string(), String::new()));
+        } else if let Some((_, body)) = out.last_mut() {
+            body.push_str(line);
+        }
+    }
+    out
+}
+
+#[test]
+fn single_output_has_no_tags_and_keeps_contents() {
+    let dir = fixture_tree();
+    let stdout = render(&dir, &[]);
+    assert!(!stdout.contains('<'), "{stdout}");
+    let sections = sections(&stdout);
+    assert_eq!(
+        sections,
+        vec![
+            ("===== README.md =====".to_string(), "# Demo\n".to_string()),
+            ("===== src/big.rs =====".to_string(), big_file()),
+        ]
+    );
+}
+
+#[test]
+fn chunked_parts_reassemble_to_the_original_file() {
+    let dir = fixture_tree();
+    let stdout = render(&dir, &["--chunk-size", "300"]);
+    assert!(!stdout.contains('<'), "{stdout}");
+    let parts: Vec<_> = sections(&stdout)
+        .into_iter()
+        .filter(|(marker, _)| marker.starts_with("----- src/big.rs (part "))
+        .collect();
+    assert!(parts.len() > 1, "{stdout}");
+    let total = parts.len();
+    for (i, (marker, _)) in parts.iter().enumerate() {
+        assert_eq!(
+            marker,
+            &format!("----- src/big.rs (part {}/{total}) -----", i + 1)
+        );
+    }
+    let joined: String = parts.into_iter().map(|(_, body)| body).collect();
+    assert_eq!(joined.trim_end(), big_file().trim_end());
+}
+
+#[test]
+fn overlapping_parts_note_the_repeated_lines() {
+    let dir = fixture_tree();
+    let stdout = render(&dir, &["--chunk-size", "300", "--part-overlap", "2"]);
+    let parts: Vec<_> = sections(&stdout)
+        .into_iter()
+        .filter(|(marker, _)| marker.starts_with("----- src/big.rs (part "))
+        .collect();
+    assert!(parts.len() > 1, "{stdout}");
+    let total = parts.len();
+    let mut joined = String::new();
+    for (i, (marker, body)) in parts.iter().enumerate() {
+        if i == 0 {
+            assert_eq!(marker, &format!("----- src/big.rs (part 1/{total}) -----"));
+            joined.push_str(body);
+        } else {
+            assert_eq!(
+                marker,
+                &format!("----- src/big.rs (part {}/{total}, overlap 2) -----", i + 1)
+            );
+            joined.extend(body.split_inclusive('\n').skip(2));
+        }
+    }
+    assert_eq!(joined.trim_end(), big_file().trim_end());
+}
+
+#[test]
+fn separator_is_configurable() {
+    let dir = fixture_tree();
+    let stdout = render(&dir, &["--plain-separator", "###"]);
+    assert!(
+        stdout.starts_with("### README.md ###\n# Demo\n### src/big.rs ###\n"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn escape_xml_is_ignored_with_a_warning() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("cmp.rs")
+        .write_str("if a < b && c > d {}\n")
+        .unwrap();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--format",
+            "plain",
+            "--escape-xml",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout("===== cmp.rs =====\nif a < b && c > d {}\n")
+        .stderr(contains("--escape-xml has no effect with --format plain"));
+}
+
+#[test]
+fn plain_rejects_multi_step() {
+    let dir = fixture_tree();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--format",
+            "plain",
+            "--multi-step",
+            ".",
+        ])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(contains(
+            "--format plain cannot be combined with --multi-step",
+        ));
+}
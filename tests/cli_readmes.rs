@@ -0,0 +1,87 @@
+use assert_fs::prelude::*;
+
+#[test]
+fn include_readmes_adds_missing_readmes_first_in_folder() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("docs/README.md")
+        .write_str("docs readme")
+        .unwrap();
+    dir.child("docs/guide.md").write_str("guide").unwrap();
+    dir.child("src/README.md").write_str("src readme").unwrap();
+    dir.child("src/inner/README.txt")
+        .write_str("inner readme")
+        .unwrap();
+    dir.child("src/inner/a.rs").write_str("fn a() {}").unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--include-readmes",
+            "docs/guide.md",
+            "docs/README.md",
+            "src/inner/a.rs",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8_lossy(&output);
+
+    // Already selected: listed without the marker
+    let docs_entry = stdout
+        .lines()
+        .find(|l| l.contains(r#"<file id="#) && l.contains("docs/README.md"))
+        .unwrap();
+    assert!(!docs_entry.contains("included-via"));
+
+    // Auto-added from a contributing folder, and read before its code
+    let inner_entry = stdout
+        .lines()
+        .find(|l| l.contains(r#"<file id="#) && l.contains("src/inner/README.txt"))
+        .expect("src/inner/README.txt should be gathered");
+    assert!(inner_entry.contains(r#"included-via="readme""#));
+    let readme_at = stdout.find("inner readme").unwrap();
+    let code_at = stdout.find("fn a() {}").unwrap();
+    assert!(readme_at < code_at, "README should lead its folder group");
+
+    // src/ itself contributes no files
+    assert!(!stdout.contains("src/README.md"));
+}
+
+#[test]
+fn include_readmes_skips_folders_emptied_by_excludes() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("keep/README.md")
+        .write_str("keep readme")
+        .unwrap();
+    dir.child("keep/a.rs").write_str("fn a() {}").unwrap();
+    dir.child("drop/README.md")
+        .write_str("drop readme")
+        .unwrap();
+    dir.child("drop/b.rs").write_str("fn b() {}").unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--include-readmes",
+            "--only",
+            "**/*.rs",
+            "--exclude-paths",
+            "drop/**",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains(r#"path="keep/README.md""#));
+    assert!(stdout.contains(r#"included-via="readme""#));
+    assert!(!stdout.contains("drop/"));
+}
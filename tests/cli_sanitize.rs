@@ -0,0 +1,66 @@
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+fn tree() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("build.log")
+        .write_str("\u{1b}[32mok\u{1b}[0m compiled\n")
+        .unwrap();
+    dir.child("snap.txt").write_str("key\u{0}value\n").unwrap();
+    dir.child("clean.rs").write_str("fn main() {}\n").unwrap();
+    dir
+}
+
+#[test]
+fn strip_removes_ansi_and_nul_and_counts_files() {
+    let dir = tree();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--sanitize", "strip", "."])
+        .assert()
+        .success()
+        .stdout(contains("ok compiled\n"))
+        .stdout(contains("keyvalue\n"))
+        .stdout(contains("\u{1b}").not())
+        .stdout(contains("\u{0}").not())
+        .stderr(contains(" • sanitized=2"));
+}
+
+#[test]
+fn replace_marks_each_control_character() {
+    let dir = tree();
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--no-clipboard",
+            "--summary-json=stdout",
+            "--sanitize",
+            "replace",
+        ])
+        .args(["--output", "out.xml", "."])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["sanitized"], 2);
+    let written = std::fs::read_to_string(dir.path().join("out.xml")).unwrap();
+    assert!(
+        written.contains("\u{FFFD}ok\u{FFFD} compiled\n"),
+        "{written}"
+    );
+    assert!(written.contains("key\u{FFFD}value\n"), "{written}");
+}
+
+#[test]
+fn keep_is_the_default() {
+    let dir = tree();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "."])
+        .assert()
+        .success()
+        .stdout(contains("key\u{0}value\n"))
+        .stderr(contains("sanitized").not());
+}
@@ -0,0 +1,73 @@
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+use std::fs;
+
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.rs").write_str("fn a() {}\n").unwrap();
+    dir.child("b.rs").write_str("fn b() {}\n").unwrap();
+    dir.child("src/c.md").write_str("# c\n").unwrap();
+    dir
+}
+
+#[test]
+fn emitted_selection_matches_scripted_choice() {
+    let dir = fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("CG_TEST_EVENTS", "ctrl+u,down,space,down,space,enter")
+        .args([
+            "--select",
+            "--emit-selection",
+            "sel.txt",
+            "--stdout",
+            "--no-clipboard",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("fn b() {}"))
+        .stdout(contains("fn a() {}").not())
+        .stderr(contains(
+            "reproduce: context-gather --paths-from sel.txt --stdout --no-clipboard",
+        ));
+
+    let emitted = fs::read_to_string(dir.path().join("sel.txt")).unwrap();
+    assert_eq!(emitted, "b.rs\nsrc/c.md\n");
+
+    // Feeding the list back reproduces the same bundle contents
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--paths-from", "sel.txt", "--stdout", "--no-clipboard"])
+        .assert()
+        .success()
+        .stdout(contains("fn b() {}"))
+        .stdout(contains("# c"))
+        .stdout(contains("fn a() {}").not());
+}
+
+#[test]
+fn emitted_selection_as_json() {
+    let dir = fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("CG_TEST_EVENTS", "ctrl+u,space,enter")
+        .args([
+            "--select",
+            "--emit-selection",
+            "out/sel.json",
+            "--emit-selection-json",
+            "--no-clipboard",
+            "--stdout",
+            ".",
+        ])
+        .assert()
+        .success();
+
+    let emitted = fs::read_to_string(dir.path().join("out/sel.json")).unwrap();
+    let parsed: Vec<String> = serde_json::from_str(&emitted).unwrap();
+    assert_eq!(parsed, ["a.rs"]);
+}
@@ -0,0 +1,49 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn project() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("pkg/__init__.py").touch().unwrap();
+    dir.child("pkg/main.py").write_str("print('hi')\n").unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> (String, String) {
+    let dir = project();
+    common::run_ok(&dir, &[args, &["."]].concat())
+}
+
+#[test]
+fn kept_empty_files_render_without_a_body() {
+    for args in [&[][..], &["-c", "500"][..]] {
+        let (stdout, _) = run(args);
+        let tag = stdout
+            .lines()
+            .find(|l| l.contains("<file-contents path=\"pkg/__init__.py\""))
+            .unwrap();
+        assert!(tag.ends_with(" empty=\"true\"/>"), "{tag}");
+        assert_eq!(stdout.matches("</file-contents>").count(), 1, "{stdout}");
+    }
+}
+
+#[test]
+fn skip_empty_leaves_empty_files_out_and_counts_them() {
+    let (stdout, stderr) = run(&["--skip-empty"]);
+    assert!(!stdout.contains("__init__.py"), "{stdout}");
+    assert!(stdout.contains("path=\"pkg/main.py\""));
+    assert!(stderr.contains("skipped=1"), "{stderr}");
+}
+
+#[test]
+fn skip_empty_with_only_empty_files_reports_why() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").touch().unwrap();
+    dir.child("b.txt").touch().unwrap();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--skip-empty", "."])
+        .assert()
+        .code(4)
+        .stderr(contains("2 empty file(s) left out by --skip-empty"));
+}
@@ -0,0 +1,112 @@
+mod common;
+use assert_fs::prelude::*;
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> (String, String) {
+    let home = assert_fs::TempDir::new().unwrap();
+    let assert = common::context_gather(dir)
+        .env("XDG_CONFIG_HOME", home.path())
+        .args(args)
+        .arg(".")
+        .assert();
+    common::output_of(assert)
+}
+
+/// One file per category `--skip-generated` detects, plus a source file.
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("Cargo.lock")
+        .write_str("# lockfile_marker\n")
+        .unwrap();
+    dir.child("web/app.min.js")
+        .write_str("/* minified_marker */\n")
+        .unwrap();
+    dir.child("web/app.js.map")
+        .write_str("{\"source_map_marker\": 3}\n")
+        .unwrap();
+    dir.child("web/bundle.js")
+        .write_str(&format!("var long_line_marker={};\n", "1+".repeat(9000)))
+        .unwrap();
+    dir.child(".gitattributes")
+        .write_str("api/*.rs linguist-generated\n")
+        .unwrap();
+    dir.child("api/client.rs")
+        .write_str("// attribute_marker\n")
+        .unwrap();
+    dir.child("src/main.rs")
+        .write_str("fn main() {}\n")
+        .unwrap();
+    dir
+}
+
+const MARKERS: &[&str] = &[
+    "lockfile_marker",
+    "minified_marker",
+    "source_map_marker",
+    "long_line_marker",
+    "attribute_marker",
+];
+
+#[test]
+fn skip_generated_drops_each_category_and_lists_them() {
+    let dir = fixture();
+    let (stdout, stderr) = run(&dir, &["--skip-generated", "--list-skipped"]);
+    for marker in MARKERS {
+        assert!(!stdout.contains(marker), "{marker} kept: {stdout}");
+    }
+    assert!(stdout.contains("fn main() {}"), "{stdout}");
+    for path in [
+        "Cargo.lock",
+        "web/app.min.js",
+        "web/app.js.map",
+        "web/bundle.js",
+    ] {
+        let entry = format!(r#"<skipped-file path="{path}" reason="generated""#);
+        assert!(stdout.contains(&entry), "{entry} missing: {stdout}");
+    }
+    assert!(stdout.contains(r#"<skipped-file path="api/client.rs" reason="generated""#));
+    assert!(stderr.contains("skipped=5"), "{stderr}");
+    let note = stderr
+        .lines()
+        .find(|l| l.starts_with("note: left out 5 generated files: "))
+        .unwrap_or_else(|| panic!("{stderr}"));
+    assert!(note.contains("Cargo.lock"), "{note}");
+}
+
+#[test]
+fn generated_files_are_kept_by_default() {
+    let dir = fixture();
+    let (stdout, stderr) = run(&dir, &[]);
+    for marker in MARKERS {
+        assert!(stdout.contains(marker), "{marker} missing: {stdout}");
+    }
+    assert!(!stderr.contains("generated file"), "{stderr}");
+}
+
+#[test]
+fn gitattributes_can_keep_a_lockfile() {
+    let dir = fixture();
+    dir.child(".gitattributes")
+        .write_str("api/*.rs linguist-generated\nCargo.lock -linguist-generated\n")
+        .unwrap();
+    let (stdout, _) = run(&dir, &["--skip-generated"]);
+    assert!(stdout.contains("lockfile_marker"), "{stdout}");
+    assert!(!stdout.contains("attribute_marker"), "{stdout}");
+}
+
+#[test]
+fn keep_generated_overrides_the_config_file() {
+    let dir = fixture();
+    dir.child(".context-gather.toml")
+        .write_str("skip_generated = true\n")
+        .unwrap();
+    let (stdout, _) = run(&dir, &[]);
+    assert!(!stdout.contains("lockfile_marker"), "{stdout}");
+
+    let (stdout, _) = run(&dir, &["--keep-generated"]);
+    for marker in MARKERS {
+        assert!(stdout.contains(marker), "{marker} missing: {stdout}");
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+use assert_fs::prelude::*;
+use std::time::{Duration, SystemTime};
+
+/// Files whose path, size, and mtime orders differ.
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let now = SystemTime::now();
+    for (name, words, age_secs) in [("a.txt", 30, 200), ("b.txt", 5, 100), ("c.txt", 15, 300)] {
+        let child = dir.child(name);
+        child.write_str(&"word ".repeat(words)).unwrap();
+        let file = std::fs::File::options()
+            .write(true)
+            .open(child.path())
+            .unwrap();
+        file.set_modified(now - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+    dir
+}
+
+/// Paths in file-map order.
+fn file_map_order(args: &[&str]) -> Vec<String> {
+    let dir = fixture();
+    let (stdout, _) = common::run_ok(&dir, &[&["-c", "1000"], args, &["."]].concat());
+    stdout
+        .lines()
+        .filter(|l| l.trim_start().starts_with("<file id="))
+        .filter_map(|l| l.split("path=\"").nth(1))
+        .map(|rest| rest.split('"').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn sort_orders_file_map_for_each_mode() {
+    assert_eq!(file_map_order(&[]), ["a.txt", "b.txt", "c.txt"]);
+    assert_eq!(
+        file_map_order(&["--sort", "tokens"]),
+        ["b.txt", "c.txt", "a.txt"]
+    );
+    assert_eq!(
+        file_map_order(&["--sort", "size"]),
+        ["b.txt", "c.txt", "a.txt"]
+    );
+    assert_eq!(
+        file_map_order(&["--sort", "mtime"]),
+        ["c.txt", "a.txt", "b.txt"]
+    );
+}
+
+#[test]
+fn reverse_flips_each_mode() {
+    assert_eq!(file_map_order(&["--reverse"]), ["c.txt", "b.txt", "a.txt"]);
+    assert_eq!(
+        file_map_order(&["--sort", "tokens", "--reverse"]),
+        ["a.txt", "c.txt", "b.txt"]
+    );
+    assert_eq!(
+        file_map_order(&["--sort", "mtime", "--reverse"]),
+        ["b.txt", "a.txt", "c.txt"]
+    );
+}
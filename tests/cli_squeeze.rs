@@ -0,0 +1,46 @@
+mod common;
+use assert_fs::prelude::*;
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> (String, String) {
+    common::run_ok(dir, &[args, &["."]].concat())
+}
+
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("lib.rs")
+        .write_str("fn f() {   \n    if true {\n        g();\n    }\n\n\n\n}\n")
+        .unwrap();
+    dir.child("notes.md")
+        .write_str("- item\n    - nested  \n\n\n")
+        .unwrap();
+    dir
+}
+
+#[test]
+fn squeeze_rewrites_code_and_reports_the_savings() {
+    let dir = fixture();
+    let (stdout, stderr) = run(&dir, &["--squeeze"]);
+    assert!(
+        stdout.contains("fn f() {\n\tif true {\n\t\tg();\n\t}\n\n}\n"),
+        "{stdout}"
+    );
+    // Markdown keeps its spaces
+    assert!(stdout.contains("- item\n    - nested  \n\n\n"), "{stdout}");
+    let line = stderr
+        .lines()
+        .find(|l| l.starts_with("whitespace squeezing saved "))
+        .unwrap_or_else(|| panic!("{stderr}"));
+    assert!(line.contains(" → "), "{line}");
+    assert!(stderr.contains(" • saved="), "{stderr}");
+}
+
+#[test]
+fn squeeze_is_off_by_default() {
+    let dir = fixture();
+    let (stdout, stderr) = run(&dir, &[]);
+    assert!(stdout.contains("fn f() {   \n    if true {"), "{stdout}");
+    assert!(!stderr.contains("whitespace squeezing"), "{stderr}");
+}
@@ -0,0 +1,103 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::str::contains;
+use std::process::Command;
+
+fn git(
+    dir: &std::path::Path,
+    args: &[&str],
+) -> bool {
+    Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// A repository where `src/staged.rs` has a staged change (and a later,
+/// unstaged one on top) and `src/unstaged.rs` only an unstaged change.
+/// `None` when git is unavailable.
+fn repo() -> Option<assert_fs::TempDir> {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/staged.rs")
+        .write_str("fn staged() {}\n")
+        .unwrap();
+    dir.child("src/unstaged.rs")
+        .write_str("fn unstaged() {}\n")
+        .unwrap();
+    let ok = git(dir.path(), &["init", "--quiet"])
+        && git(dir.path(), &["add", "--all"])
+        && git(dir.path(), &["commit", "--quiet", "-m", "initial"]);
+    if !ok {
+        return None;
+    }
+    dir.child("src/staged.rs")
+        .write_str("fn staged() { index_version(); }\n")
+        .unwrap();
+    assert!(git(dir.path(), &["add", "src/staged.rs"]));
+    dir.child("src/staged.rs")
+        .write_str("fn staged() { working_version(); }\n")
+        .unwrap();
+    dir.child("src/unstaged.rs")
+        .write_str("fn unstaged() { edited(); }\n")
+        .unwrap();
+    Some(dir)
+}
+
+fn run(
+    dir: &std::path::Path,
+    args: &[&str],
+) -> String {
+    let (stdout, stderr) = common::run_ok(dir, args);
+    assert!(stderr.contains("OK 1 files"), "{stderr}");
+    stdout
+}
+
+#[test]
+fn staged_gathers_only_files_in_the_index() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let stdout = run(dir.path(), &["--staged"]);
+    assert!(stdout.contains("path=\"src/staged.rs\""), "{stdout}");
+    assert!(!stdout.contains("unstaged"), "{stdout}");
+    // Contents come from the working tree by default
+    assert!(stdout.contains("working_version()"), "{stdout}");
+}
+
+#[test]
+fn staged_content_reads_the_index() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let stdout = run(dir.path(), &["--staged", "--staged-content"]);
+    assert!(stdout.contains("index_version()"), "{stdout}");
+    assert!(!stdout.contains("working_version()"), "{stdout}");
+}
+
+#[test]
+fn staged_files_go_through_chunking() {
+    let Some(dir) = repo() else {
+        return;
+    };
+    let stdout = run(dir.path(), &["--staged", "-c", "2000"]);
+    assert!(stdout.contains("<context-chunk"), "{stdout}");
+    assert!(stdout.contains("working_version()"), "{stdout}");
+    assert!(!stdout.contains("unstaged"), "{stdout}");
+}
+
+#[test]
+fn staged_outside_a_repository_is_an_error() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("plain.rs").write_str("fn plain() {}\n").unwrap();
+    if git(dir.path(), &["rev-parse", "--is-inside-work-tree"]) {
+        return;
+    }
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--staged"])
+        .assert()
+        .code(2)
+        .stderr(contains("--staged needs a git repository"));
+}
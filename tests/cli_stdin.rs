@@ -0,0 +1,89 @@
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+const DIFF: &str = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-old\n+new\n";
+
+fn fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src/lib.rs")
+        .write_str("pub fn new() {}\n")
+        .unwrap();
+    dir
+}
+
+#[test]
+fn dash_reads_stdin_as_a_file_next_to_the_others() {
+    let dir = fixture();
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "-", "src/"])
+        .write_stdin(DIFF)
+        .assert()
+        .success()
+        .stderr(contains("OK 2 files"))
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!(
+            "<file-contents path=\"stdin.txt\" name=\"stdin.txt\">\n{DIFF}"
+        )),
+        "{stdout}"
+    );
+    assert!(stdout.contains("path=\"src/lib.rs\""), "{stdout}");
+    let entry = stdout
+        .lines()
+        .find(|l| l.contains("<file id=") && l.contains("path=\"stdin.txt\""))
+        .unwrap();
+    assert!(
+        entry.contains(&format!("size=\"{}\"", DIFF.len())),
+        "{entry}"
+    );
+}
+
+#[test]
+fn stdin_name_sets_the_pseudo_path_and_chunking_sees_it() {
+    let dir = fixture();
+    let long: String = (0..400)
+        .map(|i| format!("+line {i} of the change\n"))
+        .collect();
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--stdin-name",
+            "review/change.diff",
+        ])
+        .args(["--chunk-size", "1000", "-"])
+        .write_stdin(long)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "path=\"review/change.diff\" name=\"change.diff\" folder=\"review\" part=\"1/"
+        ),
+        "{stdout}"
+    );
+    assert!(stdout.contains("+line 0 of the change"), "{stdout}");
+    assert!(!stdout.contains("src/lib.rs"), "{stdout}");
+}
+
+#[test]
+fn dash_conflicts_with_prompts_that_read_stdin() {
+    let dir = fixture();
+    for args in [["-m", "-"], ["--paths-from", "-"]] {
+        assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args(["--stdout", "--no-clipboard"])
+            .args(args)
+            .arg("-")
+            .write_stdin(DIFF)
+            .assert()
+            .code(2)
+            .stderr(contains("the `-` path reads stdin"));
+    }
+}
@@ -0,0 +1,35 @@
+mod common;
+use assert_fs::prelude::*;
+
+fn run(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> (String, String) {
+    common::run_ok(dir, &[args, &["."]].concat())
+}
+
+#[test]
+fn strip_comments_drops_license_headers_in_known_languages() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let license: String = (0..20)
+        .map(|i| format!("// License clause {i}.\n"))
+        .collect();
+    dir.child("lib.rs")
+        .write_str(&format!(
+            "{license}fn f() -> &'static str {{\n    \"// not a comment\"\n}}\n"
+        ))
+        .unwrap();
+    dir.child("notes.txt").write_str("// kept as is\n").unwrap();
+
+    let (stdout, stderr) = run(&dir, &["--strip-comments"]);
+    assert!(!stdout.contains("License clause"), "{stdout}");
+    assert!(
+        stdout.contains("fn f() -> &'static str {\n    \"// not a comment\"\n}\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("// kept as is"), "{stdout}");
+    assert!(stderr.contains("comment stripping saved "), "{stderr}");
+
+    let (stdout, _) = run(&dir, &[]);
+    assert!(stdout.contains("License clause 19"), "{stdout}");
+}
@@ -0,0 +1,80 @@
+use assert_fs::prelude::*;
+use predicates::str::contains;
+
+fn stub_fixture() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("src").create_dir_all().unwrap();
+    dir.child("src/lib.rs")
+        .write_str("pub fn kept() {}\n")
+        .unwrap();
+    dir.child("schema.sql")
+        .write_str("CREATE TABLE stubbed_marker (id INT);\n")
+        .unwrap();
+    dir
+}
+
+#[test]
+fn stubbed_file_is_mapped_but_not_rendered() {
+    let dir = stub_fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stub-paths", "*.sql", "--stdout", "--no-clipboard", "."])
+        .assert()
+        .success()
+        .stderr(contains("OK 1 files"))
+        .stderr(contains("stubbed=1"))
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    let entry = stdout
+        .lines()
+        .find(|l| l.contains("path=\"schema.sql\""))
+        .expect("stubbed file should be in the file map");
+    assert!(entry.contains("stubbed=\"true\""));
+    assert!(entry.contains("parts=\"0\""));
+    assert!(entry.contains("size=\"38\""));
+    assert!(!stdout.contains("stubbed_marker"));
+    assert!(stdout.contains("pub fn kept()"));
+}
+
+#[test]
+fn stubbed_file_is_excluded_from_chunks() {
+    let dir = stub_fixture();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stub-paths",
+            "schema.sql",
+            "--stdout",
+            "--no-clipboard",
+            "--chunk-size",
+            "500",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("stubbed=\"true\""));
+    assert!(!stdout.contains("stubbed_marker"));
+    assert_eq!(stdout.matches("<file-contents").count(), 1);
+}
+
+#[test]
+fn all_invalid_stub_patterns_exit_with_usage_error() {
+    let dir = stub_fixture();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stub-paths", "[", "--stdout", "--no-clipboard", "."])
+        .assert()
+        .code(2)
+        .stderr(contains("Every --stub-paths pattern was invalid"));
+}
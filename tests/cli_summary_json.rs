@@ -0,0 +1,81 @@
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+use serde_json::Value;
+
+#[test]
+fn summary_json_to_stdout_parses_and_replaces_ok_line() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..4 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"tok\n".repeat(100))
+            .unwrap();
+    }
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--no-clipboard",
+            "--summary-json=stdout",
+            "--model-context",
+            "100",
+            "-c",
+            "200",
+            "-k",
+            "-1",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stderr(contains("OK ").not())
+        .get_output()
+        .clone();
+
+    let summary: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be one JSON object");
+    assert_eq!(summary["files"], 4);
+    assert!(summary["total_tokens"].as_u64().unwrap() > 100);
+    assert!(summary["chunks"].as_u64().unwrap() > 1);
+    assert_eq!(summary["copied"], serde_json::json!([]));
+    assert_eq!(summary["skipped"], 0);
+    assert_eq!(summary["model_context"], 100);
+    assert_eq!(summary["over_budget"], true);
+}
+
+#[test]
+fn summary_json_defaults_to_stderr_without_chunking() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str("hello").unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--no-clipboard",
+            "--no-model-context",
+            "--summary-json",
+            "a.txt",
+            "missing.txt",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !stderr.contains("OK "),
+        "human summary should be suppressed"
+    );
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .expect("stderr should contain the JSON summary");
+    let summary: Value = serde_json::from_str(line).unwrap();
+    assert_eq!(summary["files"], 1);
+    assert_eq!(summary["chunks"], 1);
+    assert_eq!(summary["skipped"], 1);
+    assert!(summary["total_tokens"].as_u64().unwrap() > 0);
+    assert_eq!(summary["model_context"], Value::Null);
+    assert_eq!(summary["over_budget"], false);
+}
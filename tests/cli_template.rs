@@ -0,0 +1,102 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+use std::path::PathBuf;
+
+fn template(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/templates")
+        .join(name)
+}
+
+fn fixture_tree() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("README.md").write_str("# Demo\n").unwrap();
+    dir.child("src/main.rs")
+        .write_str("fn main() {}\n")
+        .unwrap();
+    dir
+}
+
+fn render(
+    dir: &assert_fs::TempDir,
+    args: &[&str],
+) -> String {
+    common::run_ok(dir, &[args, &["."]].concat()).0
+}
+
+#[test]
+fn context_template_replaces_the_xml_envelope() {
+    let dir = fixture_tree();
+    let path = template("context.j2");
+    let stdout = render(&dir, &["--template", path.to_str().unwrap()]);
+    assert_eq!(
+        stdout,
+        "<context files=\"2\">\n\
+         \x20 <file path=\"README.md\" tokens=\"3\">\n# Demo\n  </file>\n\
+         \x20 <file path=\"src/main.rs\" tokens=\"4\">\nfn main() {}\n  </file>\n\
+         </context>\n"
+    );
+}
+
+#[test]
+fn preamble_template_walks_the_chunk_plan() {
+    let dir = fixture_tree();
+    dir.child("src/big.rs")
+        .write_str(&"let value = compute(1, 2, 3);\n".repeat(60))
+        .unwrap();
+    let path = template("preamble.md.j2");
+
+    let single = render(&dir, &["--template", path.to_str().unwrap()]);
+    assert!(
+        single.starts_with("You are reviewing 3 files.\n"),
+        "{single}"
+    );
+    assert!(single.contains("## Part 1 of 1\n"));
+    assert!(single.contains("### src/main.rs\n\n```\nfn main() {}\n```\n"));
+
+    let chunked = render(
+        &dir,
+        &["--template", path.to_str().unwrap(), "--chunk-size", "300"],
+    );
+    assert!(chunked.contains("## Part 1 of "), "{chunked}");
+    assert!(chunked.contains("### src/big.rs (1/"), "{chunked}");
+    assert!(!chunked.contains("<shared-context"));
+}
+
+#[test]
+fn template_syntax_error_reports_line_before_gathering() {
+    let dir = fixture_tree();
+    dir.child("broken.j2")
+        .write_str("line one\nline two\n{% for f in files %}\n")
+        .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--template", "broken.j2", "."])
+        .assert()
+        .failure()
+        .code(2)
+        .stdout("")
+        .stderr(contains("broken.j2:3"))
+        .stderr(contains("3 | {% for f in files %}"));
+}
+
+#[test]
+fn template_render_error_reports_line() {
+    let dir = fixture_tree();
+    dir.child("typo.j2")
+        .write_str("{% for f in files %}\n{{ f.contnets }}\n{% endfor %}\n")
+        .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--template", "typo.j2", "."])
+        .assert()
+        .failure()
+        .code(2)
+        .stdout("")
+        .stderr(contains("typo.j2:2"))
+        .stderr(contains("f.contnets").and(contains("undefined value")));
+}
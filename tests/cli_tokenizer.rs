@@ -1,4 +1,5 @@
 use assert_fs::prelude::*;
+use predicates::prelude::*;
 use predicates::str::contains;
 
 #[test]
@@ -54,3 +55,33 @@ fn unsupported_env_tokenizer_model_errors() {
         .failure()
         .stderr(contains("unsupported tokenizer model"));
 }
+
+#[test]
+fn model_preset_sets_context_unless_overridden() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("foo.txt")
+        .write_str(&"word ".repeat(200))
+        .unwrap();
+
+    let run = |args: &[&str]| {
+        assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .env_remove("CG_TOKENIZER_MODEL")
+            .args(["--no-clipboard", "--no-config"])
+            .args(args)
+            .arg("foo.txt")
+            .assert()
+    };
+
+    run(&["--model", "gpt-4o"])
+        .success()
+        .stderr(contains("exceeds").not());
+    run(&["--model", "gpt-4o", "--model-context", "10"])
+        .success()
+        .stderr(contains("exceeds model context limit 10"));
+    run(&["--model", "nope"])
+        .failure()
+        .code(2)
+        .stderr(contains("unknown model 'nope'"))
+        .stderr(contains("claude-sonnet"));
+}
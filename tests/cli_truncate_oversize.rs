@@ -0,0 +1,96 @@
+mod common;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+const MARKER: &str = "<!-- truncated: showing first 3000 of 6000 bytes -->";
+
+fn tree() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("small.rs").write_str("fn main() {}\n").unwrap();
+    // 200 lines of 30 bytes: the cut at 3000 bytes lands on a line boundary
+    dir.child("big.log")
+        .write_str(&"let value = compute(1, 2, 3);\n".repeat(200))
+        .unwrap();
+    dir
+}
+
+fn run(
+    dir: &assert_fs::TempDir,
+    extra: &[&str],
+) -> String {
+    common::run_ok(dir, &[&["--max-size", "3000"], extra, &["."]].concat()).0
+}
+
+#[test]
+fn oversize_files_are_skipped_without_the_flag() {
+    let dir = tree();
+    let stdout = run(&dir, &[]);
+    assert!(!stdout.contains("big.log"), "{stdout}");
+}
+
+#[test]
+fn truncated_file_is_marked_in_map_and_contents() {
+    let dir = tree();
+    let stdout = run(&dir, &["--truncate-oversize"]);
+    let entry = stdout
+        .lines()
+        .find(|l| l.contains("<file id=") && l.contains("big.log"))
+        .unwrap();
+    assert!(entry.contains(r#"size="6000""#), "{entry}");
+    assert!(entry.contains(r#"truncated="true""#), "{entry}");
+    assert!(!entry.contains("sha256"), "{entry}");
+    assert!(
+        stdout.contains(r#"<file-contents path="big.log" name="big.log" truncated="true">"#),
+        "{stdout}"
+    );
+    assert!(stdout.contains(&format!("compute(1, 2, 3);\n{MARKER}\n")));
+    assert_eq!(stdout.matches("let value").count(), 100);
+    // Files under the limit carry no attribute
+    assert_eq!(stdout.matches("truncated=").count(), 2);
+}
+
+#[test]
+fn truncated_attribute_follows_split_parts_and_json() {
+    let dir = tree();
+    let chunked = run(&dir, &["--truncate-oversize", "--chunk-size", "400"]);
+    let parts: Vec<&str> = chunked
+        .lines()
+        .filter(|l| l.contains(r#"<file-contents path="big.log""#))
+        .collect();
+    assert!(parts.len() > 1, "{chunked}");
+    assert!(
+        parts
+            .iter()
+            .all(|l| l.contains(r#"part=""#) && l.ends_with(r#" truncated="true">"#))
+    );
+
+    let json = run(&dir, &["--truncate-oversize", "--format", "json"]);
+    let doc: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let entry = doc["file_map"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["path"] == "big.log")
+        .unwrap();
+    assert_eq!(entry["truncated"], true);
+}
+
+#[test]
+fn truncation_is_warned_about() {
+    let dir = tree();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--max-size",
+            "3000",
+            "--truncate-oversize",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stderr(contains("FILE_TRUNCATED"))
+        .stderr(contains("FILE_SKIPPED_TOO_LARGE").not());
+}
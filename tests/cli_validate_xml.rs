@@ -0,0 +1,96 @@
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+fn tree_with_vertical_tab() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("README.md").write_str("# Demo\n").unwrap();
+    dir.child("src/vt.txt").write_str("a\u{b}b\n").unwrap();
+    dir
+}
+
+#[test]
+fn vertical_tab_fails_before_anything_is_printed() {
+    let dir = tree_with_vertical_tab();
+    for extra in [&["--escape-xml"][..], &["--cdata"][..]] {
+        assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args(["--stdout", "--no-clipboard", "--validate-xml", "."])
+            .args(extra)
+            .assert()
+            .failure()
+            .code(7)
+            .stdout("")
+            .stderr(contains("output is not well-formed XML"))
+            .stderr(contains("U+000B"))
+            .stderr(contains("in src/vt.txt"))
+            .stderr(contains("at byte "))
+            .stderr(contains("tr -d '\\013' < src/vt.txt"))
+            .stderr(contains("INVALID_XML"));
+    }
+}
+
+#[test]
+fn chunked_mode_names_the_bad_chunk() {
+    let dir = tree_with_vertical_tab();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--validate-xml",
+            "--escape-xml",
+        ])
+        .args(["--chunk-size", "2000", "."])
+        .assert()
+        .failure()
+        .code(7)
+        .stdout("")
+        .stderr(contains("chunk 1 is not well-formed XML"))
+        .stderr(contains("in src/vt.txt"));
+}
+
+#[test]
+fn escaped_output_passes_in_every_mode() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("cmp.rs")
+        .write_str("if a < b && c > d {}\n")
+        .unwrap();
+    dir.child("src/big.rs")
+        .write_str(&"let value = compute(1, 2, 3);\n".repeat(60))
+        .unwrap();
+    for extra in [
+        &["--escape-xml"][..],
+        &["--cdata"][..],
+        &["--escape-xml", "--chunk-size", "300"][..],
+        &[
+            "--cdata",
+            "--chunk-size",
+            "300",
+            "--header-position",
+            "last",
+        ][..],
+    ] {
+        assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args(["--stdout", "--no-clipboard", "--validate-xml", "."])
+            .args(extra)
+            .assert()
+            .success()
+            .stdout(contains("if a "));
+    }
+}
+
+#[test]
+fn raw_markup_suggests_escaping() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("cmp.rs").write_str("if a <b {}\n").unwrap();
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "--validate-xml", "."])
+        .assert()
+        .failure()
+        .code(7)
+        .stderr(contains("rerun with --escape-xml or --cdata"))
+        .stderr(contains("U+").not());
+}
@@ -9,7 +9,7 @@ fn missing_literal_warns_and_reports_skipped() {
 
     assert_cmd::cargo::cargo_bin_cmd!("context-gather")
         .current_dir(&dir)
-        .args(["--stdout", "--no-clipboard", "missing.txt"])
+        .args(["--stdout", "--no-clipboard", "--allow-empty", "missing.txt"])
         .assert()
         .success()
         .stdout(contains(r#"total-files="0""#))
@@ -25,7 +25,14 @@ fn oversize_only_warns_and_reports_skipped() {
 
     assert_cmd::cargo::cargo_bin_cmd!("context-gather")
         .current_dir(&dir)
-        .args(["--stdout", "--no-clipboard", "--max-size", "1", "big.txt"])
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--allow-empty",
+            "--max-size",
+            "1",
+            "big.txt",
+        ])
         .assert()
         .success()
         .stdout(contains(r#"total-files="0""#))
@@ -40,7 +47,7 @@ fn binary_only_warns_and_reports_skipped() {
 
     assert_cmd::cargo::cargo_bin_cmd!("context-gather")
         .current_dir(&dir)
-        .args(["--stdout", "--no-clipboard", "bin.dat"])
+        .args(["--stdout", "--no-clipboard", "--allow-empty", "."])
         .assert()
         .success()
         .stdout(contains(r#"total-files="0""#))
@@ -48,6 +55,91 @@ fn binary_only_warns_and_reports_skipped() {
         .stderr(contains("skipped=1"));
 }
 
+#[test]
+fn explicit_binary_is_attached_as_placeholder() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("assets").create_dir_all().unwrap();
+    fs::write(dir.path().join("assets/logo.png"), [0_u8, 255, 0, 128]).unwrap();
+    dir.child("assets/other.png")
+        .write_binary(&[255, 254, 0, 0])
+        .unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--stdout", "--no-clipboard", "assets", "assets/logo.png"])
+        .assert()
+        .success()
+        .stderr(contains("appears to be a binary file"))
+        .stderr(contains("skipped=1"))
+        .get_output()
+        .clone();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(r#"path="assets/logo.png" tokens="0" parts="1" size="4" mtime=""#),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(
+            r#"sha256="787a4bce1c8b1ef7f111820f3840c98b4ddf51d55f5e7a9b007fb6224bb563bc" binary="true"/>"#
+        ),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(
+            r#"<file-binary path="assets/logo.png" size="4B" sha256="787a4bce1c8b1ef7f111820f3840c98b4ddf51d55f5e7a9b007fb6224bb563bc"/>"#
+        ),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("other.png"), "{stdout}");
+}
+
+#[test]
+fn utf16_and_latin1_files_are_decoded_and_marked() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let utf16: Vec<u8> = [0xff, 0xfe]
+        .into_iter()
+        .chain(
+            "Get-Date # déjà\r\n"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        )
+        .collect();
+    fs::write(dir.path().join("script.ps1"), utf16).unwrap();
+    fs::write(dir.path().join("latin1.txt"), b"r\xe9sum\xe9\n").unwrap();
+
+    for chunked in [&[][..], &["-c", "500"][..]] {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args(["--stdout", "--no-clipboard"])
+            .args(chunked)
+            .arg(".")
+            .assert()
+            .success()
+            .stderr(contains("binary").not())
+            .get_output()
+            .clone();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Get-Date # déjà"), "{stdout}");
+        assert!(stdout.contains("résumé"), "{stdout}");
+        let tag = |path: &str| {
+            stdout
+                .lines()
+                .find(|l| l.contains(&format!("<file-contents path=\"{path}\"")))
+                .unwrap()
+                .to_string()
+        };
+        assert!(tag("script.ps1").contains(r#" encoding="UTF-16LE""#));
+        assert!(tag("latin1.txt").contains(r#" encoding="windows-1252""#));
+        assert!(stdout.contains(r#"path="latin1.txt" tokens="#));
+        assert_eq!(
+            stdout.matches(r#"encoding="windows-1252""#).count(),
+            2,
+            "{stdout}"
+        );
+    }
+}
+
 #[test]
 fn exclude_all_warns_without_skipped_count() {
     let dir = assert_fs::TempDir::new().unwrap();
@@ -58,6 +150,7 @@ fn exclude_all_warns_without_skipped_count() {
         .args([
             "--stdout",
             "--no-clipboard",
+            "--allow-empty",
             "--exclude-paths",
             "**/*.rs",
             ".",
@@ -93,3 +186,151 @@ fn mixed_success_and_skip_reports_skipped_count() {
         .stderr(contains("OK 1 files"))
         .stderr(contains("skipped=1"));
 }
+
+#[test]
+fn fail_over_budget_exits_with_code_5() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    for i in 0..3 {
+        dir.child(format!("f{i}.txt"))
+            .write_str(&"budget words ".repeat(20))
+            .unwrap();
+    }
+
+    for chunk_args in [&[][..], &["-c", "200"][..]] {
+        assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .args([
+                "--no-clipboard",
+                "--model-context",
+                "10",
+                "--fail-over-budget",
+            ])
+            .args(chunk_args)
+            .arg(".")
+            .assert()
+            .code(5)
+            .stderr(contains("OK 3 files"))
+            .stderr(contains("exceeds model context limit 10 by"));
+    }
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--no-clipboard", "--model-context", "10", "."])
+        .assert()
+        .success()
+        .stderr(contains("exceeds model context limit 10 by"));
+}
+
+#[test]
+fn json_log_format_tags_events_with_stable_codes() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str(&"tok ".repeat(50)).unwrap();
+    fs::write(dir.path().join("bin.dat"), [0_u8, 255, 0, 128]).unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args([
+            "--stdout",
+            "--no-clipboard",
+            "--log-format",
+            "json",
+            "--model-context",
+            "10",
+            ".",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).expect("each log line should be JSON"))
+        .collect();
+    let binary = events
+        .iter()
+        .find(|e| e["event_code"] == "FILE_SKIPPED_BINARY")
+        .expect("binary skip should be logged");
+    assert_eq!(binary["level"], "WARN");
+    assert!(binary["path"].as_str().unwrap().ends_with("bin.dat"));
+    assert!(events.iter().any(|e| e["event_code"] == "OVERFLOW"));
+    assert!(!stderr.contains("WARN context_gather"));
+}
+
+#[test]
+fn verbosity_flags_change_stderr() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str(&"tok ".repeat(50)).unwrap();
+    let run = |flags: &[&str]| {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .current_dir(&dir)
+            .env_remove("RUST_LOG")
+            .args(["--no-config", "--no-clipboard", "--model-context", "10"])
+            .args(["--exclude-paths", "nothing/**", "-c", "1000"])
+            .args(flags)
+            .arg("a.txt")
+            .assert()
+            .success()
+            .get_output()
+            .clone();
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+
+    let default = run(&[]);
+    assert!(default.contains("exceeds model context limit"));
+    assert!(default.contains("OK 1 files"));
+    assert!(default.contains("nothing visible"));
+    assert!(!default.contains("config: no config file loaded"));
+
+    let verbose = run(&["-v"]);
+    assert!(verbose.contains("config: no config file loaded"));
+    assert!(!verbose.contains("matched 0 files"));
+
+    let debug = run(&["-vv"]);
+    assert!(debug.contains(r#"--exclude-paths "nothing/**" matched 0 files"#));
+
+    let quiet = run(&["-q"]);
+    assert!(!quiet.contains("exceeds model context limit"));
+    assert!(!quiet.contains("OK 1 files"));
+    assert!(!quiet.contains("nothing visible"));
+}
+
+#[test]
+fn rust_log_overrides_quiet() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("a.txt").write_str(&"tok ".repeat(50)).unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .env("RUST_LOG", "warn")
+        .args(["--no-clipboard", "-q", "--model-context", "10", "a.txt"])
+        .assert()
+        .success()
+        .stderr(contains("exceeds model context limit"))
+        .stderr(contains("OK 1 files").not());
+}
+
+#[test]
+fn warn_file_tokens_names_only_the_big_file() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("big.txt").write_str(&"tok ".repeat(500)).unwrap();
+    dir.child("small.txt").write_str("tiny").unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+        .current_dir(&dir)
+        .args(["--no-clipboard", "--warn-file-tokens", "100", "."])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let warnings: Vec<&str> = stderr
+        .lines()
+        .filter(|l| l.contains("--warn-file-tokens"))
+        .collect();
+    assert_eq!(warnings.len(), 1, "{stderr}");
+    assert!(warnings[0].contains("big.txt"));
+    assert!(stderr.contains("over-file-tokens=1"));
+}
@@ -1,5 +1,33 @@
+// Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
 use assert_fs::TempDir;
 use std::fs;
+use std::path::Path;
+
+/// `context-gather` run in `dir`, printing to stdout instead of the clipboard.
+pub fn context_gather(dir: impl AsRef<Path>) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("context-gather");
+    cmd.current_dir(dir).args(["--stdout", "--no-clipboard"]);
+    cmd
+}
+
+/// Stdout and stderr of a run that must succeed, decoded lossily.
+pub fn output_of(assert: assert_cmd::assert::Assert) -> (String, String) {
+    let output = assert.success().get_output().clone();
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+/// Run `context_gather` in `dir` with `args`; see `output_of`.
+pub fn run_ok(
+    dir: impl AsRef<Path>,
+    args: &[&str],
+) -> (String, String) {
+    output_of(context_gather(dir.as_ref()).args(args).assert())
+}
 
 /// Builds a fixture tree:
 /// root/
@@ -7,6 +35,7 @@ use std::fs;
 ///   README.md
 ///   bin/binary.dat  (non-UTF8)
 ///   deep/ignore.me     (# ignored via .gitignore)
+///   .env.example       (hidden)
 pub fn basic_fs() -> TempDir {
     let td = TempDir::new().unwrap();
     let root = td.path();
@@ -26,13 +55,16 @@ pub fn basic_fs() -> TempDir {
     // binary file
     let bin_dir = root.join("bin");
     fs::create_dir_all(&bin_dir).unwrap();
-    fs::write(bin_dir.join("binary.dat"), vec![0u8; 4096]).unwrap();
+    fs::write(bin_dir.join("binary.dat"), [0u8, 0xff].repeat(2048)).unwrap();
 
     // deep ignored file
     let deep_dir = root.join("deep");
     fs::create_dir_all(&deep_dir).unwrap();
     fs::write(deep_dir.join("ignore.me"), "should be ignored\n").unwrap();
 
+    // hidden dotfile
+    fs::write(root.join(".env.example"), "KEY=value\n").unwrap();
+
     // .gitignore
     fs::write(root.join(".gitignore"), "/deep\n").unwrap();
     td
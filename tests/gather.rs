@@ -65,6 +65,171 @@ fn gather_all_file_paths_respects_gitignore() {
     assert!(!all.contains(&std::path::Path::new("deep/ignore.me")));
 }
 
+#[test]
+fn hidden_and_no_ignore_are_independent() {
+    let td = basic_fs();
+    let walk = |hidden, no_ignore| {
+        let options = WalkOptions {
+            hidden,
+            no_ignore,
+            ..Default::default()
+        };
+        gather_all_file_paths_with(&[td.path().into()], &options, &|_| {})
+            .unwrap()
+            .iter()
+            .map(|p| p.strip_prefix(td.path()).unwrap().to_path_buf())
+            .collect::<Vec<_>>()
+    };
+    let dotfile = std::path::PathBuf::from(".env.example");
+    let ignored = std::path::PathBuf::from("deep/ignore.me");
+
+    let default = walk(false, false);
+    assert!(!default.contains(&dotfile));
+    assert!(!default.contains(&ignored));
+
+    let hidden = walk(true, false);
+    assert!(hidden.contains(&dotfile));
+    assert!(!hidden.contains(&ignored));
+
+    let no_ignore = walk(false, true);
+    assert!(!no_ignore.contains(&dotfile));
+    assert!(no_ignore.contains(&ignored));
+
+    let both = walk(true, true);
+    assert!(both.contains(&dotfile));
+    assert!(both.contains(&ignored));
+}
+
+#[test]
+fn parallel_walk_matches_serial_order() {
+    let td = basic_fs();
+    for d in 0..6 {
+        for f in 0..8 {
+            td.child(format!("tree{d}/sub{f}/file{f}.txt"))
+                .write_str("x")
+                .unwrap();
+        }
+    }
+    // A deep chain with files and a `.cgignore` at every level
+    let mut deep = std::path::PathBuf::from("chain");
+    for level in 0..24 {
+        deep.push(format!("level{level}"));
+        for f in 0..3 {
+            td.child(deep.join(format!("f{f}.rs")))
+                .write_str("x")
+                .unwrap();
+        }
+        td.child(deep.join("skip.log")).write_str("x").unwrap();
+        td.child(deep.join(".cgignore"))
+            .write_str("*.log\n")
+            .unwrap();
+    }
+
+    let mut serial = Vec::new();
+    for entry in ignore::WalkBuilder::new(td.path())
+        .follow_links(false)
+        .standard_filters(true)
+        .add_custom_ignore_filename(".gitignore")
+        .add_custom_ignore_filename(".cgignore")
+        .build()
+    {
+        let entry = entry.unwrap();
+        if entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+            serial.push(entry.path().to_path_buf());
+        }
+    }
+    serial.sort();
+    assert!(serial.iter().any(|p| p.ends_with("level23/f2.rs")));
+    assert!(!serial.iter().any(|p| p.ends_with("skip.log")));
+
+    for threads in [1, 4, 16] {
+        let options = WalkOptions {
+            threads,
+            ..Default::default()
+        };
+        let parallel = gather_all_file_paths_with(&[td.path().into()], &options, &|_| {}).unwrap();
+        assert_eq!(parallel, serial, "threads={threads}");
+    }
+}
+
+#[test]
+fn parallel_walk_reports_progress_counts() {
+    let td = basic_fs();
+    let max_seen = std::sync::atomic::AtomicUsize::new(0);
+    let paths = gather_all_file_paths_with(&[td.path().into()], &WalkOptions::default(), &|n| {
+        max_seen.fetch_max(n, std::sync::atomic::Ordering::Relaxed);
+    })
+    .unwrap();
+
+    assert_eq!(max_seen.into_inner(), paths.len());
+}
+
+#[test]
+fn max_depth_cuts_off_nesting() {
+    let td = basic_fs();
+    td.child("src/nested/deeper/leaf.rs")
+        .write_str("x")
+        .unwrap();
+    let walk = |max_depth| {
+        let options = WalkOptions {
+            max_depth,
+            ..Default::default()
+        };
+        gather_all_file_paths_with(&[td.path().into()], &options, &|_| {})
+            .unwrap()
+            .iter()
+            .map(|p| p.strip_prefix(td.path()).unwrap().to_path_buf())
+            .collect::<Vec<_>>()
+    };
+
+    assert!(walk(Some(0)).is_empty());
+    let depth1 = walk(Some(1));
+    assert!(depth1.contains(&"README.md".into()));
+    assert!(!depth1.contains(&"src/hello.rs".into()));
+    let depth2 = walk(Some(2));
+    assert!(depth2.contains(&"src/hello.rs".into()));
+    assert!(!depth2.iter().any(|p| p.ends_with("leaf.rs")));
+    assert!(walk(None).iter().any(|p| p.ends_with("leaf.rs")));
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_links_walks_symlinked_dirs_once() {
+    let td = basic_fs();
+    std::os::unix::fs::symlink(td.path().join("src"), td.path().join("linked")).unwrap();
+    // A link back to the root would recurse forever without loop protection.
+    std::os::unix::fs::symlink(td.path(), td.path().join("src/back")).unwrap();
+    let walk = |follow_links| {
+        let options = WalkOptions {
+            follow_links,
+            ..Default::default()
+        };
+        gather_all_file_paths_with(&[td.path().into()], &options, &|_| {})
+            .unwrap()
+            .iter()
+            .map(|p| p.strip_prefix(td.path()).unwrap().to_path_buf())
+            .collect::<Vec<_>>()
+    };
+
+    let plain = walk(false);
+    assert!(plain.contains(&"src/hello.rs".into()));
+    assert!(!plain.iter().any(|p| p.starts_with("linked")));
+
+    let followed = walk(true);
+    let hello_copies = followed.iter().filter(|p| p.ends_with("hello.rs")).count();
+    assert_eq!(hello_copies, 1, "src and linked share one canonical dir");
+    assert!(followed.contains(&"README.md".into()));
+}
+
+/// Read options with hashing on and a `max_size` limit.
+fn options(max_size: u64) -> ReadOptions {
+    ReadOptions {
+        max_size,
+        hashes: true,
+        ..ReadOptions::default()
+    }
+}
+
 #[test]
 fn read_file_skips_binary_and_too_large() {
     let dir = assert_fs::TempDir::new().unwrap();
@@ -73,17 +238,236 @@ fn read_file_skips_binary_and_too_large() {
     let bin_path = bin_child.path().to_path_buf();
     fs::write(&bin_path, [0u8, 255u8, 0u8, 128u8]).unwrap();
     // Read and detect binary
-    let err = read_file(&bin_path, 1024, dir.path()).unwrap_err();
+    let err = read_file(&bin_path, dir.path(), &options(1024)).unwrap_err();
 
     let huge_child = dir.child("huge.txt");
     let huge_path = huge_child.path().to_path_buf();
     fs::write(&huge_path, "x".repeat(2048).into_bytes()).unwrap();
-    let err2 = read_file(&huge_path, 1000, dir.path()).unwrap_err();
+    let err2 = read_file(&huge_path, dir.path(), &options(1000)).unwrap_err();
 
     assert!(format!("{err}").contains("binary"), "{err}");
     assert!(format!("{err2}").contains("exceeds 1000"), "{err2}");
 }
 
+/// `text` as UTF-16 with a byte order mark.
+fn utf16_with_bom(
+    text: &str,
+    little_endian: bool,
+) -> Vec<u8> {
+    let bom: &[u8] = if little_endian {
+        &[0xff, 0xfe]
+    } else {
+        &[0xfe, 0xff]
+    };
+    let units = text.encode_utf16().flat_map(|unit| {
+        if little_endian {
+            unit.to_le_bytes()
+        } else {
+            unit.to_be_bytes()
+        }
+    });
+    bom.iter().copied().chain(units).collect()
+}
+
+#[test]
+fn read_file_decodes_utf16_with_a_bom() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let text = "Write-Host \"héllo\"\r\n# 日本語\r\n";
+    for (name, little_endian, encoding) in
+        [("le.ps1", true, "UTF-16LE"), ("be.ps1", false, "UTF-16BE")]
+    {
+        let path = dir.child(name).path().to_path_buf();
+        let bytes = utf16_with_bom(text, little_endian);
+        fs::write(&path, &bytes).unwrap();
+
+        let file = read_file(&path, dir.path(), &options(1024)).unwrap();
+        assert_eq!(file.contents, text);
+        assert_eq!(file.encoding.as_deref(), Some(encoding));
+        assert_eq!(file.size, Some(bytes.len() as u64));
+    }
+}
+
+#[test]
+fn read_file_decodes_latin1_accents() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.child("notes.txt").path().to_path_buf();
+    // "Café crème, naïve façade" in ISO-8859-1
+    fs::write(&path, b"Caf\xe9 cr\xe8me, na\xefve fa\xe7ade\n").unwrap();
+
+    let file = read_file(&path, dir.path(), &options(1024)).unwrap();
+    assert_eq!(file.contents, "Café crème, naïve façade\n");
+    assert_eq!(file.encoding.as_deref(), Some("windows-1252"));
+
+    // UTF-8 files record no encoding
+    fs::write(&path, "Café\n").unwrap();
+    let file = read_file(&path, dir.path(), &options(1024)).unwrap();
+    assert_eq!(file.encoding, None);
+}
+
+#[test]
+fn read_file_normalizes_crlf_and_bom_on_request() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.child("win.txt").path().to_path_buf();
+    let bytes = b"\xef\xbb\xbfone\r\ntwo\r\n";
+    fs::write(&path, bytes).unwrap();
+
+    let file = read_file(
+        &path,
+        dir.path(),
+        &ReadOptions {
+            eol: true,
+            ..options(1024)
+        },
+    )
+    .unwrap();
+    assert_eq!(file.contents, "one\ntwo\n");
+    assert!(file.crlf);
+    assert_eq!(file.size, Some(bytes.len() as u64));
+
+    let raw = read_file(&path, dir.path(), &options(1024)).unwrap();
+    assert_eq!(raw.contents, "\u{feff}one\r\ntwo\r\n");
+    assert!(!raw.crlf);
+    assert_eq!(raw.sha256, file.sha256);
+
+    // A BOM alone is dropped without marking the file
+    fs::write(&path, b"\xef\xbb\xbfone\n").unwrap();
+    let file = read_file(
+        &path,
+        dir.path(),
+        &ReadOptions {
+            eol: true,
+            ..options(1024)
+        },
+    )
+    .unwrap();
+    assert_eq!(file.contents, "one\n");
+    assert!(!file.crlf);
+}
+
+#[test]
+fn read_file_still_skips_binary_that_is_not_utf8() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.child("data.bin").path().to_path_buf();
+    for bytes in [
+        &[
+            0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 0x0d,
+        ][..],
+        // A UTF-16 BOM followed by NULs and an unpaired surrogate
+        &[0xff, 0xfe, 0, 0, 0x00, 0xd8][..],
+    ] {
+        fs::write(&path, bytes).unwrap();
+        let err = read_file(&path, dir.path(), &options(1024)).unwrap_err();
+        assert!(format!("{err}").contains("binary"), "{err}");
+    }
+}
+
+#[test]
+fn truncate_oversize_cuts_utf16_after_a_whole_line() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.child("log.txt").path().to_path_buf();
+    let bytes = utf16_with_bom("one\ntwo\nthree\n", true);
+    fs::write(&path, &bytes).unwrap();
+
+    // The BOM and "one\ntw" plus half a code unit
+    let file = read_file(
+        &path,
+        dir.path(),
+        &ReadOptions {
+            truncate_oversize: true,
+            ..options(15)
+        },
+    )
+    .unwrap();
+    assert_eq!(file.encoding.as_deref(), Some("UTF-16LE"));
+    assert_eq!(
+        file.contents,
+        format!(
+            "one\n<!-- truncated: showing first 10 of {} bytes -->\n",
+            bytes.len()
+        )
+    );
+}
+
+#[test]
+fn truncate_to_tokens_keeps_whole_lines_and_the_marker_within_the_limit() {
+    let contents: String = (0..100).map(|i| format!("line number {i}\n")).collect();
+    let total = count_tokens(&contents);
+    let cut = truncate_to_tokens(&contents, 60, total);
+    assert!(count_tokens(&cut) <= 60, "{cut}");
+    let (kept, marker) = cut.rsplit_once("<!-- ").unwrap();
+    assert!(contents.starts_with(kept) && kept.ends_with('\n'));
+    assert!(kept.contains("line number 0\n"));
+    assert_eq!(
+        format!("<!-- {marker}"),
+        token_truncation_marker(count_tokens(kept), total)
+    );
+
+    // A first line that cannot fit leaves only the marker
+    assert_eq!(
+        truncate_to_tokens(&contents, 5, total),
+        token_truncation_marker(0, total)
+    );
+}
+
+#[test]
+fn truncate_oversize_cuts_at_a_line_boundary() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.child("log.txt").path().to_path_buf();
+    fs::write(&path, "line1\nline2\nline3\n").unwrap();
+
+    let file = read_file(
+        &path,
+        dir.path(),
+        &ReadOptions {
+            truncate_oversize: true,
+            ..options(9)
+        },
+    )
+    .unwrap();
+    assert!(file.truncated);
+    assert_eq!(file.size, Some(18));
+    assert_eq!(file.sha256, None);
+    assert_eq!(
+        file.contents,
+        "line1\n<!-- truncated: showing first 6 of 18 bytes -->\n"
+    );
+
+    // Under the limit nothing changes
+    let file = read_file(
+        &path,
+        dir.path(),
+        &ReadOptions {
+            truncate_oversize: true,
+            ..options(18)
+        },
+    )
+    .unwrap();
+    assert!(!file.truncated);
+    assert_eq!(file.contents, "line1\nline2\nline3\n");
+}
+
+#[test]
+fn truncate_oversize_without_newline_keeps_whole_characters() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.child("accents.txt").path().to_path_buf();
+    // Five two-byte characters; the fifth byte is the middle of the third
+    fs::write(&path, "ééééé").unwrap();
+
+    let file = read_file(
+        &path,
+        dir.path(),
+        &ReadOptions {
+            truncate_oversize: true,
+            ..options(5)
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        file.contents,
+        "éé\n<!-- truncated: showing first 4 of 10 bytes -->\n"
+    );
+}
+
 #[test]
 fn token_count_is_stable() {
     let n = count_tokens("hello world");
@@ -0,0 +1,83 @@
+//! Golden-file tests: each built-in `--render-fixture` must match its
+//! committed output under `tests/golden/`. After an intended output change,
+//! regenerate with `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use context_gather::fixtures::FIXTURES;
+use std::path::PathBuf;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.golden"))
+}
+
+/// First differing line, for a readable failure instead of two full dumps.
+fn first_difference(
+    expected: &str,
+    actual: &str,
+) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line_no in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (e, a) if e == a => continue,
+            (e, a) => {
+                return format!("line {line_no}:\n  expected: {e:?}\n  actual:   {a:?}");
+            }
+        }
+    }
+    "outputs differ only in trailing newlines".to_string()
+}
+
+#[test]
+fn fixtures_match_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+    for fixture in FIXTURES {
+        let output = assert_cmd::cargo::cargo_bin_cmd!("context-gather")
+            .args(["--render-fixture", fixture.name])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let actual = String::from_utf8(output).expect("fixture output is UTF-8");
+        let path = golden_path(fixture.name);
+        if update {
+            std::fs::write(&path, &actual).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "missing {}; run UPDATE_GOLDEN=1 cargo test --test golden",
+                path.display()
+            )
+        });
+        if actual != expected {
+            failures.push(format!(
+                "{}: {}",
+                fixture.name,
+                first_difference(&expected, &actual)
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "output differs from golden files (rerun with UPDATE_GOLDEN=1 if intended):\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn every_golden_file_has_a_fixture() {
+    for entry in std::fs::read_dir(golden_path("x").parent().unwrap()).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_string_lossy();
+        assert!(
+            FIXTURES.iter().any(|f| f.name == name),
+            "{} has no matching fixture",
+            path.display()
+        );
+    }
+}
@@ -1,4 +1,9 @@
-use context_gather::{chunker::FileMeta, header::make_header};
+use context_gather::{
+    chunker::FileMeta,
+    gather::{SkipReason, SkippedFile},
+    header::{HeaderSpec, make_header, make_multi_step_header_chunks},
+    tokenizer::count as count_tokens,
+};
 use std::path::PathBuf;
 
 #[test]
@@ -9,16 +14,24 @@ fn header_reports_totals_correctly() {
             path: PathBuf::from("a.rs"),
             tokens: 10,
             parts: 1,
+            ..Default::default()
         },
         FileMeta {
             id: 1,
             path: PathBuf::from("b.rs"),
             tokens: 20,
             parts: 2,
+            ..Default::default()
         },
     ];
-    let hdr = make_header(5, 40000, &metas, false, false, false);
+    let hdr = make_header(&HeaderSpec {
+        total_chunks: 5,
+        chunk_size: 40000,
+        files: &metas,
+        ..Default::default()
+    });
     assert!(hdr.contains(r#"total-chunks="5""#));
+    assert!(hdr.contains(r#"index="0" total="5" kind="header""#));
     assert!(hdr.contains(r#"total-files="2""#));
     assert!(hdr.contains(r#"id="1" path="b.rs" tokens="20" parts="2""#));
 }
@@ -31,8 +44,14 @@ fn test_git_info_included() {
         path: PathBuf::from("a.rs"),
         tokens: 10,
         parts: 1,
+        ..Default::default()
     }];
-    let hdr = make_header(1, 100, &metas, false, false, true);
+    let hdr = make_header(&HeaderSpec {
+        chunk_size: 100,
+        files: &metas,
+        include_git: true,
+        ..Default::default()
+    });
     if hdr.contains("git info unavailable") {
         return;
     }
@@ -50,3 +69,206 @@ fn test_git_info_included() {
         commit_count
     );
 }
+
+#[test]
+fn default_spec_renders_empty_single_chunk_header() {
+    let hdr = make_header(&HeaderSpec::default());
+    assert!(hdr.contains(r#"total-chunks="1" chunk-size="0""#));
+    assert!(hdr.contains(r#"total-files="0""#));
+    assert!(!hdr.contains("git-info"));
+}
+
+#[test]
+fn multi_step_header_splits_large_file_map_under_limit() {
+    let metas: Vec<FileMeta> = (0..5000)
+        .map(|id| FileMeta {
+            id,
+            path: PathBuf::from(format!("src/module_{}/file_{id}.rs", id % 37)),
+            tokens: 100 + id,
+            parts: 1,
+            ..Default::default()
+        })
+        .collect();
+    let limit = 8000;
+    let chunks = make_multi_step_header_chunks(&HeaderSpec {
+        chunk_size: limit,
+        files: &metas,
+        multi_step: true,
+        ..Default::default()
+    });
+
+    let total = chunks.len();
+    assert!(total > 1, "5000 entries should not fit in {limit} tokens");
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let tokens = count_tokens(chunk);
+        assert!(tokens <= limit, "chunk {idx} has {tokens} tokens");
+        assert!(chunk.contains(&format!("part=\"{}/{total}\"", idx + 1)));
+    }
+    assert!(chunks[0].contains(&format!("split into {total} parts")));
+    assert!(chunks[0].contains(&format!("total-chunks=\"{total}\"")));
+    let entries: usize = chunks.iter().map(|c| c.matches("<file id=").count()).sum();
+    assert_eq!(entries, 5000);
+}
+
+#[test]
+fn multi_step_header_stays_whole_when_it_fits() {
+    let metas = vec![FileMeta {
+        id: 0,
+        path: PathBuf::from("a.rs"),
+        tokens: 10,
+        parts: 1,
+        ..Default::default()
+    }];
+    let chunks = make_multi_step_header_chunks(&HeaderSpec {
+        chunk_size: 8000,
+        files: &metas,
+        multi_step: true,
+        ..Default::default()
+    });
+    assert_eq!(chunks.len(), 1);
+    assert!(chunks[0].starts_with("<shared-context>\n"));
+    assert!(!chunks[0].contains("part="));
+}
+
+#[test]
+fn generated_at_can_be_pinned() {
+    let hdr = make_header(&HeaderSpec {
+        generated_at: Some("2000-01-01T00:00:00Z"),
+        ..Default::default()
+    });
+    assert!(hdr.contains(r#"generated-at="2000-01-01T00:00:00Z""#));
+}
+
+#[test]
+fn custom_instructions_replace_or_append() {
+    let custom = "Answer in French.\nFocus on module X.";
+    let replaced = make_header(&HeaderSpec {
+        total_chunks: 3,
+        instructions: Some(custom),
+        ..Default::default()
+    });
+    assert!(replaced.contains(
+        "  <instructions>\n    Answer in French.\n    Focus on module X.\n    File contents are unescaped"
+    ));
+    assert!(!replaced.contains("READY"));
+
+    let appended = make_multi_step_header_chunks(&HeaderSpec {
+        multi_step: true,
+        instructions: Some(custom),
+        append_instructions: true,
+        ..Default::default()
+    })
+    .concat();
+    let builtin = appended.find("To fetch file contents").unwrap();
+    let added = appended.find("    Answer in French.\n").unwrap();
+    assert!(builtin < added, "{appended}");
+}
+
+#[test]
+fn custom_instructions_are_escaped_with_escape_xml() {
+    let custom = Some("Compare a < b & keep <tags>.");
+    let escaped = make_header(&HeaderSpec {
+        escape_xml: true,
+        instructions: custom,
+        ..Default::default()
+    });
+    assert!(escaped.contains("    Compare a &lt; b &amp; keep &lt;tags&gt;.\n"));
+
+    let raw = make_header(&HeaderSpec {
+        instructions: custom,
+        ..Default::default()
+    });
+    assert!(raw.contains("    Compare a < b & keep <tags>.\n"));
+}
+
+#[test]
+fn file_map_can_be_left_out_of_the_header() {
+    let metas: Vec<FileMeta> = (0..20)
+        .map(|id| FileMeta {
+            id,
+            path: PathBuf::from(format!("src/file_{id}.rs")),
+            tokens: 10,
+            parts: 1,
+            ..Default::default()
+        })
+        .collect();
+    let spec = HeaderSpec {
+        total_chunks: 3,
+        files: &metas,
+        ..Default::default()
+    };
+    let with_map = make_header(&spec);
+    let without_map = make_header(&HeaderSpec {
+        include_file_map: false,
+        ..spec
+    });
+    assert!(!without_map.contains("<file-map"));
+    assert!(without_map.contains("<instructions>"));
+    assert!(count_tokens(&without_map) < count_tokens(&with_map));
+}
+
+#[test]
+fn file_map_groups_entries_by_folder_with_subtotals() {
+    let meta = |id, path: &str, tokens| FileMeta {
+        id,
+        path: PathBuf::from(path),
+        tokens,
+        parts: 1,
+        ..Default::default()
+    };
+    // Interleaved folders, as after `--sort tokens`
+    let metas = vec![
+        meta(0, "src/a.rs", 40),
+        meta(1, "docs/guide.md", 25),
+        meta(2, "src/b.rs", 30),
+        meta(3, "docs/faq.md", 5),
+    ];
+    let hdr = make_header(&HeaderSpec {
+        files: &metas,
+        ..Default::default()
+    });
+    assert!(hdr.contains(r#"<file-map total-files="4" total-tokens="100">"#));
+    let src = hdr.find(r#"<folder path="src" tokens="70">"#).unwrap();
+    let docs = hdr.find(r#"<folder path="docs" tokens="30">"#).unwrap();
+    let pos = |path: &str| hdr.find(&format!("path=\"{path}\"")).unwrap();
+    assert!(src < pos("src/a.rs") && pos("src/a.rs") < pos("src/b.rs"));
+    assert!(pos("src/b.rs") < docs);
+    assert!(docs < pos("docs/guide.md") && pos("docs/guide.md") < pos("docs/faq.md"));
+    assert_eq!(hdr.matches("</folder>").count(), 2);
+}
+
+#[test]
+fn skipped_files_close_the_file_map() {
+    let metas = vec![FileMeta {
+        path: PathBuf::from("src/main.rs"),
+        tokens: 10,
+        parts: 1,
+        ..Default::default()
+    }];
+    let skipped = vec![
+        SkippedFile {
+            path: PathBuf::from("assets/logo.png"),
+            reason: SkipReason::Binary,
+            message: String::new(),
+            size: Some(14336),
+        },
+        SkippedFile {
+            path: PathBuf::from("secret.txt"),
+            reason: SkipReason::Unreadable,
+            message: String::new(),
+            size: None,
+        },
+    ];
+    let hdr = make_header(&HeaderSpec {
+        files: &metas,
+        skipped: &skipped,
+        ..Default::default()
+    });
+    assert!(hdr.contains(r#"<file-map total-files="1" total-tokens="10">"#));
+    assert!(hdr.contains(concat!(
+        "    </folder>\n",
+        "    <skipped-file path=\"assets/logo.png\" reason=\"binary\" size=\"14336\"/>\n",
+        "    <skipped-file path=\"secret.txt\" reason=\"unreadable\"/>\n",
+        "  </file-map>\n"
+    )));
+}
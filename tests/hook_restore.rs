@@ -19,7 +19,7 @@ fn tui_restores_panic_hook() {
     unsafe {
         std::env::set_var("CG_TEST_AUTOQUIT", "1");
     }
-    let _ = select_files_tui(Vec::new(), &[]);
+    let _ = select_files_tui(Vec::new(), &[], 0);
     unsafe {
         std::env::remove_var("CG_TEST_AUTOQUIT");
     }
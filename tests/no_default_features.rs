@@ -0,0 +1,23 @@
+use std::process::Command;
+
+/// CI matrix entry: the library core must build without the `tui`,
+/// `clipboard`, and `git` features. Uses its own target dir so it does not
+/// contend with the running test build.
+#[test]
+fn library_checks_without_default_features() {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let output = Command::new(env!("CARGO"))
+        .current_dir(manifest_dir)
+        .args(["check", "--lib", "--no-default-features", "--quiet"])
+        .env(
+            "CARGO_TARGET_DIR",
+            format!("{manifest_dir}/target/no-default-features"),
+        )
+        .output()
+        .expect("failed to run cargo check");
+    assert!(
+        output.status.success(),
+        "cargo check --no-default-features failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,41 @@
+use context_gather::context::validate::{check_chunk, check_well_formed};
+
+#[test]
+fn control_character_is_reported_with_its_file_and_offset() {
+    let xml = "<shared-context>\n  <file-contents path=\"src/vt.txt\" name=\"vt.txt\">\na\u{b}b\n  </file-contents>\n</shared-context>\n";
+    let problem = check_well_formed(xml).unwrap_err();
+    assert_eq!(problem.offset, xml.find('\u{b}').unwrap());
+    assert_eq!(problem.line, 3);
+    assert_eq!(problem.path.as_deref(), Some("src/vt.txt"));
+    assert_eq!(problem.invalid_char, Some('\u{b}'));
+    assert!(problem.suggestion().contains("tr -d '\\013' < src/vt.txt"));
+}
+
+#[test]
+fn markup_problems_suggest_escaping() {
+    for xml in [
+        "<a><b></a>",
+        "<a>x &nbsp; y</a>",
+        "<a>x ]]> y</a>",
+        "<a>&#11;</a>",
+        "<a>",
+    ] {
+        let problem = check_well_formed(xml).unwrap_err();
+        assert!(problem.offset <= xml.len(), "{xml}: {problem}");
+        if problem.invalid_char.is_none() {
+            assert!(
+                problem.suggestion().contains("--escape-xml"),
+                "{xml}: {problem}"
+            );
+        }
+    }
+    check_well_formed("<a t=\"&lt;&#x41;\"><![CDATA[<b>]]>&amp;</a>").unwrap();
+}
+
+#[test]
+fn chunks_may_open_or_close_the_shared_envelope() {
+    check_chunk("<shared-context>\n<shared-context-header/>\n").unwrap();
+    check_chunk("<context-chunk>x</context-chunk>\n</shared-context>\n").unwrap();
+    assert!(check_well_formed("<shared-context>\n<shared-context-header/>\n").is_err());
+    assert!(check_chunk("<context-chunk>\n</shared-context>\n").is_err());
+}
@@ -1,6 +1,10 @@
 use context_gather::{
-    gather::FileContents, xml_output::build_xml, xml_output::build_xml_with_escape,
+    chunker::build_file_meta, gather::FileContents, tokenizer::count as count_tokens,
+    xml_output::XmlStyle, xml_output::build_xml, xml_output::build_xml_with_cdata,
+    xml_output::build_xml_with_escape, xml_output::build_xml_with_meta,
 };
+use quick_xml::Reader;
+use quick_xml::events::Event;
 use std::path::PathBuf;
 
 #[test]
@@ -10,11 +14,13 @@ fn groups_by_folder_and_contains_contents() {
             folder: PathBuf::from("src"),
             path: PathBuf::from("src/main.rs"),
             contents: "fn main(){}".into(),
+            ..Default::default()
         },
         FileContents {
             folder: PathBuf::from("tests"),
             path: PathBuf::from("tests/foo.rs"),
             contents: "assert!(true);".into(),
+            ..Default::default()
         },
     ];
     let xml = build_xml(&files).unwrap();
@@ -30,6 +36,7 @@ fn escape_xml_rewrites_special_chars() {
         folder: PathBuf::from("src"),
         path: PathBuf::from("src/main.rs"),
         contents: "if a < b && b > c { println!(\"&\"); }".into(),
+        ..Default::default()
     }];
     let xml = build_xml_with_escape(&files, true).unwrap();
     assert!(xml.contains("&lt;"));
@@ -37,3 +44,105 @@ fn escape_xml_rewrites_special_chars() {
     assert!(xml.contains("&amp;"));
     assert!(!xml.contains("if a < b && b > c"));
 }
+
+/// Text of every `<file-contents>` element, with entities resolved and CDATA
+/// sections unwrapped, as a conforming XML parser sees it.
+fn parsed_file_texts(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut texts = Vec::new();
+    let mut current: Option<String> = None;
+    loop {
+        match reader
+            .read_event()
+            .expect("output should be well-formed XML")
+        {
+            Event::Start(e) if e.name().as_ref() == b"file-contents" => {
+                current = Some(String::new());
+            }
+            Event::End(e) if e.name().as_ref() == b"file-contents" => {
+                texts.extend(current.take());
+            }
+            Event::Text(t) => {
+                if let Some(text) = current.as_mut() {
+                    text.push_str(&t.decode().unwrap());
+                }
+            }
+            Event::CData(c) => {
+                if let Some(text) = current.as_mut() {
+                    text.push_str(&c.decode().unwrap());
+                }
+            }
+            Event::GeneralRef(r) => {
+                if let Some(text) = current.as_mut() {
+                    let entity = match r.decode().unwrap().as_ref() {
+                        "lt" => '<',
+                        "gt" => '>',
+                        "amp" => '&',
+                        "quot" => '"',
+                        "apos" => '\'',
+                        other => panic!("unexpected entity &{other};"),
+                    };
+                    text.push(entity);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    texts
+}
+
+fn markup_heavy_file() -> FileContents {
+    FileContents {
+        folder: PathBuf::from("src"),
+        path: PathBuf::from("src/view.tsx"),
+        contents: "<div a=\"1\">{x < y && y > z}</div>\n<![CDATA[ nested ]]> & <br/>\n".repeat(20),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn escaped_and_cdata_output_round_trip_through_an_xml_parser() {
+    let files = vec![markup_heavy_file()];
+    let expected = format!("\n{}\n    ", files[0].contents);
+    for xml in [
+        build_xml_with_escape(&files, true).unwrap(),
+        build_xml_with_cdata(&files).unwrap(),
+    ] {
+        assert_eq!(parsed_file_texts(&xml), vec![expected.clone()]);
+    }
+}
+
+#[test]
+fn cdata_output_is_no_larger_than_escaped_output() {
+    let files = vec![markup_heavy_file()];
+    let escaped = build_xml_with_escape(&files, true).unwrap();
+    let cdata = build_xml_with_cdata(&files).unwrap();
+    assert!(cdata.contains("<![CDATA[<div a=\"1\">"));
+    assert!(!cdata.contains("&lt;"));
+    assert!(
+        count_tokens(&cdata) <= count_tokens(&escaped),
+        "cdata {} > escaped {}",
+        count_tokens(&cdata),
+        count_tokens(&escaped)
+    );
+}
+
+#[test]
+fn file_map_can_be_left_out() {
+    let files = vec![FileContents {
+        folder: PathBuf::from("src"),
+        path: PathBuf::from("src/main.rs"),
+        contents: "fn main() {}".into(),
+        ..Default::default()
+    }];
+    let metas = build_file_meta(&files, false);
+    let style = XmlStyle {
+        file_map: false,
+        ..XmlStyle::default()
+    };
+    let xml = build_xml_with_meta(&files, &metas, &[], &style).unwrap();
+    assert!(!xml.contains("<file-map"), "{xml}");
+    assert!(xml.contains("<folder path=\"src\">"));
+    assert!(xml.contains("fn main() {}"));
+}